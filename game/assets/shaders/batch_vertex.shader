@@ -0,0 +1,18 @@
+#version 430 core
+
+layout (location = 0) in vec3 _pos;
+layout (location = 1) in vec2 _uvs;
+layout (location = 2) in vec4 _color;
+
+#include "common.glsl"
+
+out vec2 uvs;
+out vec4 color;
+
+void main()
+{
+    // Positions are already in world space, the TRS is baked on CPU side when batching
+    gl_Position = world_to_clip(vec4(_pos.xyz, 1.0));
+    uvs = _uvs;
+    color = _color;
+}
\ No newline at end of file
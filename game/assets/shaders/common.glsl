@@ -0,0 +1,22 @@
+// Header shared by the shaders, resolved by the shader preprocessor
+
+// Frame data shared by every program, updated once per frame by the renderer
+layout(std140, binding = 0) uniform FrameData
+{
+    mat4 VIEW;
+    mat4 PROJ;
+    vec2 resolution;
+    float time;
+};
+
+// Clip space position of a vertex placed in the world by its TRS
+vec4 to_clip(mat4 trs, vec4 position)
+{
+    return PROJ * VIEW * trs * position;
+}
+
+// Clip space position of a vertex already in world space
+vec4 world_to_clip(vec4 position)
+{
+    return PROJ * VIEW * position;
+}
@@ -0,0 +1,32 @@
+#version 430 core
+#pragma variants SCROLL
+
+// Sprite shader of material files: a detail texture blended over the main one
+
+out vec4 FragColor;
+
+in vec2 uvs;
+in vec4 color;
+
+uniform sampler2D texture0;
+uniform sampler2D texture1; // Detail texture
+uniform float detail_strength;
+uniform vec2 scroll; // Detail uvs offset per second
+
+#include "common.glsl"
+
+#ifndef DETAIL_SCALE
+#define DETAIL_SCALE 1.0
+#endif
+
+void main()
+{
+    vec2 detail_uvs = uvs * DETAIL_SCALE;
+#ifdef SCROLL
+    detail_uvs += scroll * time;
+#endif
+    vec4 detail = texture(texture1, detail_uvs);
+    vec4 texColor = texture(texture0, uvs);
+    texColor.rgb = mix(texColor.rgb, texColor.rgb * detail.rgb, detail_strength);
+    FragColor = texColor * color;
+}
@@ -0,0 +1,19 @@
+#version 430 core
+
+layout (location = 0) in vec3 _pos;
+layout (location = 1) in vec2 _uvs;
+layout (location = 2) in mat4 _trs; // per instance, uses locations 2 to 5
+layout (location = 6) in vec4 _color; // per instance
+layout (location = 7) in vec4 _uv_rect; // per instance, offset (xy) & size (zw) of the atlas region
+
+#include "common.glsl"
+
+out vec2 uvs;
+out vec4 color;
+
+void main()
+{
+    gl_Position = to_clip(_trs, vec4(_pos.xyz, 1.0));
+    uvs = _uv_rect.xy + _uvs * _uv_rect.zw;
+    color = _color;
+}
\ No newline at end of file
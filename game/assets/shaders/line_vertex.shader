@@ -5,11 +5,11 @@ layout(std430, binding = 0) buffer TVertex
     vec4 vertex[];
 };
 
+#include "common.glsl"
+
 uniform vec2 offset;
 uniform vec4 surface_color;
 uniform mat4 TRS;
-uniform mat4 VIEW;
-uniform mat4 PROJ;
 uniform float thickness;
 
 out vec4 color;
@@ -57,7 +57,7 @@ void main()
 
     vec3 mitter_vec = extruded_point + mitter_normal * (thickness * 0.5 * dir) / dot(mitter_normal, normal);
     pos = vec4(mitter_vec.xyz, 1) - vec4(offset.xy, 0, 0);
-    gl_Position = PROJ * VIEW * TRS * pos;
+    gl_Position = to_clip(TRS, pos);
 
     world_position = (TRS * pos).xyz;
     uv = uv_table[ti];
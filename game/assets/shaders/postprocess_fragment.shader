@@ -0,0 +1,189 @@
+#version 430 core
+
+// Post process effects, one fullscreen pass per effect of the camera stack.
+// Effect ids & parameters are set by the renderer (see PostProcessor)
+
+#define VIGNETTE 0
+#define COLOR_GRADING 1
+#define CHROMATIC_ABERRATION 2
+#define BLUR 3
+#define PIXELATION 4
+#define BLOOM_PREFILTER 5
+#define BLOOM_DOWNSAMPLE 6
+#define BLOOM_UPSAMPLE 7
+#define BLOOM_COMPOSITE 8
+#define TONEMAPPING 9
+#define LIGHT_ACCUMULATION 10
+#define LIGHT_COMBINE 11
+
+#define MAX_LIGHTS 32
+#define LIGHT_SPOT 1.0
+#define MAX_SHADOW_SEGMENTS 128
+#define SHADOW_SAMPLES 8
+
+out vec4 FragColor;
+
+in vec2 coords;
+
+uniform sampler2D texture0; // Previous pass
+uniform sampler2D texture1; // Color grading LUT, bloom mip, light accumulation
+
+uniform int effect;
+uniform vec4 params;
+uniform vec2 texel_size;
+
+struct Light {
+	vec4 position;  // xy, radius, falloff
+	vec4 color;     // rgb * intensity, kind
+	vec4 direction; // xy, cosines of the cone outer & inner edges
+	vec4 shadow;    // softness, casts shadows
+};
+
+// 2D lights of the lit camera, see lighting.rs
+layout(std140, binding = 1) uniform LightData
+{
+	mat4 INV_VIEW_PROJ;
+	vec4 ambient;      // rgb, lights count
+	vec4 shadow_info;  // segments count
+	Light lights[MAX_LIGHTS];
+	vec4 segments[MAX_SHADOW_SEGMENTS]; // Edges of the shadow casters, both ends
+};
+
+// LUT strip: size slices of size x size texels, blue selects the slice, green goes down the image
+vec3 grade(vec3 color, float size) {
+	float slice = color.b * (size - 1.0);
+	float first = floor(slice);
+	float second = min(first + 1.0, size - 1.0);
+
+	float x = (color.r * (size - 1.0) + 0.5) / (size * size);
+	float y = 1.0 - (color.g * (size - 1.0) + 0.5) / size;
+	vec3 a = texture(texture1, vec2(x + first / size, y)).rgb;
+	vec3 b = texture(texture1, vec2(x + second / size, y)).rgb;
+	return mix(a, b, slice - first);
+}
+
+// 4 bilinear taps at the texel corners, averages 16 texels when downsampling
+vec3 box(vec2 uv) {
+	vec4 offset = texel_size.xyxy * vec4(-1.0, -1.0, 1.0, 1.0);
+	vec3 sum = texture(texture0, uv + offset.xy).rgb + texture(texture0, uv + offset.zy).rgb;
+	sum += texture(texture0, uv + offset.xw).rgb + texture(texture0, uv + offset.zw).rgb;
+	return sum * 0.25;
+}
+
+// ACES filmic approximation (Narkowicz)
+vec3 aces(vec3 x) {
+	return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// World position seen at the uv, on the z = 0 plane of the sprites
+vec2 world_position(vec2 uv) {
+	vec4 near = INV_VIEW_PROJ * vec4(uv * 2.0 - 1.0, -1.0, 1.0);
+	vec4 far = INV_VIEW_PROJ * vec4(uv * 2.0 - 1.0, 1.0, 1.0);
+	near /= near.w;
+	far /= far.w;
+	float depth = far.z - near.z;
+	float t = abs(depth) > 0.0001 ? -near.z / depth : 0.0;
+	return mix(near.xy, far.xy, t);
+}
+
+// Whether the p -> q ray crosses the a -> b segment
+bool intersects(vec2 p, vec2 q, vec2 a, vec2 b) {
+	vec2 d = q - p;
+	vec2 e = b - a;
+	float denom = d.x * e.y - d.y * e.x;
+	if (abs(denom) < 0.000001) {
+		return false;
+	}
+	vec2 ap = a - p;
+	float t = (ap.x * e.y - ap.y * e.x) / denom;
+	float u = (ap.x * d.y - ap.y * d.x) / denom;
+	return t > 0.0 && t < 1.0 && u >= 0.0 && u <= 1.0;
+}
+
+// Part of the light source seen from the pixel, rays are cast toward points spread across the
+// source width
+float shadow_visibility(vec2 world, vec2 light, float softness) {
+	vec2 to_light = light - world;
+	float dist = length(to_light);
+	if (dist < 0.0001) {
+		return 1.0;
+	}
+	vec2 side = vec2(-to_light.y, to_light.x) / dist;
+	int samples = softness > 0.0 ? SHADOW_SAMPLES : 1;
+	float visible = 0.0;
+	for (int s = 0; s < samples; s++) {
+		float offset = ((float(s) + 0.5) / float(samples) - 0.5) * softness;
+		vec2 target = light + side * offset;
+		bool blocked = false;
+		for (int i = 0; i < int(shadow_info.x) && !blocked; i++) {
+			blocked = intersects(world, target, segments[i].xy, segments[i].zw);
+		}
+		visible += blocked ? 0.0 : 1.0;
+	}
+	return visible / float(samples);
+}
+
+vec3 accumulate_lights(vec2 uv) {
+	vec2 world = world_position(uv);
+	vec3 light = ambient.rgb;
+	for (int i = 0; i < int(ambient.w); i++) {
+		vec2 to_pixel = world - lights[i].position.xy;
+		float dist = length(to_pixel);
+		float fade = clamp(1.0 - dist / max(lights[i].position.z, 0.0001), 0.0, 1.0);
+		float attenuation = pow(fade, max(lights[i].position.w, 0.0001));
+		if (lights[i].color.w == LIGHT_SPOT) {
+			float cone = dot(to_pixel / max(dist, 0.0001), lights[i].direction.xy);
+			attenuation *= smoothstep(lights[i].direction.z, lights[i].direction.w, cone);
+		}
+		if (lights[i].shadow.y > 0.0 && attenuation > 0.0) {
+			attenuation *= shadow_visibility(world, lights[i].position.xy, lights[i].shadow.x);
+		}
+		light += lights[i].color.rgb * attenuation;
+	}
+	return light;
+}
+
+void main() {
+	vec3 color = texture(texture0, coords).rgb;
+
+	if (effect == VIGNETTE) {
+		float dist = length(coords - 0.5) * 1.41421356;
+		float fade = smoothstep(1.0 - max(params.y, 0.001), 1.0, dist);
+		color *= 1.0 - fade * params.x;
+	} else if (effect == COLOR_GRADING) {
+		color = mix(color, grade(color, params.y), params.x);
+	} else if (effect == CHROMATIC_ABERRATION) {
+		vec2 offset = (coords - 0.5) * params.x;
+		color.r = texture(texture0, coords + offset).r;
+		color.b = texture(texture0, coords - offset).b;
+	} else if (effect == BLUR) {
+		vec3 sum = vec3(0.0);
+		for (int x = -1; x <= 1; x++) {
+			for (int y = -1; y <= 1; y++) {
+				sum += texture(texture0, coords + vec2(x, y) * params.x * texel_size).rgb;
+			}
+		}
+		color = sum / 9.0;
+	} else if (effect == PIXELATION) {
+		vec2 cell = max(params.x, 1.0) * texel_size;
+		color = texture(texture0, (floor(coords / cell) + 0.5) * cell).rgb;
+	} else if (effect == BLOOM_PREFILTER) {
+		color = box(coords);
+		float brightness = max(color.r, max(color.g, color.b));
+		color *= max(brightness - params.y, 0.0) / max(brightness, 0.0001);
+	} else if (effect == BLOOM_DOWNSAMPLE) {
+		color = box(coords);
+	} else if (effect == BLOOM_UPSAMPLE) {
+		color = box(coords) + texture(texture1, coords).rgb;
+	} else if (effect == BLOOM_COMPOSITE) {
+		color += texture(texture1, coords).rgb * params.x;
+	} else if (effect == TONEMAPPING) {
+		color = aces(color * params.x);
+	} else if (effect == LIGHT_ACCUMULATION) {
+		color = accumulate_lights(coords);
+	} else if (effect == LIGHT_COMBINE) {
+		color *= texture(texture1, coords).rgb;
+	}
+
+	FragColor = vec4(color, 1.0);
+}
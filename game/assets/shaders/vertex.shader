@@ -4,16 +4,17 @@ layout (location = 0) in vec3 _pos;
 layout (location = 1) in vec2 _uvs;
 
 uniform vec4 surface_color;
+uniform vec4 uv_rect; // offset (xy) & size (zw) of the sampled atlas region
 uniform mat4 TRS;
-uniform mat4 VIEW;
-uniform mat4 PROJ;
+
+#include "common.glsl"
 
 out vec2 uvs;
 out vec4 color;
 
 void main()
 {
-    gl_Position = PROJ * VIEW * TRS * vec4(_pos.xyz, 1.0);
-    uvs = _uvs;
+    gl_Position = to_clip(TRS, vec4(_pos.xyz, 1.0));
+    uvs = uv_rect.xy + _uvs * uv_rect.zw;
     color = surface_color;
 }
\ No newline at end of file
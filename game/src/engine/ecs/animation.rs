@@ -0,0 +1,290 @@
+use super::components::{SpriteAtlas, SpriteRenderer2D};
+use crate::engine::utils::file_system::{FileSystem, FileType};
+use bevy_ecs::component::Component;
+
+// Sprite sheet animation: an Animator plays clips on the SpriteRenderer2D of its entity, frames
+// are either texture files, or regions of an atlas (cheaper, only the uv rect changes).
+//
+// Clips are described in assets/animations files:
+//
+//     # clip <name> <loop|ping_pong|once> <frame duration in seconds>
+//     clip idle loop 0.2
+//     frames Red/texture_01.png Red/texture_02.png
+//
+//     # frames are regions of the atlas, "grid <first> <count>" lists the cells of a grid atlas
+//     clip walk ping_pong 0.1
+//     atlas hero.atlas
+//     grid 0 6
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PlaybackMode {
+    #[default]
+    Loop,
+    PingPong, // Forth and back, the last and first frames are not repeated
+    Once,     // Stops on the last frame and reports it with an AnimationFinished event
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    pub atlas: Option<String>, // Frames are regions of this atlas, texture files otherwise
+    pub frames: Vec<String>,
+    pub frame_duration: f32, // In seconds
+    pub mode: PlaybackMode,
+}
+
+#[derive(Component, Debug)]
+pub struct Animator {
+    pub clips: Vec<AnimationClip>,
+    pub speed: f32, // Playback speed multiplier
+
+    current: Option<usize>,
+    step: usize, // Frames played since the clip started, wrapped on the clip cycle
+    elapsed: f32,
+    played: f32, // Time spent in the clip, scaled by the speed
+    playing: bool,
+    finished: bool,
+}
+
+impl AnimationClip {
+    pub fn load_file(file_name: &str) -> Result<Vec<AnimationClip>, String> {
+        let content = FileSystem::load_file(file_name, FileType::Animation)?;
+        AnimationClip::parse(&content).map_err(|err| format!("{} ({})", err, file_name))
+    }
+
+    pub fn parse(content: &str) -> Result<Vec<AnimationClip>, String> {
+        let mut clips: Vec<AnimationClip> = vec![];
+
+        for (index, line) in content.lines().enumerate() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let error = |message: &str| format!("[Animation] line {}: {}", index + 1, message);
+
+            if let ["clip", name, mode, duration] = tokens.as_slice() {
+                let mode = match *mode {
+                    "loop" => PlaybackMode::Loop,
+                    "ping_pong" => PlaybackMode::PingPong,
+                    "once" => PlaybackMode::Once,
+                    _ => return Err(error(&format!("unknown playback mode {}", mode))),
+                };
+                let frame_duration = match duration.parse::<f32>() {
+                    Ok(duration) if duration > 0f32 => duration,
+                    _ => return Err(error("frame duration must be a positive number")),
+                };
+
+                clips.push(AnimationClip {
+                    name: String::from(*name),
+                    atlas: None,
+                    frames: vec![],
+                    frame_duration,
+                    mode,
+                });
+                continue;
+            }
+
+            let clip = clips.last_mut();
+            match (tokens.as_slice(), clip) {
+                ([], _) => continue,
+                ([comment, ..], _) if comment.starts_with('#') => continue,
+                (["atlas", atlas], Some(clip)) => clip.atlas = Option::from(String::from(*atlas)),
+                (["frames", frames @ ..], Some(clip)) => {
+                    clip.frames
+                        .extend(frames.iter().map(|frame| String::from(*frame)));
+                }
+                (["grid", first, count], Some(clip)) => {
+                    let (Ok(first), Ok(count)) = (first.parse::<u32>(), count.parse::<u32>())
+                    else {
+                        return Err(error("grid frames must be positive integers"));
+                    };
+                    clip.frames
+                        .extend((first..first + count).map(|cell| cell.to_string()));
+                }
+                (_, None) => return Err(error("entries must follow a clip declaration")),
+                _ => return Err(error(&format!("unknown entry \"{}\"", line.trim()))),
+            }
+        }
+
+        for clip in clips.iter() {
+            if clip.frames.is_empty() {
+                return Err(format!("[Animation] clip {} has no frame", clip.name));
+            }
+            if clip.atlas.is_none() && clip.frames.iter().any(|f| f.parse::<u32>().is_ok()) {
+                return Err(format!(
+                    "[Animation] clip {} uses grid cells without an atlas",
+                    clip.name
+                ));
+            }
+        }
+
+        Ok(clips)
+    }
+
+    // Frames played in one cycle, ping pong doesn't repeat its first and last frames
+    fn cycle_length(&self) -> usize {
+        match self.mode {
+            PlaybackMode::PingPong if self.frames.len() > 1 => self.frames.len() * 2 - 2,
+            _ => self.frames.len(),
+        }
+    }
+
+    fn frame_index(&self, step: usize) -> usize {
+        let count = self.frames.len();
+        match self.mode {
+            PlaybackMode::PingPong if step >= count => self.cycle_length() - step,
+            _ => step.min(count - 1),
+        }
+    }
+}
+
+impl Animator {
+    pub fn new(clips: Vec<AnimationClip>) -> Self {
+        Self {
+            clips,
+            speed: 1f32,
+            current: None,
+            step: 0,
+            elapsed: 0f32,
+            played: 0f32,
+            playing: false,
+            finished: false,
+        }
+    }
+
+    pub fn from_file(file_name: &str) -> Result<Self, String> {
+        AnimationClip::load_file(file_name).map(Animator::new)
+    }
+
+    // Start the clip from its first frame, playing the current clip again doesn't restart it
+    pub fn play(&mut self, clip_name: &str) -> bool {
+        let Some(index) = self.clips.iter().position(|clip| clip.name == clip_name) else {
+            println!("[Animator] Unknown clip {}", clip_name);
+            return false;
+        };
+
+        if self.current != Some(index) || !self.playing {
+            self.start(index);
+        }
+        true
+    }
+
+    // Start the clip from its first frame, even when it is the current clip
+    pub fn restart(&mut self, clip_name: &str) -> bool {
+        let Some(index) = self.clips.iter().position(|clip| clip.name == clip_name) else {
+            println!("[Animator] Unknown clip {}", clip_name);
+            return false;
+        };
+
+        self.start(index);
+        true
+    }
+
+    // Freeze the animation on its current frame
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self) {
+        self.playing = self.current.is_some() && !self.finished;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn get_current_clip(&self) -> Option<&AnimationClip> {
+        self.current.map(|index| &self.clips[index])
+    }
+
+    // Clip cycles played since the clip started, 1 when a clip played once reached its end
+    pub fn get_normalized_time(&self) -> f32 {
+        let Some(clip) = self.get_current_clip() else {
+            return 0f32;
+        };
+
+        if self.finished {
+            return 1f32;
+        }
+        self.played / (clip.cycle_length() as f32 * clip.frame_duration)
+    }
+
+    pub fn get_frame_index(&self) -> Option<usize> {
+        self.get_current_clip()
+            .map(|clip| clip.frame_index(self.step))
+    }
+
+    // Move the playback forward, returns true when a clip played once reached its last frame
+    pub fn advance(&mut self, delta_time: f32) -> bool {
+        let Some(clip) = self.current.map(|index| &self.clips[index]) else {
+            return false;
+        };
+        if !self.playing {
+            return false;
+        }
+
+        let delta_time = delta_time * self.speed.max(0f32);
+        self.elapsed += delta_time;
+        self.played += delta_time;
+        while self.elapsed >= clip.frame_duration {
+            self.elapsed -= clip.frame_duration;
+
+            if clip.mode == PlaybackMode::Once && self.step + 1 >= clip.frames.len() {
+                self.playing = false;
+                self.finished = true;
+                return true;
+            }
+            self.step = (self.step + 1) % clip.cycle_length();
+        }
+
+        false
+    }
+
+    // True when the sprite already shows the current frame, writing it again would flag the
+    // sprite as changed and send a useless update to the renderer
+    pub fn is_frame_shown(&self, sprite: &SpriteRenderer2D) -> bool {
+        let Some((clip, frame)) = self.get_frame() else {
+            return true;
+        };
+
+        match (clip.atlas.as_ref(), sprite.atlas.as_ref()) {
+            (Some(atlas), Some(shown)) => shown.atlas == *atlas && shown.region == *frame,
+            (None, None) => sprite.texture.as_ref() == Some(frame),
+            _ => false,
+        }
+    }
+
+    pub fn apply_frame(&self, sprite: &mut SpriteRenderer2D) {
+        let Some((clip, frame)) = self.get_frame() else {
+            return;
+        };
+
+        match clip.atlas.as_ref() {
+            Some(atlas) => {
+                sprite.atlas = Option::from(SpriteAtlas {
+                    atlas: atlas.clone(),
+                    region: frame.clone(),
+                });
+            }
+            None => {
+                sprite.atlas = None;
+                sprite.texture = Option::from(frame.clone());
+            }
+        }
+    }
+
+    fn start(&mut self, clip_index: usize) {
+        self.current = Option::from(clip_index);
+        self.step = 0;
+        self.elapsed = 0f32;
+        self.played = 0f32;
+        self.playing = true;
+        self.finished = false;
+    }
+
+    fn get_frame(&self) -> Option<(&AnimationClip, &String)> {
+        let clip = self.get_current_clip()?;
+        Some((clip, &clip.frames[clip.frame_index(self.step)]))
+    }
+}
@@ -0,0 +1,215 @@
+use bevy_ecs::component::Component;
+use std::collections::HashMap;
+
+// Animation state machine: states are bound to clips of the entity Animator, gameplay systems
+// set parameters and the controller follows the first transition whose conditions are met.
+//
+//     let mut controller = AnimationController::new("idle", "idle_clip");
+//     controller
+//         .add_state("run", "run_clip")
+//         .add_state("hit", "hit_clip")
+//         .add_transition(AnimationTransition::new("idle", "run").when(Greater("speed", 0.1)))
+//         .add_transition(AnimationTransition::new("hit", "idle").with_exit_time(1.0))
+//         .add_transition(AnimationTransition::from_any("hit").when(IsTrue("hurt")));
+//
+//     controller.set_float("speed", 2.0);
+//     controller.set_trigger("hurt");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationParameter {
+    Bool(bool),
+    Float(f32),
+    Trigger(bool), // Reset once consumed by a transition
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionCondition {
+    IsTrue(String), // Bool parameter set or trigger raised
+    IsFalse(String),
+    Greater(String, f32),
+    Less(String, f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationState {
+    pub name: String,
+    pub clip: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationTransition {
+    pub from: Option<String>, // None for any state transitions
+    pub to: String,
+    pub conditions: Vec<TransitionCondition>,
+    pub exit_time: Option<f32>, // Normalized clip time to reach first, 1 is the end of the clip
+}
+
+#[derive(Component, Debug)]
+pub struct AnimationController {
+    states: Vec<AnimationState>,
+    transitions: Vec<AnimationTransition>,
+    parameters: HashMap<String, AnimationParameter>,
+    current: usize,
+    entered: bool, // The clip of the current state has been sent to the animator
+}
+
+impl TransitionCondition {
+    fn is_met(&self, parameters: &HashMap<String, AnimationParameter>) -> bool {
+        use AnimationParameter::*;
+
+        match self {
+            TransitionCondition::IsTrue(name) => {
+                matches!(parameters.get(name), Some(Bool(true)) | Some(Trigger(true)))
+            }
+            TransitionCondition::IsFalse(name) => matches!(
+                parameters.get(name),
+                Some(Bool(false)) | Some(Trigger(false))
+            ),
+            TransitionCondition::Greater(name, value) => {
+                matches!(parameters.get(name), Some(Float(parameter)) if parameter > value)
+            }
+            TransitionCondition::Less(name, value) => {
+                matches!(parameters.get(name), Some(Float(parameter)) if parameter < value)
+            }
+        }
+    }
+}
+
+impl AnimationTransition {
+    pub fn new(from: &str, to: &str) -> Self {
+        Self {
+            from: Option::from(String::from(from)),
+            to: String::from(to),
+            conditions: vec![],
+            exit_time: None,
+        }
+    }
+
+    // Taken from any state but the target one
+    pub fn from_any(to: &str) -> Self {
+        Self {
+            from: None,
+            ..AnimationTransition::new("", to)
+        }
+    }
+
+    pub fn when(mut self, condition: TransitionCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn with_exit_time(mut self, exit_time: f32) -> Self {
+        self.exit_time = Option::from(exit_time);
+        self
+    }
+}
+
+impl AnimationController {
+    pub fn new(entry_state: &str, clip: &str) -> Self {
+        Self {
+            states: vec![AnimationState {
+                name: String::from(entry_state),
+                clip: String::from(clip),
+            }],
+            transitions: vec![],
+            parameters: HashMap::new(),
+            current: 0,
+            entered: false,
+        }
+    }
+
+    pub fn add_state(&mut self, name: &str, clip: &str) -> &mut Self {
+        self.states.push(AnimationState {
+            name: String::from(name),
+            clip: String::from(clip),
+        });
+        self
+    }
+
+    // Transitions are tested in insertion order, any state transitions first. The ones toward a
+    // state that was never added are ignored.
+    pub fn add_transition(&mut self, transition: AnimationTransition) -> &mut Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.parameters
+            .insert(String::from(name), AnimationParameter::Bool(value));
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.parameters
+            .insert(String::from(name), AnimationParameter::Float(value));
+    }
+
+    pub fn set_trigger(&mut self, name: &str) {
+        self.parameters
+            .insert(String::from(name), AnimationParameter::Trigger(true));
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) {
+        self.parameters
+            .insert(String::from(name), AnimationParameter::Trigger(false));
+    }
+
+    pub fn get_parameter(&self, name: &str) -> Option<AnimationParameter> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn get_state(&self) -> &AnimationState {
+        &self.states[self.current]
+    }
+
+    pub fn is_in_state(&self, name: &str) -> bool {
+        self.states[self.current].name == name
+    }
+
+    // Follow at most one transition, returns the clip to restart when the state changed.
+    // The normalized time is the one of the current state clip, see Animator.
+    pub fn update(&mut self, normalized_time: f32) -> Option<String> {
+        if !self.entered {
+            self.entered = true;
+            return Option::from(self.states[self.current].clip.clone());
+        }
+
+        let current = &self.states[self.current].name;
+        let (transition, next) = self
+            .transitions
+            .iter()
+            .filter(|transition| transition.from.is_none())
+            .chain(
+                self.transitions
+                    .iter()
+                    .filter(|transition| transition.from.as_ref() == Some(current)),
+            )
+            .find_map(|transition| {
+                // Transitions toward unknown states are skipped, the next ones can still be taken
+                let next = self
+                    .states
+                    .iter()
+                    .position(|state| state.name == transition.to)?;
+                let taken = (transition.from.is_some() || transition.to != *current)
+                    && transition
+                        .exit_time
+                        .is_none_or(|exit_time| normalized_time >= exit_time)
+                    && transition
+                        .conditions
+                        .iter()
+                        .all(|condition| condition.is_met(&self.parameters));
+                taken.then(|| (transition.clone(), next))
+            })?;
+
+        // Triggers are consumed by the transition using them
+        for condition in transition.conditions.iter() {
+            if let TransitionCondition::IsTrue(name) = condition {
+                if let Some(AnimationParameter::Trigger(raised)) = self.parameters.get_mut(name) {
+                    *raised = false;
+                }
+            }
+        }
+
+        self.current = next;
+        Option::from(self.states[next].clip.clone())
+    }
+}
@@ -2,30 +2,74 @@ use std::sync::{Arc, Mutex};
 
 use bevy_ecs::{component::Component, entity::Entity, system::Resource};
 
-use crate::engine::rendering::components::ARGB8Color;
+use crate::engine::rendering::components::{
+    ARGB8Color, AmbientLight, LightKind, MaskInteraction, PostEffect, RenderTarget,
+};
 use crate::engine::{
     inputs::keyboard::Keyboard,
-    rendering::{renderer::RenderCmdHd, shaders::Material},
+    rendering::{
+        renderer::{CameraHd, RenderCmdHd},
+        shaders::Material,
+    },
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum Projection {
     #[default]
     Orthographic,
-    Perspective, // It will likely not be implemented
+    Perspective, // Uses the camera fov, sprites get smaller with their distance
 }
 
 #[derive(Component, Debug, Default)]
 pub struct Camera {
-    pub fov: f32,
+    pub fov: f32, // Vertical, in degrees
     pub near: f32,
     pub far: f32,
     pub ppu: u32,
 
     pub viewport: (f32, f32, f32, f32), // NDC
+    pub order: i32,                     // Lower orders are rendered first, below the others
     pub mode: Projection,
-    pub output_target: Option<u128>,
+    pub output_target: Option<RenderTarget>, // Render into a texture instead of the screen
     pub background_color: Option<ARGB8Color>,
+    pub hdr: bool, // Floating point framebuffers, colors above 1 feed the bloom & tonemapping
+    pub ambient_light: Option<AmbientLight>, // Lit by the Light2D entities when set
+}
+
+// Fullscreen effects of the camera on the same entity, applied in order. Systems can animate the
+// effects parameters, the renderer is updated when the component changes
+#[derive(Component, Debug, Default, Clone)]
+pub struct PostProcessStack {
+    pub effects: Vec<PostEffect>,
+}
+
+// Light placed at the entity position, spot lights point toward the entity rotation (z, degrees).
+// Lights are gathered every frame and only reach the cameras with an ambient light
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Light2D {
+    pub kind: LightKind,
+    pub color: ARGB8Color,
+    pub intensity: f32,
+    pub radius: f32,  // World units, unused by global lights
+    pub falloff: f32, // Exponent of the attenuation over the radius, 1 is linear
+    pub cast_shadows: bool,
+    pub shadow_softness: f32, // Width of the light source in world units, 0 casts hard shadows
+}
+
+// Blocks the 2D lights casting shadows, the shape follows the entity transform. Pixels inside the
+// shape are in its shadow too
+#[derive(Component, Debug, Clone)]
+pub enum ShadowCaster2D {
+    Polygon(Vec<(f32, f32)>), // Closed outline, in local units
+    SpriteRect,               // Quad of the entity sprite, 1 x 1 unit before scaling
+}
+
+// Area written in the stencil buffer, sprites clip themselves against it with their
+// mask_interaction. The shape follows the entity transform and is not drawn
+#[derive(Component, Debug, Clone)]
+pub enum SpriteMask {
+    Polygon(Vec<(f32, f32)>), // Closed outline, convex or not, in local units
+    Rect,                     // 1 x 1 unit before scaling, like the sprites quad
 }
 
 #[derive(Component, Debug, Clone, Copy, Default)]
@@ -56,11 +100,33 @@ pub struct Transform {
     pub scale: Scale,
 }
 
+// Sprites are drawn layer after layer, from Background to Overlay
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SortingLayer {
+    Background,
+    #[default]
+    Default,
+    Foreground,
+    Overlay,
+}
+
+// Region of a texture atlas (see rendering::atlas), the atlas is loaded from its ".atlas" file
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteAtlas {
+    pub atlas: String,
+    pub region: String,
+}
+
 #[derive(Component, Debug, Default)]
 pub struct SpriteRenderer2D {
     pub texture: Option<String>,
+    pub atlas: Option<SpriteAtlas>, // Takes precedence over the texture when set
     pub material: Option<Material>,
+    pub material_name: Option<String>, // File in assets/materials, takes precedence over material
     pub preserve_aspect: bool,
+    pub sorting_layer: SortingLayer,
+    pub order_in_layer: i16, // Lower orders are drawn first (behind) inside the sorting layer
+    pub mask_interaction: MaskInteraction,
 }
 
 #[derive(Component, Debug, Default, Clone, Copy)]
@@ -78,7 +144,7 @@ pub struct Inputs {
 
 #[derive(Resource, Debug)]
 pub struct CameraBinding {
-    pub cameras: Vec<(Entity, u32)>, // The entity camera and its associated framebuffer object
+    pub cameras: Vec<(Entity, CameraHd)>, // The entity camera and its renderer camera
 }
 
 // Impls ************************************************************
@@ -101,9 +167,12 @@ impl Camera {
             far: 50.0,
             ppu: 100u32,
             viewport: (0.0, 0.0, 1.0, 1.0),
+            order: 0,
             mode: Projection::Orthographic,
             output_target: Option::None,
             background_color: Option::None,
+            hdr: false,
+            ambient_light: Option::None,
         }
     }
 
@@ -119,3 +188,23 @@ impl Camera {
         }
     }
 }
+
+impl Light2D {
+    // White point light with a linear attenuation
+    pub fn point(radius: f32, intensity: f32) -> Self {
+        Light2D {
+            kind: LightKind::Point,
+            color: ARGB8Color {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 255,
+            },
+            intensity,
+            radius,
+            falloff: 1f32,
+            cast_shadows: true,
+            shadow_softness: 0f32,
+        }
+    }
+}
@@ -1,13 +1,38 @@
+use crate::engine::rendering::components::MaskInteraction;
 use crate::engine::rendering::shaders::Material;
 
-use super::components::SpriteRenderer2D;
+use super::components::{SortingLayer, SpriteAtlas, SpriteRenderer2D};
 
 impl SpriteRenderer2D {
     pub fn from(texture: String, preserve_aspect: bool) -> SpriteRenderer2D {
         SpriteRenderer2D {
             texture: Some(texture),
+            atlas: None,
             material: Some(Material::new()),
+            material_name: None,
             preserve_aspect,
+            sorting_layer: SortingLayer::Default,
+            order_in_layer: 0,
+            mask_interaction: MaskInteraction::None,
         }
     }
+
+    pub fn from_atlas(atlas: String, region: String, preserve_aspect: bool) -> SpriteRenderer2D {
+        SpriteRenderer2D {
+            texture: None,
+            atlas: Some(SpriteAtlas { atlas, region }),
+            material: Some(Material::new()),
+            material_name: None,
+            preserve_aspect,
+            sorting_layer: SortingLayer::Default,
+            order_in_layer: 0,
+            mask_interaction: MaskInteraction::None,
+        }
+    }
+
+    // Draw the sprite with a material file of assets/materials, see rendering::material
+    pub fn with_material(mut self, material_name: &str) -> Self {
+        self.material_name = Option::from(String::from(material_name));
+        self
+    }
 }
@@ -1,3 +1,5 @@
+pub mod animation;
+pub mod animation_controller;
 pub mod components;
 pub mod components_impl;
 pub mod config;
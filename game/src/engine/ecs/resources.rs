@@ -1,5 +1,6 @@
 use crate::engine::ecs::components::Transform;
-use crate::engine::utils::maths::{intersects, Rect};
+use crate::engine::utils::maths::{Frustum, Rect};
+use glm::vec3;
 use bevy_ecs::prelude::*;
 use std::cmp::PartialEq;
 
@@ -19,8 +20,19 @@ pub struct RenderingFrameData {
     pub updated_2d_render: Vec<Entity>,
     pub deleted_2d_render: Vec<Entity>,
 
+    pub new_cameras: Vec<Entity>,
     pub updated_camera_transform: Vec<Entity>,
     pub updated_camera_settings: Vec<Entity>,
+    pub updated_post_process: Vec<Entity>,
+    pub removed_cameras: Vec<Entity>, // Despawned or without their Camera component anymore
+}
+
+// Sent when a clip played once reaches its last frame. Events are kept for two frames, an
+// EventReader sees each of them once whatever its schedule or system order.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub clip: String,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -35,37 +47,80 @@ pub struct CameraCullingState {
     pub last_check_frame: f64,
 
     // this is done when the camera itself moved (Remove when QuadTree Implemented)
-    pub camera_entity: Option<Entity>,
-    pub camera_world_viewport: Rect<f32>,
+    pub camera_entity: Option<Entity>, // Main camera, spawned with the world
+    pub camera_frustums: Vec<(Entity, Frustum)>, // Volume seen by every camera
     pub force_full_pass: bool,
     pub entities: Vec<(Entity, CulledState)>,
 }
 
 impl CameraCullingState {
+    pub fn remove_camera_frustum(&mut self, camera: Entity) {
+        self.camera_frustums.retain(|(entity, _)| *entity != camera);
+        if self.camera_entity == Some(camera) {
+            self.camera_entity = None;
+        }
+    }
+
+    pub fn set_camera_frustum(&mut self, camera: Entity, frustum: Frustum) {
+        match self
+            .camera_frustums
+            .iter_mut()
+            .find(|(entity, _)| *entity == camera)
+        {
+            Some((_, camera_frustum)) => *camera_frustum = frustum,
+            None => self.camera_frustums.push((camera, frustum)),
+        }
+    }
+
+    // Frustums of the cameras, moved to their current position
+    pub fn get_camera_frustums<'a, F>(&self, get_transform: F) -> Vec<Frustum>
+    where
+        F: Fn(Entity) -> Option<&'a Transform>,
+    {
+        self.camera_frustums
+            .iter()
+            .filter_map(|(entity, frustum)| {
+                get_transform(*entity).map(|transform| Frustum {
+                    position: vec3(
+                        transform.position.x,
+                        transform.position.y,
+                        transform.position.z,
+                    ),
+                    ..*frustum
+                })
+            })
+            .collect()
+    }
+
     pub fn update_visibility(
         &mut self,
         entity: Entity,
-        camera_rect: Rect<f32>,
+        camera_frustums: &[Frustum],
         entity_transform: &Transform,
     ) {
         let frustum_state =
-            CameraCullingState::compute_visibility(self, camera_rect, entity_transform);
+            CameraCullingState::compute_visibility(self, camera_frustums, entity_transform);
         self.entities.push((entity, frustum_state));
     }
 
+    // Sprites seen by at least one camera are visible
     pub fn compute_visibility(
         &self,
-        camera_rect: Rect<f32>,
+        camera_frustums: &[Frustum],
         entity_transform: &Transform,
     ) -> CulledState {
-        // If no main camera entity has been register don't operate frustum computation
-        if self.camera_entity.is_none() {
+        // If no camera has been register don't operate frustum computation
+        if camera_frustums.is_empty() {
             return CulledState::Visible;
         }
 
         let sprite_rect = Rect::from(entity_transform);
+        let sprite_depth = entity_transform.position.z;
 
-        let frustum_state: CulledState = if intersects(camera_rect, sprite_rect) {
+        let frustum_state: CulledState = if camera_frustums
+            .iter()
+            .any(|frustum| frustum.intersects(sprite_rect, sprite_depth))
+        {
             CulledState::Visible
         } else {
             CulledState::Hidden
@@ -1,14 +1,18 @@
 use super::{
-    components::{Camera, SpriteRenderer2D, Transform},
+    animation::Animator,
+    animation_controller::AnimationController,
+    components::{Camera, PostProcessStack, SpriteRenderer2D, Transform},
     resources::RenderingFrameData,
 };
-use crate::engine::ecs::resources::CameraCullingState;
-use crate::engine::utils::maths::Rect;
+use crate::engine::ecs::resources::{AnimationFinished, CameraCullingState, Time};
+use crate::engine::utils::maths::Frustum;
 use bevy_ecs::query::Or;
 use bevy_ecs::{
     entity::Entity,
+    event::EventWriter,
     query::{Added, Changed},
-    system::{Query, ResMut},
+    removal_detection::RemovedComponents,
+    system::{Query, Res, ResMut},
 };
 
 pub fn changed_sprite_2d_system(
@@ -20,17 +24,17 @@ pub fn changed_sprite_2d_system(
         Or<(Changed<SpriteRenderer2D>, Changed<Transform>)>,
     >,
 ) {
-    // Fetch camera data, width and height can't change for now, but position need a refresh.
-    let (_, cam_tr) = camera_query.get(cull_state.camera_entity.unwrap()).unwrap();
-    let camera_rect: Rect<f32> = Rect {
-        x: cam_tr.position.x,
-        y: cam_tr.position.y,
-        ..cull_state.camera_world_viewport
-    };
+    // Cameras size is refreshed by the rendering bridge, but their position need a refresh.
+    let camera_frustums: Vec<Frustum> = cull_state.get_camera_frustums(|entity| {
+        camera_query
+            .get(entity)
+            .ok()
+            .map(|(_, transform)| transform)
+    });
 
     for (entity, transform, _sprite_renderer_2d) in sprites_query.iter_mut() {
         container.updated_2d_render.push(entity);
-        cull_state.update_visibility(entity, camera_rect, &transform);
+        cull_state.update_visibility(entity, &camera_frustums, &transform);
     }
 }
 
@@ -53,11 +57,6 @@ pub fn add_camera_2d_system(
     mut container: ResMut<RenderingFrameData>,
     mut query: Query<(Entity, &Transform, &Camera), Added<Camera>>,
 ) {
-    // todo! add multiple camera support
-    if query.iter().count() > 1 {
-        panic!("Only one camera is allowed in the scene");
-    }
-
     for (entity, _transform, camera) in query.iter_mut() {
         println!(
             "[Camera Entity]: entity {} added camera: fov {} near {} far {}",
@@ -66,11 +65,40 @@ pub fn add_camera_2d_system(
             camera.near,
             camera.far
         );
+        container.new_cameras.push(entity);
         container.updated_camera_transform.push(entity);
         container.updated_camera_settings.push(entity);
     }
 }
 
+// Follow the controllers transitions, runs before the animator system to show the new clip
+pub fn animation_controller_system(mut query: Query<(&mut AnimationController, &mut Animator)>) {
+    for (mut controller, mut animator) in query.iter_mut() {
+        if let Some(clip) = controller.update(animator.get_normalized_time()) {
+            animator.restart(&clip);
+        }
+    }
+}
+
+// Advance the animators and write their current frame in the sprite
+pub fn animator_system(
+    time: Res<Time>,
+    mut finished: EventWriter<AnimationFinished>,
+    mut query: Query<(Entity, &mut Animator, &mut SpriteRenderer2D)>,
+) {
+    for (entity, mut animator, mut sprite) in query.iter_mut() {
+        if animator.advance(time.delta_time) {
+            let clip = animator.get_current_clip().unwrap().name.clone();
+            finished.send(AnimationFinished { entity, clip });
+        }
+
+        // Only touch the sprite on frame changes, the bridge re-sends changed sprites
+        if !animator.is_frame_shown(&sprite) {
+            animator.apply_frame(&mut sprite);
+        }
+    }
+}
+
 /// Camera Update systems
 
 pub fn update_camera_transform_system(
@@ -93,4 +121,28 @@ pub fn update_camera_settings_system(
     });
 }
 
+// Removed stacks are sent too, their camera goes back to no effect
+pub fn update_post_process_system(
+    mut container: ResMut<RenderingFrameData>,
+    query: Query<Entity, Changed<PostProcessStack>>,
+    mut removed: RemovedComponents<PostProcessStack>,
+) {
+    query.iter().for_each(|entity| {
+        container.updated_post_process.push(entity);
+    });
+    removed.read().for_each(|entity| {
+        container.updated_post_process.push(entity);
+    });
+}
+
+// Cameras despawned or without their Camera component are removed from the renderer
+pub fn remove_camera_system(
+    mut container: ResMut<RenderingFrameData>,
+    mut removed: RemovedComponents<Camera>,
+) {
+    removed.read().for_each(|entity| {
+        container.removed_cameras.push(entity);
+    });
+}
+
 // End of camera Update systems
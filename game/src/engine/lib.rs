@@ -1,20 +1,33 @@
 pub mod runtime {
-    use bevy_ecs::schedule::Schedule;
+    use bevy_ecs::event::Events;
+    use bevy_ecs::schedule::{IntoSystemConfigs, Schedule};
 
     use crate::engine::ecs::resources::CameraCullingState;
     use crate::engine::{
         ecs::{
             components::{CameraBinding, Inputs},
             config::{EcsFixedUpdateSchedule, EcsLateUpdateSchedule, EcsUpdateSchedule},
-            resources::{RenderingFrameData, Time},
+            resources::{AnimationFinished, RenderingFrameData, Time},
             systems::{
-                add_camera_2d_system, add_sprite_2d_system, changed_sprite_2d_system,
-                update_camera_settings_system, update_camera_transform_system,
+                add_camera_2d_system, add_sprite_2d_system, animation_controller_system,
+                animator_system, changed_sprite_2d_system, update_camera_settings_system,
+                remove_camera_system, update_camera_transform_system, update_post_process_system,
             },
         },
         logging::{consts, logs::Logger, logs_traits::LoggerBase},
-        rendering::renderer::Renderer,
-        utils::{app_settings::ApplicationSettings, rendering_bridge::RenderingBridge},
+        platform::headless_platform::HeadlessPlatform,
+        rendering::{
+            atlas::TextureAtlas,
+            components::{DepthSortMode, FrameStats, RenderingMode},
+            gfx_device::GfxDevice,
+            gfx_recording::GfxDeviceRecording,
+            renderer::{Renderer, MAIN_CAMERA},
+        },
+        utils::{
+            app_settings::ApplicationSettings,
+            clock::{Clock, SystemClock},
+            rendering_bridge::RenderingBridge,
+        },
     };
     use bevy_ecs::world::World;
     use std::cell::{RefCell, RefMut};
@@ -24,6 +37,8 @@ pub mod runtime {
         _name: String,
         logs: Rc<dyn LoggerBase>,
         app_settings: ApplicationSettings,
+        clock: Box<dyn Clock>,
+        accumulated_time: f32,
 
         // Internal systems
         renderer: Option<Renderer>,
@@ -41,6 +56,8 @@ pub mod runtime {
                     log_type: String::from(name),
                 }),
                 app_settings: ApplicationSettings::default(),
+                clock: Box::new(SystemClock::new()),
+                accumulated_time: 0f32,
                 renderer: Option::None,
                 rendering_bridge: Option::None,
                 world: Option::None,
@@ -54,16 +71,78 @@ pub mod runtime {
                     log_type: String::from(&settings.app_name),
                 }),
                 app_settings: settings,
+                clock: Box::new(SystemClock::new()),
+                accumulated_time: 0f32,
                 renderer: Option::None,
                 rendering_bridge: None,
                 world: Option::None,
             }
         }
 
+        // Replace the frame time source, a FixedClock makes the simulation deterministic
+        pub fn set_clock(&mut self, clock: Box<dyn Clock>) -> &mut Self {
+            self.clock = clock;
+            self
+        }
+
+        pub fn set_rendering_mode(&mut self, mode: RenderingMode) -> &mut Self {
+            self.assert_warmed();
+            self.renderer.as_mut().unwrap().set_rendering_mode(mode);
+            self
+        }
+
+        // Z sorts the sprites by depth, Y draws the lowest sprites on top (top-down games)
+        pub fn set_depth_sort_mode(&mut self, mode: DepthSortMode) -> &mut Self {
+            self.assert_warmed();
+            self.renderer.as_mut().unwrap().set_depth_sort_mode(mode);
+            self
+        }
+
+        // Sprites reference the atlas by this name, it must end with ATLAS_EXTENSION
+        pub fn register_atlas(&mut self, atlas_name: &str, atlas: TextureAtlas) -> &mut Self {
+            self.assert_warmed();
+            self.renderer
+                .as_mut()
+                .unwrap()
+                .register_atlas(atlas_name, atlas);
+            self
+        }
+
+        // Draw calls & batching counters of the last rendered frame
+        pub fn get_frame_stats(&self) -> FrameStats {
+            self.assert_warmed();
+            self.renderer.as_ref().unwrap().get_frame_stats()
+        }
+
         pub fn warm(&mut self) -> &mut Self {
-            // Rendering
             let mut renderer: Renderer =
                 Renderer::init_with_glfw(&self.app_settings.window, self.logs.clone());
+            // Edited shaders are reloaded while the game runs, debug builds only
+            renderer.set_shader_hot_reload(cfg!(debug_assertions));
+            self.warm_with(renderer)
+        }
+
+        pub fn set_shader_hot_reload(&mut self, enabled: bool) -> &mut Self {
+            self.assert_warmed();
+            self.renderer
+                .as_mut()
+                .unwrap()
+                .set_shader_hot_reload(enabled);
+            self
+        }
+
+        // Runs the app without display, draw calls are recorded instead of reaching a GPU
+        pub fn warm_headless(&mut self, platform: HeadlessPlatform) -> &mut Self {
+            let recording = GfxDeviceRecording::new();
+            let shader_api = recording.shader_api();
+            let gfx_device = GfxDevice::new(Rc::new(recording), Rc::new(shader_api));
+            let renderer = Renderer::new(Box::new(platform), gfx_device, self.logs.clone());
+            self.warm_with(renderer)
+        }
+
+        pub fn warm_with(&mut self, renderer: Renderer) -> &mut Self {
+            // Rendering
+            let mut renderer: Renderer = renderer;
             renderer.warm();
             self.renderer = Option::from(renderer);
 
@@ -76,15 +155,20 @@ pub mod runtime {
             {
                 let mut world = self.world.as_mut().unwrap().borrow_mut();
 
-                let update_schedule = Schedule::new(EcsUpdateSchedule);
+                let mut update_schedule = Schedule::new(EcsUpdateSchedule);
                 let fixed_update_schedule = Schedule::new(EcsFixedUpdateSchedule);
                 let mut late_update_schedule = Schedule::new(EcsLateUpdateSchedule);
 
+                update_schedule
+                    .add_systems((animation_controller_system, animator_system).chain());
+
                 late_update_schedule.add_systems(changed_sprite_2d_system);
                 late_update_schedule.add_systems(add_sprite_2d_system);
                 late_update_schedule.add_systems(add_camera_2d_system);
                 late_update_schedule.add_systems(update_camera_settings_system);
                 late_update_schedule.add_systems(update_camera_transform_system);
+                late_update_schedule.add_systems(update_post_process_system);
+                late_update_schedule.add_systems(remove_camera_system);
 
                 world.add_schedule(update_schedule);
                 world.add_schedule(fixed_update_schedule);
@@ -98,22 +182,24 @@ pub mod runtime {
                     fixed_delta_time: 0.02f32,
                 });
 
+                world.init_resource::<Events<AnimationFinished>>();
+
                 world.insert_resource::<Inputs>(Inputs {
                     keyboard: self.renderer.as_ref().unwrap().get_keyboard_inputs(),
                 });
 
                 let main_entity_camera = { RenderingBridge::build_camera(&mut *world) };
                 world.insert_resource::<CameraBinding>(CameraBinding {
-                    cameras: vec![(main_entity_camera, 0u32)],
+                    cameras: vec![(main_entity_camera, MAIN_CAMERA)],
                 });
                 
                 let renderer = self.renderer.as_ref().unwrap();
-                let camera_viewport = renderer.get_world_camera_viewport();
+                let camera_frustum = renderer.get_camera_frustum(MAIN_CAMERA).unwrap();
 
                 world.insert_resource::<CameraCullingState>(CameraCullingState {
                     last_check_frame: 0.0,
                     camera_entity: Option::from(main_entity_camera),
-                    camera_world_viewport: camera_viewport,
+                    camera_frustums: vec![(main_entity_camera, camera_frustum)],
                     force_full_pass: false,
                     entities: Vec::with_capacity(1024),
                 });
@@ -123,8 +209,11 @@ pub mod runtime {
                     new_2d_render: Vec::new(),
                     updated_2d_render: Vec::with_capacity(200),
                     deleted_2d_render: Vec::new(),
+                    new_cameras: Vec::new(),
                     updated_camera_settings: Vec::new(),
                     updated_camera_transform: Vec::new(),
+                    updated_post_process: Vec::new(),
+                    removed_cameras: Vec::new(),
                 });
             }
 
@@ -132,66 +221,89 @@ pub mod runtime {
         }
 
         pub fn run(&mut self) -> Result<(), &'static str> {
-            assert!(
-                self.renderer.is_some(),
-                "Rendering has not been initialized"
-            );
-            assert!(self.world.is_some(), "ECS World has not been initialized");
-
-            let renderer: &mut Renderer = &mut self.renderer.as_mut().unwrap();
+            self.assert_warmed();
 
             let framerate = self.app_settings.target_frame_rate;
-            let mut time_point = std::time::Instant::now();
-            let mut accumulated_time = 0.0f32;
-            let fixed_delta_time = {
-                let world = self.world.as_mut().unwrap().borrow();
-                world.resource::<Time>().fixed_delta_time
-            };
+            self.clock.reset();
 
             // Game loop [WIP]
-            while !renderer.window.should_close() {
-                let mut world: RefMut<World> = self.world.as_mut().unwrap().borrow_mut();
-                renderer.poll_events();
-
-                let dt = std::time::Instant::elapsed(&time_point);
-                time_point = std::time::Instant::now();
+            while !self.renderer.as_ref().unwrap().should_close() {
+                let delta = self.step_frame();
 
-                let delta = dt.as_secs_f32();
-                let fixed_delta = fixed_delta_time;
+                let sleep_time: f32 = f32::max((1.0f32 / framerate) - delta, 0f32);
+                std::thread::sleep(Duration::from_secs_f32(sleep_time));
+            }
 
-                let mut time_world = world.resource_mut::<Time>();
-                time_world.delta_time = delta;
-                accumulated_time += delta;
-                time_world.time += delta as f64;
-                time_world.frames = time_world.frames + 1u64;
+            Ok(())
+        }
 
-                // Update game logic once
-                world.run_schedule(EcsUpdateSchedule);
+        // Runs exactly `frames` frames (or less if the platform closes) without frame pacing
+        pub fn run_frames(&mut self, frames: u64) -> Result<(), &'static str> {
+            self.assert_warmed();
+            self.clock.reset();
 
-                // Update physic&fixed at same time step.
-                while accumulated_time >= fixed_delta {
-                    accumulated_time -= fixed_delta;
-                    world.run_schedule(EcsFixedUpdateSchedule);
+            for _ in 0..frames {
+                if self.renderer.as_ref().unwrap().should_close() {
+                    break;
                 }
 
-                // Late update for UI.
-                world.run_schedule(EcsLateUpdateSchedule);
-                drop(world); // Free mutable ref because RenderingBridge hold a mut ref to World
+                self.step_frame();
+            }
 
-                // Bakes rendering commands
-                let rendering_bridge = self.rendering_bridge.as_mut().unwrap();
-                rendering_bridge.inject_new_rendering_entities(renderer);
-                rendering_bridge.flush_rendering_command_handles(renderer);
-                rendering_bridge.flush_camera_changes(renderer);
+            Ok(())
+        }
 
-                // Render and forward overflow time
-                renderer.render(accumulated_time);
+        fn assert_warmed(&self) {
+            assert!(
+                self.renderer.is_some(),
+                "Rendering has not been initialized"
+            );
+            assert!(self.world.is_some(), "ECS World has not been initialized");
+        }
 
-                let sleep_time: f32 = f32::max((1.0f32 / framerate) - delta, 0f32);
-                std::thread::sleep(Duration::from_secs_f32(sleep_time));
+        // Runs one full frame (events, schedules, rendering) and returns its delta time
+        fn step_frame(&mut self) -> f32 {
+            let renderer: &mut Renderer = self.renderer.as_mut().unwrap();
+            let mut world: RefMut<World> = self.world.as_mut().unwrap().borrow_mut();
+            renderer.poll_events();
+
+            let delta = self.clock.tick();
+
+            let mut time_world = world.resource_mut::<Time>();
+            let fixed_delta = time_world.fixed_delta_time;
+            time_world.delta_time = delta;
+            self.accumulated_time += delta;
+            time_world.time += delta as f64;
+            time_world.frames = time_world.frames + 1u64;
+
+            // Events sent two frames ago are dropped, the readers had a full frame to see them
+            world.resource_mut::<Events<AnimationFinished>>().update();
+
+            // Update game logic once
+            world.run_schedule(EcsUpdateSchedule);
+
+            // Update physic&fixed at same time step.
+            while self.accumulated_time >= fixed_delta {
+                self.accumulated_time -= fixed_delta;
+                world.run_schedule(EcsFixedUpdateSchedule);
             }
 
-            Ok(())
+            // Late update for UI.
+            world.run_schedule(EcsLateUpdateSchedule);
+            drop(world); // Free mutable ref because RenderingBridge hold a mut ref to World
+
+            // Bakes rendering commands, cameras first as sprites are culled against them
+            let rendering_bridge = self.rendering_bridge.as_mut().unwrap();
+            rendering_bridge.flush_camera_changes(renderer);
+            rendering_bridge.flush_lights(renderer);
+            rendering_bridge.flush_sprite_masks(renderer);
+            rendering_bridge.inject_new_rendering_entities(renderer);
+            rendering_bridge.flush_rendering_command_handles(renderer);
+
+            // Rendering time (shaders & hot reload) follows the real frame delta
+            renderer.render(delta);
+
+            delta
         }
     }
 }
@@ -4,3 +4,4 @@ pub mod utils;
 pub mod rendering;
 pub mod ecs;
 pub mod inputs;
+pub mod platform;
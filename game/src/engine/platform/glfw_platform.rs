@@ -0,0 +1,102 @@
+use super::platform_traits::{Platform, PlatformEvent};
+use crate::engine::utils::app_settings::{WindowMode, WindowSettings};
+use glfw::ffi::{glfwInit, glfwWindowHint};
+use glfw::{Context, Glfw, GlfwReceiver, PWindow, WindowEvent};
+
+pub struct GlfwPlatform {
+    pub instance: Glfw,
+    pub window: PWindow,
+    pub events: GlfwReceiver<(f64, WindowEvent)>,
+}
+
+impl GlfwPlatform {
+    pub fn init(settings: &WindowSettings) -> Self {
+        let mut instance = glfw::init(glfw::fail_on_errors).unwrap();
+
+        // Set the OpenGL version to 4.3 - todo! export this in opengl files
+        unsafe {
+            glfwInit();
+            glfwWindowHint(glfw::ffi::CONTEXT_VERSION_MAJOR, 4);
+            glfwWindowHint(glfw::ffi::CONTEXT_VERSION_MINOR, 3);
+
+            #[cfg(target_os = "macos")]
+            {
+                glfwWindowHint(glfw::ffi::OPENGL_PROFILE, glfw::ffi::OPENGL_CORE_PROFILE);
+                glfwWindowHint(glfw::ffi::OPENGL_FORWARD_COMPAT, glfw::ffi::TRUE);
+            }
+        }
+
+        let (mut window, events) = instance
+            .create_window(
+                settings.width,
+                settings.height,
+                "Game",
+                match settings.mode {
+                    WindowMode::Windowed => glfw::WindowMode::Windowed,
+                    _ => panic!(),
+                },
+            )
+            .expect("Failed to create window");
+
+        window.set_cursor_pos_polling(true);
+        window.set_key_polling(true);
+        window.set_framebuffer_size_polling(true);
+
+        Self {
+            instance,
+            window,
+            events,
+        }
+    }
+}
+
+impl Platform for GlfwPlatform {
+    fn poll_events(&mut self) -> Vec<PlatformEvent> {
+        self.instance.poll_events();
+
+        let mut events: Vec<PlatformEvent> = Vec::new();
+        for (_, event) in glfw::flush_messages(&self.events) {
+            match event {
+                WindowEvent::Key(key, _scan_code, action, modifier) => {
+                    events.push(PlatformEvent::Key(key, action, modifier));
+                }
+                WindowEvent::FramebufferSize(width, height) => {
+                    #[cfg(debug_assertions)]
+                    {
+                        let (w_factor, h_factor) = self.window.get_content_scale();
+                        println!(
+                            "Window framebuffer resized: {}x{} (scale factor {}x{})",
+                            width, height, w_factor, h_factor
+                        );
+                    }
+                    events.push(PlatformEvent::FramebufferResized(width, height));
+                }
+                WindowEvent::Close => events.push(PlatformEvent::CloseRequested),
+                _ => {}
+            }
+        }
+
+        events
+    }
+
+    fn should_close(&self) -> bool {
+        self.window.should_close()
+    }
+
+    fn set_should_close(&mut self, value: bool) {
+        self.window.set_should_close(value);
+    }
+
+    fn swap_buffers(&mut self) {
+        self.window.swap_buffers();
+    }
+
+    fn get_framebuffer_size(&self) -> (i32, i32) {
+        self.window.get_framebuffer_size()
+    }
+
+    fn load_gfx_functions(&mut self) {
+        // Load all function pointers from the graphic driver
+        gl::load_with(|procname: &str| self.window.get_proc_address(procname));
+    }
+}
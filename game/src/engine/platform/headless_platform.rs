@@ -0,0 +1,100 @@
+use super::platform_traits::{Platform, PlatformEvent};
+use std::collections::VecDeque;
+
+// Fixed size surface without window nor graphic context, events are scripted per frame index
+pub struct HeadlessPlatform {
+    width: i32,
+    height: i32,
+    frame: u64,
+    should_close: bool,
+    close_after: Option<u64>,
+    scripted_events: VecDeque<(u64, PlatformEvent)>,
+}
+
+impl HeadlessPlatform {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: width as i32,
+            height: height as i32,
+            frame: 0u64,
+            should_close: false,
+            close_after: None,
+            scripted_events: VecDeque::new(),
+        }
+    }
+
+    // Request the close of the platform once the given amount of frames has been presented
+    pub fn close_after(mut self, frames: u64) -> Self {
+        self.close_after = Option::from(frames);
+        self.should_close = frames == 0;
+        self
+    }
+
+    pub fn with_events(mut self, events: Vec<(u64, PlatformEvent)>) -> Self {
+        for (frame, event) in events {
+            self.push_event(frame, event);
+        }
+        self
+    }
+
+    // Schedule an event to be polled during the given frame (0 is the first frame)
+    pub fn push_event(&mut self, frame: u64, event: PlatformEvent) {
+        let index = self
+            .scripted_events
+            .iter()
+            .position(|(f, _)| *f > frame)
+            .unwrap_or(self.scripted_events.len());
+        self.scripted_events.insert(index, (frame, event));
+    }
+
+    pub fn presented_frames(&self) -> u64 {
+        self.frame
+    }
+}
+
+impl Platform for HeadlessPlatform {
+    fn poll_events(&mut self) -> Vec<PlatformEvent> {
+        let mut events: Vec<PlatformEvent> = Vec::new();
+
+        while let Some((frame, _)) = self.scripted_events.front() {
+            if *frame > self.frame {
+                break;
+            }
+
+            let (_, event) = self.scripted_events.pop_front().unwrap();
+            match event {
+                PlatformEvent::FramebufferResized(width, height) => {
+                    self.width = width;
+                    self.height = height;
+                }
+                PlatformEvent::CloseRequested => self.should_close = true,
+                _ => {}
+            }
+            events.push(event);
+        }
+
+        events
+    }
+
+    fn should_close(&self) -> bool {
+        self.should_close
+    }
+
+    fn set_should_close(&mut self, value: bool) {
+        self.should_close = value;
+    }
+
+    fn swap_buffers(&mut self) {
+        self.frame += 1;
+
+        if self.close_after.is_some_and(|frames| self.frame >= frames) {
+            self.should_close = true;
+        }
+    }
+
+    fn get_framebuffer_size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    fn load_gfx_functions(&mut self) {}
+}
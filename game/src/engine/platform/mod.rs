@@ -0,0 +1,3 @@
+pub mod platform_traits;
+pub mod glfw_platform;
+pub mod headless_platform;
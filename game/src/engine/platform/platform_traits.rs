@@ -0,0 +1,18 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlatformEvent {
+    Key(glfw::Key, glfw::Action, glfw::Modifiers),
+    FramebufferResized(i32, i32),
+    CloseRequested,
+}
+
+// Window + event source + swap chain, the renderer only talks to the OS through this trait
+pub trait Platform {
+    fn poll_events(&mut self) -> Vec<PlatformEvent>;
+    fn should_close(&self) -> bool;
+    fn set_should_close(&mut self, value: bool);
+    fn swap_buffers(&mut self);
+    fn get_framebuffer_size(&self) -> (i32, i32);
+
+    // Load the graphic driver function pointers once the context exists (no-op without context)
+    fn load_gfx_functions(&mut self);
+}
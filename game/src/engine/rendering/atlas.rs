@@ -0,0 +1,326 @@
+use super::shaders::Texture;
+use crate::engine::utils::maths::Rect;
+use glm::Vector4;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Texture atlas: many textures packed in a single GPU texture so the sprites using them can be
+// batched. Sprites reference a named region and sample it through a UV rect (offset & size).
+//
+// Atlases are described by a ".atlas" file in assets/textures, either listing the textures
+// packed at load time, or the regions of an existing sprite sheet:
+//
+//     # textures packed at load time, the region name defaults to the texture path
+//     texture Red/texture_08.png red
+//     texture Green/texture_02.png
+//
+//     # regions of a sprite sheet, in pixels from the top left corner of the image
+//     sheet Dark/texture_01.png
+//     region head 0 0 64 64
+//
+//     # or cells of the same size, named by their index from the top left cell, row by row
+//     sheet Dark/texture_02.png
+//     grid 4 2
+
+pub const ATLAS_EXTENSION: &str = ".atlas";
+const ATLAS_PADDING: u32 = 2; // Border texels are extruded in the padding to prevent bleeding
+const ATLAS_MAX_SIZE: u32 = 8192;
+
+// UV rect sampling the whole texture
+pub fn full_uv_rect() -> Vector4<f32> {
+    Vector4::new(0f32, 0f32, 1f32, 1f32)
+}
+
+#[derive(Debug, Clone)]
+pub struct TextureAtlas {
+    pub texture: Rc<Texture>,
+    regions: HashMap<String, Rect<u32>>, // In texels, from the bottom left like texture rows
+}
+
+pub struct AtlasPacker {
+    padding: u32,
+    max_size: u32,
+    entries: Vec<(String, Rc<Texture>)>,
+}
+
+impl TextureAtlas {
+    // Parse an atlas description, textures are fetched through the loader
+    pub fn from_description<F>(content: &str, mut load_texture: F) -> Result<TextureAtlas, String>
+    where
+        F: FnMut(&str) -> Result<Rc<Texture>, String>,
+    {
+        let mut packer = AtlasPacker::new();
+        let mut sheet: Option<Rc<Texture>> = None;
+        let mut sheet_regions: Vec<(String, Rect<u32>)> = vec![];
+        let mut grid: Option<(u32, u32)> = None;
+
+        for (index, line) in content.lines().enumerate() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let error = |message: &str| format!("[Atlas] line {}: {}", index + 1, message);
+
+            match tokens.as_slice() {
+                [] => continue,
+                [comment, ..] if comment.starts_with('#') => continue,
+                ["texture", path, name @ ..] if name.len() <= 1 => {
+                    let name = name.first().unwrap_or(path);
+                    packer.add(name, load_texture(path).map_err(|e| error(&e))?);
+                }
+                ["sheet", path] => {
+                    sheet = Option::from(load_texture(path).map_err(|e| error(&e))?);
+                }
+                ["region", name, bounds @ ..] if bounds.len() == 4 => {
+                    let bounds: Vec<u32> = bounds
+                        .iter()
+                        .map(|value| value.parse::<u32>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| error("region bounds must be positive integers"))?;
+                    sheet_regions.push((
+                        String::from(*name),
+                        Rect {
+                            x: bounds[0],
+                            y: bounds[1],
+                            width: bounds[2],
+                            height: bounds[3],
+                        },
+                    ));
+                }
+                ["grid", columns, rows] => match (columns.parse::<u32>(), rows.parse::<u32>()) {
+                    (Ok(columns), Ok(rows)) if columns > 0 && rows > 0 => {
+                        grid = Option::from((columns, rows));
+                    }
+                    _ => return Err(error("grid size must be strictly positive integers")),
+                },
+                _ => return Err(error(&format!("unknown entry \"{}\"", line.trim()))),
+            }
+        }
+
+        if let (Some(texture), Some((columns, rows))) = (sheet.as_ref(), grid) {
+            let (width, height) = (texture.width / columns, texture.height / rows);
+            for index in 0..columns * rows {
+                sheet_regions.push((
+                    index.to_string(),
+                    Rect {
+                        x: (index % columns) * width,
+                        y: (index / columns) * height,
+                        width,
+                        height,
+                    },
+                ));
+            }
+        }
+
+        match sheet {
+            Some(_) if !packer.is_empty() => Err(String::from(
+                "[Atlas] a sheet atlas can't also pack textures",
+            )),
+            Some(texture) => TextureAtlas::from_sheet(texture, sheet_regions),
+            None if !sheet_regions.is_empty() || grid.is_some() => {
+                Err(String::from("[Atlas] regions are declared without a sheet"))
+            }
+            None => packer.pack(),
+        }
+    }
+
+    // Regions are given from the top left corner of the image, like in image editors
+    pub fn from_sheet(
+        texture: Rc<Texture>,
+        regions: Vec<(String, Rect<u32>)>,
+    ) -> Result<TextureAtlas, String> {
+        let mut atlas = TextureAtlas {
+            texture,
+            regions: HashMap::new(),
+        };
+
+        for (name, rect) in regions {
+            if rect.x + rect.width > atlas.texture.width
+                || rect.y + rect.height > atlas.texture.height
+            {
+                return Err(format!("[Atlas] region {} is out of the sheet", name));
+            }
+
+            // Texture rows are flipped at load time, the origin is at the bottom left
+            let y = atlas.texture.height - rect.y - rect.height;
+            atlas.regions.insert(name, Rect { y, ..rect });
+        }
+
+        Ok(atlas)
+    }
+
+    pub fn get_region(&self, name: &str) -> Option<&Rect<u32>> {
+        self.regions.get(name)
+    }
+
+    pub fn get_regions_count(&self) -> usize {
+        self.regions.len()
+    }
+
+    // Offset (xy) and size (zw) of the region in normalized texture coordinates
+    pub fn get_uv_rect(&self, name: &str) -> Option<Vector4<f32>> {
+        let (width, height) = (self.texture.width as f32, self.texture.height as f32);
+
+        self.regions.get(name).map(|rect| {
+            Vector4::new(
+                rect.x as f32 / width,
+                rect.y as f32 / height,
+                rect.width as f32 / width,
+                rect.height as f32 / height,
+            )
+        })
+    }
+}
+
+impl Default for AtlasPacker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AtlasPacker {
+    pub fn new() -> Self {
+        Self {
+            padding: ATLAS_PADDING,
+            max_size: ATLAS_MAX_SIZE,
+            entries: vec![],
+        }
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn add(&mut self, name: &str, texture: Rc<Texture>) -> &mut Self {
+        self.entries.push((String::from(name), texture));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Shelf packing, the tallest textures are placed first on rows filled from left to right.
+    // The atlas starts at the smallest power of two fitting the textures area and grows until
+    // every texture fits.
+    pub fn pack(&self) -> Result<TextureAtlas, String> {
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        order.sort_by_key(|index| std::cmp::Reverse(self.entries[*index].1.height));
+
+        let area: u32 = self
+            .entries
+            .iter()
+            .map(|(_, texture)| self.padded_size(texture))
+            .map(|(width, height)| width * height)
+            .sum();
+        let widest = self
+            .entries
+            .iter()
+            .map(|(_, texture)| self.padded_size(texture).0)
+            .max()
+            .unwrap_or(1);
+
+        let side = ((area as f32).sqrt().ceil() as u32)
+            .max(widest)
+            .next_power_of_two();
+        let mut size = (side, side);
+
+        loop {
+            if size.0 > self.max_size || size.1 > self.max_size {
+                return Err(format!(
+                    "[Atlas] textures don't fit in a {}x{} atlas",
+                    self.max_size, self.max_size
+                ));
+            }
+
+            if let Some(placements) = self.place(&order, size) {
+                return Ok(self.blit(size, placements));
+            }
+
+            // Grow the height first, then the width to stay close to a square
+            if size.1 <= size.0 {
+                size.1 *= 2;
+            } else {
+                size.0 *= 2;
+            }
+        }
+    }
+
+    fn padded_size(&self, texture: &Texture) -> (u32, u32) {
+        (
+            texture.width + self.padding * 2,
+            texture.height + self.padding * 2,
+        )
+    }
+
+    fn place(&self, order: &[usize], size: (u32, u32)) -> Option<Vec<(usize, u32, u32)>> {
+        let mut placements = Vec::with_capacity(order.len());
+        let (mut x, mut y, mut row_height) = (0u32, 0u32, 0u32);
+
+        for index in order.iter() {
+            let (width, height) = self.padded_size(&self.entries[*index].1);
+
+            if x + width > size.0 {
+                x = 0;
+                y += row_height;
+                row_height = 0;
+            }
+            if x + width > size.0 || y + height > size.1 {
+                return None;
+            }
+
+            placements.push((*index, x + self.padding, y + self.padding));
+            x += width;
+            row_height = row_height.max(height);
+        }
+
+        Some(placements)
+    }
+
+    fn blit(&self, size: (u32, u32), placements: Vec<(usize, u32, u32)>) -> TextureAtlas {
+        let (width, height) = size;
+        let mut data = vec![0u8; (width * height * 4) as usize];
+        let mut regions = HashMap::new();
+        let padding = self.padding as i64;
+
+        for (index, x, y) in placements {
+            let (name, texture) = &self.entries[index];
+            let channels = texture.channels as usize;
+            let texel = |tx: i64, ty: i64| {
+                // Clamped to the edge, the padding repeats the border texels
+                let tx = tx.clamp(0, texture.width as i64 - 1) as usize;
+                let ty = ty.clamp(0, texture.height as i64 - 1) as usize;
+                let start = (ty * texture.width as usize + tx) * channels;
+                let source = &texture.data[start..start + channels];
+                let alpha = if channels == 4 { source[3] } else { 255u8 };
+                [source[0], source[1], source[2], alpha]
+            };
+
+            for ty in -padding..texture.height as i64 + padding {
+                for tx in -padding..texture.width as i64 + padding {
+                    let (ax, ay) = ((x as i64 + tx) as usize, (y as i64 + ty) as usize);
+                    let start = (ay * width as usize + ax) * 4;
+                    data[start..start + 4].copy_from_slice(&texel(tx, ty));
+                }
+            }
+
+            regions.insert(
+                name.clone(),
+                Rect {
+                    x,
+                    y,
+                    width: texture.width,
+                    height: texture.height,
+                },
+            );
+        }
+
+        TextureAtlas {
+            texture: Rc::new(Texture {
+                data,
+                width,
+                height,
+                channels: 4,
+            }),
+            regions,
+        }
+    }
+}
@@ -1,19 +1,35 @@
-use super::{renderer::RenderCmdHd, shaders::Material};
-use crate::engine::ecs::components::Transform;
+use super::{
+    renderer::{CameraHd, RenderCmdHd},
+    shaders::Material,
+};
+use crate::engine::ecs::components::{Projection, SortingLayer, Transform};
+use glm::{Matrix4, Vector2, Vector4};
 
 #[derive(Debug)]
 pub struct BufferSettings {
     pub keep_vertices: bool,
     pub vertex_size: i32,
     pub uvs_size: i32,
+    pub colors_size: i32,
+    pub instanced: bool, // Adds a per instance buffer holding a TRS (mat4) and a color (vec4)
 }
 
 #[derive(Debug)]
 pub struct FrameBuffer {
     pub self_handle: u32,
     pub texture_attachment: u32,
+    pub depth_attachment: u32, // 0 when the device has no depth & stencil buffer object
     pub width: i32,
     pub height: i32,
+    pub format: FramebufferFormat,
+}
+
+// Color attachment of a framebuffer, floating point colors are not clamped to [0; 1]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FramebufferFormat {
+    #[default]
+    Rgba8,
+    Rgba16F, // HDR cameras, values above 1 are kept until the tonemapping
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -23,16 +39,127 @@ pub struct ShaderStorageBuffer {
     pub count: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
+pub struct UniformBuffer {
+    pub self_handle: u32,
+    pub binding: u32,
+    pub count: usize, // floats count
+}
+
+#[derive(Debug, Clone)]
 pub struct RenderingCamera {
+    pub projection: Projection,
+    pub fov: f32, // Vertical, in degrees, perspective only
     pub near: f32,
     pub far: f32,
     pub ppu: u32,
     pub clear_color: ARGB8Color,
+    pub viewport: Vector4<f32>, // x, y, width, height of the screen area (Range is [0; 1])
+    pub order: i32,             // Cameras are rendered & composited from the lowest order
+    pub output_target: Option<RenderTarget>, // Offscreen cameras are not composited to the screen
+    pub hdr: bool,                           // Rgba16F framebuffers instead of Rgba8
+    pub ambient_light: Option<AmbientLight>, // Lit by the 2D lights when set
 
     pub transform: Transform,
 }
 
+// Offscreen texture rendered by a camera, sprites & materials sample it by its name
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderTarget {
+    pub name: String,
+    pub resolution: Option<(u32, u32)>, // Pixel size, the camera screen area when None
+}
+
+// Base light of a camera image, the 2D lights are added to it. Dark levels use a low intensity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientLight {
+    pub color: ARGB8Color,
+    pub intensity: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    #[default]
+    Point,
+    Spot { angle: f32 }, // Cone width in degrees, the light points toward its rotation
+    Global,              // Lights the whole image of every lit camera, added to their ambient
+}
+
+// 2D light gathered from the ECS every frame, in world units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderingLight {
+    pub kind: LightKind,
+    pub color: ARGB8Color,
+    pub intensity: f32,
+    pub radius: f32,
+    pub falloff: f32, // Exponent of the attenuation over the radius, 1 is linear
+    pub position: Vector2<f32>,
+    pub rotation: f32, // In degrees, 0 points toward +x
+    pub cast_shadows: bool,
+    pub shadow_softness: f32, // Width of the light source, wider penumbras as it grows
+}
+
+// Closed outline blocking the 2D lights, in world units
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderingShadowCaster {
+    pub points: Vec<Vector2<f32>>,
+}
+
+// Outline written in the stencil buffer, in world units
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderingSpriteMask {
+    pub points: Vec<Vector2<f32>>,
+}
+
+// How a sprite is clipped by the sprite masks of the frame
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MaskInteraction {
+    #[default]
+    None,
+    VisibleInsideMask,
+    VisibleOutsideMask,
+}
+
+// Stencil state of the next draws. Masks write 1 in the stencil without touching the colors
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum StencilMode {
+    #[default]
+    Disabled,
+    WriteMask,
+    VisibleInside,  // Stencil equals 1
+    VisibleOutside, // Stencil differs from 1
+}
+
+// Fullscreen effect applied to a camera image, see postprocess_fragment.shader
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostEffect {
+    Vignette { intensity: f32, smoothness: f32 }, // Smoothness is the faded part of the radius
+    ColorGrading { lut: String, intensity: f32 }, // LUT strip of size² x size texels
+    ChromaticAberration { intensity: f32 },       // Channels offset, in uvs at the borders
+    Blur { radius: f32 },                         // In pixels
+    Pixelation { cell_size: f32 },                // In pixels
+    Tonemapping { exposure: f32 },                // ACES filmic curve, HDR colors to [0; 1]
+    // Parts brighter than the threshold are blurred through a chain of half size mips
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        iterations: u32, // Mips count, limited by MAX_BLOOM_ITERATIONS
+    },
+}
+
+// Camera drawn by the renderer, the scene is rendered in its framebuffer then composited
+pub struct CameraPass {
+    pub handle: CameraHd,
+    pub camera: RenderingCamera,
+    pub framebuffer: Option<FrameBuffer>, // (Re)allocated when the camera pixel size changes
+    pub post_effects: Vec<PostEffect>,    // Applied in order, the last one writes the framebuffer
+    pub post_buffers: Vec<FrameBuffer>,   // Ping-pong buffers, the scene is drawn in the first one
+    pub bloom_buffers: Vec<FrameBuffer>,  // Downsampled mips, then the upsampled ones
+    pub light_buffers: Vec<FrameBuffer>,  // Unlit scene & light accumulation of lit cameras
+    pub matrices: (Matrix4<f32>, Matrix4<f32>), // View & Projection
+    pub updates_state: RenderingUpdateState,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct RenderingUpdateState {
     pub camera_settings: bool,
@@ -50,6 +177,8 @@ pub struct RenderRequest {
     pub mesh_info: MeshInfo,
     pub material: Material,
     pub transform: Transform,
+    pub sorting: SortingOrder,
+    pub mask_interaction: MaskInteraction,
 }
 
 pub struct RenderUpdate {
@@ -57,6 +186,41 @@ pub struct RenderUpdate {
     pub mesh_info: Option<MeshInfo>,
     pub material: Option<Material>,
     pub transform: Option<Transform>,
+    pub sorting: Option<SortingOrder>,
+    pub mask_interaction: Option<MaskInteraction>,
+}
+
+// Fields are compared in order: the sorting layer first, then the order inside the layer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SortingOrder {
+    pub layer: SortingLayer,
+    pub order_in_layer: i16,
+}
+
+// Axis used to draw commands back-to-front once layers and priorities are equal
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DepthSortMode {
+    #[default]
+    Z, // Higher z is farther from the camera
+    Y, // Top-down games, higher y is drawn behind
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderingMode {
+    Direct,  // One draw call per render command
+    Batched, // Contiguous commands sharing shader & texture are merged in a single draw call
+    Instanced, // Same grouping as batched, but drawn as instances of a single quad
+}
+
+// Counters of the last rendered frame, debug lines and framebuffer blit are not counted
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    pub draw_calls: u32,
+    pub batches: u32,
+    pub batched_commands: u32,
+    pub culled_commands: u32,
+    pub post_effects: u32, // Fullscreen passes of the cameras post process stacks
+    pub lit_cameras: u32,  // Cameras drawn with the light accumulation & combine passes
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -65,7 +229,7 @@ pub enum RenderState {
     Closed,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct ARGB8Color {
     pub r: u8,
     pub g: u8,
@@ -79,6 +243,30 @@ impl BufferSettings {
             keep_vertices: false,
             vertex_size: 3,
             uvs_size: 2,
+            colors_size: 0,
+            instanced: false,
+        }
+    }
+
+    // Interleaved world position, uvs and color, vertices are streamed every frame
+    pub fn sprite_batch() -> BufferSettings {
+        BufferSettings {
+            keep_vertices: false,
+            vertex_size: 3,
+            uvs_size: 2,
+            colors_size: 4,
+            instanced: false,
+        }
+    }
+
+    // Shared quad mesh drawn once per instance
+    pub fn sprite_instances() -> BufferSettings {
+        BufferSettings {
+            keep_vertices: false,
+            vertex_size: 3,
+            uvs_size: 2,
+            colors_size: 0,
+            instanced: true,
         }
     }
 }
@@ -103,6 +291,36 @@ impl ARGB8Color {
     }
 }
 
+impl Default for RenderingCamera {
+    // Full screen camera at the origin
+    fn default() -> Self {
+        RenderingCamera {
+            projection: Projection::Orthographic,
+            fov: 80.0,
+            near: 0.1,
+            far: 50.0,
+            ppu: 100u32,
+            clear_color: ARGB8Color::black(),
+            viewport: Vector4::new(0f32, 0f32, 1f32, 1f32),
+            order: 0,
+            output_target: None,
+            hdr: false,
+            ambient_light: None,
+            transform: Transform::default(),
+        }
+    }
+}
+
+impl From<MaskInteraction> for StencilMode {
+    fn from(interaction: MaskInteraction) -> Self {
+        match interaction {
+            MaskInteraction::None => StencilMode::Disabled,
+            MaskInteraction::VisibleInsideMask => StencilMode::VisibleInside,
+            MaskInteraction::VisibleOutsideMask => StencilMode::VisibleOutside,
+        }
+    }
+}
+
 impl RenderingUpdateState {
     pub fn reset(&mut self) {
         self.camera_settings = false;
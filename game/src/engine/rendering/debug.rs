@@ -1,10 +1,10 @@
-use crate::engine::ecs::components::Transform;
-use crate::engine::rendering::components::{RenderingCamera, ShaderStorageBuffer};
-use crate::engine::rendering::gfx_device::{BufferModule, GfxDevice, RenderCommand};
+use crate::engine::rendering::components::ShaderStorageBuffer;
+use crate::engine::rendering::gfx_device::{BufferModule, GfxDevice, RenderCommand, ShaderModule};
+use crate::engine::rendering::renderer_helpers::get_merged_files;
 use crate::engine::rendering::renderer_storage::RendererStorage;
 use crate::engine::rendering::shaders::{Material, ShaderInfo, ShaderType};
-use crate::engine::utils::maths::{compute_projection, compute_view_matrix, Grid, Rect};
-use glm::Vector4;
+use crate::engine::utils::maths::{identity_mat4, Grid};
+use glm::{Vector2, Vector4};
 
 const DEFAULT_GRID_WIDTH: i32 = 1000;
 const DEFAULT_GRID_HEIGHT: i32 = 1000;
@@ -15,6 +15,9 @@ pub struct Debug {}
 
 pub struct DebugGrid {
     pub lines: Vec<(RenderCommand, u32)>,
+    colors: Vec<Vector4<f32>>,
+    offset: Vector2<f32>,
+    files: Vec<String>, // Shaders & includes of the lines programs, see reload
 }
 
 impl Debug {
@@ -31,24 +34,15 @@ impl Debug {
             DEFAULT_GRID_THICKNESS,
         );
         let mut grid_lines: Vec<(RenderCommand, u32)> = vec![];
-
-        // load vertex shader
-        let v_info = ShaderInfo::with_name(String::from("line_vertex.shader"), ShaderType::Vertex);
-        let v_source = store.load_shader_content(&v_info);
-        let v_shad = device.alloc_shader(v_source.unwrap(), ShaderType::Vertex);
-
-        // load fragment shader
-        let f_info =
-            ShaderInfo::with_name(String::from("line_fragment.shader"), ShaderType::Fragment);
-        let f_source = store.load_shader_content(&f_info);
-        let f_shad = device.alloc_shader(f_source.unwrap(), ShaderType::Fragment);
+        let mut colors: Vec<Vector4<f32>> = vec![];
 
         // full iteration columns and rows
+        let lines_count = grid.columns.len() + grid.rows.len();
         let cols_and_rows = grid.columns.iter().chain(grid.rows.iter());
+        let (modules, files) = Self::alloc_line_programs(device, store, lines_count)
+            .unwrap_or_else(|err| panic!("[Debug] Could not build the grid programs: {}", err));
 
-        for (i, polyline) in cols_and_rows.enumerate() {
-            let shader_module = device.alloc_shader_module(v_shad, f_shad, &Material::new());
-
+        for ((i, polyline), shader_module) in cols_and_rows.enumerate().zip(modules) {
             // Update the color for x:0 and y:0 row and column to show the center of the grid
             let mut color = Vector4::new(0f32, 0f32, 0f32, 0.4f32);
             if i == grid.columns.len() / 2 {
@@ -56,23 +50,7 @@ impl Debug {
             } else if i == (grid.columns.len() + (grid.rows.len() / 2)) {
                 color = Vector4::new(0f32, 1f32, 0f32, 1f32);
             }
-
-            device
-                .shader_api
-                .set_attribute_f32(shader_module.self_handle, "thickness", 0.01f32);
-            device.shader_api.set_attribute_color(
-                shader_module.self_handle,
-                "surface_color",
-                color,
-            );
-            device.shader_api.set_attribute_vector2f(
-                shader_module.self_handle,
-                "offset",
-                &glm::Vector2::new(
-                    grid.columns.len() as f32 / 2f32,
-                    grid.rows.len() as f32 / 2f32,
-                ),
-            );
+            colors.push(color);
 
             let points = polyline
                 .points
@@ -91,6 +69,7 @@ impl Debug {
                     buffer_attributes: None,
                     vertices: None,
                     vertices_count: None,
+                    instance_buffer: None,
                 },
             );
 
@@ -99,36 +78,104 @@ impl Debug {
             grid_lines.push((render_cmd, vertex_count as u32));
         }
 
-        DebugGrid { lines: grid_lines }
+        let grid = DebugGrid {
+            lines: grid_lines,
+            colors,
+            offset: Vector2::new(
+                grid.columns.len() as f32 / 2f32,
+                grid.rows.len() as f32 / 2f32,
+            ),
+            files,
+        };
+        grid.apply_uniforms(device);
+        grid
+    }
+
+    // One program per line, the shaders are compiled once and linked to each of them
+    fn alloc_line_programs(
+        device: &GfxDevice,
+        store: &RendererStorage,
+        count: usize,
+    ) -> Result<(Vec<ShaderModule>, Vec<String>), String> {
+        let v_info = ShaderInfo::with_name(String::from("line_vertex.shader"), ShaderType::Vertex);
+        let f_info =
+            ShaderInfo::with_name(String::from("line_fragment.shader"), ShaderType::Fragment);
+        let v_source = store.load_shader_source(&v_info, &[], &[])?;
+        let f_source = store.load_shader_source(&f_info, &[], &[])?;
+
+        let files = get_merged_files(&[&v_source, &f_source]);
+
+        let v_shad = device.alloc_shader(v_source.source, ShaderType::Vertex)?;
+        let f_shad = match device.alloc_shader(f_source.source, ShaderType::Fragment) {
+            Ok(f_shad) => f_shad,
+            Err(err) => {
+                device.release_shader(v_shad);
+                return Err(err);
+            }
+        };
+
+        let mut modules: Vec<ShaderModule> = Vec::with_capacity(count);
+        for _ in 0..count {
+            match device.alloc_shader_module(v_shad, f_shad, &Material::new()) {
+                Ok(module) => modules.push(module),
+                Err(err) => {
+                    modules
+                        .into_iter()
+                        .for_each(|module| device.delete_shader_module(module));
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok((modules, files))
     }
 }
 
 impl DebugGrid {
-    pub fn draw(&self, device: &GfxDevice, camera: &RenderingCamera, rect: &Rect<u32>) {
-        let transform = Transform::default();
-        let trs_mat = &compute_view_matrix(&transform);
+    pub fn draw(&self, device: &GfxDevice) {
+        for (cmd, vertices) in self.lines.iter() {
+            device.use_shader_module(&cmd.shader_module);
+            device.draw_command(cmd, Option::from(*vertices as i32));
+        }
+    }
 
-        let view_matrix = compute_view_matrix(&camera.transform);
-        let proj_matrix = compute_projection(&camera, rect);
+    // Rebuild the lines programs when one of their files changed. The current programs are kept
+    // if the new ones fail to build.
+    pub fn reload(&mut self, device: &GfxDevice, store: &RendererStorage, changed: &[String]) {
+        if !self.files.iter().any(|file| changed.contains(file)) {
+            return;
+        }
 
-        for (cmd, vertices) in self.lines.iter() {
-            // Update GPU data
+        match Debug::alloc_line_programs(device, store, self.lines.len()) {
+            Ok((modules, files)) => {
+                for ((command, _), module) in self.lines.iter_mut().zip(modules) {
+                    let previous = std::mem::replace(&mut command.shader_module, module);
+                    device.delete_shader_module(previous);
+                }
+                self.files = files;
+                self.apply_uniforms(device);
+            }
+            Err(err) => println!("[Debug] Failed to reload the grid shaders: {}", err),
+        }
+    }
+
+    // Uniforms are set once per line program, the lines upload nothing per frame
+    fn apply_uniforms(&self, device: &GfxDevice) {
+        for ((command, _), color) in self.lines.iter().zip(self.colors.iter()) {
+            let program = command.shader_module.self_handle;
             device
                 .shader_api
-                .set_attribute_mat4(cmd.shader_module.self_handle, "TRS", trs_mat);
-            device.shader_api.set_attribute_mat4(
-                cmd.shader_module.self_handle,
-                "VIEW",
-                &view_matrix,
-            );
-            device.shader_api.set_attribute_mat4(
-                cmd.shader_module.self_handle,
-                "PROJ",
-                &proj_matrix,
-            );
-
-            // Draw the command
-            device.draw_command(cmd, Option::from(*vertices as i32));
+                .set_attribute_f32(program, "thickness", 0.01f32);
+            device
+                .shader_api
+                .set_attribute_color(program, "surface_color", *color);
+            // Lines are built in world space, VIEW & PROJ come from the FrameData block
+            device
+                .shader_api
+                .set_attribute_mat4(program, "TRS", &identity_mat4());
+            device
+                .shader_api
+                .set_attribute_vector2f(program, "offset", &self.offset);
         }
     }
 }
@@ -1,13 +1,27 @@
 use super::{
-    components::{BufferSettings, FrameBuffer},
+    atlas::full_uv_rect,
+    components::{BufferSettings, FrameBuffer, FramebufferFormat},
     renderer::RenderCmdHd,
     shaders::{Material, Texture},
 };
-use crate::engine::rendering::components::{ARGB8Color, ShaderStorageBuffer};
+use crate::engine::rendering::components::{
+    ARGB8Color, MaskInteraction, ShaderStorageBuffer, SortingOrder, StencilMode, UniformBuffer,
+};
 use crate::engine::rendering::shaders::ShaderType;
-use crate::engine::utils::maths::Rect;
+use crate::engine::utils::maths::{identity_mat4, Rect};
 use glm::{Matrix4, Vector2, Vector4};
-use std::{cell::RefCell, rc::Rc};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+// Per instance layout of instanced buffers: TRS (16 floats, column major), color (4 floats)
+// then the uv rect (4 floats)
+pub const INSTANCE_FLOATS: usize = 24;
+
+// FrameData uniform block shared by every program (std140): VIEW (16 floats), PROJ (16 floats),
+// resolution (2 floats), time (1 float) and one float of padding
+pub const FRAME_BLOCK_BINDING: u32 = 0;
+pub const FRAME_BLOCK_FLOATS: usize = 36;
 
 pub struct GfxDevice {
     instance: Rc<dyn GfxApiDevice>,
@@ -16,12 +30,26 @@ pub struct GfxDevice {
     pub shader_api: Rc<dyn GfxApiShader>,
 }
 
+// Uniform location resolved once for a program, T is the type of the uploaded value
+#[derive(Debug)]
+pub struct UniformHandle<T> {
+    pub program: u32,
+    pub location: i32,
+    value_type: PhantomData<T>,
+}
+
+// Uniform locations of every linked program, filled once at link time
+#[derive(Default)]
+pub struct UniformTable {
+    programs: HashMap<u32, HashMap<String, i32>>,
+}
+
 #[derive(Clone)]
 pub struct ShaderModule {
     pub self_handle: u32,
     pub vertex_handle: Option<u32>,   // they can be deleted already
     pub fragment_handle: Option<u32>, // they can be deleted already
-    pub texture_handles: Vec<u32>,    // Can be empty
+    pub texture_handles: Vec<u32>,    // By texture unit, 0 leaves the unit empty. Can be empty
     pub material: Material,
 }
 
@@ -33,6 +61,7 @@ pub struct BufferModule {
     pub buffer_attributes: Option<Vec<f32>>,
     pub vertices: Option<Vec<Vec<f32>>>,
     pub vertices_count: Option<Vec<u32>>,
+    pub instance_buffer: Option<u32>, // Per instance TRS & color, only allocated for instanced buffers
 }
 
 #[derive(Clone)]
@@ -41,6 +70,86 @@ pub struct RenderCommand {
     pub handle: RenderCmdHd,
     pub shader_module: ShaderModule,
     pub buffer_module: BufferModule,
+    pub trs: Matrix4<f32>, // CPU copy of the TRS uniform, used to bake vertices when batching
+    pub sorting: SortingOrder,
+    pub uv_rect: Vector4<f32>, // Sampled area of the texture, offset (xy) & size (zw)
+    pub mask_interaction: MaskInteraction,
+}
+
+impl ShaderModule {
+    // Units past the last texture are not bound at all
+    pub fn set_texture_handle(&mut self, unit: usize, handle: Option<u32>) {
+        if self.texture_handles.len() <= unit {
+            self.texture_handles.resize(unit + 1, 0u32);
+        }
+        self.texture_handles[unit] = handle.unwrap_or(0u32);
+
+        while self.texture_handles.last() == Some(&0u32) {
+            self.texture_handles.pop();
+        }
+    }
+}
+
+impl<T> UniformHandle<T> {
+    pub fn new(program: u32, location: i32) -> Self {
+        Self {
+            program,
+            location,
+            value_type: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for UniformHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for UniformHandle<T> {}
+
+impl UniformTable {
+    pub fn insert_program(&mut self, program: u32, uniforms: HashMap<String, i32>) {
+        self.programs.insert(program, uniforms);
+    }
+
+    pub fn remove_program(&mut self, program: u32) {
+        self.programs.remove(&program);
+    }
+
+    pub fn contains_program(&self, program: u32) -> bool {
+        self.programs.contains_key(&program)
+    }
+
+    pub fn get_location(&self, program: u32, identifier: &str) -> Option<i32> {
+        self.programs
+            .get(&program)
+            .and_then(|uniforms| uniforms.get(identifier).copied())
+    }
+
+    // Backends without reflection register the uniform names the first time they are resolved
+    pub fn get_or_insert_location(&mut self, program: u32, identifier: &str) -> i32 {
+        let uniforms = self.programs.entry(program).or_default();
+        let next_location = uniforms.len() as i32;
+        *uniforms
+            .entry(String::from(identifier))
+            .or_insert(next_location)
+    }
+
+    pub fn get_uniforms_count(&self, program: u32) -> usize {
+        self.programs
+            .get(&program)
+            .map_or(0, |uniforms| uniforms.len())
+    }
+
+    pub fn get_name(&self, program: u32, location: i32) -> Option<&String> {
+        self.programs.get(&program).and_then(|uniforms| {
+            uniforms
+                .iter()
+                .find(|(_, uniform_location)| **uniform_location == location)
+                .map(|(name, _)| name)
+        })
+    }
 }
 
 // ==============================
@@ -53,17 +162,41 @@ pub trait GfxApiShader {
     fn set_attribute_bool(&self, sp_hdl: u32, _identifier: &str, _value: bool);
     fn set_attribute_color(&self, sp_hdl: u32, _identifier: &str, _value: glm::Vec4);
     fn set_texture_unit(&self, prog_hdl: u32, texture_pos: i32);
+
+    // Uniform locations table, built when the program is linked and dropped with it
+    fn reflect_uniforms(&self, sp_hdl: u32);
+    fn release_uniforms(&self, sp_hdl: u32);
+    fn get_uniform_location(&self, sp_hdl: u32, identifier: &str) -> Option<i32>;
+
+    // Typed uploads through resolved handles, the program does not need to be bound
+    fn set_uniform_i32(&self, handle: &UniformHandle<i32>, value: i32);
+    fn set_uniform_f32(&self, handle: &UniformHandle<f32>, value: f32);
+    fn set_uniform_vector2f(&self, handle: &UniformHandle<Vector2<f32>>, vec: &Vector2<f32>);
+    fn set_uniform_mat4(&self, handle: &UniformHandle<Matrix4<f32>>, value: &Matrix4<f32>);
+    fn set_uniform_bool(&self, handle: &UniformHandle<bool>, value: bool);
+    fn set_uniform_color(&self, handle: &UniformHandle<Vector4<f32>>, value: Vector4<f32>);
 }
 
 pub trait GfxApiDevice {
     // ======================
     // Shaders
     // ======================
-    fn alloc_shader(&self, source: String, s_type: ShaderType) -> u32;
-    fn alloc_shader_module(&self, vertex: u32, frag: u32, material: &Material) -> ShaderModule;
+    // Err holds the compile log, nothing is allocated then
+    fn alloc_shader(&self, source: String, s_type: ShaderType) -> Result<u32, String>;
+    fn release_shader(&self, shader_handle: u32);
+    // The shaders are released once linked, whether it succeeds or not
+    fn alloc_shader_module(
+        &self,
+        vertex: u32,
+        frag: u32,
+        material: &Material,
+    ) -> Result<ShaderModule, String>;
     fn release_shader_module(&self, module_handle: u32);
     fn use_shader_module(&self, module_handle: u32);
     fn alloc_shader_storage_buffer(&self, data: &Vec<Vector4<f32>>) -> ShaderStorageBuffer;
+    fn alloc_uniform_buffer(&self, binding: u32, count: usize) -> UniformBuffer;
+    fn update_uniform_buffer(&self, buffer: &UniformBuffer, data: &[f32]);
+    fn bind_uniform_buffer(&self, buffer: &UniformBuffer);
 
     // ======================
     // Buffers
@@ -75,14 +208,23 @@ pub trait GfxApiDevice {
         settings: BufferSettings,
     ) -> BufferModule;
     fn release_buffer(&self, module: BufferModule);
-    fn alloc_framebuffer(&self, width: i32, height: i32) -> Result<FrameBuffer, &str>;
+    fn update_buffer(&self, module: &BufferModule, vertices: &[f32]);
+    fn update_instance_buffer(&self, module: &BufferModule, instances: &[f32]);
+    fn alloc_framebuffer(
+        &self,
+        width: i32,
+        height: i32,
+        format: FramebufferFormat,
+    ) -> Result<FrameBuffer, &str>;
+    fn release_framebuffer(&self, framebuffer: FrameBuffer);
     fn use_framebuffer(&self, framebuffer: Option<&FrameBuffer>);
     fn blit_main_framebuffer(&self, buffer_module: &BufferModule, framebuffer: &FrameBuffer);
+    fn draw_fullscreen_pass(&self, buffer_module: &BufferModule, textures: &[u32]); // Bound program
 
     // ======================
     // Textures
     // ======================
-    fn alloc_framebuffer_texture(&self, width: i32, height: i32) -> u32;
+    fn alloc_framebuffer_texture(&self, width: i32, height: i32, format: FramebufferFormat) -> u32;
     fn alloc_texture(&self, sp_hdl: u32, texture: &Texture) -> u32;
     fn release_texture(&self, tex_id: u32);
 
@@ -90,15 +232,13 @@ pub trait GfxApiDevice {
     // Drawing
     // ======================
     fn draw_command(&self, command: &RenderCommand, procedural: Option<i32>);
+    fn draw_command_instanced(&self, command: &RenderCommand, instances: i32);
     fn clear_color(&self, color: ARGB8Color);
     fn update_viewport(&self, x: u32, y: u32, width: u32, height: u32);
-    fn set_update_viewport_callback(
-        &self,
-        window: &mut glfw::Window,
-        viewport: RefCell<glm::Vector4<f32>>,
-    );
     fn clear_buffers(&self);
     fn enable_blending(&self); 
+    fn set_depth_test(&self, enabled: bool); // Reset when a framebuffer is bound
+    fn set_stencil_mode(&self, mode: StencilMode); // Reset when a framebuffer is bound
 }
 
 impl GfxDevice {
@@ -119,21 +259,72 @@ impl GfxDevice {
             .blit_main_framebuffer(screen_module, framebuffer);
     }
 
-    pub fn alloc_framebuffer(&self, width: i32, height: i32) -> FrameBuffer {
+    // Draws the quad with the bound program, textures are bound to the units 0..n
+    pub fn draw_fullscreen_pass(&self, screen_module: &BufferModule, textures: &[u32]) {
+        self.instance.draw_fullscreen_pass(screen_module, textures);
+    }
+
+    // Releases the framebuffer and its attachments
+    pub fn release_framebuffer(&self, framebuffer: FrameBuffer) {
+        self.instance.release_framebuffer(framebuffer);
+    }
+
+    pub fn alloc_framebuffer(
+        &self,
+        width: i32,
+        height: i32,
+        format: FramebufferFormat,
+    ) -> FrameBuffer {
         self.instance
-            .alloc_framebuffer(width, height)
+            .alloc_framebuffer(width, height, format)
             .expect(&format!(
                 "[Gfx Device] Failed to allocate framebuffer (w: {}, h: {})",
                 width, height
             ))
     }
 
-    pub fn alloc_shader(&self, source: String, s_type: ShaderType) -> u32 {
+    pub fn alloc_shader(&self, source: String, s_type: ShaderType) -> Result<u32, String> {
         self.instance.alloc_shader(source, s_type)
     }
 
-    pub fn alloc_shader_module(&self, vertex: u32, frag: u32, material: &Material) -> ShaderModule {
-        self.instance.alloc_shader_module(vertex, frag, material)
+    pub fn alloc_shader_module(
+        &self,
+        vertex: u32,
+        frag: u32,
+        material: &Material,
+    ) -> Result<ShaderModule, String> {
+        let module = self.instance.alloc_shader_module(vertex, frag, material)?;
+        self.shader_api.reflect_uniforms(module.self_handle);
+        Ok(module)
+    }
+
+    pub fn release_shader(&self, shader_handle: u32) {
+        self.instance.release_shader(shader_handle);
+    }
+
+    // Compile & link both sources, nothing is left allocated when one of them fails
+    pub fn alloc_program(
+        &self,
+        vertex_source: String,
+        fragment_source: String,
+        material: &Material,
+    ) -> Result<ShaderModule, String> {
+        let vertex = self.alloc_shader(vertex_source, ShaderType::Vertex)?;
+        let fragment = match self.alloc_shader(fragment_source, ShaderType::Fragment) {
+            Ok(fragment) => fragment,
+            Err(err) => {
+                self.release_shader(vertex);
+                return Err(err);
+            }
+        };
+        self.alloc_shader_module(vertex, fragment, material)
+    }
+
+    // Resolve the uniform location once, the handle can then be reused for every upload
+    pub fn get_uniform_handle<T>(&self, sp_hdl: u32, identifier: &str) -> Option<UniformHandle<T>> {
+        self.shader_api
+            .get_uniform_location(sp_hdl, identifier)
+            .map(|location| UniformHandle::new(sp_hdl, location))
     }
 
     pub fn use_shader_module(&self, module: &ShaderModule) {
@@ -141,7 +332,12 @@ impl GfxDevice {
     }
 
     pub fn delete_shader_module(&self, module: ShaderModule) {
-        self.instance.release_shader_module(module.self_handle);
+        self.release_program(module.self_handle);
+    }
+
+    pub fn release_program(&self, program: u32) {
+        self.shader_api.release_uniforms(program);
+        self.instance.release_shader_module(program);
     }
 
     pub fn alloc_texture(&self, sp_hdl: u32, texture: &Texture) -> u32 {
@@ -168,10 +364,34 @@ impl GfxDevice {
         self.instance.release_buffer(module)
     }
 
+    // Replace the whole content of the module vertex buffer
+    pub fn update_buffer(&self, module: &BufferModule, vertices: &[f32]) {
+        self.instance.update_buffer(module, vertices)
+    }
+
+    // Replace the per instance data, see INSTANCE_FLOATS for the layout
+    pub fn update_instance_buffer(&self, module: &BufferModule, instances: &[f32]) {
+        self.instance.update_instance_buffer(module, instances)
+    }
+
     pub fn alloc_shader_storage_buffer(&self, data: &Vec<Vector4<f32>>) -> ShaderStorageBuffer {
         self.instance.alloc_shader_storage_buffer(data)
     }
 
+    pub fn alloc_uniform_buffer(&self, binding: u32, count: usize) -> UniformBuffer {
+        self.instance.alloc_uniform_buffer(binding, count)
+    }
+
+    // Replace the whole content of the uniform buffer, data must hold buffer.count floats
+    pub fn update_uniform_buffer(&self, buffer: &UniformBuffer, data: &[f32]) {
+        self.instance.update_uniform_buffer(buffer, data)
+    }
+
+    // Bind the buffer to its block binding point, it is then visible to every program
+    pub fn bind_uniform_buffer(&self, buffer: &UniformBuffer) {
+        self.instance.bind_uniform_buffer(buffer)
+    }
+
     // ======================
     // Drawing
     // ======================
@@ -188,6 +408,10 @@ impl GfxDevice {
             initialized: true,
             shader_module: shad_mod,
             buffer_module: buff_mod,
+            trs: identity_mat4(),
+            sorting: SortingOrder::default(),
+            uv_rect: full_uv_rect(),
+            mask_interaction: MaskInteraction::None,
         }
     }
 
@@ -195,19 +419,15 @@ impl GfxDevice {
         self.instance.draw_command(command, procedural);
     }
 
+    pub fn draw_command_instanced(&self, command: &RenderCommand, instances: i32) {
+        self.instance.draw_command_instanced(command, instances);
+    }
+
     pub fn update_viewport(&self, vp_rect: Rect<u32>) {
         self.instance
             .update_viewport(vp_rect.x, vp_rect.y, vp_rect.width, vp_rect.height);
     }
 
-    pub fn set_update_viewport_callback(
-        &self,
-        window: &mut glfw::Window,
-        viewport: RefCell<glm::Vector4<f32>>,
-    ) {
-        self.instance.set_update_viewport_callback(window, viewport);
-    }
-
     pub fn clear(&self, color: ARGB8Color) {
         self.instance.clear_color(color);
         self.instance.clear_buffers();
@@ -216,4 +436,12 @@ impl GfxDevice {
     pub fn enable_blending(&self) {
         self.instance.enable_blending();
     }
+
+    pub fn set_depth_test(&self, enabled: bool) {
+        self.instance.set_depth_test(enabled);
+    }
+
+    pub fn set_stencil_mode(&self, mode: StencilMode) {
+        self.instance.set_stencil_mode(mode);
+    }
 }
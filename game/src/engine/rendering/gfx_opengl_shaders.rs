@@ -1,43 +1,29 @@
-use super::gfx_device::GfxApiShader;
-use glm::{Matrix4, Vector2};
+use super::gfx_device::{GfxApiShader, UniformHandle, UniformTable};
+use glm::{Matrix4, Vector2, Vector4};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::CString;
 
+// Uniforms are uploaded with glProgramUniform*, the program does not need to be bound and no
+// location is queried from the driver after the program reflection done at link time.
 #[derive(Default)]
-pub struct GfxOpenGLShaderApi;
+pub struct GfxOpenGLShaderApi {
+    uniforms: RefCell<UniformTable>,
+}
 
 impl GfxApiShader for GfxOpenGLShaderApi {
     fn set_attribute_i32(&self, prog_hdl: u32, identifier: &str, value: i32) {
-        println!(
-            "Setting attribute: {} to {} [{} handle]",
-            identifier, value, prog_hdl
-        );
-
-        unsafe {
-            gl::UseProgram(prog_hdl);
-        }
-
-        match self.get_uniform_location(prog_hdl, identifier) {
-            Ok(location) => unsafe {
-                gl::Uniform1i(location, value);
-            },
+        match self.get_location(prog_hdl, identifier) {
+            Ok(location) => self.set_uniform_i32(&UniformHandle::new(prog_hdl, location), value),
             Err(e) => {
                 println!("[Shader API Error]: {}", e);
             }
         }
-        unsafe {
-            gl::UseProgram(0);
-        }
     }
 
     fn set_attribute_f32(&self, prog_hdl: u32, identifier: &str, value: f32) {
-        unsafe {
-            gl::UseProgram(prog_hdl);
-        }
-
-        match self.get_uniform_location(prog_hdl, identifier) {
-            Ok(location) => unsafe {
-                gl::Uniform1f(location, value);
-            },
+        match self.get_location(prog_hdl, identifier) {
+            Ok(location) => self.set_uniform_f32(&UniformHandle::new(prog_hdl, location), value),
             Err(e) => {
                 println!("[Shader API Error]: {}", e);
             }
@@ -45,15 +31,8 @@ impl GfxApiShader for GfxOpenGLShaderApi {
     }
 
     fn set_attribute_vector2f(&self, sp_hdl: u32, identifier: &str, vec: &Vector2<f32>) {
-        unsafe {
-            gl::UseProgram(sp_hdl);
-        }
-
-        let location: Result<i32, String> = self.get_uniform_location(sp_hdl, identifier);
-        match location {
-            Ok(uniform_id) => unsafe {
-                gl::Uniform2fv(uniform_id, 1, vec.as_array().as_ptr().cast());
-            },
+        match self.get_location(sp_hdl, identifier) {
+            Ok(location) => self.set_uniform_vector2f(&UniformHandle::new(sp_hdl, location), vec),
             Err(err) => {
                 println!("[OpenGl Shader]: Failed to get uniform location {}", &err);
             }
@@ -61,20 +40,8 @@ impl GfxApiShader for GfxOpenGLShaderApi {
     }
 
     fn set_attribute_mat4(&self, sp_hdl: u32, identifier: &str, mat: &Matrix4<f32>) {
-        unsafe {
-            gl::UseProgram(sp_hdl);
-        }
-
-        let location: Result<i32, String> = self.get_uniform_location(sp_hdl, identifier);
-        match location {
-            Ok(mvp_matrix_id) => unsafe {
-                gl::UniformMatrix4fv(
-                    mvp_matrix_id,
-                    1,
-                    gl::FALSE,
-                    mat.as_array().as_ptr() as *const f32,
-                );
-            },
+        match self.get_location(sp_hdl, identifier) {
+            Ok(location) => self.set_uniform_mat4(&UniformHandle::new(sp_hdl, location), mat),
             Err(err) => {
                 println!("[OpenGl Shader]: Failed to get uniform location {}", &err)
             }
@@ -82,14 +49,8 @@ impl GfxApiShader for GfxOpenGLShaderApi {
     }
 
     fn set_attribute_bool(&self, prog_hdl: u32, identifier: &str, value: bool) {
-        unsafe {
-            gl::UseProgram(prog_hdl);
-        }
-
-        match self.get_uniform_location(prog_hdl, identifier) {
-            Ok(location) => unsafe {
-                gl::Uniform1i(location, if value == true { 1 } else { 0 });
-            },
+        match self.get_location(prog_hdl, identifier) {
+            Ok(location) => self.set_uniform_bool(&UniformHandle::new(prog_hdl, location), value),
             Err(e) => {
                 println!("[Shader API Error]: {}", e);
             }
@@ -97,17 +58,8 @@ impl GfxApiShader for GfxOpenGLShaderApi {
     }
 
     fn set_attribute_color(&self, prog_hdl: u32, identifier: &str, value: glm::Vec4) {
-        unsafe {
-            gl::UseProgram(prog_hdl);
-        }
-
-        match self.get_uniform_location(prog_hdl, identifier) {
-            Ok(location) => {
-                let vec: [f32; 4] = *value.as_array();
-                unsafe {
-                    gl::Uniform4fv(location, 1, vec.as_ptr());
-                }
-            }
+        match self.get_location(prog_hdl, identifier) {
+            Ok(location) => self.set_uniform_color(&UniformHandle::new(prog_hdl, location), value),
             Err(e) => {
                 println!("[Shader API Error]: {}", e);
             }
@@ -115,42 +67,126 @@ impl GfxApiShader for GfxOpenGLShaderApi {
     }
 
     fn set_texture_unit(&self, prog_hdl: u32, texture_id: i32) {
-        unsafe {
-            gl::UseProgram(prog_hdl);
-        }
-
         let location: Result<i32, String> =
-            self.get_uniform_location(prog_hdl, &format!("texture{}", texture_id));
+            self.get_location(prog_hdl, &format!("texture{}", texture_id));
 
         match location {
-            Ok(tex_location) => unsafe {
-                gl::Uniform1i(tex_location, texture_id);
-            },
+            Ok(tex_location) => {
+                self.set_uniform_i32(&UniformHandle::new(prog_hdl, tex_location), texture_id)
+            }
             Err(err) => {
                 println!("[OpenGl Shader]: Failed to get uniform location {}", &err)
             }
         }
     }
-}
 
-impl GfxOpenGLShaderApi {
-    fn get_uniform_location(&self, prog_hdl: u32, identifier: &str) -> Result<i32, String> {
-        let c_string = CString::new(identifier).unwrap();
+    fn reflect_uniforms(&self, sp_hdl: u32) {
+        let mut uniforms: HashMap<String, i32> = HashMap::new();
 
         unsafe {
-            let location: i32 = gl::GetUniformLocation(prog_hdl, c_string.as_ptr());
-            if location == -1 {
-                let mut count: i32 = 0;
-                gl::GetProgramiv(prog_hdl, gl::ACTIVE_UNIFORMS, &mut count);
-
-                let err = format!(
-                    "Uniform location not found: {} (Uniforms count found in shader {})",
-                    identifier, count
+            let mut count: i32 = 0;
+            let mut max_length: i32 = 0;
+            gl::GetProgramiv(sp_hdl, gl::ACTIVE_UNIFORMS, &mut count);
+            gl::GetProgramiv(sp_hdl, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+            let mut name_buffer: Vec<u8> = vec![0u8; max_length.max(1) as usize];
+            for index in 0..count {
+                let (mut length, mut size, mut uniform_type) = (0i32, 0i32, 0u32);
+                gl::GetActiveUniform(
+                    sp_hdl,
+                    index as u32,
+                    max_length,
+                    &mut length,
+                    &mut size,
+                    &mut uniform_type,
+                    name_buffer.as_mut_ptr().cast(),
                 );
 
-                return Err(err);
+                let name = String::from_utf8_lossy(&name_buffer[..length as usize]).to_string();
+                let c_string = CString::new(name.as_str()).unwrap();
+                let location = gl::GetUniformLocation(sp_hdl, c_string.as_ptr());
+
+                // Members of uniform blocks (FrameData) are active but have no location
+                if location != -1 {
+                    uniforms.insert(String::from(name.trim_end_matches("[0]")), location);
+                }
             }
-            Ok(location)
         }
+
+        self.uniforms.borrow_mut().insert_program(sp_hdl, uniforms);
+    }
+
+    fn release_uniforms(&self, sp_hdl: u32) {
+        self.uniforms.borrow_mut().remove_program(sp_hdl);
+    }
+
+    fn get_uniform_location(&self, sp_hdl: u32, identifier: &str) -> Option<i32> {
+        self.get_location(sp_hdl, identifier).ok()
+    }
+
+    fn set_uniform_i32(&self, handle: &UniformHandle<i32>, value: i32) {
+        unsafe {
+            gl::ProgramUniform1i(handle.program, handle.location, value);
+        }
+    }
+
+    fn set_uniform_f32(&self, handle: &UniformHandle<f32>, value: f32) {
+        unsafe {
+            gl::ProgramUniform1f(handle.program, handle.location, value);
+        }
+    }
+
+    fn set_uniform_vector2f(&self, handle: &UniformHandle<Vector2<f32>>, vec: &Vector2<f32>) {
+        unsafe {
+            gl::ProgramUniform2fv(
+                handle.program,
+                handle.location,
+                1,
+                vec.as_array().as_ptr().cast(),
+            );
+        }
+    }
+
+    fn set_uniform_mat4(&self, handle: &UniformHandle<Matrix4<f32>>, mat: &Matrix4<f32>) {
+        unsafe {
+            gl::ProgramUniformMatrix4fv(
+                handle.program,
+                handle.location,
+                1,
+                gl::FALSE,
+                mat.as_array().as_ptr() as *const f32,
+            );
+        }
+    }
+
+    fn set_uniform_bool(&self, handle: &UniformHandle<bool>, value: bool) {
+        unsafe {
+            gl::ProgramUniform1i(handle.program, handle.location, value as i32);
+        }
+    }
+
+    fn set_uniform_color(&self, handle: &UniformHandle<Vector4<f32>>, value: Vector4<f32>) {
+        let vec: [f32; 4] = *value.as_array();
+        unsafe {
+            gl::ProgramUniform4fv(handle.program, handle.location, 1, vec.as_ptr());
+        }
+    }
+}
+
+impl GfxOpenGLShaderApi {
+    fn get_location(&self, prog_hdl: u32, identifier: &str) -> Result<i32, String> {
+        // Programs linked outside of the GfxDevice are reflected on their first upload
+        if !self.uniforms.borrow().contains_program(prog_hdl) {
+            self.reflect_uniforms(prog_hdl);
+        }
+
+        let uniforms = self.uniforms.borrow();
+        uniforms.get_location(prog_hdl, identifier).ok_or_else(|| {
+            format!(
+                "Uniform location not found: {} (Uniforms count found in shader {})",
+                identifier,
+                uniforms.get_uniforms_count(prog_hdl)
+            )
+        })
     }
 }
@@ -0,0 +1,856 @@
+use super::components::{
+    ARGB8Color, BufferSettings, FrameBuffer, FramebufferFormat, ShaderStorageBuffer,
+    StencilMode, UniformBuffer,
+};
+use super::gfx_device::{
+    BufferModule, GfxApiDevice, GfxApiShader, RenderCommand, ShaderModule, UniformHandle,
+    UniformTable,
+};
+use super::renderer::RenderCmdHd;
+use super::shader_preprocessor::get_error_directive_log;
+use super::shaders::{Material, ShaderType, Texture};
+use glm::{Matrix4, Vector2, Vector4};
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+// Recording backend: no GPU work is done, handles are fake and every call is logged so tests
+// can assert on what the renderer would have sent to the graphic driver.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GfxHandleKind {
+    Shader,
+    Program,
+    VertexArray,
+    Buffer,
+    Texture,
+    Framebuffer,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniformValue {
+    I32(i32),
+    F32(f32),
+    Bool(bool),
+    Vec2(Vector2<f32>),
+    Vec4(Vector4<f32>),
+    Mat4(Matrix4<f32>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GfxCall {
+    AllocShader {
+        handle: u32,
+        shader_type: ShaderType,
+    },
+    ReleaseShader {
+        handle: u32,
+    },
+    AllocShaderModule {
+        handle: u32,
+        vertex: u32,
+        fragment: u32,
+    },
+    ReleaseShaderModule {
+        handle: u32,
+    },
+    UseShaderModule {
+        handle: u32,
+    },
+    AllocShaderStorageBuffer {
+        vao: u32,
+        handle: u32,
+        count: usize,
+    },
+    AllocUniformBuffer {
+        handle: u32,
+        binding: u32,
+        floats_count: usize,
+    },
+    UpdateUniformBuffer {
+        handle: u32,
+        floats_count: usize,
+    },
+    BindUniformBuffer {
+        handle: u32,
+        binding: u32,
+    },
+    AllocBuffer {
+        vao: u32,
+        buffers: Vec<u32>,
+        vertices_count: Vec<u32>,
+    },
+    ReleaseBuffer {
+        vao: u32,
+    },
+    UpdateBuffer {
+        vao: u32,
+        floats_count: usize,
+    },
+    UpdateInstanceBuffer {
+        vao: u32,
+        floats_count: usize,
+    },
+    AllocFramebuffer {
+        handle: u32,
+        texture: u32,
+        width: i32,
+        height: i32,
+        format: FramebufferFormat,
+    },
+    ReleaseFramebuffer {
+        handle: u32,
+    },
+    UseFramebuffer {
+        handle: Option<u32>,
+    },
+    BlitMainFramebuffer {
+        vao: u32,
+        framebuffer: u32,
+    },
+    DrawFullscreenPass {
+        vao: u32,
+        textures: Vec<u32>,
+    },
+    AllocFramebufferTexture {
+        handle: u32,
+        width: i32,
+        height: i32,
+        format: FramebufferFormat,
+    },
+    AllocTexture {
+        handle: u32,
+        program: u32,
+        width: u32,
+        height: u32,
+    },
+    ReleaseTexture {
+        handle: u32,
+    },
+    DrawCommand {
+        command: RenderCmdHd,
+        program: u32,
+        vao: u32,
+        textures: Vec<u32>,
+        procedural: Option<i32>,
+    },
+    DrawCommandInstanced {
+        command: RenderCmdHd,
+        program: u32,
+        vao: u32,
+        textures: Vec<u32>,
+        instances: i32,
+    },
+    ClearColor {
+        color: ARGB8Color,
+    },
+    UpdateViewport {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    ClearBuffers,
+    EnableBlending,
+    SetDepthTest {
+        enabled: bool,
+    },
+    SetStencilMode {
+        mode: StencilMode,
+    },
+    SetUniform {
+        program: u32,
+        name: String,
+        value: UniformValue,
+    },
+    SetTextureUnit {
+        program: u32,
+        unit: i32,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct GfxViolation {
+    pub call_index: usize,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct GfxRecorder {
+    calls: Vec<GfxCall>,
+    violations: Vec<GfxViolation>,
+    live_handles: HashMap<GfxHandleKind, HashSet<u32>>,
+    next_handles: HashMap<GfxHandleKind, u32>,
+    uniforms: HashMap<(u32, String), UniformValue>,
+    // OpenGL only flags deleted shaders, they live as long as a program keeps them attached
+    attached_shaders: HashMap<u32, [u32; 2]>,
+    flagged_shaders: HashMap<u32, u32>,
+    uniform_table: UniformTable, // No reflection, locations are given on first resolution
+}
+
+#[derive(Default, Clone)]
+pub struct GfxDeviceRecording {
+    recorder: Rc<RefCell<GfxRecorder>>,
+}
+
+pub struct GfxRecordingShaderApi {
+    recorder: Rc<RefCell<GfxRecorder>>,
+}
+
+impl GfxRecorder {
+    pub fn calls(&self) -> &Vec<GfxCall> {
+        &self.calls
+    }
+
+    pub fn violations(&self) -> &Vec<GfxViolation> {
+        &self.violations
+    }
+
+    pub fn draw_calls(&self) -> Vec<&GfxCall> {
+        self.calls
+            .iter()
+            .filter(|call| {
+                matches!(
+                    call,
+                    GfxCall::DrawCommand { .. } | GfxCall::DrawCommandInstanced { .. }
+                )
+            })
+            .collect()
+    }
+
+    pub fn is_alive(&self, kind: GfxHandleKind, handle: u32) -> bool {
+        self.live_handles
+            .get(&kind)
+            .is_some_and(|handles| handles.contains(&handle))
+    }
+
+    pub fn live_count(&self, kind: GfxHandleKind) -> usize {
+        self.live_handles.get(&kind).map_or(0, |handles| handles.len())
+    }
+
+    // Last value uploaded for a program uniform, textures units are stored as i32 uniforms
+    pub fn uniform(&self, program: u32, name: &str) -> Option<&UniformValue> {
+        self.uniforms.get(&(program, String::from(name)))
+    }
+
+    fn alloc(&mut self, kind: GfxHandleKind) -> u32 {
+        let next = self.next_handles.entry(kind).or_insert(1u32);
+        let handle = *next;
+        *next += 1;
+
+        self.live_handles.entry(kind).or_default().insert(handle);
+        handle
+    }
+
+    fn release(&mut self, kind: GfxHandleKind, handle: u32) {
+        let released = self
+            .live_handles
+            .get_mut(&kind)
+            .is_some_and(|handles| handles.remove(&handle));
+
+        if !released {
+            self.violation(format!(
+                "release of a dead or unknown {:?} handle {}",
+                kind, handle
+            ));
+        }
+    }
+
+    fn check(&mut self, kind: GfxHandleKind, handle: u32) {
+        let flagged = kind == GfxHandleKind::Shader && self.flagged_shaders.contains_key(&handle);
+
+        if !flagged && !self.is_alive(kind, handle) {
+            self.violation(format!(
+                "use of a dead or unknown {:?} handle {}",
+                kind, handle
+            ));
+        }
+    }
+
+    fn attach_shaders(&mut self, program: u32, shaders: [u32; 2]) {
+        for shader in shaders {
+            *self.flagged_shaders.entry(shader).or_insert(0) += 1;
+        }
+        self.attached_shaders.insert(program, shaders);
+    }
+
+    fn detach_shaders(&mut self, program: u32) {
+        for shader in self.attached_shaders.remove(&program).unwrap_or_default() {
+            if let Some(count) = self.flagged_shaders.get_mut(&shader) {
+                *count -= 1;
+                if *count == 0 {
+                    self.flagged_shaders.remove(&shader);
+                }
+            }
+        }
+    }
+
+    fn violation(&mut self, message: String) {
+        self.violations.push(GfxViolation {
+            call_index: self.calls.len(),
+            message,
+        });
+    }
+
+    fn record(&mut self, call: GfxCall) {
+        self.calls.push(call);
+    }
+
+    fn set_uniform_at(&mut self, program: u32, location: i32, value: UniformValue) {
+        match self.uniform_table.get_name(program, location).cloned() {
+            Some(name) => self.set_uniform(program, &name, value),
+            None => self.violation(format!(
+                "upload to unknown uniform location {} of program {}",
+                location, program
+            )),
+        }
+    }
+
+    fn set_uniform(&mut self, program: u32, name: &str, value: UniformValue) {
+        self.check(GfxHandleKind::Program, program);
+        self.uniforms
+            .insert((program, String::from(name)), value.clone());
+        self.record(GfxCall::SetUniform {
+            program,
+            name: String::from(name),
+            value,
+        });
+    }
+}
+
+impl GfxDeviceRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The shader api shares the command log of the device so calls are recorded in order
+    pub fn shader_api(&self) -> GfxRecordingShaderApi {
+        GfxRecordingShaderApi {
+            recorder: self.recorder.clone(),
+        }
+    }
+
+    pub fn log(&self) -> Ref<'_, GfxRecorder> {
+        self.recorder.borrow()
+    }
+
+    // Drain the recorded calls, handles and uniforms states are kept
+    pub fn take_calls(&self) -> Vec<GfxCall> {
+        std::mem::take(&mut self.recorder.borrow_mut().calls)
+    }
+}
+
+impl GfxApiDevice for GfxDeviceRecording {
+    // Sources are not compiled, only an #error directive makes them fail
+    fn alloc_shader(&self, source: String, s_type: ShaderType) -> Result<u32, String> {
+        if let Some(log) = get_error_directive_log(&source) {
+            return Err(format!("[Shader] Compilation Error: {}", log));
+        }
+
+        let mut rec = self.recorder.borrow_mut();
+        let handle = rec.alloc(GfxHandleKind::Shader);
+        rec.record(GfxCall::AllocShader {
+            handle,
+            shader_type: s_type,
+        });
+        Ok(handle)
+    }
+
+    fn release_shader(&self, shader_handle: u32) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.release(GfxHandleKind::Shader, shader_handle);
+        rec.record(GfxCall::ReleaseShader {
+            handle: shader_handle,
+        });
+    }
+
+    fn alloc_shader_module(
+        &self,
+        vertex: u32,
+        frag: u32,
+        material: &Material,
+    ) -> Result<ShaderModule, String> {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::Shader, vertex);
+        rec.check(GfxHandleKind::Shader, frag);
+
+        let handle = rec.alloc(GfxHandleKind::Program);
+        rec.record(GfxCall::AllocShaderModule {
+            handle,
+            vertex,
+            fragment: frag,
+        });
+
+        // Mirrors the OpenGL device which deletes the shaders once the program is linked
+        rec.attach_shaders(handle, [vertex, frag]);
+        for shader in [vertex, frag] {
+            if rec.is_alive(GfxHandleKind::Shader, shader) {
+                rec.release(GfxHandleKind::Shader, shader);
+            }
+        }
+
+        Ok(ShaderModule {
+            self_handle: handle,
+            vertex_handle: None,
+            fragment_handle: None,
+            texture_handles: vec![],
+            material: material.clone(),
+        })
+    }
+
+    fn release_shader_module(&self, module_handle: u32) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.release(GfxHandleKind::Program, module_handle);
+        rec.detach_shaders(module_handle);
+        rec.record(GfxCall::ReleaseShaderModule {
+            handle: module_handle,
+        });
+    }
+
+    fn use_shader_module(&self, module_handle: u32) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::Program, module_handle);
+        rec.record(GfxCall::UseShaderModule {
+            handle: module_handle,
+        });
+    }
+
+    fn alloc_shader_storage_buffer(&self, data: &Vec<Vector4<f32>>) -> ShaderStorageBuffer {
+        let mut rec = self.recorder.borrow_mut();
+        let vao_handle = rec.alloc(GfxHandleKind::VertexArray);
+        let self_handle = rec.alloc(GfxHandleKind::Buffer);
+        rec.record(GfxCall::AllocShaderStorageBuffer {
+            vao: vao_handle,
+            handle: self_handle,
+            count: data.len(),
+        });
+
+        ShaderStorageBuffer {
+            vao_handle,
+            self_handle,
+            count: data.len(),
+        }
+    }
+
+    fn alloc_uniform_buffer(&self, binding: u32, count: usize) -> UniformBuffer {
+        let mut rec = self.recorder.borrow_mut();
+        let self_handle = rec.alloc(GfxHandleKind::Buffer);
+        rec.record(GfxCall::AllocUniformBuffer {
+            handle: self_handle,
+            binding,
+            floats_count: count,
+        });
+
+        UniformBuffer {
+            self_handle,
+            binding,
+            count,
+        }
+    }
+
+    fn update_uniform_buffer(&self, buffer: &UniformBuffer, data: &[f32]) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::Buffer, buffer.self_handle);
+        if data.len() > buffer.count {
+            rec.violation(format!(
+                "update of uniform buffer {} overflows its {} floats",
+                buffer.self_handle, buffer.count
+            ));
+        }
+
+        rec.record(GfxCall::UpdateUniformBuffer {
+            handle: buffer.self_handle,
+            floats_count: data.len(),
+        });
+    }
+
+    fn bind_uniform_buffer(&self, buffer: &UniformBuffer) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::Buffer, buffer.self_handle);
+        rec.record(GfxCall::BindUniformBuffer {
+            handle: buffer.self_handle,
+            binding: buffer.binding,
+        });
+    }
+
+    fn alloc_buffer(
+        &self,
+        vertices_set: Vec<Vec<f32>>,
+        indices: Vec<Vec<u32>>,
+        settings: BufferSettings,
+    ) -> BufferModule {
+        let mut rec = self.recorder.borrow_mut();
+        let vao = rec.alloc(GfxHandleKind::VertexArray);
+        let buffer_count = vertices_set.len() + usize::min(indices.len(), vertices_set.len());
+        let buffers: Vec<u32> = (0..buffer_count)
+            .map(|_| rec.alloc(GfxHandleKind::Buffer))
+            .collect();
+        let vertices_count: Vec<u32> = vertices_set.iter().map(|x| x.len() as u32).collect();
+        let instance_buffer = settings
+            .instanced
+            .then(|| rec.alloc(GfxHandleKind::Buffer));
+
+        rec.record(GfxCall::AllocBuffer {
+            vao,
+            buffers: buffers.clone(),
+            vertices_count: vertices_count.clone(),
+        });
+
+        BufferModule {
+            handle: vao,
+            shader_storage: None,
+            buffer_handles: Option::from(buffers),
+            buffer_attributes: None,
+            vertices: if settings.keep_vertices {
+                Option::from(vertices_set)
+            } else {
+                None
+            },
+            vertices_count: Option::from(vertices_count),
+            instance_buffer,
+        }
+    }
+
+    fn release_buffer(&self, module: BufferModule) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.release(GfxHandleKind::VertexArray, module.handle);
+
+        if let Some(handles) = module.buffer_handles {
+            for handle in handles {
+                rec.release(GfxHandleKind::Buffer, handle);
+            }
+        }
+        if let Some(handle) = module.instance_buffer {
+            rec.release(GfxHandleKind::Buffer, handle);
+        }
+
+        rec.record(GfxCall::ReleaseBuffer { vao: module.handle });
+    }
+
+    fn update_buffer(&self, module: &BufferModule, vertices: &[f32]) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::VertexArray, module.handle);
+        rec.record(GfxCall::UpdateBuffer {
+            vao: module.handle,
+            floats_count: vertices.len(),
+        });
+    }
+
+    fn update_instance_buffer(&self, module: &BufferModule, instances: &[f32]) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::VertexArray, module.handle);
+        match module.instance_buffer {
+            Some(handle) => rec.check(GfxHandleKind::Buffer, handle),
+            None => rec.violation(format!(
+                "Updating instances of the non instanced vertex array {}",
+                module.handle
+            )),
+        }
+
+        rec.record(GfxCall::UpdateInstanceBuffer {
+            vao: module.handle,
+            floats_count: instances.len(),
+        });
+    }
+
+    fn alloc_framebuffer(
+        &self,
+        width: i32,
+        height: i32,
+        format: FramebufferFormat,
+    ) -> Result<FrameBuffer, &str> {
+        let mut rec = self.recorder.borrow_mut();
+        let handle = rec.alloc(GfxHandleKind::Framebuffer);
+        let texture = rec.alloc(GfxHandleKind::Texture);
+        rec.record(GfxCall::AllocFramebuffer {
+            handle,
+            texture,
+            width,
+            height,
+            format,
+        });
+
+        Ok(FrameBuffer {
+            self_handle: handle,
+            texture_attachment: texture,
+            depth_attachment: 0,
+            width,
+            height,
+            format,
+        })
+    }
+
+    fn release_framebuffer(&self, framebuffer: FrameBuffer) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.release(GfxHandleKind::Framebuffer, framebuffer.self_handle);
+        rec.release(GfxHandleKind::Texture, framebuffer.texture_attachment);
+        rec.record(GfxCall::ReleaseFramebuffer {
+            handle: framebuffer.self_handle,
+        });
+    }
+
+    fn use_framebuffer(&self, framebuffer: Option<&FrameBuffer>) {
+        let mut rec = self.recorder.borrow_mut();
+        let handle = framebuffer.map(|fbo| fbo.self_handle);
+
+        if let Some(fbo_handle) = handle {
+            rec.check(GfxHandleKind::Framebuffer, fbo_handle);
+        }
+
+        rec.record(GfxCall::UseFramebuffer { handle });
+    }
+
+    fn blit_main_framebuffer(&self, buffer_module: &BufferModule, framebuffer: &FrameBuffer) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::VertexArray, buffer_module.handle);
+        rec.check(GfxHandleKind::Framebuffer, framebuffer.self_handle);
+        rec.check(GfxHandleKind::Texture, framebuffer.texture_attachment);
+        rec.record(GfxCall::BlitMainFramebuffer {
+            vao: buffer_module.handle,
+            framebuffer: framebuffer.self_handle,
+        });
+    }
+
+    fn draw_fullscreen_pass(&self, buffer_module: &BufferModule, textures: &[u32]) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::VertexArray, buffer_module.handle);
+        for texture in textures.iter() {
+            rec.check(GfxHandleKind::Texture, *texture);
+        }
+        rec.record(GfxCall::DrawFullscreenPass {
+            vao: buffer_module.handle,
+            textures: textures.to_vec(),
+        });
+    }
+
+    fn alloc_framebuffer_texture(&self, width: i32, height: i32, format: FramebufferFormat) -> u32 {
+        let mut rec = self.recorder.borrow_mut();
+        let handle = rec.alloc(GfxHandleKind::Texture);
+        rec.record(GfxCall::AllocFramebufferTexture {
+            handle,
+            width,
+            height,
+            format,
+        });
+        handle
+    }
+
+    fn alloc_texture(&self, sp_hdl: u32, texture: &Texture) -> u32 {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::Program, sp_hdl);
+
+        let handle = rec.alloc(GfxHandleKind::Texture);
+        rec.record(GfxCall::AllocTexture {
+            handle,
+            program: sp_hdl,
+            width: texture.width,
+            height: texture.height,
+        });
+        handle
+    }
+
+    fn release_texture(&self, tex_id: u32) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.release(GfxHandleKind::Texture, tex_id);
+        rec.record(GfxCall::ReleaseTexture { handle: tex_id });
+    }
+
+    fn draw_command(&self, command: &RenderCommand, procedural: Option<i32>) {
+        let mut rec = self.recorder.borrow_mut();
+        let program = command.shader_module.self_handle;
+        let vao = command.buffer_module.handle;
+
+        rec.check(GfxHandleKind::Program, program);
+        rec.check(GfxHandleKind::VertexArray, vao);
+        if let Some(sso) = command.buffer_module.shader_storage.as_ref() {
+            rec.check(GfxHandleKind::Buffer, sso.self_handle);
+        }
+        // 0 leaves the texture unit empty
+        for texture in command.shader_module.texture_handles.iter().filter(|t| **t != 0) {
+            rec.check(GfxHandleKind::Texture, *texture);
+        }
+
+        rec.record(GfxCall::DrawCommand {
+            command: command.handle,
+            program,
+            vao,
+            textures: command.shader_module.texture_handles.clone(),
+            procedural,
+        });
+    }
+
+    fn draw_command_instanced(&self, command: &RenderCommand, instances: i32) {
+        let mut rec = self.recorder.borrow_mut();
+        let program = command.shader_module.self_handle;
+        let vao = command.buffer_module.handle;
+
+        rec.check(GfxHandleKind::Program, program);
+        rec.check(GfxHandleKind::VertexArray, vao);
+        // 0 leaves the texture unit empty
+        for texture in command.shader_module.texture_handles.iter().filter(|t| **t != 0) {
+            rec.check(GfxHandleKind::Texture, *texture);
+        }
+
+        rec.record(GfxCall::DrawCommandInstanced {
+            command: command.handle,
+            program,
+            vao,
+            textures: command.shader_module.texture_handles.clone(),
+            instances,
+        });
+    }
+
+    fn clear_color(&self, color: ARGB8Color) {
+        self.recorder
+            .borrow_mut()
+            .record(GfxCall::ClearColor { color });
+    }
+
+    fn update_viewport(&self, x: u32, y: u32, width: u32, height: u32) {
+        self.recorder.borrow_mut().record(GfxCall::UpdateViewport {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    fn clear_buffers(&self) {
+        self.recorder.borrow_mut().record(GfxCall::ClearBuffers);
+    }
+
+    fn enable_blending(&self) {
+        self.recorder.borrow_mut().record(GfxCall::EnableBlending);
+    }
+
+    fn set_depth_test(&self, enabled: bool) {
+        self.recorder
+            .borrow_mut()
+            .record(GfxCall::SetDepthTest { enabled });
+    }
+
+    fn set_stencil_mode(&self, mode: StencilMode) {
+        self.recorder
+            .borrow_mut()
+            .record(GfxCall::SetStencilMode { mode });
+    }
+}
+
+impl GfxApiShader for GfxRecordingShaderApi {
+    fn set_attribute_i32(&self, sp_hdl: u32, identifier: &str, value: i32) {
+        self.recorder
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::I32(value));
+    }
+
+    fn set_attribute_f32(&self, sp_hdl: u32, identifier: &str, value: f32) {
+        self.recorder
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::F32(value));
+    }
+
+    fn set_attribute_vector2f(&self, sp_hdl: u32, identifier: &str, vec: &Vector2<f32>) {
+        self.recorder
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::Vec2(*vec));
+    }
+
+    fn set_attribute_mat4(&self, sp_hdl: u32, identifier: &str, value: &Matrix4<f32>) {
+        self.recorder
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::Mat4(*value));
+    }
+
+    fn set_attribute_bool(&self, sp_hdl: u32, identifier: &str, value: bool) {
+        self.recorder
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::Bool(value));
+    }
+
+    fn set_attribute_color(&self, sp_hdl: u32, identifier: &str, value: glm::Vec4) {
+        self.recorder
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::Vec4(value));
+    }
+
+    fn set_texture_unit(&self, prog_hdl: u32, texture_pos: i32) {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::Program, prog_hdl);
+        rec.uniforms.insert(
+            (prog_hdl, format!("texture{}", texture_pos)),
+            UniformValue::I32(texture_pos),
+        );
+        rec.record(GfxCall::SetTextureUnit {
+            program: prog_hdl,
+            unit: texture_pos,
+        });
+    }
+
+    fn reflect_uniforms(&self, sp_hdl: u32) {
+        self.recorder
+            .borrow_mut()
+            .uniform_table
+            .insert_program(sp_hdl, HashMap::new());
+    }
+
+    fn release_uniforms(&self, sp_hdl: u32) {
+        self.recorder
+            .borrow_mut()
+            .uniform_table
+            .remove_program(sp_hdl);
+    }
+
+    fn get_uniform_location(&self, sp_hdl: u32, identifier: &str) -> Option<i32> {
+        let mut rec = self.recorder.borrow_mut();
+        rec.check(GfxHandleKind::Program, sp_hdl);
+        Some(rec.uniform_table.get_or_insert_location(sp_hdl, identifier))
+    }
+
+    fn set_uniform_i32(&self, handle: &UniformHandle<i32>, value: i32) {
+        self.recorder.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::I32(value),
+        );
+    }
+
+    fn set_uniform_f32(&self, handle: &UniformHandle<f32>, value: f32) {
+        self.recorder.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::F32(value),
+        );
+    }
+
+    fn set_uniform_vector2f(&self, handle: &UniformHandle<Vector2<f32>>, vec: &Vector2<f32>) {
+        self.recorder.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::Vec2(*vec),
+        );
+    }
+
+    fn set_uniform_mat4(&self, handle: &UniformHandle<Matrix4<f32>>, value: &Matrix4<f32>) {
+        self.recorder.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::Mat4(*value),
+        );
+    }
+
+    fn set_uniform_bool(&self, handle: &UniformHandle<bool>, value: bool) {
+        self.recorder.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::Bool(value),
+        );
+    }
+
+    fn set_uniform_color(&self, handle: &UniformHandle<Vector4<f32>>, value: Vector4<f32>) {
+        self.recorder.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::Vec4(value),
+        );
+    }
+}
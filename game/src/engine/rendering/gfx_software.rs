@@ -0,0 +1,1328 @@
+use super::atlas::full_uv_rect;
+use super::components::{
+    ARGB8Color, BufferSettings, FrameBuffer, FramebufferFormat, ShaderStorageBuffer,
+    StencilMode, UniformBuffer,
+};
+use super::gfx_device::{
+    BufferModule, GfxApiDevice, GfxApiShader, RenderCommand, ShaderModule, UniformHandle,
+    UniformTable, FRAME_BLOCK_BINDING, INSTANCE_FLOATS,
+};
+use super::gfx_recording::UniformValue;
+use super::lighting::{
+    LIGHT_BLOCK_BINDING, LIGHT_FLOATS, LIGHT_HEADER_FLOATS, LIGHT_SPOT, MAX_LIGHTS, SHADOW_SAMPLES,
+};
+use super::post_processing::{
+    POST_BLOOM_COMPOSITE, POST_BLOOM_DOWNSAMPLE, POST_BLOOM_PREFILTER, POST_BLOOM_UPSAMPLE,
+    POST_BLUR, POST_CHROMATIC_ABERRATION, POST_COLOR_GRADING, POST_LIGHT_ACCUMULATION,
+    POST_LIGHT_COMBINE, POST_PIXELATION, POST_TONEMAPPING, POST_VIGNETTE,
+};
+use super::shader_preprocessor::get_error_directive_log;
+use super::shaders::{BlendMode, Material, ShaderType, Texture};
+use crate::engine::utils::maths::{identity_mat4, Rect};
+use glm::{Matrix4, Vector2, Vector3, Vector4};
+use image::{Rgba, RgbaImage};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// CPU backend rasterizing the engine builtin shaders (sprites, debug lines, post effects, 2D
+// lights and framebuffer blit).
+// GLSL sources are not compiled, programs only hold their uniforms values.
+
+pub type Pixel = [f32; 4];
+
+#[derive(Debug, Clone)]
+pub struct SoftwareImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Pixel>, // Rows are stored bottom to top like OpenGL textures
+    pub has_alpha: bool,
+    pub hdr: bool, // Floating point framebuffers, colors are not clamped above 1
+}
+
+#[derive(Default)]
+struct SoftwareProgram {
+    uniforms: HashMap<String, UniformValue>,
+}
+
+struct SoftwareVertexArray {
+    vertices: Vec<f32>,
+    indices: Vec<u32>,
+    vertex_size: usize,
+    uvs_size: usize,
+    colors_size: usize,
+    instances: Vec<f32>, // See INSTANCE_FLOATS for the layout
+}
+
+struct SoftwareFramebuffer {
+    texture: u32,
+    depth: Vec<f32>,
+    stencil: Vec<u8>,
+}
+
+#[derive(Clone, Copy)]
+struct ClipVertex {
+    position: Vector4<f32>,
+    uv: Vector2<f32>,
+    color: Vector4<f32>,
+}
+
+#[derive(Clone, Copy)]
+enum FragmentStage {
+    Sprite(Option<u32>), // texture0 handle
+    Color,
+    Blit(u32),
+    PostEffect {
+        program: u32,
+        source: u32,
+        second: Option<u32>, // texture1, the LUT or a bloom mip
+    },
+}
+
+struct SoftwareState {
+    next_handle: u32,
+    screen: SoftwareImage,
+    screen_depth: Vec<f32>,
+    screen_stencil: Vec<u8>,
+    programs: HashMap<u32, SoftwareProgram>,
+    vertex_arrays: HashMap<u32, SoftwareVertexArray>,
+    storage_buffers: HashMap<u32, Vec<Vector4<f32>>>,
+    uniform_buffers: HashMap<u32, Vec<f32>>,
+    uniform_bindings: HashMap<u32, u32>, // binding point to uniform buffer
+    uniform_table: UniformTable,
+    textures: HashMap<u32, SoftwareImage>,
+    framebuffers: HashMap<u32, SoftwareFramebuffer>,
+
+    bound_framebuffer: Option<u32>,
+    bound_program: Option<u32>,
+    viewport: Rect<u32>,
+    clear_color: Pixel,
+    depth_test: bool,
+    stencil_mode: StencilMode,
+    blending: bool,
+}
+
+#[derive(Clone)]
+pub struct GfxDeviceSoftware {
+    state: Rc<RefCell<SoftwareState>>,
+}
+
+pub struct GfxSoftwareShaderApi {
+    state: Rc<RefCell<SoftwareState>>,
+}
+
+impl SoftwareImage {
+    pub fn new(width: u32, height: u32, has_alpha: bool) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0f32, 0f32, 0f32, 1f32]; (width * height) as usize],
+            has_alpha,
+            hdr: false,
+        }
+    }
+
+    pub fn from_texture(texture: &Texture) -> Self {
+        let channels = texture.channels as usize;
+        let pixels = texture
+            .data
+            .chunks_exact(channels)
+            .map(|texel| {
+                let alpha = if channels == 4 { texel[3] } else { 255u8 };
+                [
+                    texel[0] as f32 / 255f32,
+                    texel[1] as f32 / 255f32,
+                    texel[2] as f32 / 255f32,
+                    alpha as f32 / 255f32,
+                ]
+            })
+            .collect();
+
+        Self {
+            width: texture.width,
+            height: texture.height,
+            pixels,
+            has_alpha: channels == 4,
+            hdr: false,
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Pixel {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    fn set(&mut self, x: u32, y: u32, pixel: Pixel) {
+        let alpha = if self.has_alpha { pixel[3] } else { 1f32 };
+        let max = if self.hdr { f32::MAX } else { 1f32 };
+        self.pixels[(y * self.width + x) as usize] = [
+            pixel[0].clamp(0f32, max),
+            pixel[1].clamp(0f32, max),
+            pixel[2].clamp(0f32, max),
+            alpha.clamp(0f32, 1f32),
+        ];
+    }
+
+    // Bilinear sampling with repeat wrapping (GL_LINEAR + GL_REPEAT)
+    pub fn sample(&self, uv: Vector2<f32>) -> Pixel {
+        if self.width == 0 || self.height == 0 {
+            return [0f32, 0f32, 0f32, 1f32];
+        }
+
+        let x = uv.x * self.width as f32 - 0.5f32;
+        let y = uv.y * self.height as f32 - 0.5f32;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let wrap = |value: i64, size: u32| value.rem_euclid(size as i64) as u32;
+        let texel =
+            |tx: f32, ty: f32| self.get(wrap(tx as i64, self.width), wrap(ty as i64, self.height));
+
+        let (a, b) = (texel(x0, y0), texel(x0 + 1f32, y0));
+        let (c, d) = (texel(x0, y0 + 1f32), texel(x0 + 1f32, y0 + 1f32));
+
+        let mut out = [0f32; 4];
+        for i in 0..4 {
+            let bottom = a[i] + (b[i] - a[i]) * fx;
+            let top = c[i] + (d[i] - c[i]) * fx;
+            out[i] = bottom + (top - bottom) * fy;
+        }
+        out
+    }
+
+    // Export as an 8 bits image with the first row at the top
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let pixel = self.get(x, self.height - 1 - y);
+            Rgba(pixel.map(|channel| (channel.clamp(0f32, 1f32) * 255f32).round() as u8))
+        })
+    }
+}
+
+impl SoftwareState {
+    fn alloc_handle(&mut self) -> u32 {
+        self.next_handle += 1;
+        self.next_handle
+    }
+
+    fn uniform_mat4(&self, program: u32, name: &str) -> Matrix4<f32> {
+        match self.uniform(program, name) {
+            Some(UniformValue::Mat4(mat)) => mat,
+            _ => identity_mat4(),
+        }
+    }
+
+    // PROJ * VIEW read from the FrameData block bound by the renderer
+    fn view_projection(&self) -> Matrix4<f32> {
+        let Some(data) = self
+            .uniform_bindings
+            .get(&FRAME_BLOCK_BINDING)
+            .and_then(|buffer| self.uniform_buffers.get(buffer))
+        else {
+            return identity_mat4();
+        };
+
+        let mat4 = |offset: usize| {
+            let column = |i: usize| {
+                let i = offset + i * 4;
+                Vector4::new(data[i], data[i + 1], data[i + 2], data[i + 3])
+            };
+            Matrix4::new(column(0), column(1), column(2), column(3))
+        };
+        mat4(16).mul_m(&mat4(0))
+    }
+
+    fn uniform_vec4(&self, program: u32, name: &str, default: Vector4<f32>) -> Vector4<f32> {
+        match self.uniform(program, name) {
+            Some(UniformValue::Vec4(vec)) => vec,
+            _ => default,
+        }
+    }
+
+    fn uniform_vec2(&self, program: u32, name: &str) -> Vector2<f32> {
+        match self.uniform(program, name) {
+            Some(UniformValue::Vec2(vec)) => vec,
+            _ => Vector2::new(0f32, 0f32),
+        }
+    }
+
+    fn uniform_f32(&self, program: u32, name: &str) -> f32 {
+        match self.uniform(program, name) {
+            Some(UniformValue::F32(value)) => value,
+            _ => 0f32,
+        }
+    }
+
+    fn uniform(&self, program: u32, name: &str) -> Option<UniformValue> {
+        self.programs
+            .get(&program)
+            .and_then(|prog| prog.uniforms.get(name).cloned())
+    }
+
+    fn set_uniform_at(&mut self, program: u32, location: i32, value: UniformValue) {
+        if let Some(name) = self.uniform_table.get_name(program, location).cloned() {
+            self.set_uniform(program, &name, value);
+        }
+    }
+
+    fn set_uniform(&mut self, program: u32, name: &str, value: UniformValue) {
+        if let Some(prog) = self.programs.get_mut(&program) {
+            prog.uniforms.insert(String::from(name), value);
+        }
+    }
+
+    // Color, depth & stencil of the bound framebuffer
+    fn target(&mut self) -> (&mut SoftwareImage, &mut Vec<f32>, &mut Vec<u8>) {
+        match self.bound_framebuffer {
+            Some(fbo) => {
+                let framebuffer = self.framebuffers.get_mut(&fbo).unwrap();
+                let image = self.textures.get_mut(&framebuffer.texture).unwrap();
+                (image, &mut framebuffer.depth, &mut framebuffer.stencil)
+            }
+            None => (
+                &mut self.screen,
+                &mut self.screen_depth,
+                &mut self.screen_stencil,
+            ),
+        }
+    }
+
+    // Fullscreen quad of the framebuffer blits, positions are already in clip space
+    fn quad_vertices(&self, buffer_module: &BufferModule) -> Vec<ClipVertex> {
+        let Some(vao) = self.vertex_arrays.get(&buffer_module.handle) else {
+            return vec![];
+        };
+        let stride = vao.vertex_size + vao.uvs_size;
+
+        vao.vertices
+            .chunks_exact(stride)
+            .map(|data| ClipVertex {
+                position: Vector4::new(data[0], data[1], 0f32, 1f32),
+                uv: Vector2::new(data[vao.vertex_size], data[vao.vertex_size + 1]),
+                color: Vector4::new(1f32, 1f32, 1f32, 1f32),
+            })
+            .collect()
+    }
+
+    // Port of vertex.shader, or batch_vertex.shader when the vertex array holds colors
+    fn sprite_vertices(&self, command: &RenderCommand, count: Option<i32>) -> Vec<ClipVertex> {
+        let program = command.shader_module.self_handle;
+        let Some(vao) = self.vertex_arrays.get(&command.buffer_module.handle) else {
+            return vec![];
+        };
+
+        let mvp = self
+            .view_projection()
+            .mul_m(&self.uniform_mat4(program, "TRS"));
+        let color = self.uniform_vec4(
+            program,
+            "surface_color",
+            Vector4::new(1f32, 1f32, 1f32, 1f32),
+        );
+        // batch_vertex.shader has no uv rect, atlas regions are baked in the vertices
+        let uv_rect = self.uniform_vec4(program, "uv_rect", full_uv_rect());
+
+        Self::mesh_vertices(vao, mvp, color, uv_rect, count)
+    }
+
+    // Port of instanced_vertex.shader, the mesh is emitted once per instance
+    fn instanced_vertices(&self, command: &RenderCommand, instances: i32) -> Vec<ClipVertex> {
+        let Some(vao) = self.vertex_arrays.get(&command.buffer_module.handle) else {
+            return vec![];
+        };
+
+        let view_proj = self.view_projection();
+
+        vao.instances
+            .chunks_exact(INSTANCE_FLOATS)
+            .take(instances.max(0) as usize)
+            .flat_map(|data| {
+                let column =
+                    |i: usize| Vector4::new(data[i], data[i + 1], data[i + 2], data[i + 3]);
+                let trs = Matrix4::new(column(0), column(4), column(8), column(12));
+                Self::mesh_vertices(vao, view_proj.mul_m(&trs), column(16), column(20), None)
+            })
+            .collect()
+    }
+
+    fn mesh_vertices(
+        vao: &SoftwareVertexArray,
+        mvp: Matrix4<f32>,
+        color: Vector4<f32>,
+        uv_rect: Vector4<f32>,
+        count: Option<i32>,
+    ) -> Vec<ClipVertex> {
+        let stride = vao.vertex_size + vao.uvs_size + vao.colors_size;
+        let vertex = |index: usize| {
+            let data = &vao.vertices[index * stride..(index + 1) * stride];
+            let z = if vao.vertex_size > 2 { data[2] } else { 0f32 };
+            let colors = &data[vao.vertex_size + vao.uvs_size..];
+            ClipVertex {
+                position: mvp.mul_v(&Vector4::new(data[0], data[1], z, 1f32)),
+                uv: Vector2::new(
+                    uv_rect.x + data[vao.vertex_size] * uv_rect.z,
+                    uv_rect.y + data[vao.vertex_size + 1] * uv_rect.w,
+                ),
+                color: if colors.len() == 4 {
+                    Vector4::new(colors[0], colors[1], colors[2], colors[3]) * color
+                } else {
+                    color
+                },
+            }
+        };
+
+        if let Some(count) = count {
+            let available = vao.vertices.len() / stride;
+            (0..usize::min(count as usize, available))
+                .map(vertex)
+                .collect()
+        } else if vao.indices.is_empty() {
+            (0..vao.vertices.len() / stride).map(vertex).collect()
+        } else {
+            vao.indices.iter().map(|i| vertex(*i as usize)).collect()
+        }
+    }
+
+    // CPU port of line_vertex.shader, each segment of the polyline is extruded into 2 triangles
+    fn polyline_vertices(&self, command: &RenderCommand, count: i32) -> Vec<ClipVertex> {
+        let program = command.shader_module.self_handle;
+        let sso = command.buffer_module.shader_storage.as_ref().unwrap();
+        let Some(points) = self.storage_buffers.get(&sso.self_handle) else {
+            return vec![];
+        };
+
+        let mvp = self
+            .view_projection()
+            .mul_m(&self.uniform_mat4(program, "TRS"));
+        let color = self.uniform_vec4(
+            program,
+            "surface_color",
+            Vector4::new(1f32, 1f32, 1f32, 1f32),
+        );
+        let offset = self.uniform_vec2(program, "offset");
+        let thickness = self.uniform_f32(program, "thickness");
+
+        let xyz = |v: Vector4<f32>| Vector3::new(v.x, v.y, v.z);
+        let normal_of = |v: Vector3<f32>| glm::normalize(Vector3::new(-v.y, v.x, 0f32));
+
+        (0..count as usize)
+            .filter(|i| i / 6 + 1 < points.len())
+            .map(|i| {
+                let pi = i / 6;
+                let ti = i % 6;
+                let dir = if ti == 0 || ti == 2 || ti == 3 {
+                    -1f32
+                } else {
+                    1f32
+                };
+                let segment = xyz(points[pi + 1] - points[pi]);
+                let normal = normal_of(segment);
+
+                let (extruded_point, neighbour) = if ti == 0 || ti == 1 || ti == 4 {
+                    let prev = if pi >= 1 {
+                        xyz(points[pi] - points[pi - 1])
+                    } else {
+                        segment
+                    };
+                    (xyz(points[pi]), prev)
+                } else {
+                    let next = if pi + 2 < points.len() {
+                        xyz(points[pi + 2] - points[pi + 1])
+                    } else {
+                        segment
+                    };
+                    (xyz(points[pi + 1]), next)
+                };
+                let miter_normal = glm::normalize(normal_of(neighbour) + normal);
+                let miter = extruded_point
+                    + miter_normal * (thickness * 0.5f32 * dir / glm::dot(miter_normal, normal));
+
+                let uv_table = [
+                    (0f32, 0f32),
+                    (0f32, 1f32),
+                    (1f32, 0f32),
+                    (1f32, 0f32),
+                    (0f32, 1f32),
+                    (1f32, 1f32),
+                ];
+                ClipVertex {
+                    position: mvp.mul_v(&Vector4::new(
+                        miter.x - offset.x,
+                        miter.y - offset.y,
+                        miter.z,
+                        1f32,
+                    )),
+                    uv: Vector2::new(uv_table[ti].0, uv_table[ti].1),
+                    color,
+                }
+            })
+            .collect()
+    }
+
+    fn shade(&self, stage: FragmentStage, uv: Vector2<f32>, color: Vector4<f32>) -> Pixel {
+        match stage {
+            FragmentStage::Sprite(texture) => {
+                // Sampling a texture unit without texture returns opaque black like OpenGL
+                let texel = texture
+                    .and_then(|handle| self.textures.get(&handle))
+                    .map_or([0f32, 0f32, 0f32, 1f32], |image| image.sample(uv));
+                [
+                    texel[0] * color.x,
+                    texel[1] * color.y,
+                    texel[2] * color.z,
+                    texel[3] * color.w,
+                ]
+            }
+            FragmentStage::Color => [color.x, color.y, color.z, color.w],
+            FragmentStage::Blit(texture) => {
+                let texel = self
+                    .textures
+                    .get(&texture)
+                    .map_or([0f32; 4], |image| image.sample(uv));
+                [texel[0], texel[1], texel[2], 1f32]
+            }
+            FragmentStage::PostEffect {
+                program,
+                source,
+                second,
+            } => {
+                let Some(image) = self.textures.get(&source) else {
+                    return [0f32, 0f32, 0f32, 1f32];
+                };
+                let second = second.and_then(|handle| self.textures.get(&handle));
+                let color = self.post_effect(program, image, second, uv);
+                [color.x, color.y, color.z, 1f32]
+            }
+        }
+    }
+
+    // Port of postprocess_fragment.shader
+    fn post_effect(
+        &self,
+        program: u32,
+        image: &SoftwareImage,
+        second: Option<&SoftwareImage>,
+        uv: Vector2<f32>,
+    ) -> Vector3<f32> {
+        let effect = match self.uniform(program, "effect") {
+            Some(UniformValue::I32(effect)) => effect,
+            _ => -1,
+        };
+        let params = self.uniform_vec4(program, "params", Vector4::new(0f32, 0f32, 0f32, 0f32));
+        let texel_size = self.uniform_vec2(program, "texel_size");
+        let rgb = |texel: Pixel| Vector3::new(texel[0], texel[1], texel[2]);
+        let color = rgb(image.sample(uv));
+        let second_color = second.map_or(Vector3::new(0f32, 0f32, 0f32), |second| {
+            rgb(second.sample(uv))
+        });
+        // 4 bilinear taps at the texel corners
+        let boxed = || {
+            let mut sum = Vector3::new(0f32, 0f32, 0f32);
+            for (x, y) in [(-1f32, -1f32), (1f32, -1f32), (-1f32, 1f32), (1f32, 1f32)] {
+                let offset = Vector2::new(x * texel_size.x, y * texel_size.y);
+                sum = sum + rgb(image.sample(uv + offset));
+            }
+            sum * 0.25f32
+        };
+
+        match effect {
+            POST_VIGNETTE => {
+                let center = uv - Vector2::new(0.5f32, 0.5f32);
+                let dist = glm::length(center) * std::f32::consts::SQRT_2;
+                let fade = smoothstep(1f32 - params.y.max(0.001f32), 1f32, dist);
+                color * (1f32 - fade * params.x)
+            }
+            POST_COLOR_GRADING => {
+                let Some(lut) = second else {
+                    return color;
+                };
+                let graded = grade(lut, color, params.y);
+                color + (graded - color) * params.x
+            }
+            POST_CHROMATIC_ABERRATION => {
+                let offset = (uv - Vector2::new(0.5f32, 0.5f32)) * params.x;
+                Vector3::new(
+                    image.sample(uv + offset)[0],
+                    color.y,
+                    image.sample(uv - offset)[2],
+                )
+            }
+            POST_BLUR => {
+                let mut sum = Vector3::new(0f32, 0f32, 0f32);
+                for x in -1..=1 {
+                    for y in -1..=1 {
+                        let offset = Vector2::new(
+                            x as f32 * params.x * texel_size.x,
+                            y as f32 * params.x * texel_size.y,
+                        );
+                        sum = sum + rgb(image.sample(uv + offset));
+                    }
+                }
+                sum / 9f32
+            }
+            POST_PIXELATION => {
+                let cell = texel_size * params.x.max(1f32);
+                let snapped = Vector2::new(
+                    ((uv.x / cell.x).floor() + 0.5f32) * cell.x,
+                    ((uv.y / cell.y).floor() + 0.5f32) * cell.y,
+                );
+                rgb(image.sample(snapped))
+            }
+            POST_BLOOM_PREFILTER => {
+                let color = boxed();
+                let brightness = color.x.max(color.y).max(color.z);
+                color * ((brightness - params.y).max(0f32) / brightness.max(0.0001f32))
+            }
+            POST_BLOOM_DOWNSAMPLE => boxed(),
+            POST_BLOOM_UPSAMPLE => boxed() + second_color,
+            POST_BLOOM_COMPOSITE => color + second_color * params.x,
+            POST_TONEMAPPING => aces(color * params.x),
+            POST_LIGHT_ACCUMULATION => self.accumulate_lights(uv),
+            POST_LIGHT_COMBINE => Vector3::new(
+                color.x * second_color.x,
+                color.y * second_color.y,
+                color.z * second_color.z,
+            ),
+            _ => color,
+        }
+    }
+
+    // Ambient & 2D lights of the LightData block seen at the uv, see lighting.rs for the layout
+    fn accumulate_lights(&self, uv: Vector2<f32>) -> Vector3<f32> {
+        let Some(data) = self
+            .uniform_bindings
+            .get(&LIGHT_BLOCK_BINDING)
+            .and_then(|buffer| self.uniform_buffers.get(buffer))
+        else {
+            return Vector3::new(0f32, 0f32, 0f32);
+        };
+
+        let vec4 = |i: usize| Vector4::new(data[i], data[i + 1], data[i + 2], data[i + 3]);
+        let inverse = Matrix4::new(vec4(0), vec4(4), vec4(8), vec4(12));
+        let ambient = vec4(16);
+
+        // World position on the z = 0 plane of the sprites
+        let ndc = uv * 2f32 - 1f32;
+        let near = inverse.mul_v(&Vector4::new(ndc.x, ndc.y, -1f32, 1f32));
+        let far = inverse.mul_v(&Vector4::new(ndc.x, ndc.y, 1f32, 1f32));
+        let (near, far) = (near / near.w, far / far.w);
+        let depth = far.z - near.z;
+        let t = if depth.abs() > 0.0001f32 {
+            -near.z / depth
+        } else {
+            0f32
+        };
+        let world = Vector2::new(
+            near.x + (far.x - near.x) * t,
+            near.y + (far.y - near.y) * t,
+        );
+
+        let mut light = Vector3::new(ambient.x, ambient.y, ambient.z);
+        for i in 0..ambient.w as usize {
+            let offset = LIGHT_HEADER_FLOATS + i * LIGHT_FLOATS;
+            let (position, color, direction) = (vec4(offset), vec4(offset + 4), vec4(offset + 8));
+            let shadow = vec4(offset + 12);
+
+            let to_pixel = world - Vector2::new(position.x, position.y);
+            let dist = glm::length(to_pixel);
+            let fade = (1f32 - dist / position.z.max(0.0001f32)).clamp(0f32, 1f32);
+            let mut attenuation = fade.powf(position.w.max(0.0001f32));
+            if color.w == LIGHT_SPOT {
+                let axis = Vector2::new(direction.x, direction.y);
+                let cone = glm::dot(to_pixel / dist.max(0.0001f32), axis);
+                attenuation *= smoothstep(direction.z, direction.w, cone);
+            }
+            if shadow.y > 0f32 && attenuation > 0f32 {
+                let position = Vector2::new(position.x, position.y);
+                attenuation *= shadow_visibility(data, world, position, shadow.x);
+            }
+            light = light + Vector3::new(color.x, color.y, color.z) * attenuation;
+        }
+        light
+    }
+
+    fn rasterize(
+        &mut self,
+        vertices: &[ClipVertex],
+        stage: FragmentStage,
+        blend_mode: BlendMode,
+    ) {
+        let mut fragments: Vec<(u32, u32, f32, Pixel)> = Vec::new();
+        let (target_width, target_height) = {
+            let (image, _, _) = self.target();
+            (image.width, image.height)
+        };
+
+        for triangle in vertices.chunks_exact(3) {
+            // Triangles crossing the camera plane are not clipped, they are dropped
+            if triangle.iter().any(|v| v.position.w <= f32::EPSILON) {
+                continue;
+            }
+
+            let vp = self.viewport;
+            let screen: Vec<(f32, f32, f32, f32)> = triangle
+                .iter()
+                .map(|v| {
+                    let inv_w = 1f32 / v.position.w;
+                    let ndc = (
+                        v.position.x * inv_w,
+                        v.position.y * inv_w,
+                        v.position.z * inv_w,
+                    );
+                    (
+                        vp.x as f32 + (ndc.0 + 1f32) * 0.5f32 * vp.width as f32,
+                        vp.y as f32 + (ndc.1 + 1f32) * 0.5f32 * vp.height as f32,
+                        ndc.2,
+                        inv_w,
+                    )
+                })
+                .collect();
+
+            let edge = |a: (f32, f32), b: (f32, f32), p: (f32, f32)| {
+                (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+            };
+            let (p0, p1, p2) = (
+                (screen[0].0, screen[0].1),
+                (screen[1].0, screen[1].1),
+                (screen[2].0, screen[2].1),
+            );
+            let area = edge(p0, p1, p2);
+            if area.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            let clip_x = (vp.x.min(target_width), (vp.x + vp.width).min(target_width));
+            let clip_y = (
+                vp.y.min(target_height),
+                (vp.y + vp.height).min(target_height),
+            );
+            let min_x = (p0.0.min(p1.0).min(p2.0).floor().max(clip_x.0 as f32)) as u32;
+            let max_x = (p0.0.max(p1.0).max(p2.0).ceil().min(clip_x.1 as f32)) as u32;
+            let min_y = (p0.1.min(p1.1).min(p2.1).floor().max(clip_y.0 as f32)) as u32;
+            let max_y = (p0.1.max(p1.1).max(p2.1).ceil().min(clip_y.1 as f32)) as u32;
+
+            for py in min_y..max_y {
+                for px in min_x..max_x {
+                    let p = (px as f32 + 0.5f32, py as f32 + 0.5f32);
+                    let w0 = edge(p1, p2, p) / area;
+                    let w1 = edge(p2, p0, p) / area;
+                    let w2 = edge(p0, p1, p) / area;
+                    if w0 < 0f32 || w1 < 0f32 || w2 < 0f32 {
+                        continue;
+                    }
+
+                    let depth = w0 * screen[0].2 + w1 * screen[1].2 + w2 * screen[2].2;
+                    if !(-1f32..=1f32).contains(&depth) {
+                        continue;
+                    }
+
+                    // Perspective correct interpolation of the varyings
+                    let (b0, b1, b2) = (w0 * screen[0].3, w1 * screen[1].3, w2 * screen[2].3);
+                    let norm = 1f32 / (b0 + b1 + b2);
+                    let (b0, b1, b2) = (b0 * norm, b1 * norm, b2 * norm);
+                    let uv = triangle[0].uv * b0 + triangle[1].uv * b1 + triangle[2].uv * b2;
+                    let color =
+                        triangle[0].color * b0 + triangle[1].color * b1 + triangle[2].color * b2;
+
+                    fragments.push((px, py, depth, self.shade(stage, uv, color)));
+                }
+            }
+        }
+
+        let (depth_test, stencil_mode, blending) =
+            (self.depth_test, self.stencil_mode, self.blending);
+        let (image, depth_buffer, stencil) = self.target();
+        for (x, y, depth, src) in fragments {
+            let index = (y * image.width + x) as usize;
+            match stencil_mode {
+                StencilMode::Disabled => {}
+                // Masks only write the stencil, colors & depth are kept
+                StencilMode::WriteMask => {
+                    stencil[index] = 1;
+                    continue;
+                }
+                StencilMode::VisibleInside if stencil[index] != 1 => continue,
+                StencilMode::VisibleOutside if stencil[index] == 1 => continue,
+                _ => {}
+            }
+            if depth_test {
+                if depth * 0.5f32 + 0.5f32 >= depth_buffer[index] {
+                    continue;
+                }
+                depth_buffer[index] = depth * 0.5f32 + 0.5f32;
+            }
+
+            // Same glBlendFunc factors as the OpenGL device, on all channels
+            let dst = image.get(x, y);
+            let alpha = src[3];
+            let out = match blend_mode {
+                _ if !blending => src,
+                BlendMode::Alpha => [0, 1, 2, 3].map(|i| src[i] * alpha + dst[i] * (1f32 - alpha)),
+                BlendMode::Additive => [0, 1, 2, 3].map(|i| src[i] * alpha + dst[i]),
+                BlendMode::Multiply => [0, 1, 2, 3].map(|i| src[i] * dst[i]),
+                BlendMode::Opaque => src,
+            };
+            image.set(x, y, out);
+        }
+    }
+}
+
+impl GfxDeviceSoftware {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(SoftwareState {
+                next_handle: 0u32,
+                screen: SoftwareImage::new(width, height, true),
+                screen_depth: vec![1f32; (width * height) as usize],
+                screen_stencil: vec![0u8; (width * height) as usize],
+                programs: HashMap::new(),
+                vertex_arrays: HashMap::new(),
+                storage_buffers: HashMap::new(),
+                uniform_buffers: HashMap::new(),
+                uniform_bindings: HashMap::new(),
+                uniform_table: UniformTable::default(),
+                textures: HashMap::new(),
+                framebuffers: HashMap::new(),
+                bound_framebuffer: None,
+                bound_program: None,
+                viewport: Rect {
+                    x: 0,
+                    y: 0,
+                    width,
+                    height,
+                },
+                clear_color: [0f32, 0f32, 0f32, 1f32],
+                depth_test: false,
+                stencil_mode: StencilMode::Disabled,
+                blending: false,
+            })),
+        }
+    }
+
+    pub fn shader_api(&self) -> GfxSoftwareShaderApi {
+        GfxSoftwareShaderApi {
+            state: self.state.clone(),
+        }
+    }
+
+    // Content of the default framebuffer (what would be presented on the window)
+    pub fn read_screen(&self) -> SoftwareImage {
+        self.state.borrow().screen.clone()
+    }
+
+    // Content of the uniform buffer bound to the binding point
+    pub fn read_uniform_block(&self, binding: u32) -> Option<Vec<f32>> {
+        let state = self.state.borrow();
+        let buffer = state.uniform_bindings.get(&binding)?;
+        state.uniform_buffers.get(buffer).cloned()
+    }
+
+    pub fn read_texture(&self, handle: u32) -> Option<SoftwareImage> {
+        self.state.borrow().textures.get(&handle).cloned()
+    }
+
+    pub fn screenshot(&self) -> RgbaImage {
+        self.state.borrow().screen.to_rgba_image()
+    }
+
+    pub fn save_screenshot(&self, path: &str) -> Result<(), String> {
+        self.screenshot()
+            .save(path)
+            .map_err(|err| format!("[Software Device] Failed to save {}: {}", path, err))
+    }
+}
+
+impl GfxApiDevice for GfxDeviceSoftware {
+    // GLSL is not compiled, only an #error directive makes a source fail
+    fn alloc_shader(&self, source: String, _s_type: ShaderType) -> Result<u32, String> {
+        if let Some(log) = get_error_directive_log(&source) {
+            return Err(format!("[Shader] Compilation Error: {}", log));
+        }
+        Ok(self.state.borrow_mut().alloc_handle())
+    }
+
+    fn release_shader(&self, _shader_handle: u32) {}
+
+    fn alloc_shader_module(
+        &self,
+        _vertex: u32,
+        _frag: u32,
+        material: &Material,
+    ) -> Result<ShaderModule, String> {
+        let mut state = self.state.borrow_mut();
+        let handle = state.alloc_handle();
+        state.programs.insert(handle, SoftwareProgram::default());
+
+        Ok(ShaderModule {
+            self_handle: handle,
+            vertex_handle: None,
+            fragment_handle: None,
+            texture_handles: vec![],
+            material: material.clone(),
+        })
+    }
+
+    fn release_shader_module(&self, module_handle: u32) {
+        self.state.borrow_mut().programs.remove(&module_handle);
+    }
+
+    fn use_shader_module(&self, module_handle: u32) {
+        self.state.borrow_mut().bound_program = Option::from(module_handle);
+    }
+
+    fn alloc_shader_storage_buffer(&self, data: &Vec<Vector4<f32>>) -> ShaderStorageBuffer {
+        let mut state = self.state.borrow_mut();
+        let vao_handle = state.alloc_handle();
+        let self_handle = state.alloc_handle();
+        state.storage_buffers.insert(self_handle, data.clone());
+
+        ShaderStorageBuffer {
+            vao_handle,
+            self_handle,
+            count: data.len(),
+        }
+    }
+
+    fn alloc_uniform_buffer(&self, binding: u32, count: usize) -> UniformBuffer {
+        let mut state = self.state.borrow_mut();
+        let self_handle = state.alloc_handle();
+        state.uniform_buffers.insert(self_handle, vec![0f32; count]);
+
+        UniformBuffer {
+            self_handle,
+            binding,
+            count,
+        }
+    }
+
+    fn update_uniform_buffer(&self, buffer: &UniformBuffer, data: &[f32]) {
+        let mut state = self.state.borrow_mut();
+        if let Some(content) = state.uniform_buffers.get_mut(&buffer.self_handle) {
+            let floats_count = usize::min(data.len(), content.len());
+            content[..floats_count].copy_from_slice(&data[..floats_count]);
+        }
+    }
+
+    fn bind_uniform_buffer(&self, buffer: &UniformBuffer) {
+        let mut state = self.state.borrow_mut();
+        state
+            .uniform_bindings
+            .insert(buffer.binding, buffer.self_handle);
+    }
+
+    fn alloc_buffer(
+        &self,
+        vertices_set: Vec<Vec<f32>>,
+        indices: Vec<Vec<u32>>,
+        settings: BufferSettings,
+    ) -> BufferModule {
+        let mut state = self.state.borrow_mut();
+        let handle = state.alloc_handle();
+
+        state.vertex_arrays.insert(
+            handle,
+            SoftwareVertexArray {
+                vertices: vertices_set.first().cloned().unwrap_or_default(),
+                indices: indices.first().cloned().unwrap_or_default(),
+                vertex_size: settings.vertex_size as usize,
+                uvs_size: settings.uvs_size as usize,
+                colors_size: settings.colors_size as usize,
+                instances: vec![],
+            },
+        );
+
+        let vertices_count: Vec<u32> = vertices_set.iter().map(|x| x.len() as u32).collect();
+        BufferModule {
+            handle,
+            shader_storage: None,
+            buffer_handles: Option::from(vec![]),
+            buffer_attributes: None,
+            vertices: if settings.keep_vertices {
+                Option::from(vertices_set)
+            } else {
+                None
+            },
+            vertices_count: Option::from(vertices_count),
+            instance_buffer: settings.instanced.then_some(handle),
+        }
+    }
+
+    fn release_buffer(&self, module: BufferModule) {
+        self.state.borrow_mut().vertex_arrays.remove(&module.handle);
+    }
+
+    fn update_buffer(&self, module: &BufferModule, vertices: &[f32]) {
+        if let Some(vao) = self
+            .state
+            .borrow_mut()
+            .vertex_arrays
+            .get_mut(&module.handle)
+        {
+            vao.vertices = vertices.to_vec();
+        }
+    }
+
+    fn update_instance_buffer(&self, module: &BufferModule, instances: &[f32]) {
+        if let Some(vao) = self
+            .state
+            .borrow_mut()
+            .vertex_arrays
+            .get_mut(&module.handle)
+        {
+            vao.instances = instances.to_vec();
+        }
+    }
+
+    fn alloc_framebuffer(
+        &self,
+        width: i32,
+        height: i32,
+        format: FramebufferFormat,
+    ) -> Result<FrameBuffer, &str> {
+        let texture = self.alloc_framebuffer_texture(width, height, format);
+        let mut state = self.state.borrow_mut();
+        let handle = state.alloc_handle();
+
+        state.framebuffers.insert(
+            handle,
+            SoftwareFramebuffer {
+                texture,
+                depth: vec![1f32; (width * height) as usize],
+                stencil: vec![0u8; (width * height) as usize],
+            },
+        );
+
+        Ok(FrameBuffer {
+            self_handle: handle,
+            texture_attachment: texture,
+            depth_attachment: 0,
+            width,
+            height,
+            format,
+        })
+    }
+
+    fn release_framebuffer(&self, framebuffer: FrameBuffer) {
+        let mut state = self.state.borrow_mut();
+        state.framebuffers.remove(&framebuffer.self_handle);
+        state.textures.remove(&framebuffer.texture_attachment);
+        if state.bound_framebuffer == Some(framebuffer.self_handle) {
+            state.bound_framebuffer = None;
+        }
+    }
+
+    fn use_framebuffer(&self, framebuffer: Option<&FrameBuffer>) {
+        let mut state = self.state.borrow_mut();
+        state.bound_framebuffer = framebuffer.map(|fbo| fbo.self_handle);
+        // Same as the OpenGL device, cameras are composited in order without depth test & masks
+        state.depth_test = false;
+        state.stencil_mode = StencilMode::Disabled;
+    }
+
+    fn blit_main_framebuffer(&self, buffer_module: &BufferModule, framebuffer: &FrameBuffer) {
+        let vertices = self.state.borrow().quad_vertices(buffer_module);
+        self.state.borrow_mut().rasterize(
+            &vertices,
+            FragmentStage::Blit(framebuffer.texture_attachment),
+            BlendMode::Alpha,
+        );
+    }
+
+    fn draw_fullscreen_pass(&self, buffer_module: &BufferModule, textures: &[u32]) {
+        let (vertices, program) = {
+            let state = self.state.borrow();
+            (state.quad_vertices(buffer_module), state.bound_program)
+        };
+        let (Some(program), Some(source)) = (program, textures.first()) else {
+            return;
+        };
+
+        let stage = FragmentStage::PostEffect {
+            program,
+            source: *source,
+            second: textures.get(1).copied(),
+        };
+        self.state
+            .borrow_mut()
+            .rasterize(&vertices, stage, BlendMode::Alpha);
+    }
+
+    fn alloc_framebuffer_texture(&self, width: i32, height: i32, format: FramebufferFormat) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let handle = state.alloc_handle();
+        let mut image = SoftwareImage::new(width as u32, height as u32, false);
+        image.hdr = format == FramebufferFormat::Rgba16F;
+        state.textures.insert(handle, image);
+        handle
+    }
+
+    fn alloc_texture(&self, _sp_hdl: u32, texture: &Texture) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let handle = state.alloc_handle();
+        state
+            .textures
+            .insert(handle, SoftwareImage::from_texture(texture));
+        handle
+    }
+
+    fn release_texture(&self, tex_id: u32) {
+        self.state.borrow_mut().textures.remove(&tex_id);
+    }
+
+    fn draw_command(&self, command: &RenderCommand, procedural: Option<i32>) {
+        let mut state = self.state.borrow_mut();
+        let blend_mode = command.shader_module.material.blend_mode;
+
+        match (procedural, command.buffer_module.shader_storage.as_ref()) {
+            (Some(count), Some(_)) => {
+                let vertices = state.polyline_vertices(command, count);
+                state.rasterize(&vertices, FragmentStage::Color, blend_mode);
+            }
+            (count, _) => {
+                let texture = command.shader_module.texture_handles.first().copied();
+                let vertices = state.sprite_vertices(command, count);
+                state.rasterize(&vertices, FragmentStage::Sprite(texture), blend_mode);
+            }
+        }
+    }
+
+    fn draw_command_instanced(&self, command: &RenderCommand, instances: i32) {
+        let mut state = self.state.borrow_mut();
+        let texture = command.shader_module.texture_handles.first().copied();
+        let vertices = state.instanced_vertices(command, instances);
+        let blend_mode = command.shader_module.material.blend_mode;
+        state.rasterize(&vertices, FragmentStage::Sprite(texture), blend_mode);
+    }
+
+    fn clear_color(&self, color: ARGB8Color) {
+        // Same as the OpenGL device, the clear alpha is always opaque
+        self.state.borrow_mut().clear_color = [
+            color.r as f32 / 255f32,
+            color.g as f32 / 255f32,
+            color.b as f32 / 255f32,
+            1f32,
+        ];
+    }
+
+    fn update_viewport(&self, x: u32, y: u32, width: u32, height: u32) {
+        self.state.borrow_mut().viewport = Rect {
+            x,
+            y,
+            width,
+            height,
+        };
+    }
+
+    fn clear_buffers(&self) {
+        let mut state = self.state.borrow_mut();
+        let clear_color = state.clear_color;
+        let (image, depth, stencil) = state.target();
+
+        let has_alpha = image.has_alpha;
+        image.pixels.iter_mut().for_each(|pixel| {
+            *pixel = [
+                clear_color[0],
+                clear_color[1],
+                clear_color[2],
+                if has_alpha { clear_color[3] } else { 1f32 },
+            ]
+        });
+        depth.iter_mut().for_each(|value| *value = 1f32);
+        stencil.iter_mut().for_each(|value| *value = 0u8);
+    }
+
+    fn enable_blending(&self) {
+        self.state.borrow_mut().blending = true;
+    }
+
+    fn set_depth_test(&self, enabled: bool) {
+        self.state.borrow_mut().depth_test = enabled;
+    }
+
+    fn set_stencil_mode(&self, mode: StencilMode) {
+        self.state.borrow_mut().stencil_mode = mode;
+    }
+}
+
+impl GfxApiShader for GfxSoftwareShaderApi {
+    fn set_attribute_i32(&self, sp_hdl: u32, identifier: &str, value: i32) {
+        self.state
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::I32(value));
+    }
+
+    fn set_attribute_f32(&self, sp_hdl: u32, identifier: &str, value: f32) {
+        self.state
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::F32(value));
+    }
+
+    fn set_attribute_vector2f(&self, sp_hdl: u32, identifier: &str, vec: &Vector2<f32>) {
+        self.state
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::Vec2(*vec));
+    }
+
+    fn set_attribute_mat4(&self, sp_hdl: u32, identifier: &str, value: &Matrix4<f32>) {
+        self.state
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::Mat4(*value));
+    }
+
+    fn set_attribute_bool(&self, sp_hdl: u32, identifier: &str, value: bool) {
+        self.state
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::Bool(value));
+    }
+
+    fn set_attribute_color(&self, sp_hdl: u32, identifier: &str, value: glm::Vec4) {
+        self.state
+            .borrow_mut()
+            .set_uniform(sp_hdl, identifier, UniformValue::Vec4(value));
+    }
+
+    fn set_texture_unit(&self, _prog_hdl: u32, _texture_pos: i32) {}
+
+    fn reflect_uniforms(&self, sp_hdl: u32) {
+        self.state
+            .borrow_mut()
+            .uniform_table
+            .insert_program(sp_hdl, HashMap::new());
+    }
+
+    fn release_uniforms(&self, sp_hdl: u32) {
+        self.state.borrow_mut().uniform_table.remove_program(sp_hdl);
+    }
+
+    fn get_uniform_location(&self, sp_hdl: u32, identifier: &str) -> Option<i32> {
+        Some(
+            self.state
+                .borrow_mut()
+                .uniform_table
+                .get_or_insert_location(sp_hdl, identifier),
+        )
+    }
+
+    fn set_uniform_i32(&self, handle: &UniformHandle<i32>, value: i32) {
+        self.state.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::I32(value),
+        );
+    }
+
+    fn set_uniform_f32(&self, handle: &UniformHandle<f32>, value: f32) {
+        self.state.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::F32(value),
+        );
+    }
+
+    fn set_uniform_vector2f(&self, handle: &UniformHandle<Vector2<f32>>, vec: &Vector2<f32>) {
+        self.state.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::Vec2(*vec),
+        );
+    }
+
+    fn set_uniform_mat4(&self, handle: &UniformHandle<Matrix4<f32>>, value: &Matrix4<f32>) {
+        self.state.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::Mat4(*value),
+        );
+    }
+
+    fn set_uniform_bool(&self, handle: &UniformHandle<bool>, value: bool) {
+        self.state.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::Bool(value),
+        );
+    }
+
+    fn set_uniform_color(&self, handle: &UniformHandle<Vector4<f32>>, value: Vector4<f32>) {
+        self.state.borrow_mut().set_uniform_at(
+            handle.program,
+            handle.location,
+            UniformValue::Vec4(value),
+        );
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0f32, 1f32);
+    t * t * (3f32 - 2f32 * t)
+}
+
+// Whether the p -> q ray crosses the a -> b segment
+fn intersects(p: Vector2<f32>, q: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>) -> bool {
+    let (d, e) = (q - p, b - a);
+    let denom = d.x * e.y - d.y * e.x;
+    if denom.abs() < 0.000001f32 {
+        return false;
+    }
+    let ap = a - p;
+    let t = (ap.x * e.y - ap.y * e.x) / denom;
+    let u = (ap.x * d.y - ap.y * d.x) / denom;
+    t > 0f32 && t < 1f32 && (0f32..=1f32).contains(&u)
+}
+
+// Part of the light source seen from the pixel, the segments follow the lights in the LightData
+fn shadow_visibility(data: &[f32], world: Vector2<f32>, light: Vector2<f32>, softness: f32) -> f32 {
+    let to_light = light - world;
+    let dist = glm::length(to_light);
+    if dist < 0.0001f32 {
+        return 1f32;
+    }
+    let side = Vector2::new(-to_light.y, to_light.x) / dist;
+    let segments = LIGHT_HEADER_FLOATS + MAX_LIGHTS * LIGHT_FLOATS;
+    let count = data[LIGHT_HEADER_FLOATS - 4] as usize;
+    let samples = if softness > 0f32 { SHADOW_SAMPLES } else { 1 };
+
+    let visible = (0..samples)
+        .filter(|s| {
+            let offset = ((*s as f32 + 0.5f32) / samples as f32 - 0.5f32) * softness;
+            let target = light + side * offset;
+            !(0..count).any(|i| {
+                let segment = &data[segments + i * 4..segments + i * 4 + 4];
+                let a = Vector2::new(segment[0], segment[1]);
+                let b = Vector2::new(segment[2], segment[3]);
+                intersects(world, target, a, b)
+            })
+        })
+        .count();
+    visible as f32 / samples as f32
+}
+
+// ACES filmic approximation (Narkowicz)
+fn aces(color: Vector3<f32>) -> Vector3<f32> {
+    let curve = |x: f32| {
+        let mapped = (x * (2.51f32 * x + 0.03f32)) / (x * (2.43f32 * x + 0.59f32) + 0.14f32);
+        mapped.clamp(0f32, 1f32)
+    };
+    Vector3::new(curve(color.x), curve(color.y), curve(color.z))
+}
+
+// LUT strip lookup, blue selects the slice and both nearest slices are blended
+fn grade(lut: &SoftwareImage, color: Vector3<f32>, size: f32) -> Vector3<f32> {
+    if size < 2f32 {
+        return color;
+    }
+
+    let slice = color.z.clamp(0f32, 1f32) * (size - 1f32);
+    let first = slice.floor();
+    let second = (first + 1f32).min(size - 1f32);
+
+    let x = (color.x.clamp(0f32, 1f32) * (size - 1f32) + 0.5f32) / (size * size);
+    let y = 1f32 - (color.y.clamp(0f32, 1f32) * (size - 1f32) + 0.5f32) / size;
+    let a = lut.sample(Vector2::new(x + first / size, y));
+    let b = lut.sample(Vector2::new(x + second / size, y));
+    let t = slice - first;
+    Vector3::new(
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+    )
+}
@@ -0,0 +1,133 @@
+use super::components::{
+    ARGB8Color, AmbientLight, LightKind, RenderingLight, RenderingShadowCaster,
+};
+use crate::engine::utils::maths::identity_mat4;
+use glm::{GenSquareMat, Matrix4, Vector2, Vector3};
+
+// 2D lighting: lit cameras draw their scene unlit, then the lights are accumulated in a buffer
+// starting from the camera ambient light, and the buffer is multiplied with the scene. Both
+// passes are effects of postprocess_fragment.shader, the lights are read from the LightData block.
+// Shadows are cast by testing the rays from each pixel to the light against the casters edges.
+
+// LightData uniform block (std140): INV_VIEW_PROJ (16 floats), ambient color & lights count
+// (4 floats), shadow segments count (4 floats), then MAX_LIGHTS lights of LIGHT_FLOATS: position,
+// radius & falloff (4 floats), color & kind (4 floats), direction & cosines of the cone edges
+// (4 floats), shadow softness & casting (4 floats), then MAX_SHADOW_SEGMENTS segments (4 floats)
+pub const LIGHT_BLOCK_BINDING: u32 = 1;
+pub const MAX_LIGHTS: usize = 32;
+pub const MAX_SHADOW_SEGMENTS: usize = 128;
+pub const LIGHT_HEADER_FLOATS: usize = 24;
+pub const LIGHT_FLOATS: usize = 16;
+pub const LIGHT_BLOCK_FLOATS: usize =
+    LIGHT_HEADER_FLOATS + MAX_LIGHTS * LIGHT_FLOATS + MAX_SHADOW_SEGMENTS * 4;
+
+// Rays cast toward the light source width for soft shadows, hard shadows cast a single one
+pub const SHADOW_SAMPLES: usize = 8;
+
+// Light kinds in the block, global lights are added to the ambient instead
+pub const LIGHT_POINT: f32 = 0f32;
+pub const LIGHT_SPOT: f32 = 1f32;
+
+// The outer quarter of a spot light cone fades out
+const SPOT_SOFT_EDGE: f32 = 0.25f32;
+
+pub fn get_light_color(color: &ARGB8Color, intensity: f32) -> Vector3<f32> {
+    Vector3::new(
+        color.r as f32 / 255f32,
+        color.g as f32 / 255f32,
+        color.b as f32 / 255f32,
+    ) * intensity
+}
+
+// Content of the LightData uniform block, lights after MAX_LIGHTS and segments after
+// MAX_SHADOW_SEGMENTS are ignored
+pub fn get_light_block_data(
+    ambient: &AmbientLight,
+    lights: &[RenderingLight],
+    shadow_casters: &[RenderingShadowCaster],
+    view: &Matrix4<f32>,
+    proj: &Matrix4<f32>,
+) -> Vec<f32> {
+    let mut data: Vec<f32> = Vec::with_capacity(LIGHT_BLOCK_FLOATS);
+    let inverse = proj.mul_m(view).inverse().unwrap_or_else(identity_mat4);
+    for column in [inverse.c0, inverse.c1, inverse.c2, inverse.c3] {
+        data.extend_from_slice(&[column.x, column.y, column.z, column.w]);
+    }
+
+    let ambient = lights
+        .iter()
+        .filter(|light| light.kind == LightKind::Global)
+        .fold(
+            get_light_color(&ambient.color, ambient.intensity),
+            |sum, light| sum + get_light_color(&light.color, light.intensity),
+        );
+    let local_lights: Vec<&RenderingLight> = lights
+        .iter()
+        .filter(|light| light.kind != LightKind::Global)
+        .take(MAX_LIGHTS)
+        .collect();
+    data.extend_from_slice(&[ambient.x, ambient.y, ambient.z, local_lights.len() as f32]);
+
+    let segments = get_shadow_segments(shadow_casters);
+    data.extend_from_slice(&[segments.len() as f32, 0f32, 0f32, 0f32]);
+
+    for light in local_lights {
+        let color = get_light_color(&light.color, light.intensity);
+        let (kind, outer, inner) = match light.kind {
+            LightKind::Spot { angle } => {
+                let half_angle = (angle * 0.5f32).to_radians();
+                let outer = half_angle.cos();
+                let inner = (half_angle * (1f32 - SPOT_SOFT_EDGE)).cos();
+                (LIGHT_SPOT, outer, inner.max(outer + 0.0001f32))
+            }
+            _ => (LIGHT_POINT, -1f32, 1f32),
+        };
+        let rotation = light.rotation.to_radians();
+
+        data.extend_from_slice(&[
+            light.position.x,
+            light.position.y,
+            light.radius,
+            light.falloff,
+            color.x,
+            color.y,
+            color.z,
+            kind,
+            rotation.cos(),
+            rotation.sin(),
+            outer,
+            inner,
+            light.shadow_softness.max(0f32),
+            if light.cast_shadows { 1f32 } else { 0f32 },
+            0f32,
+            0f32,
+        ]);
+    }
+    data.resize(LIGHT_HEADER_FLOATS + MAX_LIGHTS * LIGHT_FLOATS, 0f32);
+
+    for (a, b) in segments {
+        data.extend_from_slice(&[a.x, a.y, b.x, b.y]);
+    }
+    data.resize(LIGHT_BLOCK_FLOATS, 0f32);
+
+    data
+}
+
+// Edges of the casters outlines, closed from the last point back to the first one
+fn get_shadow_segments(
+    shadow_casters: &[RenderingShadowCaster],
+) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+    shadow_casters
+        .iter()
+        .flat_map(|caster| {
+            let points = &caster.points;
+            let edges = match points.len() {
+                0 | 1 => 0,
+                2 => 1,
+                count => count,
+            };
+            (0..edges).map(move |i| (points[i], points[(i + 1) % points.len()]))
+        })
+        .take(MAX_SHADOW_SEGMENTS)
+        .collect()
+}
\ No newline at end of file
@@ -0,0 +1,135 @@
+use super::shaders::{BlendMode, Material, MaterialValue, ShaderInfo, ShaderType};
+use crate::engine::utils::file_system::{FileSystem, FileType};
+
+// Material files: the look of a sprite described in assets/materials, so it can be authored
+// without recompiling. Sprites refer to them by file name (see SpriteRenderer2D::with_material),
+// the entries left out keep the values of Material::new:
+//
+//     # shaders, the default ones are used when omitted
+//     fragment detail_fragment.shader
+//     define DETAIL_SCALE 4.0
+//     keywords SCROLL
+//
+//     # texture <unit> <file>, unit 0 is the main texture (the sprite texture takes precedence)
+//     # other units are sampled as texture<unit> in the shaders
+//     texture 1 Dark/texture_02.png
+//
+//     # uniform <name> <1, 2 or 4 floats>, uploaded before drawing the sprite
+//     uniform detail_strength 0.5
+//     uniform scroll 0.1 0
+//
+//     color 1 0.9 0.8 1
+//     blend additive
+//     priority 2
+//     pixels_per_unit 64
+//
+// Sprites with extra textures or uniforms are drawn directly, batches only share the blend mode.
+
+pub const MAX_TEXTURE_UNITS: u32 = 16; // Minimum guaranteed by OpenGL for fragment shaders
+
+impl Material {
+    pub fn load_file(file_name: &str) -> Result<Material, String> {
+        let content = FileSystem::load_file(file_name, FileType::Material)?;
+        Material::parse(&content).map_err(|err| format!("{} ({})", err, file_name))
+    }
+
+    pub fn parse(content: &str) -> Result<Material, String> {
+        let mut material = Material::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let error = |message: &str| format!("[Material] line {}: {}", index + 1, message);
+            let parse_floats = |values: &[&str]| -> Result<Vec<f32>, String> {
+                values
+                    .iter()
+                    .map(|value| value.parse::<f32>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error("values must be numbers"))
+            };
+
+            match tokens.as_slice() {
+                [] => continue,
+                [comment, ..] if comment.starts_with('#') => continue,
+                ["vertex", file] => {
+                    material.shaders.vertex = Option::from(ShaderInfo::with_name(
+                        String::from(*file),
+                        ShaderType::Vertex,
+                    ));
+                }
+                ["fragment", file] => {
+                    material.shaders.fragment = Option::from(ShaderInfo::with_name(
+                        String::from(*file),
+                        ShaderType::Fragment,
+                    ));
+                }
+                ["define", define @ ..] if !define.is_empty() && define.len() <= 2 => {
+                    material.shaders.defines.push(define.join(" "));
+                }
+                ["keywords", keywords @ ..] => {
+                    material
+                        .shaders
+                        .keywords
+                        .extend(keywords.iter().map(|keyword| String::from(*keyword)));
+                }
+                ["texture", unit, file] => {
+                    let unit = match unit.parse::<u32>() {
+                        Ok(unit) if unit < MAX_TEXTURE_UNITS => unit,
+                        _ => {
+                            return Err(error(&format!(
+                                "texture unit must be an integer below {}",
+                                MAX_TEXTURE_UNITS
+                            )))
+                        }
+                    };
+                    let file = String::from(*file);
+
+                    if unit == 0 {
+                        material.main_texture = Option::from(file);
+                    } else if material.texture_slots.iter().any(|(slot, _)| *slot == unit) {
+                        return Err(error(&format!("texture unit {} is already used", unit)));
+                    } else {
+                        material.texture_slots.push((unit, file));
+                    }
+                }
+                ["uniform", name, values @ ..] => {
+                    let value = match parse_floats(values)?.as_slice() {
+                        [x] => MaterialValue::Float(*x),
+                        [x, y] => MaterialValue::Vec2(glm::vec2(*x, *y)),
+                        [x, y, z, w] => MaterialValue::Vec4(glm::vec4(*x, *y, *z, *w)),
+                        _ => return Err(error("uniforms hold 1, 2 or 4 values")),
+                    };
+
+                    // The last value of a uniform wins
+                    material.uniforms.retain(|(uniform, _)| uniform != name);
+                    material.uniforms.push((String::from(*name), value));
+                }
+                ["color", values @ ..] if values.len() == 4 => {
+                    let color = parse_floats(values)?;
+                    material.color = glm::vec4(color[0], color[1], color[2], color[3]);
+                }
+                ["blend", mode] => {
+                    material.blend_mode = match *mode {
+                        "alpha" => BlendMode::Alpha,
+                        "additive" => BlendMode::Additive,
+                        "multiply" => BlendMode::Multiply,
+                        "opaque" => BlendMode::Opaque,
+                        _ => return Err(error(&format!("unknown blend mode {}", mode))),
+                    };
+                }
+                ["priority", priority] => {
+                    material.render_priority = priority
+                        .parse::<i8>()
+                        .map_err(|_| error("priority must be an integer in [-128, 127]"))?;
+                }
+                ["pixels_per_unit", pixels] => match pixels.parse::<u8>() {
+                    Ok(pixels) if pixels > 0 => material.pixel_per_unit = pixels,
+                    _ => return Err(error("pixels per unit must be an integer in [1, 255]")),
+                },
+                _ => return Err(error(&format!("unknown entry \"{}\"", line.trim()))),
+            }
+        }
+
+        material.texture_slots.sort_by_key(|(unit, _)| *unit);
+        Ok(material)
+    }
+}
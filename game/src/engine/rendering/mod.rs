@@ -1,9 +1,18 @@
 pub mod renderer;
 pub mod renderer_helpers;
 pub mod shaders;
+pub mod sprite_batch;
 pub mod gfx_device;
 pub mod opengl;
 pub mod renderer_storage;
 pub mod gfx_opengl_shaders;
+pub mod gfx_recording;
+pub mod gfx_software;
 pub mod components;
-pub mod debug;
\ No newline at end of file
+pub mod atlas;
+pub mod debug;
+pub mod post_processing;
+pub mod lighting;
+pub mod shader_preprocessor;
+pub mod shader_watcher;
+pub mod material;
\ No newline at end of file
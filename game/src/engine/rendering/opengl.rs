@@ -1,22 +1,27 @@
-use super::components::{ARGB8Color, BufferSettings, FrameBuffer, ShaderStorageBuffer};
+use super::components::{
+    ARGB8Color, BufferSettings, FrameBuffer, FramebufferFormat, ShaderStorageBuffer,
+    StencilMode, UniformBuffer,
+};
 use super::shaders::Texture;
 use crate::engine::rendering::gfx_device;
-use crate::engine::rendering::gfx_device::{BufferModule, RenderCommand, ShaderModule};
-use crate::engine::rendering::shaders::Material;
+use crate::engine::rendering::gfx_device::{
+    BufferModule, RenderCommand, ShaderModule, INSTANCE_FLOATS,
+};
+use crate::engine::rendering::shader_preprocessor::map_compile_log;
+use crate::engine::rendering::shaders::{BlendMode, Material};
 use crate::engine::rendering::shaders::ShaderType;
 use gfx_device::GfxApiDevice;
-use gl::types::{GLsizei, GLsizeiptr};
+use gl::types::{GLenum, GLsizei, GLsizeiptr};
 use glm::Vector4;
-use std::cell::RefCell;
 use std::ffi::CString;
-use std::mem::size_of;
+use std::mem::{size_of, size_of_val};
 use std::ptr;
 
 #[derive(Default)]
 pub struct GfxDeviceOpengl;
 
 impl GfxApiDevice for GfxDeviceOpengl {
-    fn alloc_shader(&self, source: String, s_type: ShaderType) -> u32 {
+    fn alloc_shader(&self, source: String, s_type: ShaderType) -> Result<u32, String> {
         #[allow(unused)]
         let mut shader_handle = 0u32;
 
@@ -53,17 +58,28 @@ impl GfxApiDevice for GfxDeviceOpengl {
                     ptr::null_mut(),
                     info_log.as_mut_ptr().cast(),
                 );
-                println!(
+                let log = String::from_utf8(info_log).unwrap();
+                gl::DeleteShader(shader_handle);
+                return Err(format!(
                     "[Shader] Compilation Error: {}",
-                    String::from_utf8(info_log).unwrap()
-                );
+                    map_compile_log(&source, log.trim_end_matches('\0'))
+                ));
             }
         }
 
-        shader_handle
+        Ok(shader_handle)
+    }
+
+    fn release_shader(&self, shader_handle: u32) {
+        unsafe { gl::DeleteShader(shader_handle) }
     }
 
-    fn alloc_shader_module(&self, vertex: u32, frag: u32, material: &Material) -> ShaderModule {
+    fn alloc_shader_module(
+        &self,
+        vertex: u32,
+        frag: u32,
+        material: &Material,
+    ) -> Result<ShaderModule, String> {
         #[allow(unused)]
         let mut program_handle = 0u32;
         let delete_shader: bool = true; // @todo: create a config for this
@@ -87,17 +103,29 @@ impl GfxApiDevice for GfxDeviceOpengl {
             let mut linked = 0;
             gl::GetProgramiv(program_handle, gl::LINK_STATUS, &mut linked);
             if linked == 0 {
-                println!("[OpenGL Shader Link] Shader program is not linked!");
+                let mut info_log: Vec<u8> = vec![0; 1024];
+                gl::GetProgramInfoLog(
+                    program_handle,
+                    1024,
+                    ptr::null_mut(),
+                    info_log.as_mut_ptr().cast(),
+                );
+                let log = String::from_utf8(info_log).unwrap();
+                gl::DeleteProgram(program_handle);
+                return Err(format!(
+                    "[OpenGL Shader Link] Shader program is not linked: {}",
+                    log.trim_end_matches('\0')
+                ));
             }
         }
 
-        ShaderModule {
+        Ok(ShaderModule {
             self_handle: program_handle,
             fragment_handle: frag_hd,
             vertex_handle: vert_hd,
             texture_handles: vec![],
             material: material.clone(),
-        }
+        })
     }
 
     fn release_shader_module(&self, module_handle: u32) {
@@ -139,6 +167,49 @@ impl GfxApiDevice for GfxDeviceOpengl {
         }
     }
 
+    fn alloc_uniform_buffer(&self, binding: u32, count: usize) -> UniformBuffer {
+        let mut buffer_handle = 0u32;
+
+        unsafe {
+            gl::GenBuffers(1, &mut buffer_handle as *mut u32);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer_handle);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                (size_of::<f32>() * count) as GLsizeiptr,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+
+        UniformBuffer {
+            self_handle: buffer_handle,
+            binding,
+            count,
+        }
+    }
+
+    fn update_uniform_buffer(&self, buffer: &UniformBuffer, data: &[f32]) {
+        let floats_count = usize::min(data.len(), buffer.count);
+
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer.self_handle);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                (size_of::<f32>() * floats_count) as GLsizeiptr,
+                data.as_ptr().cast(),
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+
+    fn bind_uniform_buffer(&self, buffer: &UniformBuffer) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, buffer.binding, buffer.self_handle);
+        }
+    }
+
     fn alloc_buffer(
         &self,
         vertices_set: Vec<Vec<f32>>,
@@ -147,6 +218,7 @@ impl GfxApiDevice for GfxDeviceOpengl {
     ) -> BufferModule {
         let mut vao_handle = 0u32;
         let mut buffer_handles: Vec<u32> = Vec::new();
+        let mut instance_handle: Option<u32> = None;
 
         unsafe {
             gl::GenVertexArrays(1, ptr::addr_of_mut!(vao_handle));
@@ -157,8 +229,8 @@ impl GfxApiDevice for GfxDeviceOpengl {
             for vertex_buffer in &vertices_set {
                 let mut vbo_handles: u32 = 0;
                 let buffer_size: usize = size_of::<f32>() * vertex_buffer.len();
-                let vertex_stride: usize =
-                    size_of::<f32>() * (settings.vertex_size + settings.uvs_size) as usize;
+                let vertex_stride: usize = size_of::<f32>()
+                    * (settings.vertex_size + settings.uvs_size + settings.colors_size) as usize;
 
                 gl::GenBuffers(1, ptr::addr_of_mut!(vbo_handles));
                 gl::BindBuffer(gl::ARRAY_BUFFER, vbo_handles);
@@ -191,6 +263,20 @@ impl GfxApiDevice for GfxDeviceOpengl {
                 );
                 gl::EnableVertexAttribArray(1);
 
+                // colors attribute (only used by batched vertices)
+                if settings.colors_size > 0 {
+                    gl::VertexAttribPointer(
+                        2,
+                        settings.colors_size,
+                        gl::FLOAT,
+                        gl::FALSE,
+                        vertex_stride as GLsizei,
+                        ((settings.vertex_size + settings.uvs_size) as usize * size_of::<f32>())
+                            as *const _,
+                    );
+                    gl::EnableVertexAttribArray(2);
+                }
+
                 if indices.len() > i {
                     let mut ebo_handles: u32 = 0;
 
@@ -208,6 +294,30 @@ impl GfxApiDevice for GfxDeviceOpengl {
 
                 buffer_handles.push(vbo_handles);
 
+                // per instance attributes: TRS (mat4 is 4 vec4 locations), color and uv rect
+                if settings.instanced && instance_handle.is_none() {
+                    let mut ibo_handle: u32 = 0;
+                    let instance_stride: usize = size_of::<f32>() * INSTANCE_FLOATS;
+
+                    gl::GenBuffers(1, ptr::addr_of_mut!(ibo_handle));
+                    gl::BindBuffer(gl::ARRAY_BUFFER, ibo_handle);
+
+                    for location in 0..6u32 {
+                        gl::VertexAttribPointer(
+                            2 + location,
+                            4,
+                            gl::FLOAT,
+                            gl::FALSE,
+                            instance_stride as GLsizei,
+                            (location as usize * 4 * size_of::<f32>()) as *const _,
+                        );
+                        gl::EnableVertexAttribArray(2 + location);
+                        gl::VertexAttribDivisor(2 + location, 1);
+                    }
+
+                    instance_handle = Option::from(ibo_handle);
+                }
+
                 gl::BindVertexArray(0);
                 i += 1;
             }
@@ -229,6 +339,7 @@ impl GfxApiDevice for GfxDeviceOpengl {
                 None
             },
             vertices_count: Option::from(buffers_sizes),
+            instance_buffer: instance_handle,
         }
     }
 
@@ -241,10 +352,56 @@ impl GfxApiDevice for GfxDeviceOpengl {
                     gl::DeleteBuffers(1, ptr::addr_of!(handle));
                 }
             }
+
+            if let Some(handle) = module.instance_buffer {
+                gl::DeleteBuffers(1, ptr::addr_of!(handle));
+            }
+        }
+    }
+
+    fn update_buffer(&self, module: &BufferModule, vertices: &[f32]) {
+        // The vertex buffer is always pushed after the optional index buffer
+        let Some(vbo_handle) = module.buffer_handles.as_ref().and_then(|x| x.last()) else {
+            println!("[GFX DEVICE] Can't update a buffer module without vertex buffer");
+            return;
+        };
+
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, *vbo_handle);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                size_of_val(vertices) as GLsizeiptr,
+                vertices.as_ptr().cast(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
+    fn update_instance_buffer(&self, module: &BufferModule, instances: &[f32]) {
+        let Some(ibo_handle) = module.instance_buffer else {
+            println!("[GFX DEVICE] Can't update instances of a non instanced buffer module");
+            return;
+        };
+
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, ibo_handle);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                size_of_val(instances) as GLsizeiptr,
+                instances.as_ptr().cast(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
     }
 
-    fn alloc_framebuffer(&self, width: i32, height: i32) -> Result<FrameBuffer, &str> {
+    fn alloc_framebuffer(
+        &self,
+        width: i32,
+        height: i32,
+        format: FramebufferFormat,
+    ) -> Result<FrameBuffer, &str> {
         let mut fbo: u32 = 0;
         #[allow(unused)]
         let mut tex_hdl: u32 = 0;
@@ -254,7 +411,7 @@ impl GfxApiDevice for GfxDeviceOpengl {
             gl::GenFramebuffers(1, &mut fbo);
             gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
 
-            tex_hdl = self.alloc_framebuffer_texture(width, height);
+            tex_hdl = self.alloc_framebuffer_texture(width, height, format);
             gl::FramebufferTexture2D(
                 gl::FRAMEBUFFER,
                 gl::COLOR_ATTACHMENT0,
@@ -287,11 +444,21 @@ impl GfxApiDevice for GfxDeviceOpengl {
         Ok(FrameBuffer {
             self_handle: fbo,
             texture_attachment: tex_hdl,
+            depth_attachment: rbo_handle,
             width,
             height,
+            format,
         })
     }
 
+    fn release_framebuffer(&self, framebuffer: FrameBuffer) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &framebuffer.self_handle);
+            gl::DeleteRenderbuffers(1, &framebuffer.depth_attachment);
+            gl::DeleteTextures(1, &framebuffer.texture_attachment);
+        }
+    }
+
     fn use_framebuffer(&self, framebuffer: Option<&FrameBuffer>) {
         let mut handle: u32 = 0;
 
@@ -302,13 +469,11 @@ impl GfxApiDevice for GfxDeviceOpengl {
         unsafe {
             gl::BindFramebuffer(gl::FRAMEBUFFER, handle);
 
-            // @warning: maybe a shortcut here.. if the framebuffer is empty then we assume it's bliting the main framebuffer to the screen
-            if framebuffer.is_none() {
-                gl::Enable(gl::DEPTH_TEST);
-            } else {
-                gl::Disable(gl::DEPTH_TEST)
-            }
+            // Sprites are sorted back-to-front and cameras are composited by order on the screen,
+            // none of them rely on the depth test or on the sprite masks
+            gl::Disable(gl::DEPTH_TEST);
         }
+        self.set_stencil_mode(StencilMode::Disabled);
     }
 
     fn blit_main_framebuffer(&self, screen_module: &BufferModule, framebuffer: &FrameBuffer) {
@@ -319,8 +484,27 @@ impl GfxApiDevice for GfxDeviceOpengl {
         }
     }
 
-    fn alloc_framebuffer_texture(&self, width: i32, height: i32) -> u32 {
+    fn draw_fullscreen_pass(&self, screen_module: &BufferModule, textures: &[u32]) {
+        unsafe {
+            gl::BindVertexArray(screen_module.handle);
+            textures.iter().enumerate().for_each(|(i, texture)| {
+                gl::ActiveTexture(gl::TEXTURE0 + i as u32);
+                gl::BindTexture(gl::TEXTURE_2D, *texture);
+            });
+            gl::DrawArrays(gl::TRIANGLES, 0, 6);
+
+            // The framebuffer blit binds its texture on the active unit
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(0);
+        }
+    }
+
+    fn alloc_framebuffer_texture(&self, width: i32, height: i32, format: FramebufferFormat) -> u32 {
         let mut texture_handle: u32 = 0;
+        let (internal_format, pixel_format, pixel_type) = match format {
+            FramebufferFormat::Rgba8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            FramebufferFormat::Rgba16F => (gl::RGBA16F, gl::RGBA, gl::FLOAT),
+        };
 
         unsafe {
             gl::GenTextures(1, &mut texture_handle);
@@ -329,12 +513,12 @@ impl GfxApiDevice for GfxDeviceOpengl {
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0i32,
-                gl::RGB as i32,
+                internal_format as i32,
                 width,
                 height,
                 0i32,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
+                pixel_format,
+                pixel_type,
                 ptr::null(),
             );
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
@@ -402,7 +586,9 @@ impl GfxApiDevice for GfxDeviceOpengl {
         unsafe {
             gl::BindVertexArray(command.buffer_module.handle);
             gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            let blend_mode = command.shader_module.material.blend_mode;
+            let (source, destination) = get_blend_factors(blend_mode);
+            gl::BlendFunc(source, destination);
 
             // if there is a shader buffer object, bind it !
             if let Some(sso) = command.buffer_module.shader_storage.as_ref() {
@@ -428,6 +614,34 @@ impl GfxApiDevice for GfxDeviceOpengl {
             gl::BindVertexArray(0);
             gl::BindTexture(gl::TEXTURE_2D, 0);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            // Blits & post passes blend with alpha
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+
+    fn draw_command_instanced(&self, command: &RenderCommand, instances: i32) {
+        unsafe {
+            gl::BindVertexArray(command.buffer_module.handle);
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+            let blend_mode = command.shader_module.material.blend_mode;
+            let (source, destination) = get_blend_factors(blend_mode);
+            gl::BlendFunc(source, destination);
+
+            command
+                .shader_module
+                .texture_handles
+                .iter()
+                .enumerate()
+                .for_each(|(i, x)| {
+                    gl::ActiveTexture(gl::TEXTURE0 + i as u32);
+                    gl::BindTexture(gl::TEXTURE_2D, *x);
+                });
+
+            gl::DrawElementsInstanced(gl::TRIANGLES, 6, gl::UNSIGNED_INT, ptr::null(), instances);
+
+            gl::BindVertexArray(0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
         }
     }
 
@@ -448,43 +662,11 @@ impl GfxApiDevice for GfxDeviceOpengl {
         }
     }
 
-    fn set_update_viewport_callback(
-        &self,
-        window: &mut glfw::Window,
-        viewport: RefCell<Vector4<f32>>,
-    ) {
-        window.set_size_callback(move |window: &mut glfw::Window, w: i32, h: i32| {
-            let (scaled_width, scaled_height) = window.get_framebuffer_size();
-
-            let vp_borrow = viewport.borrow();
-            let final_x = scaled_width as f32 * vp_borrow.x;
-            let final_y = scaled_height as f32 * vp_borrow.y;
-            let final_w = scaled_width as f32 * vp_borrow.z;
-            let final_h = scaled_height as f32 * vp_borrow.w;
-
-            #[cfg(debug_assertions)]
-            {
-                let (w_factor, h_factor) = window.get_content_scale();
-                println!(
-                    "Window screen coords resized: {}x{} (scale factor {}x{})",
-                    w, h, w_factor, h_factor
-                );
-            }
-
-            unsafe {
-                gl::Viewport(
-                    final_x as i32,
-                    final_y as i32,
-                    final_w as i32,
-                    final_h as i32,
-                );
-            }
-        });
-    }
-
     fn clear_buffers(&self) {
         unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            // The stencil is only cleared when its writes are allowed
+            gl::StencilMask(0xFF);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
         }
     }
 
@@ -495,4 +677,57 @@ impl GfxApiDevice for GfxDeviceOpengl {
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
         }
     }
+
+    fn set_depth_test(&self, enabled: bool) {
+        unsafe {
+            if enabled {
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(gl::LESS);
+            } else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+        }
+    }
+
+    fn set_stencil_mode(&self, mode: StencilMode) {
+        unsafe {
+            match mode {
+                StencilMode::Disabled => gl::Disable(gl::STENCIL_TEST),
+                StencilMode::WriteMask => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+                }
+                StencilMode::VisibleInside | StencilMode::VisibleOutside => {
+                    let func = if mode == StencilMode::VisibleInside {
+                        gl::EQUAL
+                    } else {
+                        gl::NOTEQUAL
+                    };
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(func, 1, 0xFF);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+                }
+            }
+
+            // Masks only write the stencil
+            let writes = if mode == StencilMode::WriteMask {
+                gl::FALSE
+            } else {
+                gl::TRUE
+            };
+            gl::ColorMask(writes, writes, writes, writes);
+            gl::DepthMask(writes);
+        }
+    }
+}
+
+// glBlendFunc factors of the blend mode, applied on all channels like the software device
+fn get_blend_factors(mode: BlendMode) -> (GLenum, GLenum) {
+    match mode {
+        BlendMode::Alpha => (gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+        BlendMode::Additive => (gl::SRC_ALPHA, gl::ONE),
+        BlendMode::Multiply => (gl::DST_COLOR, gl::ZERO),
+        BlendMode::Opaque => (gl::ONE, gl::ZERO),
+    }
 }
@@ -0,0 +1,398 @@
+use super::components::{CameraPass, FrameBuffer, FrameStats, PostEffect};
+use super::gfx_device::{BufferModule, GfxDevice, ShaderModule, UniformHandle};
+use super::renderer_helpers::get_or_alloc_program;
+use super::renderer_storage::{ProgramKey, RendererStorage};
+use super::shaders::Material;
+use crate::engine::utils::maths::Rect;
+use glm::{Vector2, Vector4};
+
+// Post processing: the effects of a camera stack are applied in order, one fullscreen pass each.
+// The scene is drawn in the first ping-pong buffer, every effect samples the previous pass and the
+// last one writes into the camera framebuffer, which is then composited or sampled as a render
+// texture like any camera without effects.
+// Bloom takes several passes: the bright parts are downsampled in a chain of half size mips, each
+// mip is then upsampled and added to the previous one, the result is added to the image.
+// The 2D lighting of lit cameras also runs as two fullscreen passes, before the effects.
+
+const POST_PROCESS_VERTEX_SHADER: &str = "framebuffer_vertex.shader";
+const POST_PROCESS_FRAGMENT_SHADER: &str = "postprocess_fragment.shader";
+const LUT_TEXTURE_UNIT: i32 = 1;
+pub const MAX_BLOOM_ITERATIONS: u32 = 8;
+
+// Effect ids, see postprocess_fragment.shader
+pub const POST_VIGNETTE: i32 = 0;
+pub const POST_COLOR_GRADING: i32 = 1;
+pub const POST_CHROMATIC_ABERRATION: i32 = 2;
+pub const POST_BLUR: i32 = 3;
+pub const POST_PIXELATION: i32 = 4;
+pub const POST_BLOOM_PREFILTER: i32 = 5;
+pub const POST_BLOOM_DOWNSAMPLE: i32 = 6;
+pub const POST_BLOOM_UPSAMPLE: i32 = 7;
+pub const POST_BLOOM_COMPOSITE: i32 = 8;
+pub const POST_TONEMAPPING: i32 = 9;
+pub const POST_LIGHT_ACCUMULATION: i32 = 10;
+pub const POST_LIGHT_COMBINE: i32 = 11;
+
+struct PostProcessUniforms {
+    effect: Option<UniformHandle<i32>>,
+    params: Option<UniformHandle<Vector4<f32>>>,
+    texel_size: Option<UniformHandle<Vector2<f32>>>,
+}
+
+pub struct PostProcessor {
+    module: ShaderModule,
+    uniforms: PostProcessUniforms,
+}
+
+// One fullscreen pass, texture1 is the LUT or a bloom mip
+struct PostPass<'a> {
+    source: &'a FrameBuffer,
+    second_texture: Option<u32>,
+    target: &'a FrameBuffer,
+    effect_id: i32,
+    params: Vector4<f32>,
+}
+
+impl PostProcessUniforms {
+    fn resolve(gfx: &GfxDevice, program: u32) -> Self {
+        Self {
+            effect: gfx.get_uniform_handle(program, "effect"),
+            params: gfx.get_uniform_handle(program, "params"),
+            texel_size: gfx.get_uniform_handle(program, "texel_size"),
+        }
+    }
+}
+
+impl PostEffect {
+    pub fn get_effect_id(&self) -> i32 {
+        match self {
+            PostEffect::Vignette { .. } => POST_VIGNETTE,
+            PostEffect::ColorGrading { .. } => POST_COLOR_GRADING,
+            PostEffect::ChromaticAberration { .. } => POST_CHROMATIC_ABERRATION,
+            PostEffect::Blur { .. } => POST_BLUR,
+            PostEffect::Pixelation { .. } => POST_PIXELATION,
+            PostEffect::Bloom { .. } => POST_BLOOM_COMPOSITE,
+            PostEffect::Tonemapping { .. } => POST_TONEMAPPING,
+        }
+    }
+
+    // Shader params, the LUT size (y) of color grading is set by the post processor.
+    // Bloom passes share theirs: intensity (x) for the composite, threshold (y) for the prefilter
+    pub fn get_params(&self) -> Vector4<f32> {
+        match self {
+            PostEffect::Vignette {
+                intensity,
+                smoothness,
+            } => Vector4::new(*intensity, *smoothness, 0f32, 0f32),
+            PostEffect::ColorGrading { intensity, .. } => {
+                Vector4::new(*intensity, 0f32, 0f32, 0f32)
+            }
+            PostEffect::ChromaticAberration { intensity } => {
+                Vector4::new(*intensity, 0f32, 0f32, 0f32)
+            }
+            PostEffect::Blur { radius } => Vector4::new(*radius, 0f32, 0f32, 0f32),
+            PostEffect::Pixelation { cell_size } => Vector4::new(*cell_size, 0f32, 0f32, 0f32),
+            PostEffect::Bloom {
+                threshold,
+                intensity,
+                ..
+            } => Vector4::new(*intensity, *threshold, 0f32, 0f32),
+            PostEffect::Tonemapping { exposure } => Vector4::new(*exposure, 0f32, 0f32, 0f32),
+        }
+    }
+
+    pub fn get_lut(&self) -> Option<&String> {
+        match self {
+            PostEffect::ColorGrading { lut, .. } => Option::from(lut),
+            _ => None,
+        }
+    }
+
+    pub fn get_bloom_iterations(&self) -> Option<u32> {
+        match self {
+            PostEffect::Bloom { iterations, .. } => {
+                Option::from((*iterations).clamp(1, MAX_BLOOM_ITERATIONS))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Bloom mips of a camera: the downsampled ones, then the upsampled ones (all but the smallest).
+// Bloom effects of a stack share the chain, it is sized for the most iterations.
+pub fn get_bloom_buffer_sizes(effects: &[PostEffect], width: i32, height: i32) -> Vec<(i32, i32)> {
+    let iterations = effects
+        .iter()
+        .filter_map(PostEffect::get_bloom_iterations)
+        .max()
+        .unwrap_or(0) as usize;
+
+    let mut sizes = Vec::with_capacity(iterations * 2);
+    let (mut mip_width, mut mip_height) = (width, height);
+    for _ in 0..iterations {
+        mip_width = (mip_width / 2).max(1);
+        mip_height = (mip_height / 2).max(1);
+        sizes.push((mip_width, mip_height));
+    }
+    for i in (0..iterations.saturating_sub(1)).rev() {
+        sizes.push(sizes[i]);
+    }
+    sizes
+}
+
+impl PostProcessor {
+    pub fn new(gfx: &GfxDevice, store: &mut RendererStorage) -> Self {
+        let key = ProgramKey {
+            vertex: String::from(POST_PROCESS_VERTEX_SHADER),
+            fragment: String::from(POST_PROCESS_FRAGMENT_SHADER),
+            defines: vec![],
+            keywords: vec![],
+        };
+        let program = get_or_alloc_program(gfx, store, &key)
+            .unwrap_or_else(|err| panic!("[Post Processing] Could not build program: {}", err));
+        gfx.shader_api.set_texture_unit(program, LUT_TEXTURE_UNIT);
+
+        Self {
+            module: ShaderModule {
+                self_handle: program,
+                vertex_handle: None,
+                fragment_handle: None,
+                texture_handles: vec![],
+                material: Material::new(),
+            },
+            uniforms: PostProcessUniforms::resolve(gfx, program),
+        }
+    }
+
+    // Swap a reloaded program, the LUT unit & the uniform handles are set again
+    pub fn replace_program(&mut self, gfx: &GfxDevice, handle: u32, new_handle: u32) {
+        if self.module.self_handle != handle {
+            return;
+        }
+
+        gfx.shader_api.set_texture_unit(new_handle, LUT_TEXTURE_UNIT);
+        self.module.self_handle = new_handle;
+        self.uniforms = PostProcessUniforms::resolve(gfx, new_handle);
+    }
+
+    // LUTs live in the textures cache, each effect sampling one holds a reference
+    pub fn acquire_luts(
+        &self,
+        gfx: &GfxDevice,
+        store: &mut RendererStorage,
+        effects: &[PostEffect],
+    ) {
+        for lut in effects.iter().filter_map(PostEffect::get_lut) {
+            if store.has_gpu_texture_refs(lut) {
+                let handle = store.get_gpu_texture_handle(lut);
+                store.increment_texture_handle(lut, handle);
+                continue;
+            }
+
+            match store.load_texture(lut) {
+                Ok(texture) => {
+                    let handle = gfx.alloc_texture(self.module.self_handle, &texture);
+                    store.increment_texture_handle(lut, handle);
+                }
+                Err(err) => println!("[Post Process] Failed to load LUT {}: {}", lut, err),
+            }
+        }
+    }
+
+    pub fn release_luts(store: &mut RendererStorage, effects: &[PostEffect]) {
+        for lut in effects.iter().filter_map(PostEffect::get_lut) {
+            store.decrement_texture_handle(lut);
+        }
+    }
+
+    // Draws the camera effects, each pass sets the viewport to the size of its target
+    pub fn apply(
+        &self,
+        gfx: &GfxDevice,
+        store: &RendererStorage,
+        pass: &CameraPass,
+        screen_quad: &BufferModule,
+        stats: &mut FrameStats,
+    ) {
+        let (Some(framebuffer), Some(scene)) =
+            (pass.framebuffer.as_ref(), pass.post_buffers.first())
+        else {
+            return;
+        };
+
+        gfx.use_shader_module(&self.module);
+
+        let last = pass.post_effects.len() - 1;
+        let mut source = scene;
+        for (i, effect) in pass.post_effects.iter().enumerate() {
+            let target = if i == last {
+                framebuffer
+            } else {
+                &pass.post_buffers[(i + 1) % pass.post_buffers.len()]
+            };
+
+            let mut post_pass = PostPass {
+                source,
+                second_texture: None,
+                target,
+                effect_id: effect.get_effect_id(),
+                params: effect.get_params(),
+            };
+
+            if let Some(iterations) = effect.get_bloom_iterations() {
+                self.apply_bloom(
+                    gfx,
+                    &pass.bloom_buffers,
+                    iterations,
+                    post_pass,
+                    screen_quad,
+                    stats,
+                );
+                source = target;
+                continue;
+            }
+
+            if let Some(lut) = effect.get_lut() {
+                let texture = store
+                    .has_gpu_texture_refs(lut)
+                    .then(|| store.load_texture(lut).ok())
+                    .flatten();
+                match texture {
+                    Some(texture) => {
+                        post_pass.params.y = texture.height as f32;
+                        post_pass.second_texture = Option::from(store.get_gpu_texture_handle(lut));
+                    }
+                    // Without its LUT the effect only copies the previous pass
+                    None => post_pass.params.x = 0f32,
+                }
+            }
+
+            self.draw_pass(gfx, &post_pass, screen_quad);
+            stats.post_effects += 1;
+            source = target;
+        }
+    }
+
+    // Prefilter & downsample chain, every mip is then upsampled into the bigger one and the
+    // biggest is added to the source by the composite pass
+    fn apply_bloom(
+        &self,
+        gfx: &GfxDevice,
+        bloom_buffers: &[FrameBuffer],
+        iterations: u32,
+        composite: PostPass,
+        screen_quad: &BufferModule,
+        stats: &mut FrameStats,
+    ) {
+        if bloom_buffers.is_empty() {
+            return;
+        }
+
+        let levels = bloom_buffers.len() / 2 + 1;
+        let iterations = (iterations as usize).min(levels);
+        let (down, up) = bloom_buffers.split_at(levels);
+
+        let mut previous = composite.source;
+        for (i, mip) in down.iter().take(iterations).enumerate() {
+            let post_pass = PostPass {
+                source: previous,
+                second_texture: None,
+                target: mip,
+                effect_id: if i == 0 {
+                    POST_BLOOM_PREFILTER
+                } else {
+                    POST_BLOOM_DOWNSAMPLE
+                },
+                params: composite.params,
+            };
+            self.draw_pass(gfx, &post_pass, screen_quad);
+            stats.post_effects += 1;
+            previous = mip;
+        }
+
+        // Upsampled mips are stored from the smallest, up[j] is the size of down[levels - 2 - j]
+        for level in (0..iterations - 1).rev() {
+            let post_pass = PostPass {
+                source: previous,
+                second_texture: Option::from(down[level].texture_attachment),
+                target: &up[levels - 2 - level],
+                effect_id: POST_BLOOM_UPSAMPLE,
+                params: composite.params,
+            };
+            self.draw_pass(gfx, &post_pass, screen_quad);
+            stats.post_effects += 1;
+            previous = post_pass.target;
+        }
+
+        let composite = PostPass {
+            second_texture: Option::from(previous.texture_attachment),
+            ..composite
+        };
+        self.draw_pass(gfx, &composite, screen_quad);
+        stats.post_effects += 1;
+    }
+
+    // The lights are accumulated from the unlit scene, then multiplied with it where the scene of
+    // an unlit camera is drawn. The LightData block of the camera must be bound
+    pub fn apply_lighting(
+        &self,
+        gfx: &GfxDevice,
+        pass: &CameraPass,
+        screen_quad: &BufferModule,
+        stats: &mut FrameStats,
+    ) {
+        let scene = pass.post_buffers.first().or(pass.framebuffer.as_ref());
+        let (Some(scene), [unlit, lights]) = (scene, pass.light_buffers.as_slice()) else {
+            return;
+        };
+
+        gfx.use_shader_module(&self.module);
+        let params = Vector4::new(0f32, 0f32, 0f32, 0f32);
+        let accumulation = PostPass {
+            source: unlit,
+            second_texture: None,
+            target: lights,
+            effect_id: POST_LIGHT_ACCUMULATION,
+            params,
+        };
+        self.draw_pass(gfx, &accumulation, screen_quad);
+
+        let combine = PostPass {
+            source: unlit,
+            second_texture: Option::from(lights.texture_attachment),
+            target: scene,
+            effect_id: POST_LIGHT_COMBINE,
+            params,
+        };
+        self.draw_pass(gfx, &combine, screen_quad);
+        stats.lit_cameras += 1;
+    }
+
+    fn draw_pass(&self, gfx: &GfxDevice, post_pass: &PostPass, screen_quad: &BufferModule) {
+        let (source, target) = (post_pass.source, post_pass.target);
+        gfx.use_framebuffer(Some(target));
+        gfx.update_viewport(Rect {
+            x: 0,
+            y: 0,
+            width: target.width as u32,
+            height: target.height as u32,
+        });
+
+        if let Some(uniform) = self.uniforms.effect.as_ref() {
+            gfx.shader_api.set_uniform_i32(uniform, post_pass.effect_id);
+        }
+        if let Some(uniform) = self.uniforms.params.as_ref() {
+            gfx.shader_api.set_uniform_color(uniform, post_pass.params);
+        }
+        // Texel size of the sampled pass
+        if let Some(uniform) = self.uniforms.texel_size.as_ref() {
+            let size = Vector2::new(1f32 / source.width as f32, 1f32 / source.height as f32);
+            gfx.shader_api.set_uniform_vector2f(uniform, &size);
+        }
+
+        let textures: Vec<u32> = std::iter::once(source.texture_attachment)
+            .chain(post_pass.second_texture)
+            .collect();
+        gfx.draw_fullscreen_pass(screen_quad, &textures);
+    }
+}
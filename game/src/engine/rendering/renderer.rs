@@ -1,38 +1,45 @@
 extern crate gl;
 extern crate glfw;
+use super::atlas::TextureAtlas;
 use super::components::{
-    ARGB8Color, RenderUpdate, RenderingCamera, RenderingUpdateState,
+    ARGB8Color, CameraPass, DepthSortMode, FrameStats, FramebufferFormat, PostEffect, RenderUpdate,
+    RenderingCamera, RenderingLight, RenderingMode, RenderingShadowCaster, RenderingSpriteMask,
+    RenderingUpdateState, StencilMode, UniformBuffer,
 };
-use super::gfx_device::BufferModule;
+use super::gfx_device::{BufferModule, FRAME_BLOCK_BINDING, FRAME_BLOCK_FLOATS};
+use super::lighting::{get_light_block_data, LIGHT_BLOCK_BINDING, LIGHT_BLOCK_FLOATS};
 use super::renderer_helpers::{
-    compute_gfx_viewport_rect, get_material_changes, get_shader_info_or_default,
-    shader_texture_update, MaterialUpdateMask, TextureUpdateReq, COLOR_MASK, TEXTURE_MASK,
-    TRANSFORM_MASK,
+    compile_program, compute_camera_target_rect, compute_gfx_viewport_rect, get_frame_block_data,
+    get_material_changes, get_or_alloc_program, get_or_alloc_texture, get_program_key,
+    get_shader_info_or_default, set_material_uniforms, shader_texture_update, MaterialUpdateMask,
+    RenderSortKey, TextureUpdateReq, COLOR_MASK, PROPERTIES_MASK, SORTING_MASK, STENCIL_MASK,
+    TEXTURE_MASK, TRANSFORM_MASK, UV_RECT_MASK,
 };
 use super::{
-    components::{BufferSettings, FrameBuffer, RenderRequest, RenderState},
+    components::{BufferSettings, RenderRequest, RenderState},
     gfx_device::{RenderCommand, ShaderModule},
     gfx_opengl_shaders::GfxOpenGLShaderApi,
-    renderer_storage::RendererStorage,
+    post_processing::{get_bloom_buffer_sizes, PostProcessor},
+    renderer_storage::{ProgramKey, RendererStorage},
+    shader_watcher::ShaderWatcher,
     shaders::{Material, ShaderInfo, ShaderType},
+    sprite_batch::SpriteBatcher,
 };
-use crate::engine::ecs::components::Transform;
+use crate::engine::ecs::components::{Projection, Transform};
+use crate::engine::platform::glfw_platform::GlfwPlatform;
+use crate::engine::platform::platform_traits::{Platform, PlatformEvent};
 use crate::engine::rendering::debug::{Debug, DebugGrid};
 use crate::engine::rendering::gfx_device::GfxDevice;
 use crate::engine::rendering::opengl::GfxDeviceOpengl;
 use crate::engine::utils::maths::{
-    compute_projection, compute_trs, compute_view_matrix, Rect,
+    compute_projection, compute_trs, compute_view_matrix, identity_mat4, Frustum, Rect,
 };
-use crate::{
-    engine::{
-        inputs::keyboard::Keyboard, logging::logs_traits::LoggerBase,
-        utils::app_settings::WindowSettings,
-    },
-    WindowMode,
+use crate::engine::{
+    inputs::keyboard::Keyboard, logging::consts::ENGINE_RENDERING,
+    logging::logs_traits::LoggerBase, utils::app_settings::WindowSettings,
 };
-use glfw::ffi::glfwWindowHint;
-use glfw::{ffi::glfwInit, Action, Context, Glfw, GlfwReceiver, Key, PWindow, WindowEvent};
-use glm::{Matrix4, Vector4};
+use glfw::{Action, Key};
+use glm::{Matrix4, Vector3, Vector4};
 use std::cell::{Ref, RefMut};
 use std::{
     cell::RefCell,
@@ -42,23 +49,36 @@ use std::{
 
 pub type OnWindowResizedCb = dyn FnMut(&mut glfw::Window, i32, i32);
 pub type RenderCmdHd = usize;
+pub type CameraHd = usize;
+
+// Camera created when the renderer warms, the ECS main camera is bound to it
+pub const MAIN_CAMERA: CameraHd = 0;
 
 pub struct Renderer {
     keyboard_inputs: Arc<Mutex<Keyboard>>,
     rendering_state: RenderState,
     rendering_store: RendererStorage,
-    viewport_normalized: RefCell<Vector4<f32>>, // x, y, width, height (Range is [0; 1])
     window_rect: Rect<u32>,
-    main_camera: RenderingCamera,
-    updates_state: RenderingUpdateState,
+    cameras: Vec<CameraPass>, // Sorted by order, then by creation
+    next_camera_handle: CameraHd,
+    rendering_mode: RenderingMode,
+    depth_sort_mode: DepthSortMode,
+    frame_stats: FrameStats,
+    elapsed_time: f32,
+    lights: Vec<RenderingLight>, // Sent by the bridge every frame, like the casters & masks
+    shadow_casters: Vec<RenderingShadowCaster>,
+    sprite_masks: Vec<RenderingSpriteMask>, // Written in the stencil of every camera
+    shader_watcher: Option<ShaderWatcher>,  // Polled every frame when hot reload is enabled
 
-    main_framebuffer: Option<FrameBuffer>,
     screen_shader_module: Option<ShaderModule>,
     screen_quad_buffer: Option<BufferModule>,
+    sprite_batcher: Option<SpriteBatcher>,
+    post_processor: Option<PostProcessor>,
+    frame_block: Option<UniformBuffer>,
+    light_block: Option<UniformBuffer>,
+
+    platform: Box<dyn Platform>,
 
-    pub instance: Glfw,
-    pub window: PWindow,
-    pub events: GlfwReceiver<(f64, WindowEvent)>,
     pub log: Rc<dyn LoggerBase>,
     pub on_window_resized: Option<fn(i32, i32)>,
 
@@ -70,81 +90,58 @@ pub struct Renderer {
 
 impl Renderer {
     pub fn init_with_glfw(settings: &WindowSettings, log: Rc<dyn LoggerBase>) -> Self {
-        let mut instance = glfw::init(glfw::fail_on_errors).unwrap();
-
-        // Set the OpenGL version to 4.3 - todo! export this in opengl files
-        unsafe {
-            glfwInit();
-            glfwWindowHint(glfw::ffi::CONTEXT_VERSION_MAJOR, 4);
-            glfwWindowHint(glfw::ffi::CONTEXT_VERSION_MINOR, 3);
+        let platform = GlfwPlatform::init(settings);
+        let gfx_device = GfxDevice::new(
+            Rc::from(GfxDeviceOpengl::default()),
+            Rc::from(GfxOpenGLShaderApi::default()),
+        );
 
-            #[cfg(target_os = "macos")]
-            {
-                glfwWindowHint(glfw::ffi::OPENGL_PROFILE, glfw::ffi::OPENGL_CORE_PROFILE);
-                glfwWindowHint(glfw::ffi::OPENGL_FORWARD_COMPAT, glfw::ffi::TRUE);
-            }
-        }
+        Self::new(Box::new(platform), gfx_device, log)
+    }
 
-        let (window, events) = instance
-            .create_window(
-                settings.width,
-                settings.height,
-                "Game",
-                match settings.mode {
-                    WindowMode::Windowed => glfw::WindowMode::Windowed,
-                    _ => panic!(),
-                },
-            )
-            .expect("Failed to create window");
+    pub fn new(platform: Box<dyn Platform>, gfx_device: GfxDevice, log: Rc<dyn LoggerBase>) -> Self {
+        let (width, height) = platform.get_framebuffer_size();
 
         Self {
             keyboard_inputs: Arc::from(Mutex::from(Keyboard::new())),
             rendering_state: RenderState::Closed,
             rendering_store: RendererStorage::new(),
-            viewport_normalized: RefCell::new(glm::vec4(0.0, 0.0, 1.0, 1.0)),
             window_rect: Rect {
                 x: 0,
                 y: 0,
-                width: settings.width,
-                height: settings.height,
-            },
-            main_camera: RenderingCamera {
-                near: 0.1,
-                far: 50.0,
-                ppu: 100u32,
-                clear_color: ARGB8Color::black(),
-                transform: Transform::default(),
-            },
-            updates_state: RenderingUpdateState {
-                camera_settings: false,
-                camera_transform: false,
+                width: width as u32,
+                height: height as u32,
             },
+            cameras: vec![],
+            next_camera_handle: MAIN_CAMERA,
+            rendering_mode: RenderingMode::Batched,
+            depth_sort_mode: DepthSortMode::Z,
+            frame_stats: FrameStats::default(),
+            elapsed_time: 0f32,
+            lights: vec![],
+            shadow_casters: vec![],
+            sprite_masks: vec![],
+            shader_watcher: None,
 
-            main_framebuffer: None,
             screen_shader_module: None,
             screen_quad_buffer: None,
+            sprite_batcher: None,
+            post_processor: None,
+            frame_block: None,
+            light_block: None,
 
-            instance,
-            window,
-            events,
+            platform,
             log,
-            gfx_device: Option::from(Box::new(GfxDevice::new(
-                Rc::from(GfxDeviceOpengl::default()),
-                Rc::from(GfxOpenGLShaderApi::default()),
-            ))),
+            gfx_device: Option::from(Box::new(gfx_device)),
             on_window_resized: None,
             grid: None,
         }
     }
 
     pub fn warm(&mut self) {
-        self.window.set_cursor_pos_polling(true);
-        self.window.set_key_polling(true);
+        self.platform.load_gfx_functions();
 
-        // Load all function pointers from the graphic driver
-        gl::load_with(|procname: &str| self.window.get_proc_address(procname));
-
-        let (scaled_width, scaled_height) = self.window.get_framebuffer_size();
+        let (scaled_width, scaled_height) = self.platform.get_framebuffer_size();
 
         // Update viewport pixels just in case it would have changed
         self.window_rect.width = scaled_width as u32;
@@ -154,33 +151,23 @@ impl Renderer {
             .gfx_device
             .as_ref()
             .expect("Graphic device not allocated");
-        device.set_update_viewport_callback(&mut self.window, self.viewport_normalized.clone());
-
-        // Alloc the main framebuffer for post process, it will blit to the screen buffer
-        let frame_buffer: FrameBuffer = device.alloc_framebuffer(scaled_width, scaled_height);
 
-        // allocate base shaders and programs to show the main framebuffer quad to the whole screen
-        let vertex_info = ShaderInfo {
-            file_name: String::from("framebuffer_vertex.shader"),
-            shader_type: ShaderType::Vertex,
-            load_type: super::shaders::ShaderLoadType::OnDemand,
+        // allocate base shaders and programs to blit the cameras framebuffers to the screen.
+        // The program is cached like the others, the hot reload then covers it.
+        let screen_key = ProgramKey {
+            vertex: String::from("framebuffer_vertex.shader"),
+            fragment: String::from("framebuffer_fragment.shader"),
+            defines: vec![],
+            keywords: vec![],
         };
-        let fragment_info = ShaderInfo {
-            file_name: String::from("framebuffer_fragment.shader"),
-            shader_type: ShaderType::Fragment,
-            load_type: super::shaders::ShaderLoadType::OnDemand,
+        let shader_module = ShaderModule {
+            self_handle: get_or_alloc_program(device, &mut self.rendering_store, &screen_key)
+                .unwrap_or_else(|err| panic!("[Renderer] Could not build program: {}", err)),
+            vertex_handle: None,
+            fragment_handle: None,
+            texture_handles: vec![],
+            material: Material::new(),
         };
-        let vert_source = self
-            .rendering_store
-            .load_shader_content(&vertex_info)
-            .expect("Failed to create shader");
-        let frag_source = self
-            .rendering_store
-            .load_shader_content(&fragment_info)
-            .expect("Failed to create shader");
-        let vert_hdl = device.alloc_shader(vert_source, ShaderType::Vertex);
-        let frag_hdl = device.alloc_shader(frag_source, ShaderType::Fragment);
-        let shader_module = device.alloc_shader_module(vert_hdl, frag_hdl, &Material::new());
 
         // Alloc the quad buffer used to draw the entire viewport
         let screen_quad: BufferModule = device.alloc_buffer(
@@ -190,9 +177,19 @@ impl Renderer {
                 keep_vertices: false,
                 vertex_size: 2,
                 uvs_size: 2,
+                colors_size: 0,
+                instanced: false,
             },
         );
 
+        let sprite_batcher = SpriteBatcher::new(device);
+        let post_processor = PostProcessor::new(device, &mut self.rendering_store);
+
+        // Camera & frame data shared by all the programs, see FrameData in the vertex shaders
+        let frame_block = device.alloc_uniform_buffer(FRAME_BLOCK_BINDING, FRAME_BLOCK_FLOATS);
+        // Ambient & lights of the lit cameras, see LightData in postprocess_fragment.shader
+        let light_block = device.alloc_uniform_buffer(LIGHT_BLOCK_BINDING, LIGHT_BLOCK_FLOATS);
+
         // Build debug grid
         let grid = Debug::build_grid(
             self.gfx_device.as_mut().unwrap().as_mut(),
@@ -200,33 +197,47 @@ impl Renderer {
         );
         self.grid = Option::from(grid);
 
-        // store the main framebuffer to self
-        self.main_framebuffer = Option::from(frame_buffer);
         self.screen_shader_module = Option::from(shader_module);
         self.screen_quad_buffer = Option::from(screen_quad);
+        self.sprite_batcher = Option::from(sprite_batcher);
+        self.post_processor = Option::from(post_processor);
+        self.frame_block = Option::from(frame_block);
+        self.light_block = Option::from(light_block);
+
+        // Cameras framebuffers are allocated on their first render
+        self.add_camera(RenderingCamera::default());
     }
 
     pub fn get_keyboard_inputs(&self) -> Arc<Mutex<Keyboard>> {
         self.keyboard_inputs.clone()
     }
 
+    pub fn should_close(&self) -> bool {
+        self.platform.should_close()
+    }
+
     pub fn poll_events(&mut self) {
-        self.instance.poll_events();
+        let events: Vec<PlatformEvent> = self.platform.poll_events();
 
         let mut keyboard_inputs = self.keyboard_inputs.lock().unwrap();
         keyboard_inputs.pre_update_states();
 
-        for (_, event) in glfw::flush_messages(&self.events) {
+        for event in events {
             match event {
-                WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
-                    self.window.set_should_close(true)
+                PlatformEvent::Key(Key::Escape, Action::Press, _) => {
+                    self.platform.set_should_close(true)
                 }
-                _ => match event {
-                    WindowEvent::Key(k, _scan_code, action, modifier) => {
-                        keyboard_inputs.update_key_state(k, action, modifier);
-                    }
-                    _ => {}
-                },
+                PlatformEvent::Key(k, action, modifier) => {
+                    keyboard_inputs.update_key_state(k, action, modifier);
+                }
+                PlatformEvent::FramebufferResized(width, height) => {
+                    self.window_rect.width = width as u32;
+                    self.window_rect.height = height as u32;
+                    self.cameras
+                        .iter_mut()
+                        .for_each(|pass| pass.updates_state.camera_settings = true);
+                }
+                PlatformEvent::CloseRequested => self.platform.set_should_close(true),
             }
         }
     }
@@ -242,32 +253,56 @@ impl Renderer {
             .as_deref_mut()
             .expect("Graphic device not allocated");
 
-        let [vert_info, frag_info] = get_shader_info_or_default(&render_req);
-        let vs_content = self
-            .rendering_store
-            .load_shader_content(&vert_info)
-            .expect("[Renderer] Could not load shader");
-        let fs_content = self
-            .rendering_store
-            .load_shader_content(&frag_info)
-            .expect("[Renderer] Could not load shader");
+        // TRS is per object data and set before each draw, View & Proj live in the frame block
+        let trs_matrix: Matrix4<f32> = compute_trs(&render_req.transform);
 
-        let vs_hdl = gfx.alloc_shader(vs_content, ShaderType::Vertex);
-        let fs_hdl = gfx.alloc_shader(fs_content, ShaderType::Fragment);
+        // Materials sharing shaders & defines share the same program
+        let [vert_info, frag_info] = get_shader_info_or_default(&render_req);
+        let program_key = get_program_key(&vert_info, &frag_info, &render_req.material.shaders);
+        let store = &mut self.rendering_store;
+        let program_handle = match get_or_alloc_program(gfx, store, &program_key) {
+            Ok(program) => program,
+            Err(err) => {
+                // A broken shader doesn't stop the game, the default shaders draw the sprite
+                self.log.error(
+                    ENGINE_RENDERING,
+                    &format!("Could not build program, using the default shaders: {}", err),
+                );
+                let default_key = get_program_key(
+                    &ShaderInfo::default(ShaderType::Vertex),
+                    &ShaderInfo::default(ShaderType::Fragment),
+                    &Material::new().shaders,
+                );
+                get_or_alloc_program(gfx, &mut self.rendering_store, &default_key)
+                    .unwrap_or_else(|err| panic!("[Renderer] Could not build program: {}", err))
+            }
+        };
 
-        let mut shader_module = gfx.alloc_shader_module(vs_hdl, fs_hdl, &render_req.material);
+        let mut shader_module = ShaderModule {
+            self_handle: program_handle,
+            vertex_handle: None,
+            fragment_handle: None,
+            texture_handles: vec![],
+            material: render_req.material.clone(),
+        };
 
+        // Vertices are kept on CPU side so the command can be baked into a sprite batch
         let buffer_module = gfx.alloc_buffer(
             RendererStorage::load(&render_req.mesh_info),
             vec![RendererStorage::get_quad_indices()],
-            BufferSettings::quad_default(),
+            BufferSettings {
+                keep_vertices: true,
+                ..BufferSettings::quad_default()
+            },
         );
 
         if let Some(tex_name) = render_req.material.main_texture.as_ref() {
             let texture_handle: u32;
 
             // Try to load the gpu handle if possible, otherwise allocate a new texture on the gpu side
-            if !self.rendering_store.has_gpu_texture_refs(tex_name) {
+            if let Some(render_texture) = self.rendering_store.get_render_texture(tex_name) {
+                texture_handle = render_texture;
+            } else if !self.rendering_store.has_gpu_texture_refs(tex_name) {
                 let texture = self.rendering_store.load_texture(&tex_name).ok().unwrap();
                 texture_handle = gfx.alloc_texture(shader_module.self_handle, &texture);
 
@@ -279,32 +314,23 @@ impl Renderer {
                 texture_handle = self.rendering_store.get_gpu_texture_handle(tex_name);
             }
 
-            const DEFAULT_TEXTURE_IDX: i32 = 0;
-            gfx.shader_api
-                .set_texture_unit(shader_module.self_handle, DEFAULT_TEXTURE_IDX);
             shader_module.texture_handles.push(texture_handle);
         }
 
-        gfx.shader_api.set_attribute_color(
-            shader_module.self_handle,
-            "surface_color",
-            shader_module.material.color,
-        );
-
-        // compute the TRS, View and Proj matrix and forward them to the GPU device
-        let trs_matrix: Matrix4<f32> = compute_trs(&render_req.transform);
-        let view_matrix: Matrix4<f32> = compute_view_matrix(&self.main_camera.transform);
-        let proj_matrix: Matrix4<f32> = compute_projection(&self.main_camera, &self.window_rect);
-
-        gfx.shader_api
-            .set_attribute_mat4(shader_module.self_handle, "TRS", &trs_matrix);
-        gfx.shader_api
-            .set_attribute_mat4(shader_module.self_handle, "VIEW", &view_matrix);
-        gfx.shader_api
-            .set_attribute_mat4(shader_module.self_handle, "PROJ", &proj_matrix);
+        // The other textures of the material are bound to their own unit
+        for (unit, texture_name) in render_req.material.texture_slots.iter() {
+            let store = &mut self.rendering_store;
+            let texture_handle = get_or_alloc_texture(gfx, store, program_handle, texture_name);
+            shader_module.set_texture_handle(*unit as usize, texture_handle);
+        }
 
-        let command: RenderCommand = gfx.build_command(shader_module, buffer_module);
-        let command_handle = self.rendering_store.store_command(command, true);
+        let mut command: RenderCommand = gfx.build_command(shader_module, buffer_module);
+        command.trs = trs_matrix;
+        command.sorting = render_req.sorting;
+        command.uv_rect = self.rendering_store.get_uv_rect(&render_req.material);
+        command.mask_interaction = render_req.mask_interaction;
+        // Not pushed to the frame queue, the rendering bridge enqueues every live command each frame
+        let command_handle = self.rendering_store.store_command(command, false);
 
         command_handle
     }
@@ -324,6 +350,20 @@ impl Renderer {
             update_mask |= TRANSFORM_MASK;
         }
 
+        if let Some(sorting) = update_req.sorting {
+            if self.rendering_store.get_ref(update_req.render_cmd).sorting != sorting {
+                update_mask |= SORTING_MASK;
+            }
+        }
+
+        if let Some(mask_interaction) = update_req.mask_interaction {
+            if self.rendering_store.get_ref(update_req.render_cmd).mask_interaction
+                != mask_interaction
+            {
+                update_mask |= STENCIL_MASK;
+            }
+        }
+
         if update_mask == 0 {
             return false;
         }
@@ -335,12 +375,7 @@ impl Renderer {
                 self.rendering_store.get_mut_ref(update_req.render_cmd);
             let new_color: Vector4<f32> = update_req.material.as_ref().unwrap().color;
 
-            gpu.shader_api.set_attribute_color(
-                command.shader_module.self_handle,
-                "surface_color",
-                new_color,
-            );
-
+            // surface_color is uploaded before drawing the command, programs are shared
             command.shader_module.material.color = new_color;
         }
 
@@ -355,7 +390,9 @@ impl Renderer {
 
             let texture_handle: Option<u32>;
 
-            if self.rendering_store.has_gpu_texture_refs(texture_name) {
+            if let Some(render_texture) = self.rendering_store.get_render_texture(texture_name) {
+                texture_handle = Option::from(render_texture);
+            } else if self.rendering_store.has_gpu_texture_refs(texture_name) {
                 texture_handle =
                     Option::from(self.rendering_store.get_gpu_texture_handle(texture_name));
             } else {
@@ -390,13 +427,65 @@ impl Renderer {
         }
 
         if (update_mask & TRANSFORM_MASK) != 0 {
-            let command = self.rendering_store.get_ref(update_req.render_cmd);
-            let new_trs_mat4: Matrix4<f32> = compute_trs(update_req.transform.as_ref().unwrap());
-            gpu.shader_api.set_attribute_mat4(
-                command.shader_module.self_handle,
-                "TRS",
-                &new_trs_mat4,
-            );
+            let mut command = self.rendering_store.get_mut_ref(update_req.render_cmd);
+            command.trs = compute_trs(update_req.transform.as_ref().unwrap());
+        }
+
+        // A new atlas or region moves the sampled area of the texture
+        if (update_mask & (TEXTURE_MASK | UV_RECT_MASK)) != 0 {
+            let material = update_req.material.as_ref().unwrap();
+            let uv_rect = self.rendering_store.get_uv_rect(material);
+            let mut command = self.rendering_store.get_mut_ref(update_req.render_cmd);
+            command.shader_module.material.atlas_region = material.atlas_region.clone();
+            command.uv_rect = uv_rect;
+        }
+
+        // The new order is applied when the next frame queue is sorted
+        if (update_mask & SORTING_MASK) != 0 {
+            let mut command = self.rendering_store.get_mut_ref(update_req.render_cmd);
+            if let Some(sorting) = update_req.sorting {
+                command.sorting = sorting;
+            }
+            if let Some(material) = update_req.material.as_ref() {
+                command.shader_module.material.render_priority = material.render_priority;
+            }
+        }
+
+        if (update_mask & STENCIL_MASK) != 0 {
+            let mut command = self.rendering_store.get_mut_ref(update_req.render_cmd);
+            command.mask_interaction = update_req.mask_interaction.unwrap();
+        }
+
+        if (update_mask & PROPERTIES_MASK) != 0 {
+            let material = update_req.material.as_ref().unwrap();
+            let (program, previous_slots) = {
+                let command = self.rendering_store.get_ref(update_req.render_cmd);
+                let module = &command.shader_module;
+                (module.self_handle, module.material.texture_slots.clone())
+            };
+
+            // Textures are only fetched again when the slots changed, uniforms can change often
+            let mut slot_handles: Vec<(usize, Option<u32>)> = vec![];
+            if previous_slots != material.texture_slots {
+                slot_handles.extend(previous_slots.iter().map(|(unit, _)| (*unit as usize, None)));
+                for (unit, texture_name) in material.texture_slots.iter() {
+                    let store = &mut self.rendering_store;
+                    let handle = get_or_alloc_texture(gpu, store, program, texture_name);
+                    slot_handles.push((*unit as usize, handle));
+                }
+                // Released after the new references, the textures kept are never dropped
+                for (_, texture_name) in previous_slots.iter() {
+                    self.rendering_store.decrement_texture_handle(texture_name);
+                }
+            }
+
+            let mut command = self.rendering_store.get_mut_ref(update_req.render_cmd);
+            for (unit, handle) in slot_handles {
+                command.shader_module.set_texture_handle(unit, handle);
+            }
+            command.shader_module.material.texture_slots = material.texture_slots.clone();
+            command.shader_module.material.uniforms = material.uniforms.clone();
+            command.shader_module.material.blend_mode = material.blend_mode;
         }
 
         true
@@ -411,119 +500,525 @@ impl Renderer {
     }
 
     pub fn remove_render_command(&mut self, handle: RenderCmdHd) {
-        self.rendering_store.remove_render_command(handle)
+        let Some(command) = self.rendering_store.remove_render_command(handle) else {
+            return;
+        };
+
+        // Slot textures are counted per command, dropped at the end of the frame when unused
+        for (_, texture_name) in command.borrow().shader_module.material.texture_slots.iter() {
+            self.rendering_store.decrement_texture_handle(texture_name);
+        }
+
+        // Release the shared program once its last command is gone
+        let program_handle = command.borrow().shader_module.self_handle;
+        if self.rendering_store.decrement_program_handle(program_handle) {
+            let gfx = self.gfx_device.as_ref().expect("Graphic device not allocated");
+            gfx.delete_shader_module(command.borrow().shader_module.clone());
+        }
     }
 
     pub fn cull(&mut self, handle: RenderCmdHd, value: bool) {
         self.rendering_store.mark_culled(handle, value);
     }
 
-    pub fn get_world_camera_viewport(&self) -> Rect<f32> {
-        let position = &self.main_camera.transform.position;
-        let window_rect = &self.window_rect;
+    // World volume seen by the camera, from its position
+    pub fn get_camera_frustum(&self, handle: CameraHd) -> Option<Frustum> {
+        let camera = self.get_camera(handle)?;
+        let target_rect = compute_camera_target_rect(camera, &self.window_rect);
+
+        Some(Frustum::from_camera(camera, &target_rect))
+    }
+
+    // World position under a window pixel (origin at the top left) on the z plane. None when
+    // the pixel is out of the camera screen area or the plane can't be seen by the camera.
+    pub fn screen_to_world(
+        &self,
+        handle: CameraHd,
+        screen_position: (f32, f32),
+        z: f32,
+    ) -> Option<Vector3<f32>> {
+        let camera = self.get_camera(handle)?;
+        let screen_rect = compute_gfx_viewport_rect(&camera.viewport, &self.window_rect);
+
+        // Viewports start from the bottom left of the window
+        let x = (screen_position.0 - screen_rect.x as f32) / screen_rect.width as f32;
+        let y = (self.window_rect.height as f32 - screen_position.1 - screen_rect.y as f32)
+            / screen_rect.height as f32;
+        if !(0f32..=1f32).contains(&x) || !(0f32..=1f32).contains(&y) {
+            return None;
+        }
+
+        let area = self.get_camera_frustum(handle)?.get_rect_at(z)?;
+        Some(Vector3::new(
+            area.min_x() + x * area.width,
+            area.min_y() + y * area.height,
+            z,
+        ))
+    }
+
+    pub fn set_rendering_mode(&mut self, mode: RenderingMode) {
+        self.rendering_mode = mode;
+    }
+
+    pub fn get_rendering_mode(&self) -> RenderingMode {
+        self.rendering_mode
+    }
+
+    pub fn set_depth_sort_mode(&mut self, mode: DepthSortMode) {
+        self.depth_sort_mode = mode;
+    }
+
+    pub fn get_depth_sort_mode(&self) -> DepthSortMode {
+        self.depth_sort_mode
+    }
+
+    // Make an atlas packed at runtime available to sprites, like a loaded ".atlas" file
+    pub fn register_atlas(&mut self, atlas_name: &str, atlas: TextureAtlas) {
+        self.rendering_store.register_atlas(atlas_name, atlas);
+    }
+
+    // Material file of assets/materials, parsed once then shared
+    pub fn load_material(&self, material_name: &str) -> Option<Rc<Material>> {
+        match self.rendering_store.load_material(material_name) {
+            Ok(material) => Some(material),
+            Err(err) => {
+                println!("[Renderer] Failed to load material: {}", err);
+                None
+            }
+        }
+    }
+
+    // Statistics of the last rendered frame
+    pub fn get_frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    // Cameras are rendered in their own framebuffer, then composited by increasing order
+    pub fn add_camera(&mut self, camera: RenderingCamera) -> CameraHd {
+        let handle = self.next_camera_handle;
+        self.next_camera_handle += 1;
+
+        let mut pass = CameraPass {
+            handle,
+            camera,
+            framebuffer: None,
+            post_effects: vec![],
+            post_buffers: vec![],
+            bloom_buffers: vec![],
+            light_buffers: vec![],
+            matrices: (identity_mat4(), identity_mat4()),
+            updates_state: RenderingUpdateState {
+                camera_settings: true,
+                camera_transform: true,
+            },
+        };
+
+        // Render textures exist as soon as their camera does, sprites can sample them right away
+        if pass.camera.output_target.is_some() {
+            let gfx = self.gfx_device.as_ref().expect("Graphic device not allocated");
+            prepare_camera_framebuffer(gfx, &mut self.rendering_store, &mut pass, &self.window_rect);
+        }
+
+        self.cameras.push(pass);
+        self.sort_cameras();
+
+        handle
+    }
+
+    pub fn remove_camera(&mut self, handle: CameraHd) {
+        let Some(index) = self.cameras.iter().position(|pass| pass.handle == handle) else {
+            return;
+        };
+
+        let pass = self.cameras.remove(index);
+        if let Some(target) = pass.camera.output_target.as_ref() {
+            self.rendering_store.unregister_render_texture(&target.name);
+        }
+        PostProcessor::release_luts(&mut self.rendering_store, &pass.post_effects);
+
+        let gfx = self.gfx_device.as_ref().expect("Graphic device not allocated");
+        let buffers = pass
+            .post_buffers
+            .into_iter()
+            .chain(pass.bloom_buffers)
+            .chain(pass.light_buffers);
+        for framebuffer in pass.framebuffer.into_iter().chain(buffers) {
+            gfx.release_framebuffer(framebuffer);
+        }
+    }
+
+    // GPU handle of a camera render texture, sprites can also sample it by its name
+    pub fn get_render_texture(&self, name: &str) -> Option<u32> {
+        self.rendering_store.get_render_texture(name)
+    }
+
+    pub fn get_camera(&self, handle: CameraHd) -> Option<&RenderingCamera> {
+        self.cameras
+            .iter()
+            .find(|pass| pass.handle == handle)
+            .map(|pass| &pass.camera)
+    }
+
+    pub fn get_cameras_count(&self) -> usize {
+        self.cameras.len()
+    }
+
+    pub fn update_camera_settings(&mut self, handle: CameraHd, camera_update: RenderingCamera) {
+        let Some(pass) = self.cameras.iter_mut().find(|pass| pass.handle == handle) else {
+            println!("[Renderer] Unknown camera {}", handle);
+            return;
+        };
+
+        // A renamed render texture is a new texture, the previous one goes with its framebuffer
+        if pass.camera.output_target.as_ref().map(|target| &target.name)
+            != camera_update.output_target.as_ref().map(|target| &target.name)
+        {
+            let gfx = self.gfx_device.as_ref().expect("Graphic device not allocated");
+            if let Some(target) = pass.camera.output_target.as_ref() {
+                self.rendering_store.unregister_render_texture(&target.name);
+            }
+            if let Some(framebuffer) = pass.framebuffer.take() {
+                gfx.release_framebuffer(framebuffer);
+            }
+        }
 
-        Rect {
-            x: position.x,
-            y: position.y,
-            width: window_rect.width as f32 / self.main_camera.ppu as f32,
-            height: window_rect.height as f32 / self.main_camera.ppu as f32,
+        pass.camera = camera_update;
+        pass.updates_state.camera_settings = true;
+        pass.updates_state.camera_transform = true;
+
+        if pass.camera.output_target.is_some() {
+            let gfx = self.gfx_device.as_ref().expect("Graphic device not allocated");
+            prepare_camera_framebuffer(gfx, &mut self.rendering_store, pass, &self.window_rect);
         }
+        self.sort_cameras();
     }
 
-    pub fn update_normalized_viewport(&mut self, x: f32, y: f32, width: f32, height: f32) {
-        // Update the viewport on CPU side for now
-        let mut vp_borrow = self.viewport_normalized.borrow_mut();
+    // Lights & shadow casters of the current frame, only cameras with an ambient light are lit
+    pub fn update_lights(
+        &mut self,
+        lights: Vec<RenderingLight>,
+        shadow_casters: Vec<RenderingShadowCaster>,
+    ) {
+        self.lights = lights;
+        self.shadow_casters = shadow_casters;
+    }
 
-        vp_borrow.x = x;
-        vp_borrow.y = y;
-        vp_borrow.z = width;
-        vp_borrow.w = height;
+    // Sprite masks of the current frame, sprites interacting with masks are clipped by all of them
+    pub fn update_sprite_masks(&mut self, masks: Vec<RenderingSpriteMask>) {
+        self.sprite_masks = masks;
     }
 
-    pub fn update_camera_settings(&mut self, camera_update: RenderingCamera) {
-        self.main_camera = camera_update;
-        self.updates_state.camera_settings = true;
+    // Effects are applied in order to the camera image, before it is composited
+    pub fn update_camera_post_effects(&mut self, handle: CameraHd, effects: Vec<PostEffect>) {
+        let Some(pass) = self.cameras.iter_mut().find(|pass| pass.handle == handle) else {
+            println!("[Renderer] Unknown camera {}", handle);
+            return;
+        };
+
+        let gfx = self.gfx_device.as_ref().expect("Graphic device not allocated");
+        let post_processor = self
+            .post_processor
+            .as_ref()
+            .expect("Post processor not allocated");
+
+        // LUTs kept by the new stack are acquired first, they are not released in between
+        post_processor.acquire_luts(gfx, &mut self.rendering_store, &effects);
+        PostProcessor::release_luts(&mut self.rendering_store, &pass.post_effects);
+        pass.post_effects = effects;
     }
 
-    pub fn update_camera_transform(&mut self, transform: Transform) {
-        self.main_camera.transform = transform;
-        self.updates_state.camera_transform = true;
+    pub fn get_camera_post_effects(&self, handle: CameraHd) -> Option<&Vec<PostEffect>> {
+        self.cameras
+            .iter()
+            .find(|pass| pass.handle == handle)
+            .map(|pass| &pass.post_effects)
+    }
+
+    pub fn update_camera_transform(&mut self, handle: CameraHd, transform: Transform) {
+        let Some(pass) = self.cameras.iter_mut().find(|pass| pass.handle == handle) else {
+            println!("[Renderer] Unknown camera {}", handle);
+            return;
+        };
+
+        pass.camera.transform = transform;
+        pass.updates_state.camera_transform = true;
+    }
+
+    // Offscreen cameras go first, their texture is ready when the others sample it
+    fn sort_cameras(&mut self) {
+        self.cameras.sort_by_key(|pass| {
+            (pass.camera.output_target.is_none(), pass.camera.order, pass.handle)
+        });
+    }
+
+    // Watch assets/shaders and reload the programs of the changed files, see reload_shaders
+    pub fn set_shader_hot_reload(&mut self, enabled: bool) {
+        self.shader_watcher = enabled.then(ShaderWatcher::watch_shaders);
+    }
+
+    // Rebuild the programs using one of the files (shaders or includes) and swap them into every
+    // module drawn with them. A program failing to build is kept until the next change.
+    pub fn reload_shaders(&mut self, changed_files: &[String]) {
+        let gfx = self
+            .gfx_device
+            .as_ref()
+            .expect("Graphic device not allocated");
+
+        for (key, handle) in self.rendering_store.get_programs() {
+            let uses_changed_file = self
+                .rendering_store
+                .get_program_files(handle)
+                .is_some_and(|files| files.iter().any(|file| changed_files.contains(file)));
+            if !uses_changed_file {
+                continue;
+            }
+
+            let new_handle = match compile_program(gfx, &mut self.rendering_store, &key) {
+                Ok(new_handle) => new_handle,
+                Err(err) => {
+                    println!(
+                        "[Shaders] Failed to reload {} & {}, the previous program is kept: {}",
+                        key.vertex, key.fragment, err
+                    );
+                    continue;
+                }
+            };
+
+            self.rendering_store.replace_program_handle(handle, new_handle);
+            if let Some(sprite_batcher) = self.sprite_batcher.as_mut() {
+                sprite_batcher.replace_program(handle, new_handle);
+            }
+            if let Some(post_processor) = self.post_processor.as_mut() {
+                post_processor.replace_program(gfx, handle, new_handle);
+            }
+            if let Some(screen_module) = self.screen_shader_module.as_mut() {
+                if screen_module.self_handle == handle {
+                    screen_module.self_handle = new_handle;
+                }
+            }
+            gfx.release_program(handle);
+            println!("[Shaders] Reloaded {} & {}", key.vertex, key.fragment);
+        }
+
+        if let Some(sprite_batcher) = self.sprite_batcher.as_mut() {
+            sprite_batcher.forget_failed_programs();
+        }
+
+        // The grid lines have their own programs, they are not cached
+        if let Some(grid) = self.grid.as_mut() {
+            grid.reload(gfx, &self.rendering_store, changed_files);
+        }
     }
 
-    pub fn render(&mut self, _delta_time: f32) {
+    pub fn render(&mut self, delta_time: f32) {
         self.rendering_state = RenderState::Opened;
+        self.elapsed_time += delta_time;
+
+        let changed_shaders = self
+            .shader_watcher
+            .as_mut()
+            .map_or(vec![], |watcher| watcher.poll(delta_time));
+        if !changed_shaders.is_empty() {
+            self.reload_shaders(&changed_shaders);
+        }
 
         let gfx_device = self
             .gfx_device
             .as_ref()
             .expect("Graphic device not allocated");
 
-        // Updates to default viewport before drawing the scene into the main buffer texture
-        let default_viewport: Vector4<f32> = Vector4 {
-            x: 0f32,
-            y: 0f32,
-            z: 1f32,
-            w: 1f32,
-        };
-        let viewport: Rect<u32> = compute_gfx_viewport_rect(&default_viewport, &self.window);
-        gfx_device.update_viewport(viewport);
-
-        gfx_device.use_framebuffer(Option::from(&self.main_framebuffer));
-        gfx_device.clear(self.main_camera.clear_color);
-        // @todo: do state sorting later to prevent batching break, invalidation and stall draw calls
-        gfx_device.enable_blending();
+        let mut stats = FrameStats::default();
+        let sprite_batcher = self
+            .sprite_batcher
+            .as_mut()
+            .expect("Sprite batcher not allocated");
 
-        // rendering_pass. WIP -> will be multithreaded at end
+        // Sort the visible commands, blending requires drawing them back-to-front
         let mut rendering_queue = self.rendering_store.renderer_queue.borrow_mut();
-        while !rendering_queue.is_empty() {
-            if let Some(cmd_ptr) = rendering_queue.pop_front() {
-                let command: Ref<RenderCommand> = cmd_ptr.borrow();
+        let mut sorted_queue: Vec<(RenderSortKey, Rc<RefCell<RenderCommand>>)> =
+            Vec::with_capacity(rendering_queue.len());
+        for cmd_ptr in rendering_queue.drain(..) {
+            let command: Ref<RenderCommand> = cmd_ptr.borrow();
+            if self.rendering_store.is_culled(command.handle) {
+                stats.culled_commands += 1;
+                continue;
+            }
 
-                gfx_device.use_shader_module(&command.shader_module);
+            let sort_key = RenderSortKey::from_command(&command, self.depth_sort_mode);
+            drop(command);
+            sorted_queue.push((sort_key, cmd_ptr));
+        }
+        drop(rendering_queue);
+        // Stable sort, commands with equal keys keep their queue order
+        sorted_queue.sort_by(|(a, _), (b, _)| a.compare(b));
+
+        // The sorted queue is drawn once per camera, in the camera framebuffer
+        for pass in self.cameras.iter_mut() {
+            let target_rect = prepare_camera_framebuffer(
+                gfx_device,
+                &mut self.rendering_store,
+                pass,
+                &self.window_rect,
+            );
 
-                // only updates VIEW/PROJ matrix if the camera transform/settings changed
-                if self.updates_state.camera_transform {
-                    gfx_device.shader_api.set_attribute_mat4(
-                        command.shader_module.self_handle,
-                        "VIEW",
-                        &compute_view_matrix(&self.main_camera.transform),
-                    );
+            // A camera never samples its own render texture, it is the one being drawn
+            let own_texture = pass
+                .camera
+                .output_target
+                .as_ref()
+                .and(pass.framebuffer.as_ref())
+                .map(|fbo| fbo.texture_attachment);
+
+            // With post effects the scene is drawn in the first ping-pong buffer, lit cameras draw
+            // it unlit in their first light buffer
+            let scene_buffer = pass.post_buffers.first().or(pass.framebuffer.as_ref());
+            gfx_device.update_viewport(target_rect);
+            gfx_device.use_framebuffer(pass.light_buffers.first().or(scene_buffer));
+            gfx_device.clear(pass.camera.clear_color);
+            gfx_device.enable_blending();
+            // Sprites are drawn back-to-front, perspective cameras also test their real depth
+            gfx_device.set_depth_test(pass.camera.projection == Projection::Perspective);
+
+            // only recompute VIEW/PROJ matrix if the camera transform/settings changed
+            if pass.updates_state.camera_transform {
+                pass.matrices.0 = compute_view_matrix(&pass.camera.transform);
+            }
+            if pass.updates_state.camera_settings {
+                pass.matrices.1 = compute_projection(&pass.camera, &target_rect);
+            }
+            pass.updates_state.reset();
+
+            // One upload per camera, the block is shared by every program (sprites, batches & grid)
+            let frame_block = self.frame_block.as_ref().expect("Frame block not allocated");
+            let (view, proj) = &pass.matrices;
+            let frame_data = get_frame_block_data(view, proj, &target_rect, self.elapsed_time);
+            gfx_device.update_uniform_buffer(frame_block, &frame_data);
+            gfx_device.bind_uniform_buffer(frame_block);
+
+            // Masks are written in the stencil first, the sprites interacting with them test it
+            if !self.sprite_masks.is_empty() {
+                gfx_device.set_stencil_mode(StencilMode::WriteMask);
+                let store = &mut self.rendering_store;
+                sprite_batcher.draw_masks(gfx_device, store, &self.sprite_masks, &mut stats);
+                gfx_device.set_stencil_mode(StencilMode::Disabled);
+            }
+            let mut stencil_mode = StencilMode::Disabled;
+
+            // rendering_pass. WIP -> will be multithreaded at end
+            for (_, cmd_ptr) in sorted_queue.iter() {
+                let command: Ref<RenderCommand> = cmd_ptr.borrow();
+                if own_texture.is_some()
+                    && command.shader_module.texture_handles.first() == own_texture.as_ref()
+                {
+                    continue;
                 }
-                if self.updates_state.camera_settings {
-                    gfx_device.shader_api.set_attribute_mat4(
-                        command.shader_module.self_handle,
-                        "PROJ",
-                        &compute_projection(&self.main_camera, &self.window_rect),
-                    );
+
+                // Pending batched sprites are drawn with the previous stencil test
+                let command_stencil = StencilMode::from(command.mask_interaction);
+                if command_stencil != stencil_mode {
+                    sprite_batcher.flush(gfx_device, &mut stats);
+                    gfx_device.set_stencil_mode(command_stencil);
+                    stencil_mode = command_stencil;
                 }
 
-                if self.rendering_store.is_culled(command.handle) {
-                    continue;
+                // Commands whose batch programs don't build fall back to a direct draw
+                if self.rendering_mode != RenderingMode::Direct
+                    && SpriteBatcher::is_batchable(&command)
+                {
+                    let instanced = self.rendering_mode == RenderingMode::Instanced;
+                    let store = &mut self.rendering_store;
+                    if sprite_batcher.push(gfx_device, store, &command, instanced, &mut stats) {
+                        continue;
+                    }
                 }
 
+                // Draw order must be kept, pending batched sprites are drawn first
+                sprite_batcher.flush(gfx_device, &mut stats);
+                gfx_device.use_shader_module(&command.shader_module);
+                let program = command.shader_module.self_handle;
+                if let Some(uniforms) = self.rendering_store.get_object_uniforms(program) {
+                    if let Some(trs) = uniforms.trs.as_ref() {
+                        gfx_device.shader_api.set_uniform_mat4(trs, &command.trs);
+                    }
+                    if let Some(surface_color) = uniforms.surface_color.as_ref() {
+                        let color = command.shader_module.material.color;
+                        gfx_device.shader_api.set_uniform_color(surface_color, color);
+                    }
+                    if let Some(uv_rect) = uniforms.uv_rect.as_ref() {
+                        gfx_device.shader_api.set_uniform_color(uv_rect, command.uv_rect);
+                    }
+                }
+                set_material_uniforms(gfx_device, program, &command.shader_module.material);
                 gfx_device.draw_command(&command, None);
+                stats.draw_calls += 1;
+            }
+            sprite_batcher.flush(gfx_device, &mut stats);
+            if stencil_mode != StencilMode::Disabled {
+                gfx_device.set_stencil_mode(StencilMode::Disabled);
+            }
+
+            let post_processor = self
+                .post_processor
+                .as_ref()
+                .expect("Post processor not allocated");
+            if let Some(ambient_light) = pass.camera.ambient_light.as_ref() {
+                let light_block = self.light_block.as_ref().expect("Light block not allocated");
+                let light_data = get_light_block_data(
+                    ambient_light,
+                    &self.lights,
+                    &self.shadow_casters,
+                    view,
+                    proj,
+                );
+                gfx_device.update_uniform_buffer(light_block, &light_data);
+                gfx_device.bind_uniform_buffer(light_block);
+                post_processor.apply_lighting(
+                    gfx_device,
+                    pass,
+                    self.screen_quad_buffer.as_ref().unwrap(),
+                    &mut stats,
+                );
+
+                // The grid is drawn unlit over the lit scene
+                gfx_device.update_viewport(target_rect);
+                gfx_device.use_framebuffer(scene_buffer);
+            }
+
+            if let Some(grid) = self.grid.as_ref() {
+                grid.draw(gfx_device);
             }
-        }
-        drop(rendering_queue);
 
-        if let Some(grid) = self.grid.as_ref() {
-            grid.draw(gfx_device, &self.main_camera, &self.window_rect);
+            if !pass.post_effects.is_empty() {
+                post_processor.apply(
+                    gfx_device,
+                    &self.rendering_store,
+                    pass,
+                    self.screen_quad_buffer.as_ref().unwrap(),
+                    &mut stats,
+                );
+            }
         }
+        self.frame_stats = stats;
 
-        // Back to screen buffer
+        // Back to screen buffer, cameras are composited in their screen area
         gfx_device.use_framebuffer(None);
-        gfx_device.clear(self.main_camera.clear_color);
+        gfx_device.update_viewport(self.window_rect);
+        gfx_device.clear(ARGB8Color::black());
+        gfx_device.use_shader_module(self.screen_shader_module.as_ref().unwrap());
 
-        let normalized_screen_viewport = self.viewport_normalized.clone().into_inner();
-        let pixel_screen_viewport: Rect<u32> =
-            compute_gfx_viewport_rect(&normalized_screen_viewport, &self.window);
-        gfx_device.update_viewport(pixel_screen_viewport);
+        for pass in self.cameras.iter() {
+            if pass.camera.output_target.is_some() {
+                continue;
+            }
 
-        gfx_device.use_shader_module(self.screen_shader_module.as_ref().unwrap());
-        gfx_device.blit_main_framebuffer(
-            self.screen_quad_buffer.as_ref().unwrap(),
-            self.main_framebuffer.as_ref().unwrap(),
-        );
+            let pixel_screen_viewport: Rect<u32> =
+                compute_gfx_viewport_rect(&pass.camera.viewport, &self.window_rect);
+            gfx_device.update_viewport(pixel_screen_viewport);
+            gfx_device.blit_main_framebuffer(
+                self.screen_quad_buffer.as_ref().unwrap(),
+                pass.framebuffer.as_ref().unwrap(),
+            );
+        }
 
         // Release all dangling textures
         self.rendering_store.iter_dangling_textures(|name, hdl| {
@@ -533,10 +1028,79 @@ impl Renderer {
 
         // Reset the various states for the current frame
         self.rendering_store.reset_frame();
-        self.updates_state.reset();
 
-        self.window.swap_buffers();
+        self.platform.swap_buffers();
 
         self.rendering_state = RenderState::Closed;
     }
 }
+
+// (Re)allocate the camera framebuffers when its pixel size or format changed, returns the rendered
+// area. The framebuffer texture of offscreen cameras is registered as their render texture.
+// Post effects need one ping-pong buffer, two when there are more than one effect, and bloom its
+// mips chain. Lit cameras draw their unlit scene and their lights in two more buffers.
+fn prepare_camera_framebuffer(
+    gfx: &GfxDevice,
+    store: &mut RendererStorage,
+    pass: &mut CameraPass,
+    window_rect: &Rect<u32>,
+) -> Rect<u32> {
+    let target_rect = compute_camera_target_rect(&pass.camera, window_rect);
+    let (width, height) = (target_rect.width as i32, target_rect.height as i32);
+
+    let format = if pass.camera.hdr {
+        FramebufferFormat::Rgba16F
+    } else {
+        FramebufferFormat::Rgba8
+    };
+
+    let resized = !pass.framebuffer.as_ref().is_some_and(|fbo| {
+        fbo.width == width && fbo.height == height && fbo.format == format
+    });
+
+    if resized {
+        if let Some(previous) = pass.framebuffer.take() {
+            gfx.release_framebuffer(previous);
+        }
+
+        let framebuffer = gfx.alloc_framebuffer(width, height, format);
+        if let Some(target) = pass.camera.output_target.as_ref() {
+            store.register_render_texture(&target.name, framebuffer.texture_attachment);
+        }
+        pass.framebuffer = Option::from(framebuffer);
+        pass.updates_state.camera_settings = true;
+    }
+
+    let buffers_count = pass.post_effects.len().min(2);
+    if resized || pass.post_buffers.len() != buffers_count {
+        for buffer in pass.post_buffers.drain(..) {
+            gfx.release_framebuffer(buffer);
+        }
+        for _ in 0..buffers_count {
+            pass.post_buffers.push(gfx.alloc_framebuffer(width, height, format));
+        }
+    }
+
+    let lights_count = if pass.camera.ambient_light.is_some() { 2 } else { 0 };
+    if resized || pass.light_buffers.len() != lights_count {
+        for buffer in pass.light_buffers.drain(..) {
+            gfx.release_framebuffer(buffer);
+        }
+        for _ in 0..lights_count {
+            pass.light_buffers.push(gfx.alloc_framebuffer(width, height, format));
+        }
+    }
+
+    let bloom_sizes = get_bloom_buffer_sizes(&pass.post_effects, width, height);
+    if resized || pass.bloom_buffers.len() != bloom_sizes.len() {
+        for buffer in pass.bloom_buffers.drain(..) {
+            gfx.release_framebuffer(buffer);
+        }
+        for (mip_width, mip_height) in bloom_sizes {
+            let buffer = gfx.alloc_framebuffer(mip_width, mip_height, format);
+            pass.bloom_buffers.push(buffer);
+        }
+    }
+
+    target_rect
+}
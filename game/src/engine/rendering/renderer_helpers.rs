@@ -1,17 +1,37 @@
-use super::shaders::{Material, ShaderPack};
+use super::shaders::{Material, MaterialValue, ShaderPack};
 use crate::engine::ecs::components::SpriteRenderer2D;
-use crate::engine::rendering::components::RenderRequest;
-use crate::engine::rendering::gfx_device::RenderCommand;
+use crate::engine::rendering::components::{
+    DepthSortMode, RenderRequest, RenderingCamera, SortingOrder,
+};
+use crate::engine::rendering::gfx_device::{GfxDevice, RenderCommand, FRAME_BLOCK_FLOATS};
 use crate::engine::rendering::renderer::RenderCmdHd;
-use crate::engine::rendering::renderer_storage::RendererStorage;
+use crate::engine::rendering::renderer_storage::{ObjectUniforms, ProgramKey, RendererStorage};
+use crate::engine::rendering::shader_preprocessor::ShaderSource;
+use glm::Matrix4;
 use crate::engine::rendering::shaders::{ShaderInfo, ShaderType};
 use std::cell::RefMut;
+use std::cmp::Ordering;
 use crate::engine::utils::maths::Rect;
 
 pub type MaterialUpdateMask = u8;
 pub const TEXTURE_MASK: u8 = 1 << 0;
 pub const COLOR_MASK: u8 = 1 << 1;
 pub const TRANSFORM_MASK: u8 = 1 << 2;
+pub const SORTING_MASK: u8 = 1 << 3;
+pub const UV_RECT_MASK: u8 = 1 << 4;
+pub const STENCIL_MASK: u8 = 1 << 5;
+pub const PROPERTIES_MASK: u8 = 1 << 6; // Blend mode, uniforms & extra texture slots
+
+// Draw order of a command: sorting layer & order in layer, material priority, then depth
+// (back-to-front). Commands left equal are grouped by program & texture to keep batches long.
+#[derive(Clone, Debug)]
+pub struct RenderSortKey {
+    sorting: SortingOrder,
+    priority: i8,
+    depth: f32,
+    program: u32,
+    texture: u32,
+}
 
 #[derive(Clone, Debug)]
 pub struct TextureUpdateReq {
@@ -19,6 +39,38 @@ pub struct TextureUpdateReq {
     pub input_texture_handle: Option<(String, u32)>,
 }
 
+impl RenderSortKey {
+    pub fn from_command(command: &RenderCommand, mode: DepthSortMode) -> Self {
+        let position = command.trs.c3;
+        let distance = match mode {
+            DepthSortMode::Z => position.z,
+            DepthSortMode::Y => position.y,
+        };
+
+        Self {
+            sorting: command.sorting,
+            priority: command.shader_module.material.render_priority,
+            depth: -distance, // the farthest is drawn first
+            program: command.shader_module.self_handle,
+            texture: command
+                .shader_module
+                .texture_handles
+                .first()
+                .copied()
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn compare(&self, other: &Self) -> Ordering {
+        self.sorting
+            .cmp(&other.sorting)
+            .then(self.priority.cmp(&other.priority))
+            .then(self.depth.total_cmp(&other.depth))
+            .then(self.program.cmp(&other.program))
+            .then(self.texture.cmp(&other.texture))
+    }
+}
+
 pub fn get_shader_info_or_default(render_request: &RenderRequest) -> [ShaderInfo; 2] {
     let vert_default: ShaderInfo = ShaderInfo::default(ShaderType::Vertex);
     let frag_default: ShaderInfo = ShaderInfo::default(ShaderType::Fragment);
@@ -42,38 +94,182 @@ pub fn get_shader_info_or_default(render_request: &RenderRequest) -> [ShaderInfo
     [vert_info, frag_info]
 }
 
+pub fn get_program_key(vertex: &ShaderInfo, fragment: &ShaderInfo, shaders: &ShaderPack) -> ProgramKey {
+    ProgramKey {
+        vertex: vertex.file_name.clone(),
+        fragment: fragment.file_name.clone(),
+        defines: shaders.defines.clone(),
+        keywords: get_sorted_keywords(&shaders.keywords),
+    }
+}
+
+pub fn get_sorted_keywords(keywords: &[String]) -> Vec<String> {
+    let mut keywords = keywords.to_vec();
+    keywords.sort();
+    keywords.dedup();
+    keywords
+}
+
+// Insert the defines right after the #version directive (GLSL requires it to come first)
+pub fn apply_shader_defines(source: String, defines: &[String]) -> String {
+    if defines.is_empty() {
+        return source;
+    }
+
+    let directives: String = defines
+        .iter()
+        .map(|define| format!("#define {}\n", define))
+        .collect();
+
+    match source.find("#version") {
+        Some(version_start) => {
+            let line_end = source[version_start..]
+                .find('\n')
+                .map_or(source.len(), |end| version_start + end + 1);
+            let mut result = String::from(&source[..line_end]);
+            if !result.ends_with('\n') {
+                result.push('\n');
+            }
+            result.push_str(&directives);
+            result.push_str(&source[line_end..]);
+            result
+        }
+        None => directives + &source,
+    }
+}
+
+// Fetch the program from the storage cache or compile & link it. Either way the program
+// reference count is incremented, release it with RendererStorage::decrement_program_handle.
+// Nothing is counted when the program fails to build.
+pub fn get_or_alloc_program(
+    gfx: &GfxDevice,
+    store: &mut RendererStorage,
+    key: &ProgramKey,
+) -> Result<u32, String> {
+    if let Some(handle) = store.get_gpu_program_handle(key) {
+        store.increment_program_handle(key, handle);
+        return Ok(handle);
+    }
+
+    let program = compile_program(gfx, store, key)?;
+    store.increment_program_handle(key, program);
+    Ok(program)
+}
+
+// Compile & link the program of the key and resolve its per object uniforms, it is not added to
+// the cache. The hot reload swaps it in place of the cached program.
+pub fn compile_program(
+    gfx: &GfxDevice,
+    store: &mut RendererStorage,
+    key: &ProgramKey,
+) -> Result<u32, String> {
+    let vert_info = ShaderInfo::with_name(key.vertex.clone(), ShaderType::Vertex);
+    let frag_info = ShaderInfo::with_name(key.fragment.clone(), ShaderType::Fragment);
+    let vertex = store.load_shader_source(&vert_info, &key.defines, &key.keywords)?;
+    let fragment = store.load_shader_source(&frag_info, &key.defines, &key.keywords)?;
+
+    let files = get_merged_files(&[&vertex, &fragment]);
+    let program = gfx.alloc_program(vertex.source, fragment.source, &Material::new())?;
+
+    // VIEW & PROJ come from the shared FrameData block, nothing else to upload per program
+    const DEFAULT_TEXTURE_IDX: i32 = 0;
+    gfx.shader_api
+        .set_texture_unit(program.self_handle, DEFAULT_TEXTURE_IDX);
+
+    store.set_object_uniforms(
+        program.self_handle,
+        ObjectUniforms {
+            trs: gfx.get_uniform_handle(program.self_handle, "TRS"),
+            surface_color: gfx.get_uniform_handle(program.self_handle, "surface_color"),
+            uv_rect: gfx.get_uniform_handle(program.self_handle, "uv_rect"),
+        },
+    );
+    store.set_program_files(program.self_handle, files);
+    Ok(program.self_handle)
+}
+
+// Files of the sources, each once
+pub fn get_merged_files(sources: &[&ShaderSource]) -> Vec<String> {
+    let mut files: Vec<String> = vec![];
+    for file in sources.iter().flat_map(|source| source.files.iter()) {
+        if !files.contains(file) {
+            files.push(file.clone());
+        }
+    }
+    files
+}
+
+// Content of the FrameData uniform block, see FRAME_BLOCK_FLOATS for the layout
+pub fn get_frame_block_data(
+    view: &Matrix4<f32>,
+    proj: &Matrix4<f32>,
+    window_rect: &Rect<u32>,
+    time: f32,
+) -> Vec<f32> {
+    let mut data: Vec<f32> = Vec::with_capacity(FRAME_BLOCK_FLOATS);
+    for matrix in [view, proj] {
+        for column in [matrix.c0, matrix.c1, matrix.c2, matrix.c3] {
+            data.extend_from_slice(&[column.x, column.y, column.z, column.w]);
+        }
+    }
+    data.extend_from_slice(&[
+        window_rect.width as f32,
+        window_rect.height as f32,
+        time,
+        0f32,
+    ]);
+
+    data
+}
+
+// Texture & atlas region drawn by the sprite, atlases are bound like textures by their file name
+pub fn get_sprite_texture(sprite: &SpriteRenderer2D) -> (Option<String>, Option<String>) {
+    match sprite.atlas.as_ref() {
+        Some(atlas) => (Some(atlas.atlas.clone()), Some(atlas.region.clone())),
+        None => (sprite.texture.clone(), None),
+    }
+}
+
 pub fn prepare_material(sprite: &SpriteRenderer2D, material: Option<&Material>) -> Material {
-    let sprite_texture = sprite.texture.clone();
+    let (sprite_texture, atlas_region) = get_sprite_texture(sprite);
 
     if let Some(mat) = material {
+        // Sprites without texture draw the main texture of their material
+        let (main_texture, atlas_region) = match sprite_texture {
+            Some(texture) => (Some(texture), atlas_region),
+            None => (mat.main_texture.clone(), mat.atlas_region.clone()),
+        };
+
         return Material {
-            color: mat.color,
-            render_priority: mat.render_priority,
-            main_texture: sprite_texture,
-            shaders: mat.shaders.clone(),
-            pixel_per_unit: mat.pixel_per_unit,
+            main_texture,
+            atlas_region,
+            ..mat.clone()
         };
     }
 
-    Material {
-        color: glm::Vector4 {
-            x: 1f32,
-            y: 1f32,
-            z: 1f32,
-            w: 1f32,
-        },
-        render_priority: 0,
-        main_texture: None,
-        shaders: ShaderPack {
-            vertex: None,
-            fragment: None,
-        },
-        pixel_per_unit: 100,
+    Material::new()
+}
+
+// Pixel area the camera renders to, its render texture resolution or its screen area
+pub fn compute_camera_target_rect(camera: &RenderingCamera, window_rect: &Rect<u32>) -> Rect<u32> {
+    let (width, height) = match camera.output_target.as_ref().and_then(|target| target.resolution) {
+        Some(resolution) => resolution,
+        None => {
+            let pixel_rect = compute_gfx_viewport_rect(&camera.viewport, window_rect);
+            (pixel_rect.width, pixel_rect.height)
+        }
+    };
+
+    Rect {
+        x: 0,
+        y: 0,
+        width: width.max(1),
+        height: height.max(1),
     }
 }
 
-pub fn compute_gfx_viewport_rect(viewport: &glm::Vector4<f32>, window: &glfw::Window) -> Rect<u32> {
-    let (scaled_width, scaled_height) = window.get_framebuffer_size();
+pub fn compute_gfx_viewport_rect(viewport: &glm::Vector4<f32>, window_rect: &Rect<u32>) -> Rect<u32> {
+    let (scaled_width, scaled_height) = (window_rect.width, window_rect.height);
 
     let final_x: f32 = scaled_width as f32 * viewport.x;
     let final_y: f32 = scaled_height as f32 * viewport.y;
@@ -102,10 +298,66 @@ pub fn get_material_changes(
     if rendering_mat.color != updating_mat.color {
         update_mask |= COLOR_MASK;
     }
+    if rendering_mat.render_priority != updating_mat.render_priority {
+        update_mask |= SORTING_MASK;
+    }
+    if rendering_mat.atlas_region != updating_mat.atlas_region {
+        update_mask |= UV_RECT_MASK;
+    }
+    if rendering_mat.blend_mode != updating_mat.blend_mode
+        || rendering_mat.uniforms != updating_mat.uniforms
+        || rendering_mat.texture_slots != updating_mat.texture_slots
+    {
+        update_mask |= PROPERTIES_MASK;
+    }
 
     update_mask
 }
 
+// Custom uniforms & texture slot samplers of the material, uploaded before drawing it. Programs
+// are shared, materials leaving a uniform out draw with the last value uploaded.
+pub fn set_material_uniforms(gfx: &GfxDevice, program: u32, material: &Material) {
+    let shader_api = &gfx.shader_api;
+    for (unit, _) in material.texture_slots.iter() {
+        shader_api.set_texture_unit(program, *unit as i32);
+    }
+
+    for (name, value) in material.uniforms.iter() {
+        match value {
+            MaterialValue::Float(value) => shader_api.set_attribute_f32(program, name, *value),
+            MaterialValue::Vec2(value) => shader_api.set_attribute_vector2f(program, name, value),
+            MaterialValue::Vec4(value) => shader_api.set_attribute_color(program, name, *value),
+        }
+    }
+}
+
+// GPU handle of a material texture slot, counted on each use. Render textures are owned by their
+// camera and not counted.
+pub fn get_or_alloc_texture(
+    gfx: &GfxDevice,
+    store: &mut RendererStorage,
+    program: u32,
+    texture_name: &str,
+) -> Option<u32> {
+    if let Some(render_texture) = store.get_render_texture(texture_name) {
+        return Some(render_texture);
+    }
+
+    let handle = if store.has_gpu_texture_refs(texture_name) {
+        store.get_gpu_texture_handle(texture_name)
+    } else {
+        match store.load_texture(texture_name) {
+            Ok(texture) => gfx.alloc_texture(program, &texture),
+            Err(err) => {
+                println!("[Renderer] Failed to load texture {}: {}", texture_name, err);
+                return None;
+            }
+        }
+    };
+    store.increment_texture_handle(texture_name, handle);
+    Some(handle)
+}
+
 pub fn shader_texture_update(store: &mut RendererStorage, request: TextureUpdateReq) {
     let prev_texture = store
         .get_ref(request.handle)
@@ -119,16 +371,18 @@ pub fn shader_texture_update(store: &mut RendererStorage, request: TextureUpdate
         store.decrement_texture_handle(&previous_texture);
     }
     // Adds the input texture to store (either increments or adds to the ref count)
+    // Render textures are released with their camera, they are not counted
     if let Some((tex_name, handle)) = request.input_texture_handle.as_ref() {
-        store.increment_texture_handle(&tex_name, *handle);
+        if store.get_render_texture(tex_name).is_none() {
+            store.increment_texture_handle(&tex_name, *handle);
+        }
     }
 
-    // Clear all previous texture handles and adds the input texture to the render command
-    // This is done that way because multi texturing is not yet implemented
+    // The main texture is bound to the first unit, the texture slots of the material are kept
     let mut command: RefMut<RenderCommand> = store.get_mut_ref(request.handle);
-    command.shader_module.texture_handles.clear();
-    if let Some((tex_name, handle)) = &request.input_texture_handle {
-        command.shader_module.texture_handles.push(*handle);
+    let input_handle = request.input_texture_handle.as_ref().map(|(_, handle)| *handle);
+    command.shader_module.set_texture_handle(0, input_handle);
+    if let Some((tex_name, _)) = &request.input_texture_handle {
         command.shader_module.material.main_texture = Option::from(tex_name.clone());
     }
 }
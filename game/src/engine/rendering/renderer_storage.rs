@@ -1,10 +1,16 @@
 use super::{
-    components::MeshInfo, gfx_device::RenderCommand, renderer::RenderCmdHd, shaders::ShaderInfo,
+    atlas::{full_uv_rect, TextureAtlas, ATLAS_EXTENSION},
+    components::MeshInfo,
+    gfx_device::{RenderCommand, UniformHandle},
+    renderer::RenderCmdHd,
+    shaders::ShaderInfo,
 };
-use crate::engine::rendering::shaders::Texture;
+use crate::engine::rendering::shader_preprocessor::{preprocess_shader, ShaderSource};
+use crate::engine::rendering::shaders::{Material, Texture};
 use crate::engine::utils::file_system::FileSystem;
 use crate::engine::utils::file_system::FileType;
 use bit_set::BitSet;
+use glm::{Matrix4, Vector4};
 use std::{
     cell::RefCell,
     collections::{HashMap, VecDeque},
@@ -47,14 +53,39 @@ struct HandleCountPair<T> {
     pub count: u32,
 }
 
+// Identify a linked shader program, materials with the same key share the same program
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProgramKey {
+    pub vertex: String,
+    pub fragment: String,
+    pub defines: Vec<String>,
+    pub keywords: Vec<String>, // Sorted, the order materials enable them in doesn't matter
+}
+
+// Per object uniforms uploaded before each direct draw, resolved once per program
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectUniforms {
+    pub trs: Option<UniformHandle<Matrix4<f32>>>,
+    pub surface_color: Option<UniformHandle<Vector4<f32>>>,
+    pub uv_rect: Option<UniformHandle<Vector4<f32>>>,
+}
+
 pub struct RendererStorage {
     pub render_command_storage: HashMap<RenderCmdHd, Rc<RefCell<RenderCommand>>>,
     pub renderer_queue: RefCell<VecDeque<Rc<RefCell<RenderCommand>>>>,
     pub culled_handles: BitSet,
 
     ram_texture_cache: RefCell<HashMap<String, Rc<Texture>>>,
+    atlases: RefCell<HashMap<String, Rc<TextureAtlas>>>, // Their texture is in the texture caches
+    materials: RefCell<HashMap<String, Rc<Material>>>, // Material files, by file name
     gpu_texture_cache: HashMap<String, HandleCountPair<u32>>,
     dangling_textures: Vec<(String, u32)>,
+    render_textures: HashMap<String, u32>, // Owned by their camera, not reference counted
+
+    gpu_program_cache: HashMap<ProgramKey, HandleCountPair<u32>>,
+    program_keys: HashMap<u32, ProgramKey>, // reverse lookup, program handle to its cache key
+    program_uniforms: HashMap<u32, ObjectUniforms>,
+    program_files: HashMap<u32, Vec<String>>, // Shaders & includes, watched by the hot reload
 }
 
 impl RendererStorage {
@@ -63,10 +94,18 @@ impl RendererStorage {
             render_command_storage: HashMap::new(),
             renderer_queue: RefCell::new(VecDeque::new()),
             ram_texture_cache: RefCell::new(HashMap::new()),
+            atlases: RefCell::new(HashMap::new()),
+            materials: RefCell::new(HashMap::new()),
             gpu_texture_cache: HashMap::new(),
             culled_handles: BitSet::with_capacity(2048),
 
             dangling_textures: Vec::with_capacity(200usize),
+            render_textures: HashMap::new(),
+
+            gpu_program_cache: HashMap::new(),
+            program_keys: HashMap::new(),
+            program_uniforms: HashMap::new(),
+            program_files: HashMap::new(),
         }
     }
 
@@ -117,8 +156,8 @@ impl RendererStorage {
         panic!("Could not find Render Command Handle");
     }
 
-    pub fn remove_render_command(&mut self, hd: RenderCmdHd) {
-        self.render_command_storage.remove(&hd);
+    pub fn remove_render_command(&mut self, hd: RenderCmdHd) -> Option<Rc<RefCell<RenderCommand>>> {
+        self.render_command_storage.remove(&hd)
     }
 
     pub fn add_to_frame_queue(&mut self, hd: RenderCmdHd) {
@@ -127,7 +166,24 @@ impl RendererStorage {
         self.renderer_queue.borrow_mut().push_back(cmd.clone());
     }
 
-    pub fn load_shader_content(&self, shader_info: &ShaderInfo) -> Result<String, String> {
+    // Preprocessed source of the shader, see shader_preprocessor.rs
+    pub fn load_shader_content(
+        &self,
+        shader_info: &ShaderInfo,
+        defines: &[String],
+        keywords: &[String],
+    ) -> Result<String, String> {
+        self.load_shader_source(shader_info, defines, keywords)
+            .map(|shader| shader.source)
+    }
+
+    // Same as load_shader_content, with the files the source was built from
+    pub fn load_shader_source(
+        &self,
+        shader_info: &ShaderInfo,
+        defines: &[String],
+        keywords: &[String],
+    ) -> Result<ShaderSource, String> {
         let mut file_name: String = shader_info.file_name.clone();
 
         if file_name.contains("[[default]]") {
@@ -140,10 +196,16 @@ impl RendererStorage {
         #[cfg(debug_assertions)]
         println!("[Shaders] Loading shader {}", &file_name);
 
-        FileSystem::load_file(&file_name, FileType::Shader)
+        preprocess_shader(&file_name, defines, keywords, |path| {
+            FileSystem::load_file(path, FileType::Shader)
+        })
     }
 
     pub fn load_texture(&self, texture_name: &str) -> Result<Rc<Texture>, String> {
+        if texture_name.ends_with(ATLAS_EXTENSION) {
+            return self.load_atlas(texture_name).map(|atlas| atlas.texture.clone());
+        }
+
         let mut texture_cache = self.ram_texture_cache.borrow_mut();
         if texture_cache.contains_key(texture_name) {
             let texture = texture_cache[texture_name].clone();
@@ -162,6 +224,68 @@ impl RendererStorage {
         Err("Unknown".to_owned())
     }
 
+    // Atlases are built from their description file once, then shared by every sprite using them
+    pub fn load_atlas(&self, atlas_name: &str) -> Result<Rc<TextureAtlas>, String> {
+        if let Some(atlas) = self.atlases.borrow().get(atlas_name) {
+            return Ok(atlas.clone());
+        }
+
+        let description = FileSystem::load_file(atlas_name, FileType::Texture)?;
+        let atlas = TextureAtlas::from_description(&description, |texture_name| {
+            self.load_texture(texture_name)
+        })?;
+
+        Ok(self.register_atlas(atlas_name, atlas))
+    }
+
+    // Atlases packed at runtime are registered under a name ending with ATLAS_EXTENSION
+    pub fn register_atlas(&self, atlas_name: &str, atlas: TextureAtlas) -> Rc<TextureAtlas> {
+        let atlas = Rc::new(atlas);
+        self.ram_texture_cache
+            .borrow_mut()
+            .insert(String::from(atlas_name), atlas.texture.clone());
+        self.atlases
+            .borrow_mut()
+            .insert(String::from(atlas_name), atlas.clone());
+
+        atlas
+    }
+
+    // Material files are parsed once, then shared by every sprite referring to them
+    pub fn load_material(&self, material_name: &str) -> Result<Rc<Material>, String> {
+        if let Some(material) = self.materials.borrow().get(material_name) {
+            return Ok(material.clone());
+        }
+
+        let material = Rc::new(Material::load_file(material_name)?);
+        self.materials
+            .borrow_mut()
+            .insert(String::from(material_name), material.clone());
+
+        Ok(material)
+    }
+
+    // UV rect of the material atlas region, the whole texture when the material has no region
+    pub fn get_uv_rect(&self, material: &Material) -> Vector4<f32> {
+        let (Some(atlas_name), Some(region)) = (
+            material.main_texture.as_ref(),
+            material.atlas_region.as_ref(),
+        ) else {
+            return full_uv_rect();
+        };
+
+        match self.load_atlas(atlas_name) {
+            Ok(atlas) => atlas.get_uv_rect(region).unwrap_or_else(|| {
+                println!("[Atlas] Region {} not found in {}", region, atlas_name);
+                full_uv_rect()
+            }),
+            Err(err) => {
+                println!("[Atlas] Failed to load {}: {}", atlas_name, err);
+                full_uv_rect()
+            }
+        }
+    }
+
     pub fn mark_culled(&mut self, handle: RenderCmdHd, culled: bool) {
         if culled {
             self.culled_handles.insert(handle);
@@ -208,6 +332,123 @@ impl RendererStorage {
         self.gpu_texture_cache.get(texture_name).unwrap().handle
     }
 
+    // Commands already sampling the render texture are moved to the new handle
+    pub fn register_render_texture(&mut self, texture_name: &str, handle: u32) {
+        self.render_textures
+            .insert(String::from(texture_name), handle);
+        self.patch_texture_handles(texture_name, Some(handle));
+    }
+
+    pub fn unregister_render_texture(&mut self, texture_name: &str) {
+        if self.render_textures.remove(texture_name).is_some() {
+            self.patch_texture_handles(texture_name, None);
+        }
+    }
+
+    pub fn get_render_texture(&self, texture_name: &str) -> Option<u32> {
+        self.render_textures.get(texture_name).copied()
+    }
+
+    fn patch_texture_handles(&self, texture_name: &str, handle: Option<u32>) {
+        for command in self.render_command_storage.values() {
+            let mut command = command.borrow_mut();
+            let module = &mut command.shader_module;
+            if module.material.main_texture.as_deref() == Some(texture_name) {
+                module.set_texture_handle(0, handle);
+            }
+
+            let units: Vec<usize> = module
+                .material
+                .texture_slots
+                .iter()
+                .filter(|(_, name)| name == texture_name)
+                .map(|(unit, _)| *unit as usize)
+                .collect();
+            for unit in units {
+                module.set_texture_handle(unit, handle);
+            }
+        }
+    }
+
+    pub fn get_gpu_program_handle(&self, key: &ProgramKey) -> Option<u32> {
+        self.gpu_program_cache.get(key).map(|pair| pair.handle)
+    }
+
+    pub fn increment_program_handle(&mut self, key: &ProgramKey, handle: u32) {
+        if let Some(pair) = self.gpu_program_cache.get_mut(key) {
+            pair.count += 1;
+            return;
+        }
+
+        self.gpu_program_cache
+            .insert(key.clone(), HandleCountPair { handle, count: 1 });
+        self.program_keys.insert(handle, key.clone());
+    }
+
+    // Returns true when the program has no user left, it is then removed from the cache
+    pub fn decrement_program_handle(&mut self, handle: u32) -> bool {
+        let Some(key) = self.program_keys.get(&handle) else {
+            return false;
+        };
+
+        let pair = self.gpu_program_cache.get_mut(key).unwrap();
+        pair.count = pair.count.saturating_sub(1);
+
+        if pair.count == 0 {
+            let key = self.program_keys.remove(&handle).unwrap();
+            self.gpu_program_cache.remove(&key);
+            self.program_uniforms.remove(&handle);
+            self.program_files.remove(&handle);
+            return true;
+        }
+
+        false
+    }
+
+    // Cached programs with their handle, users are not counted
+    pub fn get_programs(&self) -> Vec<(ProgramKey, u32)> {
+        self.gpu_program_cache
+            .iter()
+            .map(|(key, pair)| (key.clone(), pair.handle))
+            .collect()
+    }
+
+    // Swap a reloaded program into the cache and the commands drawn with it, the users are kept.
+    // The uniforms & files of the new program must be set, the old one is then free to release.
+    pub fn replace_program_handle(&mut self, handle: u32, new_handle: u32) {
+        let Some(key) = self.program_keys.remove(&handle) else {
+            return;
+        };
+
+        self.gpu_program_cache.get_mut(&key).unwrap().handle = new_handle;
+        self.program_keys.insert(new_handle, key);
+        self.program_uniforms.remove(&handle);
+        self.program_files.remove(&handle);
+
+        for command in self.render_command_storage.values() {
+            let mut command = command.borrow_mut();
+            if command.shader_module.self_handle == handle {
+                command.shader_module.self_handle = new_handle;
+            }
+        }
+    }
+
+    pub fn set_program_files(&mut self, program: u32, files: Vec<String>) {
+        self.program_files.insert(program, files);
+    }
+
+    pub fn get_program_files(&self, program: u32) -> Option<&Vec<String>> {
+        self.program_files.get(&program)
+    }
+
+    pub fn set_object_uniforms(&mut self, program: u32, uniforms: ObjectUniforms) {
+        self.program_uniforms.insert(program, uniforms);
+    }
+
+    pub fn get_object_uniforms(&self, program: u32) -> Option<&ObjectUniforms> {
+        self.program_uniforms.get(&program)
+    }
+
     pub fn iter_dangling_textures<F>(&mut self, mut callback: F)
     where
         F: FnMut(&String, u32),
@@ -0,0 +1,231 @@
+use super::renderer_helpers::apply_shader_defines;
+
+// Shader preprocessor, run on every shader source loaded by the renderer:
+// - #include "file" is replaced by the file content. Paths are relative to assets/shaders and a
+//   file is only included once, headers need no guard.
+// - #pragma variants declares the keywords materials can enable. Enabled keywords are defined
+//   after #version with the material defines, programs are linked once per enabled set.
+// - #line directives keep the original line numbers. Their source string number is the index of
+//   the file in the "// Sources:" comment, map_compile_log uses it to name the file in errors.
+
+const INCLUDE_DIRECTIVE: &str = "#include";
+const VARIANTS_PRAGMA: &str = "#pragma variants";
+const VERSION_DIRECTIVE: &str = "#version";
+const SOURCES_COMMENT: &str = "// Sources:";
+const LINE_DIRECTIVE: &str = "#line";
+const ERROR_DIRECTIVE: &str = "#error";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderSource {
+    pub source: String,
+    pub files: Vec<String>,    // Main shader first, then the includes by first inclusion
+    pub variants: Vec<String>, // Keywords declared by the shader & its includes
+}
+
+struct Expansion {
+    version: Option<String>,
+    body: String,
+    files: Vec<String>,
+    variants: Vec<String>,
+}
+
+// Expand the shader, files are read through the loader. Keywords the shader doesn't declare are
+// ignored, errors name the file & line of the directive.
+pub fn preprocess_shader<F>(
+    file_name: &str,
+    defines: &[String],
+    keywords: &[String],
+    mut load: F,
+) -> Result<ShaderSource, String>
+where
+    F: FnMut(&str) -> Result<String, String>,
+{
+    let content = load(file_name).map_err(|err| format!("[Shaders] {}", err))?;
+    let mut expansion = Expansion {
+        version: None,
+        body: String::new(),
+        files: vec![],
+        variants: vec![],
+    };
+    expansion.expand(file_name, &content, &mut load)?;
+
+    for keyword in keywords {
+        if !expansion.variants.contains(keyword) {
+            println!("[Shaders] {} has no {} variant", file_name, keyword);
+        }
+    }
+    // Declaration order, materials listing the same keywords get the same source
+    let mut directives: Vec<String> = defines.to_vec();
+    directives.extend(
+        expansion
+            .variants
+            .iter()
+            .filter(|variant| keywords.contains(variant))
+            .cloned(),
+    );
+
+    let sources: Vec<String> = expansion
+        .files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| format!("{} {}", index, file))
+        .collect();
+    let mut source = String::new();
+    if let Some(version) = expansion.version.as_ref() {
+        source.push_str(version);
+        source.push('\n');
+    }
+    source.push_str(&format!("{} {}\n", SOURCES_COMMENT, sources.join(", ")));
+    source.push_str(&expansion.body);
+
+    Ok(ShaderSource {
+        source: apply_shader_defines(source, &directives),
+        files: expansion.files,
+        variants: expansion.variants,
+    })
+}
+
+// Name the files in a compile log of a preprocessed source, "1(12)" (NVIDIA) and "1:12(5)"
+// (Mesa, AMD) locations become "common.glsl:12"
+pub fn map_compile_log(source: &str, log: &str) -> String {
+    let files: Vec<&str> = source
+        .lines()
+        .find_map(|line| line.strip_prefix(SOURCES_COMMENT))
+        .map_or(vec![], |sources| {
+            sources
+                .split(',')
+                .filter_map(|entry| entry.trim().split_once(' ').map(|(_, file)| file))
+                .collect()
+        });
+
+    log.lines()
+        .map(|line| map_log_line(line, &files))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Compile log of the first #error directive of a preprocessed source, conditionals are ignored.
+// Backends without a GLSL compiler use it to fail like a driver would.
+pub fn get_error_directive_log(source: &str) -> Option<String> {
+    let (mut file, mut line) = (0usize, 1usize);
+    for text in source.lines() {
+        let trimmed = text.trim();
+        if let Some(location) = trimmed.strip_prefix(LINE_DIRECTIVE) {
+            let mut numbers = location
+                .split_whitespace()
+                .filter_map(|number| number.parse::<usize>().ok());
+            line = numbers.next().unwrap_or(line);
+            file = numbers.next().unwrap_or(file);
+            continue;
+        }
+        if let Some(message) = trimmed.strip_prefix(ERROR_DIRECTIVE) {
+            let log = format!("{}({}) : error: {}", file, line, message.trim());
+            return Option::from(map_compile_log(source, &log));
+        }
+        line += 1;
+    }
+
+    None
+}
+
+impl Expansion {
+    fn expand<F>(&mut self, file_name: &str, content: &str, load: &mut F) -> Result<(), String>
+    where
+        F: FnMut(&str) -> Result<String, String>,
+    {
+        let file_index = self.files.len();
+        self.files.push(String::from(file_name));
+        self.body
+            .push_str(&format!("#line 1 {}\n", file_index));
+
+        for (index, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            let error = |message: &str| {
+                format!("[Shaders] {}:{}: {}", file_name, index + 1, message)
+            };
+
+            if trimmed.starts_with(VERSION_DIRECTIVE) {
+                if file_index != 0 {
+                    return Err(error("#version is only allowed in the main shader"));
+                }
+                self.version = Option::from(String::from(trimmed));
+                self.body.push('\n');
+            } else if let Some(keywords) = trimmed.strip_prefix(VARIANTS_PRAGMA) {
+                for keyword in keywords.split_whitespace() {
+                    if !self.variants.iter().any(|variant| variant == keyword) {
+                        self.variants.push(String::from(keyword));
+                    }
+                }
+                self.body.push('\n');
+            } else if let Some(path) = trimmed.strip_prefix(INCLUDE_DIRECTIVE) {
+                let path = path
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|path| path.strip_suffix('"'))
+                    .filter(|path| !path.is_empty())
+                    .ok_or_else(|| error("expected #include \"file\""))?;
+
+                if self.files.iter().any(|file| file == path) {
+                    self.body.push('\n');
+                    continue;
+                }
+                let included = load(path).map_err(|err| error(&err))?;
+                self.expand(path, &included, load)?;
+                self.body
+                    .push_str(&format!("#line {} {}\n", index + 2, file_index));
+            } else {
+                self.body.push_str(line);
+                self.body.push('\n');
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn map_log_line(line: &str, files: &[&str]) -> String {
+    let bytes = line.as_bytes();
+    let digits_end = |start: usize| {
+        (start..bytes.len())
+            .find(|i| !bytes[*i].is_ascii_digit())
+            .unwrap_or(bytes.len())
+    };
+
+    // First "<file>(<line>)" or "<file>:<line>" where the file index is a known source
+    for start in 0..bytes.len() {
+        if !bytes[start].is_ascii_digit() || (start > 0 && bytes[start - 1].is_ascii_alphanumeric())
+        {
+            continue;
+        }
+        let file_end = digits_end(start);
+        let Some(separator) = bytes.get(file_end).copied() else {
+            break;
+        };
+        if separator != b'(' && separator != b':' {
+            continue;
+        }
+        let line_end = digits_end(file_end + 1);
+        let closed = separator == b':' || bytes.get(line_end) == Some(&b')');
+        if line_end == file_end + 1 || !closed {
+            continue;
+        }
+        let Some(file) = line[start..file_end]
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| files.get(index))
+        else {
+            continue;
+        };
+
+        let end = if separator == b'(' { line_end + 1 } else { line_end };
+        return format!(
+            "{}{}:{}{}",
+            &line[..start],
+            file,
+            &line[file_end + 1..line_end],
+            &line[end..]
+        );
+    }
+
+    String::from(line)
+}
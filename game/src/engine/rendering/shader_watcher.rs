@@ -0,0 +1,87 @@
+use crate::engine::utils::file_system::{FileSystem, FileType};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Shader hot reload: the shader directory is polled and the files whose modification time (or
+// size) changed since the previous scan are reported. The renderer then rebuilds the programs
+// using them, see Renderer::reload_shaders.
+
+pub const SHADER_POLL_INTERVAL: f32 = 0.5; // seconds
+
+pub struct ShaderWatcher {
+    directory: PathBuf,
+    interval: f32,
+    elapsed: f32,
+    files: HashMap<String, (SystemTime, u64)>, // Paths relative to the directory, like includes
+}
+
+impl ShaderWatcher {
+    // The files present now are the reference, only the later changes are reported
+    pub fn new(directory: PathBuf, interval: f32) -> Self {
+        let mut watcher = Self {
+            directory,
+            interval,
+            elapsed: 0f32,
+            files: HashMap::new(),
+        };
+        watcher.scan();
+        watcher
+    }
+
+    pub fn watch_shaders() -> Self {
+        Self::new(
+            FileSystem::get_directory(FileType::Shader),
+            SHADER_POLL_INTERVAL,
+        )
+    }
+
+    // Scan once per interval, returns the changed files
+    pub fn poll(&mut self, delta_time: f32) -> Vec<String> {
+        self.elapsed += delta_time;
+        if self.elapsed < self.interval {
+            return vec![];
+        }
+
+        self.elapsed = 0f32;
+        self.scan()
+    }
+
+    // Files created or modified since the previous scan, sorted. Removed files are forgotten.
+    pub fn scan(&mut self) -> Vec<String> {
+        let mut files: HashMap<String, (SystemTime, u64)> =
+            HashMap::with_capacity(self.files.len());
+        collect_files(&self.directory, "", &mut files);
+
+        let mut changed: Vec<String> = files
+            .iter()
+            .filter(|(path, stamp)| self.files.get(*path) != Some(stamp))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.sort();
+
+        self.files = files;
+        changed
+    }
+}
+
+fn collect_files(directory: &Path, prefix: &str, files: &mut HashMap<String, (SystemTime, u64)>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        println!("[Shaders] Can't watch directory {}", directory.display());
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let path = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+
+        if metadata.is_dir() {
+            collect_files(&entry.path(), &format!("{}/", path), files);
+        } else if let Ok(modified) = metadata.modified() {
+            files.insert(path, (modified, metadata.len()));
+        }
+    }
+}
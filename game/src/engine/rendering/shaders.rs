@@ -4,7 +4,7 @@ pub enum ShaderLoadType {
     OnDemand,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ShaderType {
     Vertex,
     Fragment,
@@ -21,6 +21,8 @@ pub struct ShaderInfo {
 pub struct ShaderPack {
     pub vertex: Option<ShaderInfo>,
     pub fragment: Option<ShaderInfo>,
+    pub defines: Vec<String>, // "NAME" or "NAME VALUE", injected in both stages sources
+    pub keywords: Vec<String>, // Enabled variants, see shader_preprocessor.rs
 }
 
 #[derive(Debug, Clone)]
@@ -31,13 +33,35 @@ pub struct Texture {
     pub channels: u32,
 }
 
+// How the sprite colors are combined with the target ones
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    #[default]
+    Alpha,    // src * src_alpha + dst * (1 - src_alpha)
+    Additive, // src * src_alpha + dst
+    Multiply, // src * dst
+    Opaque,   // src, the target is overwritten
+}
+
+// Value of a custom material uniform, uploaded before drawing the sprite
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MaterialValue {
+    Float(f32),
+    Vec2(glm::Vec2),
+    Vec4(glm::Vec4),
+}
+
 #[derive(Debug, Clone)]
 pub struct Material {
     pub color: glm::Vec4,
     pub render_priority: i8,
     pub main_texture: Option<String>,
+    pub atlas_region: Option<String>, // Region of the main texture when it is an atlas
     pub shaders: ShaderPack,
     pub pixel_per_unit: u8,
+    pub texture_slots: Vec<(u32, String)>, // By unit (>= 1), sampled as texture<unit>
+    pub uniforms: Vec<(String, MaterialValue)>,
+    pub blend_mode: BlendMode,
 }
 
 impl Material {
@@ -46,11 +70,17 @@ impl Material {
             color: glm::vec4(1.0, 1.0, 1.0, 1.0),
             render_priority: 0,
             main_texture: texture,
+            atlas_region: None,
             shaders: ShaderPack {
                 vertex: Option::Some(ShaderInfo::default(ShaderType::Vertex)),
                 fragment: Option::Some(ShaderInfo::default(ShaderType::Fragment)),
+                defines: vec![],
+                keywords: vec![],
             },
             pixel_per_unit: 100,
+            texture_slots: vec![],
+            uniforms: vec![],
+            blend_mode: BlendMode::Alpha,
         }
     }
 
@@ -59,11 +89,17 @@ impl Material {
             color: glm::vec4(1f32, 1f32, 1f32, 1f32),
             render_priority: 0,
             main_texture: None,
+            atlas_region: None,
             shaders: ShaderPack {
                 vertex: None,
                 fragment: None,
+                defines: vec![],
+                keywords: vec![],
             },
             pixel_per_unit: 100,
+            texture_slots: vec![],
+            uniforms: vec![],
+            blend_mode: BlendMode::Alpha,
         }
     }
 }
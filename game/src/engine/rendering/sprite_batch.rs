@@ -0,0 +1,330 @@
+use super::atlas::full_uv_rect;
+use super::components::{
+    BufferSettings, FrameStats, MaskInteraction, MeshInfo, RenderingSpriteMask, SortingOrder,
+};
+use super::gfx_device::{BufferModule, GfxDevice, RenderCommand, ShaderModule};
+use super::renderer::RenderCmdHd;
+use super::renderer_helpers::{get_or_alloc_program, get_sorted_keywords};
+use super::renderer_storage::{ProgramKey, RendererStorage};
+use super::shaders::{BlendMode, Material};
+use crate::engine::utils::maths::{identity_mat4, triangulate_polygon};
+use glm::Vector4;
+use std::collections::HashMap;
+
+// Sprite batching: contiguous commands sharing the same fragment shader, texture and blend mode
+// are merged in a single draw call. Either vertices are transformed on CPU and streamed into one
+// dynamic buffer, or a shared quad is drawn instanced with the TRS & color of each sprite as
+// instance data.
+// Only contiguous commands are merged so the queue order (and thus blending) is preserved.
+
+const BATCH_VERTEX_SHADER: &str = "batch_vertex.shader";
+const INSTANCED_VERTEX_SHADER: &str = "instanced_vertex.shader";
+const DEFAULT_SHADER_KEY: &str = "[[default]]";
+const FLOATS_PER_VERTEX: usize = 9; // position (3), uvs (2), color (4)
+const QUAD_STRIDE: usize = 5; // position (3), uvs (2)
+
+#[derive(Debug, Clone, PartialEq)]
+struct BatchKey {
+    programs: ProgramKey,
+    texture: Option<u32>,
+    blend_mode: BlendMode,
+    instanced: bool,
+}
+
+struct BatchPrograms {
+    batched: u32,
+    instanced: u32,
+}
+
+pub struct SpriteBatcher {
+    buffer: BufferModule,
+    instanced_buffer: BufferModule,
+    programs: HashMap<ProgramKey, Option<BatchPrograms>>, // By batched program key, None if broken
+    quad_indices: Vec<u32>,
+
+    current: Option<BatchKey>,
+    first_command: RenderCmdHd,
+    commands_count: u32,
+    batch_data: Vec<f32>, // Streamed vertices, or instances data when instanced
+}
+
+impl SpriteBatcher {
+    pub fn new(gfx: &GfxDevice) -> Self {
+        let buffer = gfx.alloc_buffer(vec![vec![]], vec![], BufferSettings::sprite_batch());
+        let instanced_buffer = gfx.alloc_buffer(
+            RendererStorage::load(&MeshInfo {
+                file_path: None,
+                count: 0,
+                vertices_set: None,
+            }),
+            vec![RendererStorage::get_quad_indices()],
+            BufferSettings::sprite_instances(),
+        );
+
+        Self {
+            buffer,
+            instanced_buffer,
+            programs: HashMap::new(),
+            quad_indices: RendererStorage::get_quad_indices(),
+            current: None,
+            first_command: 0,
+            commands_count: 0,
+            batch_data: Vec::with_capacity(FLOATS_PER_VERTEX * 6 * 128),
+        }
+    }
+
+    // Commands with a custom vertex shader rely on their own TRS uniform and are drawn directly,
+    // like the materials with custom uniforms or extra textures
+    pub fn is_batchable(command: &RenderCommand) -> bool {
+        let material = &command.shader_module.material;
+        let uses_default_vertex = material
+            .shaders
+            .vertex
+            .as_ref()
+            .is_none_or(|info| info.file_name.contains(DEFAULT_SHADER_KEY));
+
+        uses_default_vertex
+            && material.uniforms.is_empty()
+            && material.texture_slots.is_empty()
+            && command.buffer_module.vertices.is_some()
+    }
+
+    // Fetch (once per fragment shader & defines) the programs used to draw batches of this
+    // material, on its first batch. The batcher keeps one reference on them for its whole
+    // lifetime. Programs failing to build are remembered, their sprites are drawn directly.
+    fn get_programs(
+        &mut self,
+        gfx: &GfxDevice,
+        store: &mut RendererStorage,
+        programs_key: &ProgramKey,
+    ) -> Option<&BatchPrograms> {
+        if !self.programs.contains_key(programs_key) {
+            let programs = Self::alloc_programs(gfx, store, programs_key)
+                .inspect_err(|err| {
+                    println!("[Sprite Batch] Could not build program, drawn directly: {}", err)
+                })
+                .ok();
+            self.programs.insert(programs_key.clone(), programs);
+        }
+        self.programs.get(programs_key).and_then(Option::as_ref)
+    }
+
+    fn alloc_programs(
+        gfx: &GfxDevice,
+        store: &mut RendererStorage,
+        programs_key: &ProgramKey,
+    ) -> Result<BatchPrograms, String> {
+        let instanced_key = ProgramKey {
+            vertex: String::from(INSTANCED_VERTEX_SHADER),
+            ..programs_key.clone()
+        };
+
+        let batched = get_or_alloc_program(gfx, store, programs_key)?;
+        match get_or_alloc_program(gfx, store, &instanced_key) {
+            Ok(instanced) => Ok(BatchPrograms { batched, instanced }),
+            Err(err) => {
+                if store.decrement_program_handle(batched) {
+                    gfx.release_program(batched);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    // Programs failing to build are tried again on their next batch, after a shader reload
+    pub fn forget_failed_programs(&mut self) {
+        self.programs.retain(|_, programs| programs.is_some());
+    }
+
+    // Swap a reloaded program, see RendererStorage::replace_program_handle
+    pub fn replace_program(&mut self, handle: u32, new_handle: u32) {
+        for programs in self.programs.values_mut().flatten() {
+            for program in [&mut programs.batched, &mut programs.instanced] {
+                if *program == handle {
+                    *program = new_handle;
+                }
+            }
+        }
+    }
+
+    // Append the command to the current batch, the batch is flushed first if its state differs.
+    // Returns false when the batch programs can't be built, the command must be drawn directly.
+    pub fn push(
+        &mut self,
+        gfx: &GfxDevice,
+        store: &mut RendererStorage,
+        command: &RenderCommand,
+        instanced: bool,
+        stats: &mut FrameStats,
+    ) -> bool {
+        let key = BatchKey {
+            programs: Self::programs_key(&command.shader_module.material),
+            texture: command.shader_module.texture_handles.first().copied(),
+            blend_mode: command.shader_module.material.blend_mode,
+            instanced,
+        };
+        if self.get_programs(gfx, store, &key.programs).is_none() {
+            return false;
+        }
+
+        if self.current.as_ref() != Some(&key) {
+            self.flush(gfx, stats);
+            self.first_command = command.handle;
+            self.current = Option::from(key);
+        }
+
+        let color: Vector4<f32> = command.shader_module.material.color;
+        let uv_rect: Vector4<f32> = command.uv_rect;
+        self.commands_count += 1;
+
+        if instanced {
+            let trs = &command.trs;
+            for column in [trs.c0, trs.c1, trs.c2, trs.c3, color, uv_rect] {
+                self.batch_data
+                    .extend_from_slice(&[column.x, column.y, column.z, column.w]);
+            }
+            return true;
+        }
+
+        let quad: &Vec<f32> = &command.buffer_module.vertices.as_ref().unwrap()[0];
+        for index in self.quad_indices.iter() {
+            let vertex = &quad[*index as usize * QUAD_STRIDE..(*index as usize + 1) * QUAD_STRIDE];
+            let world = command
+                .trs
+                .mul_v(&Vector4::new(vertex[0], vertex[1], vertex[2], 1f32));
+
+            // Atlas regions are baked into the uvs
+            let u = uv_rect.x + vertex[3] * uv_rect.z;
+            let v = uv_rect.y + vertex[4] * uv_rect.w;
+            self.batch_data.extend_from_slice(&[
+                world.x, world.y, world.z, u, v, color.x, color.y, color.z, color.w,
+            ]);
+        }
+        true
+    }
+
+    pub fn flush(&mut self, gfx: &GfxDevice, stats: &mut FrameStats) {
+        let Some(key) = self.current.take() else {
+            return;
+        };
+
+        let programs = self
+            .programs
+            .get(&key.programs)
+            .and_then(Option::as_ref)
+            .expect("[Sprite Batch] Batch program not allocated");
+        let (program, buffer) = if key.instanced {
+            (programs.instanced, &self.instanced_buffer)
+        } else {
+            (programs.batched, &self.buffer)
+        };
+
+        let command = self.batch_command(program, buffer, key.texture, key.blend_mode);
+        gfx.use_shader_module(&command.shader_module);
+        if key.instanced {
+            gfx.update_instance_buffer(buffer, &self.batch_data);
+            gfx.draw_command_instanced(&command, self.commands_count as i32);
+        } else {
+            gfx.update_buffer(buffer, &self.batch_data);
+            gfx.draw_command(
+                &command,
+                Option::from((self.batch_data.len() / FLOATS_PER_VERTEX) as i32),
+            );
+        }
+
+        stats.draw_calls += 1;
+        stats.batches += 1;
+        stats.batched_commands += self.commands_count;
+
+        self.batch_data.clear();
+        self.commands_count = 0;
+    }
+
+    // Write the triangulated masks outlines in the stencil buffer in a single draw call, the
+    // stencil mode is set by the caller. Masks use the programs of the default material.
+    pub fn draw_masks(
+        &mut self,
+        gfx: &GfxDevice,
+        store: &mut RendererStorage,
+        masks: &[RenderingSpriteMask],
+        stats: &mut FrameStats,
+    ) {
+        self.flush(gfx, stats);
+
+        for mask in masks {
+            for triangle in triangulate_polygon(&mask.points) {
+                for point in triangle.map(|index| mask.points[index]) {
+                    self.batch_data.extend_from_slice(&[
+                        point.x, point.y, 0f32, 0f32, 0f32, 1f32, 1f32, 1f32, 1f32,
+                    ]);
+                }
+            }
+        }
+        if self.batch_data.is_empty() {
+            return;
+        }
+
+        let Some(programs) = self.get_programs(gfx, store, &Self::programs_key(&Material::new()))
+        else {
+            self.batch_data.clear();
+            return;
+        };
+        let program = programs.batched;
+        let command = self.batch_command(program, &self.buffer, None, BlendMode::Alpha);
+        gfx.use_shader_module(&command.shader_module);
+        gfx.update_buffer(&self.buffer, &self.batch_data);
+        gfx.draw_command(
+            &command,
+            Option::from((self.batch_data.len() / FLOATS_PER_VERTEX) as i32),
+        );
+        stats.draw_calls += 1;
+        self.batch_data.clear();
+    }
+
+    // Command drawing the streamed vertices or instances, its material only holds the blend mode
+    fn batch_command(
+        &self,
+        program: u32,
+        buffer: &BufferModule,
+        texture: Option<u32>,
+        blend_mode: BlendMode,
+    ) -> RenderCommand {
+        RenderCommand {
+            initialized: true,
+            handle: self.first_command,
+            shader_module: ShaderModule {
+                self_handle: program,
+                vertex_handle: None,
+                fragment_handle: None,
+                texture_handles: texture.into_iter().collect(),
+                material: Material {
+                    blend_mode,
+                    ..Material::new()
+                },
+            },
+            buffer_module: buffer.clone(),
+            trs: identity_mat4(),
+            sorting: SortingOrder::default(),
+            uv_rect: full_uv_rect(),
+            mask_interaction: MaskInteraction::None,
+        }
+    }
+
+    // Programs are looked up by the key of their batched variant
+    fn programs_key(material: &Material) -> ProgramKey {
+        let fragment = material
+            .shaders
+            .fragment
+            .as_ref()
+            .map_or(String::from(DEFAULT_SHADER_KEY), |info| {
+                info.file_name.clone()
+            });
+
+        ProgramKey {
+            vertex: String::from(BATCH_VERTEX_SHADER),
+            fragment,
+            defines: material.shaders.defines.clone(),
+            keywords: get_sorted_keywords(&material.shaders.keywords),
+        }
+    }
+}
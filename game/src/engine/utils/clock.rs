@@ -0,0 +1,58 @@
+use std::time::Instant;
+
+// Source of the frame delta time, the app asks it once per frame
+pub trait Clock {
+    // Seconds elapsed since the previous tick
+    fn tick(&mut self) -> f32;
+
+    // Called when the game loop starts so time spent before isn't counted
+    fn reset(&mut self) {}
+}
+
+pub struct SystemClock {
+    time_point: Instant,
+}
+
+// Deterministic clock advancing by the same delta every frame
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock {
+    pub delta: f32,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            time_point: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn tick(&mut self) -> f32 {
+        let dt = Instant::elapsed(&self.time_point);
+        self.time_point = Instant::now();
+        dt.as_secs_f32()
+    }
+
+    fn reset(&mut self) {
+        self.time_point = Instant::now();
+    }
+}
+
+impl FixedClock {
+    pub fn new(delta: f32) -> Self {
+        Self { delta }
+    }
+}
+
+impl Clock for FixedClock {
+    fn tick(&mut self) -> f32 {
+        self.delta
+    }
+}
@@ -14,6 +14,7 @@ static SHADER_PATH: &str = "shaders/";
 static TEXTURE_PATH: &str = "textures/";
 static MESH_PATH: &str = "meshes/";
 static MATERIAL_PATH: &str = "materials/";
+static ANIMATION_PATH: &str = "animations/";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
@@ -21,6 +22,7 @@ pub enum FileType {
     Texture,
     Mesh,
     Material,
+    Animation,
 }
 
 pub struct FileSystem;
@@ -82,25 +84,34 @@ impl FileSystem {
             .expect("Could not write to file");
     }
 
-    fn get_path(file_path: &str, f_type: FileType) -> String {
+    pub fn remove_file(file_path: &str, f_type: FileType) {
+        let asset_path: String = FileSystem::get_path(file_path, f_type);
+
+        if let Err(err) = std::fs::remove_file(&asset_path) {
+            println!("[File System] Could not remove file {}: {}", asset_path, err);
+        }
+    }
+
+    // Directory of the assets of this type, file paths are relative to it
+    pub fn get_directory(f_type: FileType) -> PathBuf {
         let current_dir: PathBuf = env::current_dir().expect("Could not get current directory");
-        let path: String;
 
         let type_path: &str = match f_type {
             FileType::Material => MATERIAL_PATH,
             FileType::Shader => SHADER_PATH,
             FileType::Texture => TEXTURE_PATH,
             FileType::Mesh => MESH_PATH,
+            FileType::Animation => ANIMATION_PATH,
         };
 
-        path = current_dir
-            .join(ASSETS_PATH)
-            .join(type_path)
+        current_dir.join(ASSETS_PATH).join(type_path)
+    }
+
+    fn get_path(file_path: &str, f_type: FileType) -> String {
+        FileSystem::get_directory(f_type)
             .join(file_path)
             .into_os_string()
             .into_string()
-            .unwrap();
-
-        path
+            .unwrap()
     }
 }
@@ -1,4 +1,4 @@
-use crate::engine::ecs::components::{Position, Scale, Transform};
+use crate::engine::ecs::components::{Position, Projection, Scale, Transform};
 use crate::engine::rendering::components::RenderingCamera;
 use glm::{vec3, BaseFloat, Matrix4, Vector2, Vector3};
 use std::ops::{Add, Div, Mul, Sub};
@@ -77,6 +77,13 @@ pub fn compute_view_matrix(transform: &Transform) -> Matrix4<f32> {
 }
 
 pub fn compute_projection(camera: &RenderingCamera, window_rect: &Rect<u32>) -> Matrix4<f32> {
+    match camera.projection {
+        Projection::Orthographic => compute_orthographic_projection(camera, window_rect),
+        Projection::Perspective => compute_perspective_projection(camera, window_rect),
+    }
+}
+
+fn compute_orthographic_projection(camera: &RenderingCamera, window_rect: &Rect<u32>) -> Matrix4<f32> {
     let width = window_rect.width as f32 / camera.ppu as f32;
     let height = window_rect.height as f32 / camera.ppu as f32;
     let h_w = width * 0.5f32;
@@ -100,6 +107,89 @@ pub fn compute_projection(camera: &RenderingCamera, window_rect: &Rect<u32>) ->
     orthographic_projection
 }
 
+// Looks toward +z like the orthographic projection, depth goes from -1 (near) to 1 (far)
+fn compute_perspective_projection(camera: &RenderingCamera, window_rect: &Rect<u32>) -> Matrix4<f32> {
+    let aspect = window_rect.width as f32 / window_rect.height as f32;
+    let focal = 1f32 / (camera.fov.to_radians() * 0.5f32).tan();
+    let near = camera.near;
+    let far = camera.far;
+
+    let mut perspective_projection = identity_mat4();
+
+    perspective_projection.c0.x = focal / aspect;
+    perspective_projection.c1.y = focal;
+    perspective_projection.c2.z = (far + near) / (far - near);
+    perspective_projection.c2.w = 1f32;
+    perspective_projection.c3.z = -2f32 * far * near / (far - near);
+    perspective_projection.c3.w = 0f32;
+
+    perspective_projection
+}
+
+// Volume seen by a camera, sprites are culled against the area it covers at their depth
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    pub position: Vector3<f32>, // Camera position
+    pub size: Vector2<f32>,     // World size seen, at one unit of distance in perspective
+    pub near: f32,
+    pub far: f32,
+    pub perspective: bool,
+}
+
+impl Frustum {
+    pub fn from_camera(camera: &RenderingCamera, target_rect: &Rect<u32>) -> Self {
+        let (width, height) = (target_rect.width as f32, target_rect.height as f32);
+        let size = match camera.projection {
+            Projection::Orthographic => {
+                Vector2::new(width / camera.ppu as f32, height / camera.ppu as f32)
+            }
+            Projection::Perspective => {
+                let unit_height = 2f32 * (camera.fov.to_radians() * 0.5f32).tan();
+                Vector2::new(unit_height * width / height, unit_height)
+            }
+        };
+        let position = &camera.transform.position;
+
+        Self {
+            position: vec3(position.x, position.y, position.z),
+            size,
+            near: camera.near,
+            far: camera.far,
+            perspective: camera.projection == Projection::Perspective,
+        }
+    }
+
+    // World area seen on the z plane, None when a perspective camera can't see the plane.
+    // Orthographic cameras see the same area at any depth.
+    pub fn get_rect_at(&self, z: f32) -> Option<Rect<f32>> {
+        if !self.perspective {
+            return Some(Rect {
+                x: self.position.x,
+                y: self.position.y,
+                width: self.size.x,
+                height: self.size.y,
+            });
+        }
+
+        let distance = z - self.position.z;
+        if distance < self.near || distance > self.far {
+            return None;
+        }
+
+        Some(Rect {
+            x: self.position.x,
+            y: self.position.y,
+            width: self.size.x * distance,
+            height: self.size.y * distance,
+        })
+    }
+
+    pub fn intersects(&self, rect: Rect<f32>, z: f32) -> bool {
+        self.get_rect_at(z)
+            .is_some_and(|area| intersects(area, rect))
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 pub struct Rect<T> {
     pub x: T,
@@ -153,6 +243,58 @@ pub fn intersects(base_rect: Rect<f32>, rect: Rect<f32>) -> bool {
     x_axis && y_axis
 }
 
+// Ear clipping of a closed outline (convex or not, either winding, not self intersecting),
+// returns the triangles as indices in the points
+pub fn triangulate_polygon(points: &[Vector2<f32>]) -> Vec<[usize; 3]> {
+    let cross = |o: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>| {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+    // Positive for counter clockwise outlines, the corners of the ears turn the same way
+    let winding: f32 = (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f32>()
+        .signum();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles: Vec<[usize; 3]> = Vec::with_capacity(points.len().saturating_sub(2));
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let corner = |i: usize| {
+            [remaining[(i + count - 1) % count], remaining[i], remaining[(i + 1) % count]]
+        };
+        let ear = (0..count).find(|i| {
+            let triangle = corner(*i);
+            let [a, b, c] = triangle.map(|index| points[index]);
+            if cross(a, b, c) * winding <= 0f32 {
+                return false; // Reflex or flat corner
+            }
+            remaining
+                .iter()
+                .filter(|index| !triangle.contains(index))
+                .map(|index| points[*index])
+                .all(|p| {
+                    cross(a, b, p) * winding < 0f32
+                        || cross(b, c, p) * winding < 0f32
+                        || cross(c, a, p) * winding < 0f32
+                })
+        });
+
+        let Some(ear) = ear else {
+            break; // Degenerate outline, the rest is left out
+        };
+        triangles.push(corner(ear));
+        remaining.remove(ear);
+    }
+
+    if remaining.len() == 3 && winding != 0f32 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+    }
+    triangles
+}
+
 #[derive(Debug, Clone)]
 pub struct Segment {
     pub points: [usize; 2],
@@ -1,4 +1,5 @@
 pub mod app_settings;
+pub mod clock;
 pub mod rendering_bridge;
 pub mod file_system;
 pub mod maths;
\ No newline at end of file
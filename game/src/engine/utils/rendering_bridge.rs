@@ -1,20 +1,27 @@
-use crate::engine::ecs::components::{Camera, Transform};
+use crate::engine::ecs::components::{
+    Camera, CameraBinding, Light2D, PostProcessStack, ShadowCaster2D, SpriteMask, Transform,
+};
 use crate::engine::ecs::resources::CameraCullingState;
 use crate::engine::ecs::{components::SpriteRenderer2D, resources::RenderingFrameData};
 use crate::engine::rendering::components::{
-    ARGB8Color, MeshInfo, RenderRequest, RenderUpdate, RenderingCamera,
+    ARGB8Color, MeshInfo, RenderRequest, RenderUpdate, RenderingCamera, RenderingLight,
+    RenderingShadowCaster, RenderingSpriteMask, SortingOrder,
 };
-use crate::engine::rendering::renderer::{RenderCmdHd, Renderer};
+use crate::engine::rendering::renderer::{CameraHd, RenderCmdHd, Renderer};
 use crate::engine::rendering::renderer_helpers::prepare_material;
 use crate::engine::rendering::shaders::Material;
-use crate::engine::utils::maths::Rect;
+use crate::engine::utils::maths::{compute_trs, Frustum};
 use bevy_ecs::entity::Entity;
 use bevy_ecs::world::{EntityRef, World};
 use bit_set::BitSet;
+use glm::{Vector2, Vector4};
 use std::cell::{Ref, RefCell, RefMut};
 use std::collections::HashMap;
 use std::rc::Rc;
 
+// Outline of the sprites unit quad, see renderer_storage.rs
+const QUAD_OUTLINE: [(f32, f32); 4] = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)];
+
 #[derive(Debug)]
 pub struct RenderingBridge {
     ecs_world: Rc<RefCell<World>>,
@@ -52,6 +59,8 @@ impl RenderingBridge {
                 let transform = entity_ref
                     .get::<Transform>()
                     .expect("Entity have no transform");
+                let file_material = RenderingBridge::load_sprite_material(renderer, comp);
+                let material = file_material.as_deref().or(comp.material.as_ref());
                 let handle: RenderCmdHd = renderer.create_render_command(RenderRequest {
                     mesh_info: MeshInfo {
                         // Todo: Will be default until mesh is implemented
@@ -59,8 +68,13 @@ impl RenderingBridge {
                         count: 0,
                         vertices_set: None,
                     },
-                    material: prepare_material(comp, comp.material.as_ref()),
+                    material: prepare_material(comp, material),
                     transform: transform.clone(),
+                    sorting: SortingOrder {
+                        layer: comp.sorting_layer,
+                        order_in_layer: comp.order_in_layer,
+                    },
+                    mask_interaction: comp.mask_interaction,
                 });
 
                 let links_len: usize = self.entity_handle_pairs.borrow().len();
@@ -141,22 +155,17 @@ impl RenderingBridge {
         if force_full_pass {
             let world = self.get_world();
             let culling_state = world.get_resource::<CameraCullingState>().unwrap();
+            let camera_frustums: Vec<Frustum> =
+                culling_state.get_camera_frustums(|entity| world.get::<Transform>(entity));
 
             for (rendering_hdl, sprite_entity) in self.entity_handle_pairs.borrow().iter() {
                 if self.culled_check.borrow().contains(*rendering_hdl) {
                     continue;
                 }
 
-                let camera_transform = world
-                    .get::<Transform>(culling_state.camera_entity.unwrap())
-                    .unwrap();
                 let entity_transform = world.get::<Transform>(*sprite_entity).unwrap();
-                let camera_rect: Rect<f32> = Rect {
-                    x: camera_transform.position.x,
-                    y: camera_transform.position.y,
-                    ..culling_state.camera_world_viewport
-                };
-                let culled_state = culling_state.compute_visibility(camera_rect, &entity_transform);
+                let culled_state =
+                    culling_state.compute_visibility(&camera_frustums, &entity_transform);
 
                 renderer.cull(*rendering_hdl, !culled_state.is_visible());
             }
@@ -195,22 +204,35 @@ impl RenderingBridge {
             let transform: &Transform = world.get::<Transform>(*updated_entity).unwrap();
 
             // Blit the texture from the sprite component to the rendering material sprite
-            let new_material: Option<Material> =
-                component.material.as_ref().map(|map: &Material| {
-                    let mut new_material: Material = map.clone();
-                    new_material.main_texture = component.texture.clone();
-                    return new_material;
-                });
+            let file_material = RenderingBridge::load_sprite_material(renderer, component);
+            let new_material: Option<Material> = file_material
+                .as_deref()
+                .or(component.material.as_ref())
+                .map(|material| prepare_material(component, Some(material)));
 
             renderer.update_render_command(RenderUpdate {
                 render_cmd: cmd_handle,
                 mesh_info: None,
                 material: new_material,
                 transform: Option::from(transform.clone()),
+                sorting: Some(SortingOrder {
+                    layer: component.sorting_layer,
+                    order_in_layer: component.order_in_layer,
+                }),
+                mask_interaction: Some(component.mask_interaction),
             });
         }
     }
 
+    // Material file the sprite refers to, it takes precedence over the sprite material
+    fn load_sprite_material(
+        renderer: &Renderer,
+        sprite: &SpriteRenderer2D,
+    ) -> Option<Rc<Material>> {
+        let material_name = sprite.material_name.as_ref()?;
+        renderer.load_material(material_name)
+    }
+
     fn process_deleted_2d_sprites(&self, renderer: &mut Renderer, container: &RenderingFrameData) {
         for entity in container.deleted_2d_render.iter() {
             if self.handle_index_by_entity.borrow().contains_key(entity) {
@@ -237,41 +259,186 @@ impl RenderingBridge {
 
     pub fn flush_camera_changes(&mut self, renderer: &mut Renderer) {
         let mut world: RefMut<World> = self.get_world_mut();
+        let mut resources = world.get_resource_mut::<RenderingFrameData>().unwrap();
+        let new_cameras: Vec<Entity> = resources.new_cameras.drain(..).collect();
+        let updated_settings: Vec<Entity> = resources.updated_camera_settings.drain(..).collect();
+        let updated_transforms: Vec<Entity> =
+            resources.updated_camera_transform.drain(..).collect();
+        let updated_post_process: Vec<Entity> =
+            resources.updated_post_process.drain(..).collect();
+        let removed_cameras: Vec<Entity> = resources.removed_cameras.drain(..).collect();
+
+        // Removed first, a camera removed then added again in the same frame gets a new handle
+        for entity in removed_cameras.iter() {
+            let mut binding = world.get_resource_mut::<CameraBinding>().unwrap();
+            let Some(index) = binding.cameras.iter().position(|(bound, _)| bound == entity) else {
+                continue;
+            };
+
+            let (_, handle) = binding.cameras.remove(index);
+            renderer.remove_camera(handle);
 
-        let culling_state = world.get_resource::<CameraCullingState>().unwrap();
-        let camera = world.entity(culling_state.camera_entity.unwrap());
+            let mut culling_state = world.get_resource_mut::<CameraCullingState>().unwrap();
+            culling_state.remove_camera_frustum(*entity);
+            culling_state.entities.clear();
+            culling_state.force_full_pass = true;
+            println!(
+                "[ECS Rendering] Camera removed (entity: {} <=> camera_handle: {})",
+                entity.index(),
+                handle
+            );
+        }
 
-        // Flush viewport in case of any changes
-        {
-            let camera_component = camera.get::<Camera>().unwrap();
-            let (x, y, w, h) = camera_component.viewport.clone();
-            renderer.update_normalized_viewport(x, y, w, h);
+        // The main camera is bound when the app warms, other cameras get a new renderer camera
+        for entity in new_cameras.iter() {
+            if RenderingBridge::get_camera_handle(&world, *entity).is_some() {
+                continue;
+            }
+
+            let camera = RenderingBridge::build_rendering_camera(&world, *entity);
+            let handle = renderer.add_camera(camera);
+            let mut binding = world.get_resource_mut::<CameraBinding>().unwrap();
+            binding.cameras.push((*entity, handle));
+            println!(
+                "[ECS Rendering] New camera created with link (entity: {} <=> camera_handle: {})",
+                entity.index(),
+                handle
+            );
         }
 
-        let resources = world.get_resource::<RenderingFrameData>().unwrap();
+        for entity in updated_settings.iter() {
+            let Some(handle) = RenderingBridge::get_camera_handle(&world, *entity) else {
+                continue;
+            };
 
-        for entity in resources.updated_camera_settings.iter() {
-            let camera_comp: &Camera = world.get::<Camera>(*entity).unwrap();
-            let transform_comp: &Transform = world.get::<Transform>(*entity).unwrap();
-            let background_color = camera_comp.background_color.unwrap_or(ARGB8Color::black());
+            let camera = RenderingBridge::build_rendering_camera(&world, *entity);
+            renderer.update_camera_settings(handle, camera);
 
-            renderer.update_camera_settings(RenderingCamera {
-                near: camera_comp.near,
-                far: camera_comp.far,
-                ppu: camera_comp.ppu,
-                clear_color: background_color,
-                transform: transform_comp.clone(),
-            });
+            // Sprites visibility is computed again against the new cameras area
+            let frustum = renderer.get_camera_frustum(handle).unwrap();
+            let mut culling_state = world.get_resource_mut::<CameraCullingState>().unwrap();
+            culling_state.set_camera_frustum(*entity, frustum);
+            culling_state.entities.clear();
+            culling_state.force_full_pass = true;
         }
 
-        for entity in resources.updated_camera_transform.iter() {
+        for entity in updated_transforms.iter() {
+            let Some(handle) = RenderingBridge::get_camera_handle(&world, *entity) else {
+                continue;
+            };
+
             let camera_comp: &Transform = world.get::<Transform>(*entity).unwrap();
-            renderer.update_camera_transform(camera_comp.clone());
+            renderer.update_camera_transform(handle, camera_comp.clone());
         }
 
-        let mut mut_container = world.get_resource_mut::<RenderingFrameData>().unwrap();
-        mut_container.updated_camera_settings.clear();
-        mut_container.updated_camera_transform.clear();
+        for entity in updated_post_process.iter() {
+            let Some(handle) = RenderingBridge::get_camera_handle(&world, *entity) else {
+                continue;
+            };
+
+            let effects = world
+                .get::<PostProcessStack>(*entity)
+                .map_or(vec![], |stack| stack.effects.clone());
+            renderer.update_camera_post_effects(handle, effects);
+        }
+    }
+
+    // Lights & shadow casters are sent every frame, they follow their entity without any change
+    // tracking
+    pub fn flush_lights(&mut self, renderer: &mut Renderer) {
+        let mut world: RefMut<World> = self.get_world_mut();
+        let lights: Vec<RenderingLight> = world
+            .query::<(&Light2D, &Transform)>()
+            .iter(&world)
+            .map(|(light, transform)| RenderingLight {
+                kind: light.kind,
+                color: light.color,
+                intensity: light.intensity,
+                radius: light.radius,
+                falloff: light.falloff,
+                position: Vector2::new(transform.position.x, transform.position.y),
+                rotation: transform.rotation.z,
+                cast_shadows: light.cast_shadows,
+                shadow_softness: light.shadow_softness,
+            })
+            .collect();
+
+        let casters: Vec<RenderingShadowCaster> = world
+            .query::<(&ShadowCaster2D, &Transform)>()
+            .iter(&world)
+            .map(|(caster, transform)| {
+                let local_points = match caster {
+                    ShadowCaster2D::Polygon(points) => points.as_slice(),
+                    ShadowCaster2D::SpriteRect => &QUAD_OUTLINE,
+                };
+                RenderingShadowCaster {
+                    points: Self::get_world_outline(local_points, transform),
+                }
+            })
+            .collect();
+
+        renderer.update_lights(lights, casters);
+    }
+
+    // Sprite masks are sent every frame too
+    pub fn flush_sprite_masks(&mut self, renderer: &mut Renderer) {
+        let mut world: RefMut<World> = self.get_world_mut();
+        let masks: Vec<RenderingSpriteMask> = world
+            .query::<(&SpriteMask, &Transform)>()
+            .iter(&world)
+            .map(|(mask, transform)| {
+                let local_points = match mask {
+                    SpriteMask::Polygon(points) => points.as_slice(),
+                    SpriteMask::Rect => &QUAD_OUTLINE,
+                };
+                RenderingSpriteMask {
+                    points: Self::get_world_outline(local_points, transform),
+                }
+            })
+            .collect();
+
+        renderer.update_sprite_masks(masks);
+    }
+
+    fn get_world_outline(points: &[(f32, f32)], transform: &Transform) -> Vec<Vector2<f32>> {
+        let trs = compute_trs(transform);
+        points
+            .iter()
+            .map(|(x, y)| {
+                let world = trs.mul_v(&Vector4::new(*x, *y, 0f32, 1f32));
+                Vector2::new(world.x, world.y)
+            })
+            .collect()
+    }
+
+    fn get_camera_handle(world: &World, entity: Entity) -> Option<CameraHd> {
+        world
+            .resource::<CameraBinding>()
+            .cameras
+            .iter()
+            .find(|(camera_entity, _)| *camera_entity == entity)
+            .map(|(_, handle)| *handle)
+    }
+
+    fn build_rendering_camera(world: &World, entity: Entity) -> RenderingCamera {
+        let camera_comp: &Camera = world.get::<Camera>(entity).unwrap();
+        let transform_comp: &Transform = world.get::<Transform>(entity).unwrap();
+        let (x, y, width, height) = camera_comp.viewport;
+
+        RenderingCamera {
+            projection: camera_comp.mode,
+            fov: camera_comp.fov,
+            near: camera_comp.near,
+            far: camera_comp.far,
+            ppu: camera_comp.ppu,
+            clear_color: camera_comp.background_color.unwrap_or(ARGB8Color::black()),
+            viewport: Vector4::new(x, y, width, height),
+            order: camera_comp.order,
+            output_target: camera_comp.output_target.clone(),
+            hdr: camera_comp.hdr,
+            ambient_light: camera_comp.ambient_light,
+            transform: transform_comp.clone(),
+        }
     }
 
     pub fn build_camera(world: &mut World) -> Entity {
@@ -2,28 +2,20 @@ use bevy_ecs::entity::Entity;
 use bevy_ecs::query::Without;
 use bevy_ecs::schedule::Schedules;
 use bevy_ecs::system::{Query, Res};
+use engine::ecs::animation::{AnimationClip, Animator};
 use engine::ecs::components::{Camera, Inputs, Rotation, Scale, SpriteRenderer2D, Transform};
-use engine::ecs::config::{EcsFixedUpdateSchedule, EcsLateUpdateSchedule, EcsUpdateSchedule};
+use engine::ecs::config::{EcsFixedUpdateSchedule, EcsUpdateSchedule};
 use engine::ecs::resources::Time;
 use engine::lib::runtime::App;
 
 use crate::engine::ecs::components::Position;
 use crate::engine::rendering::components::ARGB8Color;
 use engine::utils::app_settings::{ApplicationSettings, WindowMode, WindowSettings};
-use glm::{abs, vec4};
-use rand::random;
 use crate::engine::ecs::resources::CameraCullingState;
 
 pub mod engine;
 mod tests;
 
-#[derive(bevy_ecs::component::Component)]
-pub struct ChangeChecker {
-    pub accumulated_time: f32,
-    pub color_timer: f32,
-    pub flip_color: bool,
-}
-
 pub fn update_camera(inputs: Res<Inputs>, mut query: Query<(Entity, &mut Camera)>) {
     let mut camera_count: i32 = 0;
 
@@ -106,42 +98,6 @@ pub fn fixed_update_system(_time: Res<Time>) {
     // println!("Fixed update: {}", &time.fixed_delta_time);
 }
 
-pub fn late_update_system(
-    _time: Res<Time>,
-    mut query: Query<(&mut SpriteRenderer2D, &mut ChangeChecker)>,
-) {
-    for (mut sprite, mut checker) in query.iter_mut() {
-        let color_white = vec4(1f32, 1f32, 1f32, 1f32);
-        let color_other = vec4(0.1f32, 1f32, 0.8f32, 1f32);
-        let colors = ["Dark", "Green", "Orange", "Purple", "Red"];
-
-        checker.accumulated_time += _time.delta_time;
-        checker.color_timer += _time.delta_time;
-
-        if checker.accumulated_time >= 2f32 {
-            let rng_idx = abs(random::<i32>() % 9);
-            let rng_folder = abs(random::<i32>() % 5);
-
-            let number = (rng_idx + 1).clamp(1, 9);
-            let folder = colors[rng_folder as usize];
-            sprite.texture = Option::from(format!("{}/texture_0{}.png", folder, number));
-            checker.accumulated_time = 0f32;
-        }
-
-        // if checker.color_timer >= 2f32 {
-        //     let color = checker
-        //         .flip_color
-        //         .then(|| color_other)
-        //         .unwrap_or(color_white);
-        //
-        //     sprite.material.get_or_insert_with(Material::new).color = color;
-        //
-        //     checker.flip_color = !checker.flip_color;
-        //     checker.color_timer = 0f32;
-        // }
-    }
-}
-
 fn main() {
     let app_settings = ApplicationSettings {
         window: WindowSettings {
@@ -167,10 +123,6 @@ fn main() {
             .get_mut(EcsFixedUpdateSchedule)
             .unwrap()
             .add_systems(fixed_update_system);
-        schedules
-            .get_mut(EcsLateUpdateSchedule)
-            .unwrap()
-            .add_systems(late_update_system);
         schedules
             .get_mut(EcsUpdateSchedule)
             .unwrap()
@@ -180,8 +132,13 @@ fn main() {
             .unwrap()
             .add_systems(move_camera_2d);
 
+        let clips = AnimationClip::load_file("prototype.anim").unwrap();
         let pos: f32 = 1.0f32;
         for i in 0..100 {
+            let mut animator = Animator::new(clips.clone());
+            animator.play(if i % 2 == 0 { "blink" } else { "pulse" });
+            animator.speed = 1f32 + (i % 3) as f32 * 0.5f32;
+
             let _entity = world.spawn((
                 Transform {
                     position: Position {
@@ -200,12 +157,12 @@ fn main() {
                         z: 1.0,
                     },
                 },
-                SpriteRenderer2D::from(String::from("Red/texture_08.png"), false),
-                ChangeChecker {
-                    accumulated_time: 0f32,
-                    color_timer: 0f32,
-                    flip_color: false,
-                },
+                SpriteRenderer2D::from_atlas(
+                    String::from("prototype.atlas"),
+                    String::from("red"),
+                    false,
+                ),
+                animator,
             ));
         }
         
@@ -0,0 +1,253 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::animation::{AnimationClip, Animator, PlaybackMode};
+    use crate::engine::ecs::components::SpriteRenderer2D;
+    use crate::engine::ecs::config::EcsUpdateSchedule;
+    use crate::engine::ecs::resources::AnimationFinished;
+    use crate::engine::ecs::systems::animator_system;
+    use crate::engine::rendering::atlas::{AtlasPacker, TextureAtlas};
+    use crate::engine::rendering::shaders::Texture;
+    use crate::tests::fixtures::sprite_transform;
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::prelude::*;
+    use std::rc::Rc;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const ATLAS: &str = "solid.atlas";
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+
+    fn clip(mode: PlaybackMode, frames: usize) -> AnimationClip {
+        AnimationClip {
+            name: String::from("clip"),
+            atlas: None,
+            frames: (0..frames).map(|i| format!("frame_{}.png", i)).collect(),
+            frame_duration: 0.25f32,
+            mode,
+        }
+    }
+
+    // Frame index shown after each step of a frame duration
+    fn play_steps(animator: &mut Animator, steps: usize) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                animator.advance(0.25f32);
+                animator.get_frame_index().unwrap()
+            })
+            .collect()
+    }
+
+    fn solid_atlas() -> TextureAtlas {
+        let solid = |texel: [u8; 4]| {
+            Rc::new(Texture {
+                data: texel.repeat(64),
+                width: 8,
+                height: 8,
+                channels: 4,
+            })
+        };
+
+        let mut packer = AtlasPacker::new();
+        packer.add("red", solid(RED)).add("green", solid(GREEN));
+        packer.pack().unwrap()
+    }
+
+    #[derive(Resource, Default)]
+    struct FinishedClips(Vec<AnimationFinished>);
+
+    fn collect_finished(
+        mut reader: EventReader<AnimationFinished>,
+        mut clips: ResMut<FinishedClips>,
+    ) {
+        clips.0.extend(reader.read().cloned());
+    }
+
+    fn spawn_animated(scene: &mut GoldenScene, description: &str, clip: &str) -> Entity {
+        let mut animator = Animator::new(AnimationClip::parse(description).unwrap());
+        animator.play(clip);
+
+        scene
+            .world()
+            .spawn((
+                sprite_transform((0f32, 0f32, 0f32), 0.5f32),
+                SpriteRenderer2D::from_atlas(String::from(ATLAS), String::from("red"), false),
+                animator,
+            ))
+            .id()
+    }
+
+    // Slightly off the center, the debug grid axes cross at the origin
+    fn sprite_pixel(scene: &mut GoldenScene, frames: u64) -> [u8; 4] {
+        scene
+            .render_frames(frames)
+            .get_pixel(WIDTH / 2 + 7, HEIGHT / 2 + 5)
+            .0
+    }
+
+    #[test]
+    fn clips_should_be_parsed_from_descriptions() {
+        let description = "# comment\n\
+                           clip idle loop 0.5\n\
+                           frames a.png b.png\n\
+                           frames c.png\n\
+                           \n\
+                           clip walk ping_pong 0.1\n\
+                           atlas hero.atlas\n\
+                           grid 4 3\n";
+        let clips = AnimationClip::parse(description).unwrap();
+
+        assert_eq!(clips.len(), 2);
+        assert_eq!(clips[0].mode, PlaybackMode::Loop);
+        assert_eq!(clips[0].frames, vec!["a.png", "b.png", "c.png"]);
+        assert_eq!(clips[0].atlas, None);
+        assert_eq!(clips[1].mode, PlaybackMode::PingPong);
+        assert_eq!(clips[1].frame_duration, 0.1f32);
+        assert_eq!(clips[1].atlas.as_deref(), Some("hero.atlas"));
+        assert_eq!(clips[1].frames, vec!["4", "5", "6"]);
+    }
+
+    #[test]
+    fn invalid_descriptions_should_be_reported() {
+        assert!(AnimationClip::parse("frames a.png").is_err());
+        assert!(AnimationClip::parse("clip idle forever 0.5\nframes a.png").is_err());
+        assert!(AnimationClip::parse("clip idle loop 0\nframes a.png").is_err());
+        assert!(AnimationClip::parse("clip idle loop 0.5").is_err());
+        assert!(AnimationClip::parse("clip idle loop 0.5\ngrid 0 4").is_err());
+        assert!(AnimationClip::parse("clip idle loop 0.5\nframe a.png").is_err());
+    }
+
+    #[test]
+    fn animation_files_should_be_loaded_from_the_assets() {
+        let animator = Animator::from_file("prototype.anim").unwrap();
+
+        assert_eq!(animator.clips.len(), 3);
+        assert!(animator
+            .clips
+            .iter()
+            .all(|clip| clip.atlas.is_none() || clip.atlas.as_deref() == Some("prototype.atlas")));
+        assert!(Animator::from_file("missing.anim").is_err());
+    }
+
+    #[test]
+    fn playback_modes_should_order_the_frames() {
+        let mut looping = Animator::new(vec![clip(PlaybackMode::Loop, 3)]);
+        looping.play("clip");
+        assert_eq!(play_steps(&mut looping, 5), vec![1, 2, 0, 1, 2]);
+
+        let mut ping_pong = Animator::new(vec![clip(PlaybackMode::PingPong, 3)]);
+        ping_pong.play("clip");
+        assert_eq!(play_steps(&mut ping_pong, 6), vec![1, 2, 1, 0, 1, 2]);
+
+        let mut once = Animator::new(vec![clip(PlaybackMode::Once, 3)]);
+        once.play("clip");
+        assert_eq!(play_steps(&mut once, 5), vec![1, 2, 2, 2, 2]);
+        assert!(once.is_finished());
+        assert!(!once.is_playing());
+    }
+
+    #[test]
+    fn clips_played_once_should_report_their_end_a_single_time() {
+        let mut animator = Animator::new(vec![clip(PlaybackMode::Once, 2)]);
+        animator.play("clip");
+
+        let finished: Vec<bool> = (0..4).map(|_| animator.advance(0.25f32)).collect();
+        assert_eq!(finished, vec![false, true, false, false]);
+
+        // Playing it again restarts it
+        animator.play("clip");
+        assert_eq!(animator.get_frame_index(), Some(0));
+        assert!(animator.is_playing());
+    }
+
+    #[test]
+    fn speed_and_pause_should_scale_the_playback() {
+        let mut animator = Animator::new(vec![clip(PlaybackMode::Loop, 4)]);
+        animator.play("clip");
+        animator.speed = 2f32;
+        animator.advance(0.25f32);
+        assert_eq!(animator.get_frame_index(), Some(2));
+
+        animator.pause();
+        animator.advance(1f32);
+        assert_eq!(animator.get_frame_index(), Some(2));
+
+        animator.resume();
+        animator.speed = 0.5f32;
+        animator.advance(0.25f32);
+        assert_eq!(animator.get_frame_index(), Some(2));
+        animator.advance(0.25f32);
+        assert_eq!(animator.get_frame_index(), Some(3));
+
+        assert!(!animator.play("unknown"));
+    }
+
+    #[test]
+    fn grid_atlases_should_name_their_cells_from_the_top_left() {
+        let sheet = Rc::new(Texture {
+            data: vec![0u8; 64 * 32 * 4],
+            width: 64,
+            height: 32,
+            channels: 4,
+        });
+        let atlas =
+            TextureAtlas::from_description("sheet sheet.png\ngrid 4 2", |_| Ok(sheet.clone()))
+                .unwrap();
+
+        assert_eq!(atlas.get_regions_count(), 8);
+        let first = atlas.get_region("0").unwrap();
+        let last = atlas.get_region("7").unwrap();
+        assert_eq!(
+            (first.x, first.y, first.width, first.height),
+            (0, 16, 16, 16)
+        );
+        assert_eq!((last.x, last.y), (48, 0));
+        assert!(TextureAtlas::from_description("grid 4 2", |_| Ok(sheet.clone())).is_err());
+    }
+
+    #[test]
+    fn animator_system_should_switch_the_sprite_region() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        scene.app.register_atlas(ATLAS, solid_atlas());
+        let description = format!("clip blink loop 0.04\natlas {}\nframes red green", ATLAS);
+        spawn_animated(&mut scene, &description, "blink");
+
+        // 1/60s per frame, the region changes every 0.04s
+        assert_eq!(sprite_pixel(&mut scene, 1), RED);
+        assert_eq!(sprite_pixel(&mut scene, 2), GREEN);
+        assert_eq!(sprite_pixel(&mut scene, 2), RED);
+        assert_eq!(scene.app.get_frame_stats().draw_calls, 1);
+    }
+
+    #[test]
+    fn animator_system_should_send_finished_events() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        scene.app.register_atlas(ATLAS, solid_atlas());
+        let description = format!("clip hit once 0.04\natlas {}\nframes red green", ATLAS);
+        let entity = spawn_animated(&mut scene, &description, "hit");
+        {
+            let mut world = scene.world();
+            world.init_resource::<FinishedClips>();
+            // Worst order for a gameplay system: the events are sent after it read them
+            world
+                .resource_mut::<Schedules>()
+                .get_mut(EcsUpdateSchedule)
+                .unwrap()
+                .add_systems(collect_finished.before(animator_system));
+        }
+
+        assert_eq!(sprite_pixel(&mut scene, 4), GREEN);
+        scene.render_frames(1);
+        assert!(scene.world().resource::<FinishedClips>().0.is_empty());
+
+        // Still readable the next frame, then read only once
+        scene.render_frames(1);
+        let expected = vec![AnimationFinished {
+            entity,
+            clip: String::from("hit"),
+        }];
+        assert_eq!(scene.world().resource::<FinishedClips>().0, expected);
+        assert_eq!(sprite_pixel(&mut scene, 3), GREEN);
+        assert_eq!(scene.world().resource::<FinishedClips>().0, expected);
+    }
+}
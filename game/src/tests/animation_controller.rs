@@ -0,0 +1,195 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::animation::{AnimationClip, Animator, PlaybackMode};
+    use crate::engine::ecs::animation_controller::TransitionCondition::{
+        Greater, IsFalse, IsTrue, Less,
+    };
+    use crate::engine::ecs::animation_controller::{
+        AnimationController, AnimationParameter, AnimationTransition,
+    };
+    use crate::engine::ecs::components::{Position, Rotation, Scale, SpriteRenderer2D, Transform};
+    use crate::tests::golden::GoldenScene;
+
+    fn clip(name: &str, folder: &str, mode: PlaybackMode) -> AnimationClip {
+        AnimationClip {
+            name: String::from(name),
+            atlas: None,
+            frames: vec![
+                format!("{}/texture_01.png", folder),
+                format!("{}/texture_02.png", folder),
+            ],
+            frame_duration: 0.25f32,
+            mode,
+        }
+    }
+
+    // idle <-> run on the speed, any state -> hit on a trigger, hit -> idle once played
+    fn character_controller() -> AnimationController {
+        let mut controller = AnimationController::new("idle", "idle");
+        controller
+            .add_state("run", "run")
+            .add_state("hit", "hit")
+            .add_transition(
+                AnimationTransition::new("idle", "run")
+                    .when(Greater(String::from("speed"), 0.1f32)),
+            )
+            .add_transition(
+                AnimationTransition::new("run", "idle").when(Less(String::from("speed"), 0.1f32)),
+            )
+            .add_transition(AnimationTransition::new("hit", "idle").with_exit_time(1f32))
+            .add_transition(
+                AnimationTransition::from_any("hit").when(IsTrue(String::from("hurt"))),
+            );
+        controller
+    }
+
+    #[test]
+    fn entry_state_clip_should_be_played_first() {
+        let mut controller = character_controller();
+
+        assert_eq!(controller.update(0f32).as_deref(), Some("idle"));
+        assert_eq!(controller.update(0f32), None);
+        assert!(controller.is_in_state("idle"));
+    }
+
+    #[test]
+    fn float_conditions_should_switch_states() {
+        let mut controller = character_controller();
+        controller.update(0f32);
+
+        controller.set_float("speed", 2f32);
+        assert_eq!(controller.update(0f32).as_deref(), Some("run"));
+        assert_eq!(controller.update(0f32), None);
+
+        controller.set_float("speed", 0f32);
+        assert_eq!(controller.update(0f32).as_deref(), Some("idle"));
+        assert_eq!(controller.get_state().clip, "idle");
+    }
+
+    #[test]
+    fn transitions_to_unknown_states_should_be_skipped() {
+        let mut controller = AnimationController::new("idle", "idle");
+        controller
+            .add_state("run", "run")
+            .add_transition(
+                AnimationTransition::new("idle", "walk")
+                    .when(Greater(String::from("speed"), 0.1f32)),
+            )
+            .add_transition(
+                AnimationTransition::new("idle", "run")
+                    .when(Greater(String::from("speed"), 0.1f32)),
+            );
+        controller.update(0f32);
+
+        controller.set_float("speed", 2f32);
+        assert_eq!(controller.update(0f32).as_deref(), Some("run"));
+        assert!(controller.is_in_state("run"));
+    }
+
+    #[test]
+    fn triggers_should_be_consumed_by_their_transition() {
+        let mut controller = character_controller();
+        controller.update(0f32);
+
+        controller.set_trigger("hurt");
+        assert_eq!(controller.update(0f32).as_deref(), Some("hit"));
+        assert_eq!(
+            controller.get_parameter("hurt"),
+            Some(AnimationParameter::Trigger(false))
+        );
+
+        // Any state transitions don't re-enter their target state
+        controller.set_trigger("hurt");
+        assert_eq!(controller.update(0.5f32), None);
+        controller.reset_trigger("hurt");
+        assert_eq!(controller.update(0.5f32), None);
+    }
+
+    #[test]
+    fn exit_time_should_delay_the_transition() {
+        let mut controller = character_controller();
+        controller.update(0f32);
+        controller.set_trigger("hurt");
+        controller.update(0f32);
+
+        assert_eq!(controller.update(0.9f32), None);
+        assert_eq!(controller.update(1f32).as_deref(), Some("idle"));
+    }
+
+    #[test]
+    fn bool_conditions_should_be_combined() {
+        let mut controller = AnimationController::new("idle", "idle");
+        controller.add_state("fall", "fall").add_transition(
+            AnimationTransition::new("idle", "fall")
+                .when(IsFalse(String::from("grounded")))
+                .when(Less(String::from("velocity"), 0f32)),
+        );
+        controller.update(0f32);
+
+        // Unset parameters never meet a condition
+        controller.set_float("velocity", -1f32);
+        assert_eq!(controller.update(0f32), None);
+
+        controller.set_bool("grounded", true);
+        assert_eq!(controller.update(0f32), None);
+
+        controller.set_bool("grounded", false);
+        assert_eq!(controller.update(0f32).as_deref(), Some("fall"));
+    }
+
+    #[test]
+    fn controller_system_should_drive_the_animator() {
+        let mut scene = GoldenScene::new(320, 240);
+        let entity = {
+            let mut animator = Animator::new(vec![
+                clip("idle", "Red", PlaybackMode::Loop),
+                clip("run", "Green", PlaybackMode::Loop),
+                clip("hit", "Orange", PlaybackMode::Once),
+            ]);
+            animator.speed = 10f32;
+
+            scene
+                .world()
+                .spawn((
+                    Transform {
+                        position: Position::default(),
+                        rotation: Rotation::default(),
+                        scale: Scale::default(),
+                    },
+                    SpriteRenderer2D::default(),
+                    animator,
+                    character_controller(),
+                ))
+                .id()
+        };
+
+        let texture = |scene: &GoldenScene| {
+            let world = scene.world();
+            world
+                .get::<SpriteRenderer2D>(entity)
+                .unwrap()
+                .texture
+                .clone()
+        };
+
+        scene.render_frames(1);
+        assert_eq!(texture(&scene).as_deref(), Some("Red/texture_01.png"));
+
+        scene
+            .world()
+            .get_mut::<AnimationController>(entity)
+            .unwrap()
+            .set_trigger("hurt");
+        scene.render_frames(1);
+        assert_eq!(texture(&scene).as_deref(), Some("Orange/texture_01.png"));
+
+        // At 10x speed the 0.5s hit clip ends after 3 frames, then the controller goes back to idle
+        scene.render_frames(4);
+        let world = scene.world();
+        assert!(world
+            .get::<AnimationController>(entity)
+            .unwrap()
+            .is_in_state("idle"));
+        assert!(world.get::<Animator>(entity).unwrap().is_playing());
+    }
+}
@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, PostProcessStack, SpriteRenderer2D};
+    use crate::engine::ecs::resources::CameraCullingState;
+    use crate::engine::rendering::components::{FramebufferFormat, PostEffect};
+    use crate::engine::rendering::gfx_recording::{GfxCall, UniformValue};
+    use crate::engine::rendering::renderer::MAIN_CAMERA;
+    use crate::tests::fixtures::{recording_renderer, spawn_sprite_renderer};
+    use crate::tests::golden::GoldenScene;
+    use glm::vec4;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+
+    fn bloom(iterations: u32) -> PostEffect {
+        PostEffect::Bloom {
+            threshold: 1f32,
+            intensity: 1f32,
+            iterations,
+        }
+    }
+
+    // Red sprite 50px wide centered on the screen, its color is 4 times brighter than the texture
+    fn render_bright_sprite(hdr: bool, effects: Vec<PostEffect>) -> image::RgbaImage {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            let mut sprite = SpriteRenderer2D::from(String::from("Red/texture_01.png"), false);
+            sprite.material.as_mut().unwrap().color = vec4(4f32, 4f32, 4f32, 1f32);
+            spawn_sprite_renderer(&mut world, sprite, (0f32, 0f32, 0f32), 0.5f32);
+
+            let camera = world
+                .resource::<CameraCullingState>()
+                .camera_entity
+                .unwrap();
+            world.get_mut::<Camera>(camera).unwrap().hdr = hdr;
+            world
+                .entity_mut(camera)
+                .insert(PostProcessStack { effects });
+        }
+        scene.render_frames(2)
+    }
+
+    fn allocated_framebuffers(calls: &[GfxCall]) -> Vec<(i32, i32, FramebufferFormat)> {
+        calls
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::AllocFramebuffer {
+                    width,
+                    height,
+                    format,
+                    ..
+                } => Some((*width, *height, *format)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hdr_sprites_should_bloom_into_their_surroundings() {
+        // The sprite right edge is at x = 185
+        let tonemapped =
+            render_bright_sprite(true, vec![PostEffect::Tonemapping { exposure: 1f32 }]);
+        let bloomed = render_bright_sprite(
+            true,
+            vec![bloom(4), PostEffect::Tonemapping { exposure: 1f32 }],
+        );
+
+        let glow = bloomed.get_pixel(WIDTH / 2 + 32, HEIGHT / 2 + 5).0;
+        assert_eq!(tonemapped.get_pixel(WIDTH / 2 + 32, HEIGHT / 2 + 5).0[0], 0);
+        assert!(glow[0] > 20 && glow[1] < glow[0], "{:?}", glow);
+
+        // The glow fades with the distance to the sprite
+        let far = bloomed.get_pixel(WIDTH / 2 + 60, HEIGHT / 2 + 5).0;
+        assert!(far[0] < glow[0], "{:?} {:?}", far, glow);
+        assert_eq!(bloomed.get_pixel(5, 5).0[0], 0);
+    }
+
+    #[test]
+    fn ldr_cameras_should_clamp_colors_before_the_bloom() {
+        // Colors are clamped to 1 in 8 bits framebuffers, nothing is above the threshold
+        let image = render_bright_sprite(false, vec![bloom(4)]);
+        assert_eq!(image.get_pixel(WIDTH / 2 + 32, HEIGHT / 2 + 5).0[0], 0);
+        assert_eq!(image.get_pixel(WIDTH / 2 + 7, HEIGHT / 2 + 5).0[0], 255);
+    }
+
+    #[test]
+    fn tonemapping_should_compress_hdr_colors_below_white() {
+        let plain = render_bright_sprite(true, vec![]);
+        let tonemapped =
+            render_bright_sprite(true, vec![PostEffect::Tonemapping { exposure: 1f32 }]);
+        let dimmed = render_bright_sprite(true, vec![PostEffect::Tonemapping { exposure: 0.1f32 }]);
+
+        let center = (WIDTH / 2 + 7, HEIGHT / 2 + 5);
+        assert_eq!(plain.get_pixel(center.0, center.1).0[0], 255);
+        let bright = tonemapped.get_pixel(center.0, center.1).0[0];
+        let dim = dimmed.get_pixel(center.0, center.1).0[0];
+        assert!(bright > 240 && bright < 255, "{}", bright);
+        assert!(dim > 50 && dim < 150, "{}", dim);
+    }
+
+    #[test]
+    fn bloom_should_downsample_then_upsample_through_its_mips() {
+        let (recording, mut renderer) = recording_renderer("Bloom");
+        let mut camera = renderer.get_camera(MAIN_CAMERA).unwrap().clone();
+        camera.hdr = true;
+        renderer.update_camera_settings(MAIN_CAMERA, camera.clone());
+        renderer.update_camera_post_effects(
+            MAIN_CAMERA,
+            vec![bloom(3), PostEffect::Tonemapping { exposure: 1f32 }],
+        );
+
+        recording.take_calls();
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        let effects: Vec<&UniformValue> = calls
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::SetUniform { name, value, .. } if name == "effect" => Some(value),
+                _ => None,
+            })
+            .collect();
+
+        // Camera framebuffer, ping-pong buffers, 3 downsampled & 2 upsampled mips
+        let hdr = FramebufferFormat::Rgba16F;
+        assert_eq!(
+            allocated_framebuffers(&calls),
+            vec![
+                (320, 240, hdr),
+                (320, 240, hdr),
+                (320, 240, hdr),
+                (160, 120, hdr),
+                (80, 60, hdr),
+                (40, 30, hdr),
+                (80, 60, hdr),
+                (160, 120, hdr),
+            ]
+        );
+        // Prefilter, 2 downsamples, 2 upsamples, composite then tonemapping
+        let expected: Vec<UniformValue> = [5, 6, 6, 7, 7, 8, 9]
+            .into_iter()
+            .map(UniformValue::I32)
+            .collect();
+        assert_eq!(effects, expected.iter().collect::<Vec<_>>());
+        assert_eq!(renderer.get_frame_stats().post_effects, 7);
+
+        // Back to 8 bits colors, every buffer is allocated again
+        camera.hdr = false;
+        renderer.update_camera_settings(MAIN_CAMERA, camera);
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        let allocated = allocated_framebuffers(&calls);
+        assert_eq!(allocated.len(), 8);
+        assert!(allocated
+            .iter()
+            .all(|(_, _, format)| *format == FramebufferFormat::Rgba8));
+        assert_eq!(
+            calls
+                .iter()
+                .filter(|call| matches!(call, GfxCall::ReleaseFramebuffer { .. }))
+                .count(),
+            8
+        );
+        assert!(recording.log().violations().is_empty());
+    }
+}
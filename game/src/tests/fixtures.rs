@@ -0,0 +1,74 @@
+use crate::engine::ecs::components::{Position, Rotation, Scale, SpriteRenderer2D, Transform};
+use crate::engine::logging::logs::Logger;
+use crate::engine::platform::headless_platform::HeadlessPlatform;
+use crate::engine::rendering::gfx_device::GfxDevice;
+use crate::engine::rendering::gfx_recording::GfxDeviceRecording;
+use crate::engine::rendering::renderer::Renderer;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::world::World;
+use std::rc::Rc;
+
+// Fixtures shared by the tests: renderers drawing through the recording device and sprites
+// spawned in the scenes of the golden harness (see GoldenScene).
+
+const RECORDING_WIDTH: u32 = 320;
+const RECORDING_HEIGHT: u32 = 240;
+
+// Renderer of a 320x240 headless window, not warmed yet
+pub fn unwarmed_recording_renderer(log_type: &str) -> (GfxDeviceRecording, Renderer) {
+    let recording = GfxDeviceRecording::new();
+    let shader_api = recording.shader_api();
+    let renderer = Renderer::new(
+        Box::new(HeadlessPlatform::new(RECORDING_WIDTH, RECORDING_HEIGHT)),
+        GfxDevice::new(Rc::new(recording.clone()), Rc::new(shader_api)),
+        Rc::new(Logger {
+            log_type: String::from(log_type),
+        }),
+    );
+    (recording, renderer)
+}
+
+pub fn recording_renderer(log_type: &str) -> (GfxDeviceRecording, Renderer) {
+    let (recording, mut renderer) = unwarmed_recording_renderer(log_type);
+    renderer.warm();
+    (recording, renderer)
+}
+
+// Unrotated transform, sprites are scaled uniformly on x & y
+pub fn sprite_transform(position: (f32, f32, f32), scale: f32) -> Transform {
+    Transform {
+        position: Position {
+            x: position.0,
+            y: position.1,
+            z: position.2,
+        },
+        rotation: Rotation::default(),
+        scale: Scale {
+            x: scale,
+            y: scale,
+            z: 1f32,
+        },
+    }
+}
+
+pub fn spawn_sprite(
+    world: &mut World,
+    texture: &str,
+    position: (f32, f32, f32),
+    scale: f32,
+) -> Entity {
+    let sprite = SpriteRenderer2D::from(String::from(texture), false);
+    spawn_sprite_renderer(world, sprite, position, scale)
+}
+
+// Sprites with their own sorting, atlas region or material
+pub fn spawn_sprite_renderer(
+    world: &mut World,
+    sprite: SpriteRenderer2D,
+    position: (f32, f32, f32),
+    scale: f32,
+) -> Entity {
+    world
+        .spawn((sprite_transform(position, scale), sprite))
+        .id()
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Position, Transform};
+    use crate::engine::rendering::components::{
+        MaskInteraction, MeshInfo, RenderRequest, RenderingMode, SortingOrder,
+    };
+    use crate::engine::rendering::gfx_device::{FRAME_BLOCK_BINDING, FRAME_BLOCK_FLOATS};
+    use crate::engine::rendering::gfx_recording::{GfxCall, GfxDeviceRecording};
+    use crate::engine::rendering::renderer::{Renderer, MAIN_CAMERA};
+    use crate::engine::rendering::shaders::Material;
+    use crate::tests::fixtures::recording_renderer;
+    use crate::tests::golden::GoldenScene;
+
+    fn sprites_renderer(count: usize, mode: RenderingMode) -> (GfxDeviceRecording, Renderer) {
+        let (recording, mut renderer) = recording_renderer("Frame Uniforms");
+        renderer.set_rendering_mode(mode);
+
+        for _ in 0..count {
+            let handle = renderer.create_render_command(RenderRequest {
+                mesh_info: MeshInfo {
+                    file_path: None,
+                    count: 0,
+                    vertices_set: None,
+                },
+                material: Material::new(),
+                transform: Transform::default(),
+                sorting: SortingOrder::default(),
+                mask_interaction: MaskInteraction::None,
+            });
+            renderer.enqueue_cmd_for_current_frame(handle);
+        }
+        (recording, renderer)
+    }
+
+    fn move_camera_and_render(
+        recording: &GfxDeviceRecording,
+        renderer: &mut Renderer,
+    ) -> Vec<GfxCall> {
+        recording.take_calls();
+        renderer.update_camera_transform(
+            MAIN_CAMERA,
+            Transform {
+                position: Position {
+                    x: 1f32,
+                    y: 2f32,
+                    z: 0f32,
+                },
+                ..Transform::default()
+            },
+        );
+        renderer.render(1f32 / 60f32);
+        recording.take_calls()
+    }
+
+    #[test]
+    fn moving_the_camera_should_cost_one_uniform_buffer_update() {
+        for mode in [RenderingMode::Direct, RenderingMode::Batched] {
+            let (recording, mut renderer) = sprites_renderer(100, mode);
+            let calls = move_camera_and_render(&recording, &mut renderer);
+
+            let block_updates: Vec<&GfxCall> = calls
+                .iter()
+                .filter(|call| matches!(call, GfxCall::UpdateUniformBuffer { .. }))
+                .collect();
+            assert_eq!(block_updates.len(), 1, "{:?}", mode);
+            assert!(matches!(
+                block_updates[0],
+                GfxCall::UpdateUniformBuffer {
+                    floats_count: FRAME_BLOCK_FLOATS,
+                    ..
+                }
+            ));
+            assert!(calls.iter().any(|call| matches!(
+                call,
+                GfxCall::BindUniformBuffer {
+                    binding: FRAME_BLOCK_BINDING,
+                    ..
+                }
+            )));
+
+            // Neither the sprites nor the debug grid programs get camera uniforms anymore
+            assert!(!calls.iter().any(|call| matches!(
+                call,
+                GfxCall::SetUniform { name, .. } if name == "VIEW" || name == "PROJ"
+            )));
+            assert!(recording.log().violations().is_empty());
+        }
+    }
+
+    #[test]
+    fn frame_time_should_add_up_the_app_frame_deltas() {
+        // The fixed update step (0.02s) doesn't divide the frame delta, the time must not
+        // follow the fixed step remainder
+        let mut scene = GoldenScene::new(64, 64);
+        scene.app.run_frames(3).unwrap();
+
+        let block = scene
+            .software
+            .read_uniform_block(FRAME_BLOCK_BINDING)
+            .unwrap();
+        assert_eq!(block.len(), FRAME_BLOCK_FLOATS);
+        let time = block[34];
+        assert!((time - 3f32 / 60f32).abs() < 1e-5, "{}", time);
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::rendering::components::BufferSettings;
+    use crate::engine::rendering::debug::Debug;
+    use crate::engine::rendering::gfx_device::{GfxDevice, UniformHandle};
+    use crate::engine::rendering::gfx_recording::{
+        GfxCall, GfxDeviceRecording, GfxHandleKind, UniformValue,
+    };
+    use crate::engine::rendering::renderer_storage::RendererStorage;
+    use crate::engine::rendering::shaders::{Material, ShaderType, Texture};
+    use crate::engine::utils::maths::identity_mat4;
+    use glm::{Matrix4, Vector4};
+    use std::rc::Rc;
+
+    fn recording_device() -> (GfxDeviceRecording, GfxDevice) {
+        let recording = GfxDeviceRecording::new();
+        let device = GfxDevice::new(Rc::new(recording.clone()), Rc::new(recording.shader_api()));
+        (recording, device)
+    }
+
+    #[test]
+    fn debug_grid_should_draw_one_procedural_command_per_line() {
+        let (recording, mut device) = recording_device();
+        let grid = Debug::build_grid(&mut device, &RendererStorage::new());
+
+        assert_eq!(
+            recording.log().live_count(GfxHandleKind::Program),
+            grid.lines.len()
+        );
+        // Shaders are released once linked to the program
+        assert_eq!(recording.log().live_count(GfxHandleKind::Shader), 0);
+        recording.take_calls();
+
+        grid.draw(&device);
+
+        let log = recording.log();
+        let draws = log.draw_calls();
+        assert_eq!(draws.len(), grid.lines.len());
+        assert!(draws.iter().all(|call| matches!(
+            call,
+            GfxCall::DrawCommand {
+                procedural: Some(6),
+                ..
+            }
+        )));
+        // Camera matrices come from the frame uniform block, lines upload nothing per frame
+        assert!(!log
+            .calls()
+            .iter()
+            .any(|call| matches!(call, GfxCall::SetUniform { .. })));
+        assert!(log.violations().is_empty());
+    }
+
+    #[test]
+    fn shader_api_should_keep_the_last_uniform_value_per_program() {
+        let (recording, device) = recording_device();
+        let vert = device
+            .alloc_shader(String::new(), ShaderType::Vertex)
+            .unwrap();
+        let frag = device
+            .alloc_shader(String::new(), ShaderType::Fragment)
+            .unwrap();
+        let module = device
+            .alloc_shader_module(vert, frag, &Material::new())
+            .unwrap();
+
+        device
+            .shader_api
+            .set_attribute_f32(module.self_handle, "thickness", 1f32);
+        device
+            .shader_api
+            .set_attribute_f32(module.self_handle, "thickness", 2f32);
+
+        let log = recording.log();
+        assert_eq!(
+            log.uniform(module.self_handle, "thickness"),
+            Some(&UniformValue::F32(2f32))
+        );
+        assert!(log.violations().is_empty());
+    }
+
+    #[test]
+    fn uniform_handles_should_be_resolved_once_and_reused() {
+        let (recording, device) = recording_device();
+        let vert = device
+            .alloc_shader(String::new(), ShaderType::Vertex)
+            .unwrap();
+        let frag = device
+            .alloc_shader(String::new(), ShaderType::Fragment)
+            .unwrap();
+        let module = device
+            .alloc_shader_module(vert, frag, &Material::new())
+            .unwrap();
+
+        let trs: UniformHandle<Matrix4<f32>> = device
+            .get_uniform_handle(module.self_handle, "TRS")
+            .unwrap();
+        let color: UniformHandle<Vector4<f32>> = device
+            .get_uniform_handle(module.self_handle, "surface_color")
+            .unwrap();
+        assert_ne!(trs.location, color.location);
+        let resolved_again: UniformHandle<Matrix4<f32>> = device
+            .get_uniform_handle(module.self_handle, "TRS")
+            .unwrap();
+        assert_eq!(resolved_again.location, trs.location);
+
+        let matrix = identity_mat4() * 2f32;
+        device.shader_api.set_uniform_mat4(&trs, &matrix);
+        device
+            .shader_api
+            .set_uniform_color(&color, Vector4::new(1f32, 0f32, 0f32, 1f32));
+
+        let log = recording.log();
+        assert_eq!(
+            log.uniform(module.self_handle, "TRS"),
+            Some(&UniformValue::Mat4(matrix))
+        );
+        assert_eq!(
+            log.uniform(module.self_handle, "surface_color"),
+            Some(&UniformValue::Vec4(Vector4::new(1f32, 0f32, 0f32, 1f32)))
+        );
+        assert!(log.violations().is_empty());
+        drop(log);
+
+        // The locations table is dropped with the program
+        device.delete_shader_module(module);
+        device.shader_api.set_uniform_mat4(&trs, &matrix);
+        assert_eq!(recording.log().violations().len(), 1);
+    }
+
+    #[test]
+    fn drawing_with_a_released_texture_should_be_reported() {
+        let (recording, mut device) = recording_device();
+        let vert = device
+            .alloc_shader(String::new(), ShaderType::Vertex)
+            .unwrap();
+        let frag = device
+            .alloc_shader(String::new(), ShaderType::Fragment)
+            .unwrap();
+        let mut module = device
+            .alloc_shader_module(vert, frag, &Material::new())
+            .unwrap();
+        let texture = device.alloc_texture(
+            module.self_handle,
+            &Texture {
+                data: vec![255u8; 4],
+                width: 1,
+                height: 1,
+                channels: 4,
+            },
+        );
+        module.texture_handles.push(texture);
+
+        let buffer = device.alloc_buffer(
+            vec![RendererStorage::load_2d_quad()],
+            vec![],
+            BufferSettings::quad_default(),
+        );
+        let command = device.build_command(module, buffer);
+
+        device.draw_command(&command, None);
+        assert!(recording.log().violations().is_empty());
+
+        device.release_texture(texture);
+        device.draw_command(&command, None);
+        device.release_texture(texture);
+
+        assert_eq!(recording.log().violations().len(), 2);
+        assert_eq!(recording.log().draw_calls().len(), 2);
+    }
+}
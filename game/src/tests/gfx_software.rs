@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Position, Scale, Transform};
+    use crate::engine::rendering::components::{
+        ARGB8Color, BufferSettings, FramebufferFormat, MeshInfo,
+    };
+    use crate::engine::rendering::gfx_device::{GfxDevice, RenderCommand};
+    use crate::engine::rendering::gfx_software::GfxDeviceSoftware;
+    use crate::engine::rendering::renderer_storage::RendererStorage;
+    use crate::engine::rendering::shaders::{Material, ShaderType, Texture};
+    use crate::engine::utils::maths::compute_trs;
+    use glm::Vector4;
+    use std::rc::Rc;
+
+    const BLUE: ARGB8Color = ARGB8Color {
+        r: 0,
+        g: 0,
+        b: 255,
+        a: 255,
+    };
+
+    fn software_device(size: u32) -> (GfxDeviceSoftware, GfxDevice) {
+        let software = GfxDeviceSoftware::new(size, size);
+        let device = GfxDevice::new(Rc::new(software.clone()), Rc::new(software.shader_api()));
+        (software, device)
+    }
+
+    fn texel_command(
+        device: &mut GfxDevice,
+        texel: [u8; 4],
+        transform: &Transform,
+    ) -> RenderCommand {
+        let vert = device
+            .alloc_shader(String::new(), ShaderType::Vertex)
+            .unwrap();
+        let frag = device
+            .alloc_shader(String::new(), ShaderType::Fragment)
+            .unwrap();
+        let mut module = device
+            .alloc_shader_module(vert, frag, &Material::new())
+            .unwrap();
+        let texture = device.alloc_texture(
+            module.self_handle,
+            &Texture {
+                data: texel.to_vec(),
+                width: 1,
+                height: 1,
+                channels: 4,
+            },
+        );
+        module.texture_handles.push(texture);
+        device
+            .shader_api
+            .set_attribute_mat4(module.self_handle, "TRS", &compute_trs(transform));
+
+        let buffer = device.alloc_buffer(
+            RendererStorage::load(&MeshInfo {
+                file_path: None,
+                count: 0,
+                vertices_set: None,
+            }),
+            vec![RendererStorage::get_quad_indices()],
+            BufferSettings::quad_default(),
+        );
+        device.build_command(module, buffer)
+    }
+
+    fn unit_transform() -> Transform {
+        Transform {
+            scale: Scale::one(),
+            ..Transform::default()
+        }
+    }
+
+    #[test]
+    fn textured_quad_should_cover_its_projected_area() {
+        let (software, mut device) = software_device(64);
+        let command = texel_command(&mut device, [255, 0, 0, 255], &unit_transform());
+
+        device.use_framebuffer(None);
+        device.clear(BLUE);
+        device.draw_command(&command, None);
+
+        let screen = software.read_screen();
+        assert_eq!(screen.get(32, 32), [1f32, 0f32, 0f32, 1f32]);
+        assert_eq!(screen.get(16, 16), [1f32, 0f32, 0f32, 1f32]);
+        assert_eq!(screen.get(15, 32), [0f32, 0f32, 1f32, 1f32]);
+        assert_eq!(screen.get(48, 32), [0f32, 0f32, 1f32, 1f32]);
+    }
+
+    #[test]
+    fn surface_color_should_tint_the_texture() {
+        let (software, mut device) = software_device(16);
+        let command = texel_command(&mut device, [255, 255, 255, 255], &unit_transform());
+        device.shader_api.set_attribute_color(
+            command.shader_module.self_handle,
+            "surface_color",
+            Vector4::new(0f32, 1f32, 0f32, 1f32),
+        );
+
+        device.use_framebuffer(None);
+        device.draw_command(&command, None);
+
+        assert_eq!(software.read_screen().get(8, 8), [0f32, 1f32, 0f32, 1f32]);
+    }
+
+    #[test]
+    fn blending_should_mix_transparent_sprites_with_the_background() {
+        let (software, mut device) = software_device(16);
+        let command = texel_command(&mut device, [255, 0, 0, 51], &unit_transform());
+
+        device.use_framebuffer(None);
+        device.clear(BLUE);
+        device.enable_blending();
+        device.draw_command(&command, None);
+
+        let pixel = software.read_screen().get(8, 8);
+        assert!((pixel[0] - 0.2f32).abs() < 1e-4);
+        assert!((pixel[2] - 0.8f32).abs() < 1e-4);
+    }
+
+    #[test]
+    fn framebuffer_blit_should_present_the_scene_with_rows_from_top_to_bottom() {
+        let (software, mut device) = software_device(32);
+        // Quad moved to the upper half of the screen
+        let transform = Transform {
+            position: Position {
+                x: 0f32,
+                y: 0.5f32,
+                z: 0f32,
+            },
+            scale: Scale {
+                x: 2f32,
+                y: 1f32,
+                z: 1f32,
+            },
+            ..Transform::default()
+        };
+        let command = texel_command(&mut device, [255, 255, 0, 255], &transform);
+        let framebuffer = device.alloc_framebuffer(32, 32, FramebufferFormat::Rgba8);
+        let screen_quad = device.alloc_buffer(
+            vec![RendererStorage::load_2d_quad()],
+            vec![],
+            BufferSettings {
+                keep_vertices: false,
+                vertex_size: 2,
+                uvs_size: 2,
+                colors_size: 0,
+                instanced: false,
+            },
+        );
+
+        device.use_framebuffer(Option::from(&framebuffer));
+        device.clear(ARGB8Color::black());
+        device.draw_command(&command, None);
+        device.use_framebuffer(None);
+        device.blit_main_framebuffer(&screen_quad, &framebuffer);
+
+        let image = software.screenshot();
+        assert_eq!(image.get_pixel(16, 4).0, [255, 255, 0, 255]);
+        assert_eq!(image.get_pixel(16, 28).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::engine::lib::runtime::App;
+use crate::engine::logging::logs::Logger;
+use crate::engine::platform::headless_platform::HeadlessPlatform;
+use crate::engine::rendering::gfx_device::GfxDevice;
+use crate::engine::rendering::gfx_software::GfxDeviceSoftware;
+use crate::engine::rendering::renderer::Renderer;
+use crate::engine::utils::clock::FixedClock;
+use bevy_ecs::world::World;
+use image::{Rgba, RgbaImage};
+use std::cell::RefMut;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+// Golden image harness: scenes run in a headless app rendered by the software device, the last
+// presented frame is compared with a reference PNG stored in test_data/golden.
+// Set GOLDEN_BLESS=1 to (re)generate the references.
+
+const GOLDEN_PATH: &str = "test_data/golden/";
+const GOLDEN_OUTPUT_PATH: &str = "target/golden/";
+
+pub struct GoldenScene {
+    pub app: App,
+    pub software: GfxDeviceSoftware,
+}
+
+impl GoldenScene {
+    pub fn new(width: u32, height: u32) -> Self {
+        let software = GfxDeviceSoftware::new(width, height);
+        let shader_api = software.shader_api();
+        let renderer = Renderer::new(
+            Box::new(HeadlessPlatform::new(width, height)),
+            GfxDevice::new(Rc::new(software.clone()), Rc::new(shader_api)),
+            Rc::new(Logger {
+                log_type: String::from("Golden"),
+            }),
+        );
+
+        let mut app = App::new("Golden");
+        app.set_clock(Box::new(FixedClock::new(1f32 / 60f32)));
+        app.warm_with(renderer);
+
+        Self { app, software }
+    }
+
+    pub fn world(&self) -> RefMut<'_, World> {
+        self.app.world.as_ref().unwrap().borrow_mut()
+    }
+
+    pub fn render_frames(&mut self, frames: u64) -> RgbaImage {
+        self.app
+            .run_frames(frames)
+            .expect("[Golden] Failed to run the scene");
+        self.software.screenshot()
+    }
+}
+
+// Compare an image with its reference, channels can differ by at most `tolerance`.
+// On failure the actual and diff images are written to target/golden.
+pub fn assert_golden(name: &str, image: &RgbaImage, tolerance: u8) {
+    let reference_path = PathBuf::from(GOLDEN_PATH).join(format!("{}.png", name));
+
+    if std::env::var("GOLDEN_BLESS").is_ok_and(|value| value == "1") {
+        image
+            .save(&reference_path)
+            .expect("[Golden] Failed to write the reference image");
+        println!("[Golden] Reference {} blessed", reference_path.display());
+        return;
+    }
+
+    let reference = match image::open(&reference_path) {
+        Ok(reference) => reference.to_rgba8(),
+        Err(err) => {
+            write_output(name, "actual", image);
+            panic!(
+                "[Golden] Missing reference {} ({}), run with GOLDEN_BLESS=1 to create it",
+                reference_path.display(),
+                err
+            );
+        }
+    };
+
+    if reference.dimensions() != image.dimensions() {
+        write_output(name, "actual", image);
+        panic!(
+            "[Golden] {}: size mismatch, expected {:?} got {:?}",
+            name,
+            reference.dimensions(),
+            image.dimensions()
+        );
+    }
+
+    let (diff, mismatches) = diff_images(&reference, image, tolerance);
+    if mismatches > 0 {
+        write_output(name, "actual", image);
+        write_output(name, "diff", &diff);
+        panic!(
+            "[Golden] {}: {} pixels differ by more than {} (see {})",
+            name, mismatches, tolerance, GOLDEN_OUTPUT_PATH
+        );
+    }
+}
+
+// Mismatching pixels are red on a darkened copy of the reference
+pub fn diff_images(reference: &RgbaImage, image: &RgbaImage, tolerance: u8) -> (RgbaImage, u32) {
+    let mut mismatches = 0u32;
+
+    let diff = RgbaImage::from_fn(reference.width(), reference.height(), |x, y| {
+        let expected = reference.get_pixel(x, y).0;
+        let actual = image.get_pixel(x, y).0;
+        let differs = (0..4).any(|i| expected[i].abs_diff(actual[i]) > tolerance);
+
+        if differs {
+            mismatches += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            Rgba([expected[0] / 4, expected[1] / 4, expected[2] / 4, 255])
+        }
+    });
+
+    (diff, mismatches)
+}
+
+fn write_output(name: &str, suffix: &str, image: &RgbaImage) {
+    let output = PathBuf::from(GOLDEN_OUTPUT_PATH);
+    std::fs::create_dir_all(&output).expect("[Golden] Failed to create the output directory");
+    let _ = image.save(output.join(format!("{}.{}.png", name, suffix)));
+}
@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, SpriteRenderer2D, Transform};
+    use crate::engine::ecs::resources::CameraCullingState;
+    use crate::engine::rendering::components::ARGB8Color;
+    use crate::tests::fixtures::spawn_sprite;
+    use crate::tests::golden::{assert_golden, GoldenScene};
+    use bevy_ecs::entity::Entity;
+    use bevy_ecs::world::World;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const TOLERANCE: u8 = 2;
+
+    fn spawn_rotated_sprite(
+        world: &mut World,
+        texture: &str,
+        position: (f32, f32),
+        scale: f32,
+        angle: f32,
+    ) -> Entity {
+        let sprite = spawn_sprite(world, texture, (position.0, position.1, 0f32), scale);
+        world.get_mut::<Transform>(sprite).unwrap().rotation.z = angle;
+        sprite
+    }
+
+    fn camera_entity(world: &World) -> Entity {
+        world
+            .resource::<CameraCullingState>()
+            .camera_entity
+            .unwrap()
+    }
+
+    fn set_background(world: &mut World, color: ARGB8Color) {
+        let camera = camera_entity(world);
+        world.get_mut::<Camera>(camera).unwrap().background_color = Option::from(color);
+    }
+
+    fn move_camera(world: &mut World, x: f32, y: f32) {
+        let camera = camera_entity(world);
+        let mut transform = world.get_mut::<Transform>(camera).unwrap();
+        transform.position.x = x;
+        transform.position.y = y;
+    }
+
+    #[test]
+    fn golden_single_sprite_with_grid() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            set_background(
+                &mut world,
+                ARGB8Color {
+                    r: 100,
+                    g: 149,
+                    b: 150,
+                    a: 255,
+                },
+            );
+            spawn_sprite(&mut world, "Red/texture_08.png", (0f32, 0f32, 0f32), 1f32);
+        }
+
+        let image = scene.render_frames(2);
+        assert_golden("single_sprite_with_grid", &image, TOLERANCE);
+    }
+
+    #[test]
+    fn golden_transformed_sprites() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            spawn_sprite(
+                &mut world,
+                "Green/texture_02.png",
+                (-1f32, 0.5f32, 0f32),
+                0.75f32,
+            );
+            spawn_rotated_sprite(
+                &mut world,
+                "Orange/texture_05.png",
+                (0.5f32, 0f32),
+                1f32,
+                45f32,
+            );
+            spawn_rotated_sprite(
+                &mut world,
+                "Purple/texture_03.png",
+                (1.25f32, -0.75f32),
+                0.5f32,
+                20f32,
+            );
+            move_camera(&mut world, 0.25f32, 0.125f32);
+        }
+
+        let image = scene.render_frames(2);
+        assert_golden("transformed_sprites", &image, TOLERANCE);
+    }
+
+    #[test]
+    fn golden_camera_move_reveals_culled_sprites() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            spawn_sprite(&mut world, "Red/texture_01.png", (0f32, 0f32, 0f32), 1f32);
+            spawn_sprite(&mut world, "Light/texture_04.png", (4f32, 0f32, 0f32), 1f32);
+        }
+
+        scene.render_frames(1);
+        move_camera(&mut scene.world(), 3f32, 0f32);
+
+        let image = scene.render_frames(2);
+        assert_golden("camera_move_reveals_culled_sprites", &image, TOLERANCE);
+    }
+
+    #[test]
+    fn golden_texture_swap() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        let sprite = spawn_sprite(
+            &mut scene.world(),
+            "Red/texture_08.png",
+            (0f32, 0f32, 0f32),
+            2f32,
+        );
+
+        scene.render_frames(2);
+        scene
+            .world()
+            .get_mut::<SpriteRenderer2D>(sprite)
+            .unwrap()
+            .texture = Option::from(String::from("Dark/texture_01.png"));
+
+        let image = scene.render_frames(2);
+        assert_golden("texture_swap", &image, TOLERANCE);
+    }
+}
@@ -0,0 +1,190 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::Inputs;
+    use crate::engine::ecs::components::{Position, Transform};
+    use crate::engine::ecs::config::{
+        EcsFixedUpdateSchedule, EcsLateUpdateSchedule, EcsUpdateSchedule,
+    };
+    use crate::engine::ecs::resources::Time;
+    use crate::engine::lib::runtime::App;
+    use crate::engine::platform::headless_platform::HeadlessPlatform;
+    use crate::engine::platform::platform_traits::PlatformEvent;
+    use crate::engine::utils::clock::{Clock, FixedClock};
+    use bevy_ecs::prelude::*;
+    use glfw::{Action, Key, Modifiers};
+
+    #[derive(Resource, Default)]
+    struct PressedFrames(u32);
+
+    fn count_pressed_w(inputs: Res<Inputs>, mut pressed: ResMut<PressedFrames>) {
+        if inputs.keyboard.lock().unwrap().is_key_pressed(Key::W) {
+            pressed.0 += 1;
+        }
+    }
+
+    #[derive(Resource, Default)]
+    struct ScheduleRuns {
+        update: u32,
+        fixed: u32,
+        late: u32,
+    }
+
+    #[derive(Component)]
+    struct Velocity(f32);
+
+    fn move_system(time: Res<Time>, mut query: Query<(&mut Transform, &Velocity)>) {
+        for (mut transform, velocity) in query.iter_mut() {
+            transform.position.x += velocity.0 * time.delta_time;
+        }
+    }
+
+    fn count_update(mut runs: ResMut<ScheduleRuns>) {
+        runs.update += 1;
+    }
+
+    fn count_fixed(mut runs: ResMut<ScheduleRuns>) {
+        runs.fixed += 1;
+    }
+
+    fn count_late(mut runs: ResMut<ScheduleRuns>) {
+        runs.late += 1;
+    }
+
+    // Behaves like the system clock: the time spent before the first tick is counted unless reset
+    struct StalledClock {
+        pending: f32,
+        delta: f32,
+    }
+
+    impl Clock for StalledClock {
+        fn tick(&mut self) -> f32 {
+            std::mem::replace(&mut self.pending, self.delta)
+        }
+
+        fn reset(&mut self) {
+            self.pending = self.delta;
+        }
+    }
+
+    fn headless_app(platform: HeadlessPlatform) -> App {
+        let mut app = App::new("Headless");
+        app.warm_headless(platform);
+
+        let mut world = app.world.as_ref().unwrap().borrow_mut();
+        world.init_resource::<PressedFrames>();
+        world
+            .resource_mut::<Schedules>()
+            .get_mut(EcsUpdateSchedule)
+            .unwrap()
+            .add_systems(count_pressed_w);
+        drop(world);
+
+        app
+    }
+
+    #[test]
+    fn headless_app_should_run_until_the_platform_closes() {
+        let platform = HeadlessPlatform::new(320, 240).close_after(3).with_events(vec![(
+            1,
+            PlatformEvent::Key(Key::W, Action::Press, Modifiers::empty()),
+        )]);
+        let mut app = headless_app(platform);
+
+        assert!(app.run().is_ok());
+
+        let world = app.world.as_ref().unwrap().borrow();
+        assert_eq!(world.resource::<Time>().frames, 3);
+        assert_eq!(world.resource::<PressedFrames>().0, 2);
+    }
+
+    #[test]
+    fn scripted_escape_key_should_close_the_headless_app() {
+        let platform = HeadlessPlatform::new(320, 240).close_after(10).with_events(vec![(
+            1,
+            PlatformEvent::Key(Key::Escape, Action::Press, Modifiers::empty()),
+        )]);
+        let mut app = headless_app(platform);
+
+        assert!(app.run().is_ok());
+
+        let world = app.world.as_ref().unwrap().borrow();
+        assert_eq!(world.resource::<Time>().frames, 2);
+    }
+
+    #[test]
+    fn fixed_clock_should_advance_schedules_by_exact_deltas() {
+        let mut app = headless_app(HeadlessPlatform::new(320, 240));
+        app.set_clock(Box::new(FixedClock::new(1f32 / 64f32)));
+
+        let entity = {
+            let mut world = app.world.as_ref().unwrap().borrow_mut();
+            world.resource_mut::<Time>().fixed_delta_time = 1f32 / 32f32;
+            world.init_resource::<ScheduleRuns>();
+
+            let mut schedules = world.resource_mut::<Schedules>();
+            schedules
+                .get_mut(EcsUpdateSchedule)
+                .unwrap()
+                .add_systems((count_update, move_system));
+            schedules
+                .get_mut(EcsFixedUpdateSchedule)
+                .unwrap()
+                .add_systems(count_fixed);
+            schedules
+                .get_mut(EcsLateUpdateSchedule)
+                .unwrap()
+                .add_systems(count_late);
+
+            world
+                .spawn((
+                    Transform {
+                        position: Position::default(),
+                        ..Transform::default()
+                    },
+                    Velocity(8f32),
+                ))
+                .id()
+        };
+
+        assert!(app.run_frames(10).is_ok());
+
+        let world = app.world.as_ref().unwrap().borrow();
+        let time = world.resource::<Time>();
+        let runs = world.resource::<ScheduleRuns>();
+        assert_eq!(time.frames, 10);
+        assert_eq!(time.delta_time, 1f32 / 64f32);
+        assert_eq!(time.time, 10f64 / 64f64);
+        assert_eq!(runs.update, 10);
+        assert_eq!(runs.fixed, 5);
+        assert_eq!(runs.late, 10);
+        assert_eq!(world.get::<Transform>(entity).unwrap().position.x, 1.25f32);
+    }
+
+    #[test]
+    fn run_frames_should_be_resumable() {
+        let mut app = headless_app(HeadlessPlatform::new(320, 240));
+        app.set_clock(Box::new(FixedClock::new(0.5f32)));
+
+        assert!(app.run_frames(2).is_ok());
+        assert!(app.run_frames(3).is_ok());
+
+        let world = app.world.as_ref().unwrap().borrow();
+        assert_eq!(world.resource::<Time>().frames, 5);
+        assert_eq!(world.resource::<Time>().time, 2.5f64);
+    }
+
+    #[test]
+    fn run_frames_should_not_count_the_time_spent_before() {
+        let mut app = headless_app(HeadlessPlatform::new(320, 240));
+        app.set_clock(Box::new(StalledClock {
+            pending: 5f32,
+            delta: 0.25f32,
+        }));
+
+        assert!(app.run_frames(2).is_ok());
+
+        let world = app.world.as_ref().unwrap().borrow();
+        assert_eq!(world.resource::<Time>().delta_time, 0.25f32);
+        assert_eq!(world.resource::<Time>().time, 0.5f64);
+    }
+}
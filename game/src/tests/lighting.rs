@@ -0,0 +1,187 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, Light2D, SpriteRenderer2D, Transform};
+    use crate::engine::ecs::resources::CameraCullingState;
+    use crate::engine::rendering::components::{
+        ARGB8Color, AmbientLight, LightKind, RenderingLight,
+    };
+    use crate::engine::rendering::lighting::{
+        get_light_block_data, LIGHT_BLOCK_FLOATS, LIGHT_FLOATS, LIGHT_HEADER_FLOATS, LIGHT_SPOT,
+        MAX_LIGHTS,
+    };
+    use crate::engine::utils::maths::identity_mat4;
+    use crate::tests::fixtures::sprite_transform;
+    use crate::tests::golden::GoldenScene;
+    use glm::Vector2;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const GRAY: ARGB8Color = ARGB8Color {
+        r: 128,
+        g: 128,
+        b: 128,
+        a: 255,
+    };
+    const WHITE: ARGB8Color = ARGB8Color {
+        r: 255,
+        g: 255,
+        b: 255,
+        a: 255,
+    };
+
+    fn transform_at(x: f32, y: f32, rotation: f32) -> Transform {
+        let mut transform = sprite_transform((x, y, 0f32), 0.5f32);
+        transform.rotation.z = rotation;
+        transform
+    }
+
+    // Gray background lit by the main camera ambient light
+    fn lit_scene(ambient_intensity: Option<f32>) -> GoldenScene {
+        let scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            let camera = world
+                .resource::<CameraCullingState>()
+                .camera_entity
+                .unwrap();
+            let mut camera = world.get_mut::<Camera>(camera).unwrap();
+            camera.background_color = Option::from(GRAY);
+            camera.ambient_light = ambient_intensity.map(|intensity| AmbientLight {
+                color: WHITE,
+                intensity,
+            });
+        }
+        scene
+    }
+
+    fn assert_near(pixel: [u8; 4], expected: u8) {
+        assert!(pixel[0].abs_diff(expected) <= 3, "{:?} {}", pixel, expected);
+    }
+
+    #[test]
+    fn dark_ambient_should_dim_the_whole_image() {
+        let mut scene = lit_scene(Option::from(0.25f32));
+        scene.world().spawn((
+            transform_at(0f32, 0f32, 0f32),
+            SpriteRenderer2D::from(String::from("Red/texture_01.png"), false),
+        ));
+
+        let image = scene.render_frames(2);
+        assert_near(image.get_pixel(10, 10).0, 32);
+        let sprite = image.get_pixel(WIDTH / 2 + 7, HEIGHT / 2 + 5).0;
+        assert!(sprite[0] > 50 && sprite[0] < 70, "{:?}", sprite);
+        assert_eq!(scene.app.get_frame_stats().lit_cameras, 1);
+    }
+
+    #[test]
+    fn point_lights_should_only_light_their_radius() {
+        let mut scene = lit_scene(Option::from(0f32));
+        let light = scene
+            .world()
+            .spawn((
+                transform_at(0.8f32, 0f32, 0f32),
+                Light2D::point(0.5f32, 1f32),
+            ))
+            .id();
+
+        // Linear falloff: 90% of the light 5px from its center, half of it at 25px
+        let image = scene.render_frames(2);
+        assert_near(image.get_pixel(240, 125).0, 115);
+        assert_near(image.get_pixel(240, 145).0, 64);
+        assert_eq!(image.get_pixel(300, 125).0[0], 0);
+        assert_eq!(image.get_pixel(80, 125).0[0], 0);
+
+        // Lights follow their entity
+        *scene.world().get_mut::<Transform>(light).unwrap() = transform_at(-0.8f32, 0f32, 0f32);
+        let image = scene.render_frames(1);
+        assert_near(image.get_pixel(80, 125).0, 115);
+        assert_eq!(image.get_pixel(240, 125).0[0], 0);
+
+        scene.world().despawn(light);
+        let image = scene.render_frames(1);
+        assert_eq!(image.get_pixel(80, 125).0[0], 0);
+    }
+
+    #[test]
+    fn spot_lights_should_light_their_cone_only() {
+        let mut scene = lit_scene(Option::from(0f32));
+        // Pointing up, 60 degrees wide
+        scene.world().spawn((
+            transform_at(-0.8f32, 0f32, 90f32),
+            Light2D {
+                kind: LightKind::Spot { angle: 60f32 },
+                ..Light2D::point(1f32, 1f32)
+            },
+        ));
+
+        let image = scene.render_frames(2);
+        let above = image.get_pixel(80, 90).0;
+        assert!(above[0] > 60, "{:?}", above);
+        assert_eq!(image.get_pixel(80, 150).0[0], 0);
+        assert_eq!(image.get_pixel(110, 115).0[0], 0);
+    }
+
+    #[test]
+    fn global_lights_should_only_reach_lit_cameras() {
+        let mut scene = lit_scene(Option::from(0f32));
+        scene.world().spawn((
+            transform_at(0f32, 0f32, 0f32),
+            Light2D {
+                kind: LightKind::Global,
+                ..Light2D::point(0f32, 0.5f32)
+            },
+        ));
+        assert_near(scene.render_frames(2).get_pixel(10, 10).0, 64);
+
+        // Without ambient light the camera is unlit, the lights are ignored
+        let mut scene = lit_scene(None);
+        scene.world().spawn((
+            transform_at(0f32, 0f32, 0f32),
+            Light2D {
+                kind: LightKind::Global,
+                ..Light2D::point(0f32, 0.5f32)
+            },
+        ));
+        assert_eq!(scene.render_frames(2).get_pixel(10, 10).0[0], 128);
+        assert_eq!(scene.app.get_frame_stats().lit_cameras, 0);
+    }
+
+    #[test]
+    fn light_block_should_pack_the_local_lights() {
+        let light = |kind: LightKind| RenderingLight {
+            kind,
+            color: WHITE,
+            intensity: 2f32,
+            radius: 3f32,
+            falloff: 2f32,
+            position: Vector2::new(1f32, -1f32),
+            rotation: 90f32,
+            cast_shadows: false,
+            shadow_softness: 0f32,
+        };
+        let mut lights = vec![
+            light(LightKind::Global),
+            light(LightKind::Spot { angle: 90f32 }),
+        ];
+        lights.extend((0..MAX_LIGHTS).map(|_| light(LightKind::Point)));
+
+        let ambient = AmbientLight {
+            color: WHITE,
+            intensity: 0.5f32,
+        };
+        let data = get_light_block_data(&ambient, &lights, &[], &identity_mat4(), &identity_mat4());
+        assert_eq!(data.len(), LIGHT_BLOCK_FLOATS);
+
+        // Global lights are added to the ambient, extra lights are dropped
+        assert_eq!(&data[16..20], &[2.5f32, 2.5f32, 2.5f32, MAX_LIGHTS as f32]);
+
+        let spot = &data[LIGHT_HEADER_FLOATS..LIGHT_HEADER_FLOATS + LIGHT_FLOATS];
+        assert_eq!(
+            &spot[0..8],
+            &[1f32, -1f32, 3f32, 2f32, 2f32, 2f32, 2f32, LIGHT_SPOT]
+        );
+        assert!(spot[8].abs() < 0.0001f32 && (spot[9] - 1f32).abs() < 0.0001f32);
+        assert!((spot[10] - 45f32.to_radians().cos()).abs() < 0.0001f32);
+        assert!(spot[11] > spot[10]);
+    }
+}
@@ -0,0 +1,353 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{SpriteRenderer2D, Transform};
+    use crate::engine::rendering::atlas::{AtlasPacker, TextureAtlas};
+    use crate::engine::rendering::components::{
+        MaskInteraction, MeshInfo, RenderRequest, RenderUpdate, RenderingMode, SortingOrder,
+    };
+    use crate::engine::rendering::gfx_recording::{GfxCall, GfxDeviceRecording, UniformValue};
+    use crate::engine::rendering::renderer::{RenderCmdHd, Renderer};
+    use crate::engine::rendering::renderer_helpers::prepare_material;
+    use crate::engine::rendering::shaders::{BlendMode, Material, MaterialValue, Texture};
+    use crate::engine::utils::file_system::{FileSystem, FileType};
+    use crate::tests::fixtures::{recording_renderer, spawn_sprite_renderer};
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::entity::Entity;
+    use std::rc::Rc;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const ATLAS: &str = "material_solid.atlas";
+
+    // Material written in assets/materials for one test, removed once dropped
+    struct TempMaterial {
+        name: &'static str,
+    }
+
+    impl TempMaterial {
+        fn write(name: &'static str, content: &str) -> Self {
+            FileSystem::write_file(name, content, FileType::Material);
+            Self { name }
+        }
+    }
+
+    impl Drop for TempMaterial {
+        fn drop(&mut self) {
+            FileSystem::remove_file(self.name, FileType::Material);
+        }
+    }
+
+    fn solid_atlas() -> TextureAtlas {
+        let solid = |texel: [u8; 4]| {
+            Rc::new(Texture {
+                data: texel.repeat(64),
+                width: 8,
+                height: 8,
+                channels: 4,
+            })
+        };
+
+        let mut packer = AtlasPacker::new();
+        packer
+            .add("red", solid([255, 0, 0, 255]))
+            .add("green", solid([0, 255, 0, 255]));
+        packer.pack().unwrap()
+    }
+
+    fn spawn_region(scene: &mut GoldenScene, region: &str, order: i16) -> Entity {
+        let mut sprite =
+            SpriteRenderer2D::from_atlas(String::from(ATLAS), String::from(region), false);
+        sprite.order_in_layer = order;
+        spawn_sprite_renderer(&mut scene.world(), sprite, (0f32, 0f32, 0f32), 0.5f32)
+    }
+
+    // Slightly off the center, the debug grid axes cross at the origin
+    fn sprite_pixel(scene: &mut GoldenScene) -> [u8; 4] {
+        scene
+            .render_frames(1)
+            .get_pixel(WIDTH / 2 + 7, HEIGHT / 2 + 5)
+            .0
+    }
+
+    fn detail_material(detail: &Material) -> Material {
+        Material {
+            main_texture: Option::from(String::from("Red/texture_01.png")),
+            ..detail.clone()
+        }
+    }
+
+    fn create_command(renderer: &mut Renderer, material: Material) -> RenderCmdHd {
+        renderer.create_render_command(RenderRequest {
+            mesh_info: MeshInfo {
+                file_path: None,
+                count: 0,
+                vertices_set: None,
+            },
+            material,
+            transform: Transform::default(),
+            sorting: SortingOrder::default(),
+            mask_interaction: MaskInteraction::None,
+        })
+    }
+
+    fn released_textures(recording: &GfxDeviceRecording) -> Vec<u32> {
+        recording
+            .take_calls()
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::ReleaseTexture { handle } => Some(*handle),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn material_files_should_be_parsed() {
+        let description = "# comment\n\
+                           vertex vertex.shader\n\
+                           fragment detail_fragment.shader\n\
+                           define DETAIL_SCALE 4.0\n\
+                           define SHARP\n\
+                           keywords SCROLL\n\
+                           \n\
+                           texture 2 Dark/texture_03.png\n\
+                           texture 0 Red/texture_01.png\n\
+                           texture 1 Dark/texture_02.png\n\
+                           uniform strength 0.5\n\
+                           uniform scroll 0.1 0\n\
+                           uniform tint 1 0.5 0 1\n\
+                           uniform strength 0.25\n\
+                           color 1 0.5 0.5 1\n\
+                           blend multiply\n\
+                           priority -2\n\
+                           pixels_per_unit 64\n";
+        let material = Material::parse(description).unwrap();
+
+        let shaders = &material.shaders;
+        assert_eq!(shaders.vertex.as_ref().unwrap().file_name, "vertex.shader");
+        assert_eq!(
+            shaders.fragment.as_ref().unwrap().file_name,
+            "detail_fragment.shader"
+        );
+        assert_eq!(shaders.defines, vec!["DETAIL_SCALE 4.0", "SHARP"]);
+        assert_eq!(shaders.keywords, vec!["SCROLL"]);
+        assert_eq!(material.main_texture.as_deref(), Some("Red/texture_01.png"));
+        assert_eq!(
+            material.texture_slots,
+            vec![
+                (1, String::from("Dark/texture_02.png")),
+                (2, String::from("Dark/texture_03.png"))
+            ]
+        );
+        assert_eq!(
+            material.uniforms,
+            vec![
+                (
+                    String::from("scroll"),
+                    MaterialValue::Vec2(glm::vec2(0.1f32, 0f32))
+                ),
+                (
+                    String::from("tint"),
+                    MaterialValue::Vec4(glm::vec4(1f32, 0.5f32, 0f32, 1f32))
+                ),
+                (String::from("strength"), MaterialValue::Float(0.25f32)),
+            ]
+        );
+        assert_eq!(material.color, glm::vec4(1f32, 0.5f32, 0.5f32, 1f32));
+        assert_eq!(material.blend_mode, BlendMode::Multiply);
+        assert_eq!(material.render_priority, -2);
+        assert_eq!(material.pixel_per_unit, 64);
+
+        // Left out entries keep the values of a new material
+        let empty = Material::parse("# nothing\n").unwrap();
+        assert!(empty.shaders.fragment.is_none());
+        assert_eq!(empty.blend_mode, BlendMode::Alpha);
+        assert_eq!(empty.color, glm::vec4(1f32, 1f32, 1f32, 1f32));
+    }
+
+    #[test]
+    fn invalid_material_entries_should_be_reported() {
+        assert_eq!(
+            Material::parse("color 1 1 1 1\nblend screen").unwrap_err(),
+            "[Material] line 2: unknown blend mode screen"
+        );
+        assert_eq!(
+            Material::parse("texture 1 a.png\ntexture 1 b.png").unwrap_err(),
+            "[Material] line 2: texture unit 1 is already used"
+        );
+        assert!(Material::parse("texture 16 a.png").is_err());
+        assert!(Material::parse("uniform strength").is_err());
+        assert!(Material::parse("uniform strength 1 2 3").is_err());
+        assert!(Material::parse("uniform strength high").is_err());
+        assert!(Material::parse("color 1 1 1").is_err());
+        assert!(Material::parse("priority 200").is_err());
+        assert!(Material::parse("pixels_per_unit 0").is_err());
+        assert!(Material::parse("shader sprite.shader").is_err());
+    }
+
+    #[test]
+    fn material_files_should_be_loaded_from_the_assets() {
+        let detail = Material::load_file("detail.mat").unwrap();
+        assert_eq!(detail.texture_slots.len(), 1);
+        assert_eq!(detail.uniforms.len(), 2);
+        let glow = Material::load_file("glow.mat").unwrap();
+        assert_eq!(glow.blend_mode, BlendMode::Additive);
+
+        let error = Material::load_file("missing.mat").unwrap_err();
+        assert!(error.contains("missing.mat"), "{}", error);
+        let broken = TempMaterial::write("material_broken.mat", "blend\n");
+        assert_eq!(
+            Material::load_file(broken.name).unwrap_err(),
+            "[Material] line 1: unknown entry \"blend\" (material_broken.mat)"
+        );
+    }
+
+    #[test]
+    fn sprites_without_texture_should_draw_the_material_texture() {
+        let glow = Material::load_file("glow.mat").unwrap();
+
+        let untextured = SpriteRenderer2D::default();
+        let material = prepare_material(&untextured, Some(&glow));
+        assert_eq!(
+            material.main_texture.as_deref(),
+            Some("Light/texture_01.png")
+        );
+        assert_eq!(material.blend_mode, BlendMode::Additive);
+
+        let textured = SpriteRenderer2D::from(String::from("Red/texture_01.png"), false);
+        let material = prepare_material(&textured, Some(&glow));
+        assert_eq!(material.main_texture.as_deref(), Some("Red/texture_01.png"));
+        assert_eq!(material.render_priority, 1);
+    }
+
+    #[test]
+    fn named_materials_should_blend_sprites_with_the_ones_behind() {
+        let additive = TempMaterial::write("material_additive.mat", "blend additive\n");
+        let multiply = TempMaterial::write("material_multiply.mat", "blend multiply\n");
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        scene.app.register_atlas(ATLAS, solid_atlas());
+        spawn_region(&mut scene, "green", 0);
+        let front = spawn_region(&mut scene, "red", 1);
+        assert_eq!(sprite_pixel(&mut scene), [255, 0, 0, 255]);
+
+        // The material of a live sprite can be swapped
+        for (material, expected) in [
+            (Some(additive.name), [255, 255, 0, 255]),
+            (Some(multiply.name), [0, 0, 0, 255]),
+            (None, [255, 0, 0, 255]),
+        ] {
+            let mut world = scene.world();
+            let mut sprite = world.get_mut::<SpriteRenderer2D>(front).unwrap();
+            sprite.material_name = material.map(String::from);
+            drop(world);
+            assert_eq!(sprite_pixel(&mut scene), expected, "{:?}", material);
+        }
+
+        // Sprites with another blend mode break the batches
+        scene.app.set_rendering_mode(RenderingMode::Batched);
+        let mut world = scene.world();
+        let mut sprite = world.get_mut::<SpriteRenderer2D>(front).unwrap();
+        *sprite = SpriteRenderer2D::from_atlas(String::from(ATLAS), String::from("red"), false)
+            .with_material(additive.name);
+        sprite.order_in_layer = 1;
+        drop(world);
+        assert_eq!(sprite_pixel(&mut scene), [255, 255, 0, 255]);
+        assert_eq!(scene.app.get_frame_stats().batches, 2);
+    }
+
+    #[test]
+    fn material_uniforms_and_texture_slots_should_be_set_before_drawing() {
+        let (recording, mut renderer) = recording_renderer("Materials");
+        renderer.set_rendering_mode(RenderingMode::Batched);
+
+        let detail = renderer.load_material("detail.mat").unwrap();
+        let handle = create_command(&mut renderer, detail_material(&detail));
+        assert!(Rc::ptr_eq(
+            &detail,
+            &renderer.load_material("detail.mat").unwrap()
+        ));
+        let program = renderer.get_command(handle).shader_module.self_handle;
+        recording.take_calls();
+
+        renderer.enqueue_cmd_for_current_frame(handle);
+        renderer.render(1f32 / 60f32);
+
+        // Drawn directly, the main & detail textures are bound to their unit
+        let textures: Vec<Vec<u32>> = recording
+            .take_calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                GfxCall::DrawCommand {
+                    command, textures, ..
+                } if command == handle => Some(textures),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(textures.len(), 1);
+        assert_eq!(textures[0].len(), 2);
+        assert_eq!(renderer.get_frame_stats().batches, 0);
+
+        let log = recording.log();
+        assert_eq!(
+            log.uniform(program, "texture1"),
+            Some(&UniformValue::I32(1))
+        );
+        assert_eq!(
+            log.uniform(program, "detail_strength"),
+            Some(&UniformValue::F32(0.6f32))
+        );
+        assert_eq!(
+            log.uniform(program, "scroll"),
+            Some(&UniformValue::Vec2(glm::vec2(0.1f32, 0f32)))
+        );
+        assert!(log.violations().is_empty());
+    }
+
+    #[test]
+    fn slot_textures_should_be_released_with_their_last_user() {
+        let (recording, mut renderer) = recording_renderer("Materials");
+        let detail = renderer.load_material("detail.mat").unwrap();
+        let first = create_command(&mut renderer, detail_material(&detail));
+        let second = create_command(&mut renderer, detail_material(&detail));
+        let slot_texture = renderer.get_command(first).shader_module.texture_handles[1];
+        assert_eq!(
+            renderer.get_command(second).shader_module.texture_handles[1],
+            slot_texture
+        );
+
+        let render_frame = |renderer: &mut Renderer| {
+            renderer.enqueue_cmd_for_current_frame(first);
+            renderer.enqueue_cmd_for_current_frame(second);
+            renderer.render(1f32 / 60f32);
+        };
+        render_frame(&mut renderer);
+        recording.take_calls();
+
+        // The first command drops its slot, the second one still samples the texture
+        renderer.update_render_command(RenderUpdate {
+            render_cmd: first,
+            mesh_info: None,
+            material: Option::from(Material {
+                texture_slots: vec![],
+                ..detail_material(&detail)
+            }),
+            transform: None,
+            sorting: None,
+            mask_interaction: None,
+        });
+        let textures = renderer
+            .get_command(first)
+            .shader_module
+            .texture_handles
+            .clone();
+        assert_eq!(textures.len(), 1);
+        render_frame(&mut renderer);
+        assert!(!released_textures(&recording).contains(&slot_texture));
+
+        renderer.remove_render_command(second);
+        renderer.enqueue_cmd_for_current_frame(first);
+        renderer.render(1f32 / 60f32);
+        assert_eq!(released_textures(&recording), vec![slot_texture]);
+        assert!(recording.log().violations().is_empty());
+    }
+}
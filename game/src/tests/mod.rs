@@ -1 +1,27 @@
-mod polylines;
\ No newline at end of file
+mod animation;
+mod animation_controller;
+mod bloom;
+#[cfg(test)]
+mod fixtures;
+mod frame_uniforms;
+mod gfx_recording;
+mod gfx_software;
+#[cfg(test)]
+mod golden;
+mod golden_scenes;
+mod headless_app;
+mod lighting;
+mod materials;
+mod multi_camera;
+mod perspective;
+mod polylines;
+mod post_processing;
+mod program_cache;
+mod render_sorting;
+mod render_texture;
+mod shader_hot_reload;
+mod shader_preprocessor;
+mod shadows;
+mod sprite_batching;
+mod sprite_masks;
+mod texture_atlas;
\ No newline at end of file
@@ -0,0 +1,251 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, CameraBinding, Transform};
+    use crate::engine::ecs::resources::CameraCullingState;
+    use crate::engine::rendering::components::{ARGB8Color, RenderTarget, RenderingCamera};
+    use crate::engine::rendering::gfx_recording::GfxCall;
+    use crate::engine::rendering::renderer::MAIN_CAMERA;
+    use crate::tests::fixtures::{recording_renderer, spawn_sprite};
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::entity::Entity;
+    use bevy_ecs::world::World;
+    use glm::Vector4;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const BLUE: ARGB8Color = ARGB8Color {
+        r: 0,
+        g: 0,
+        b: 255,
+        a: 255,
+    };
+    const GRAY: ARGB8Color = ARGB8Color {
+        r: 128,
+        g: 128,
+        b: 128,
+        a: 255,
+    };
+
+    fn spawn_camera(world: &mut World, camera: Camera, x: f32) -> Entity {
+        let mut transform = Camera::default_transform();
+        transform.position.x = x;
+        world.spawn((camera, transform)).id()
+    }
+
+    fn main_camera(world: &World) -> Entity {
+        world
+            .resource::<CameraCullingState>()
+            .camera_entity
+            .unwrap()
+    }
+
+    fn pixel(image: &image::RgbaImage, x: u32, y: u32) -> [u8; 4] {
+        image.get_pixel(x, y).0
+    }
+
+    fn rgba(color: ARGB8Color) -> [u8; 4] {
+        [color.r, color.g, color.b, color.a]
+    }
+
+    #[test]
+    fn split_screen_cameras_should_render_their_own_area() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            spawn_sprite(
+                &mut world,
+                "Red/texture_01.png",
+                (-3f32, 0f32, 0f32),
+                0.5f32,
+            );
+            spawn_sprite(
+                &mut world,
+                "Green/texture_01.png",
+                (3f32, 0f32, 0f32),
+                0.5f32,
+            );
+
+            let main = main_camera(&world);
+            world.get_mut::<Camera>(main).unwrap().viewport = (0f32, 0f32, 0.5f32, 1f32);
+            world.get_mut::<Transform>(main).unwrap().position.x = -3f32;
+
+            let right = Camera {
+                viewport: (0.5f32, 0f32, 0.5f32, 1f32),
+                ..Camera::default()
+            };
+            spawn_camera(&mut world, right, 3f32);
+        }
+
+        // Sprites out of one camera are still drawn by the other one
+        let image = scene.render_frames(2);
+        let left = pixel(&image, WIDTH / 4 + 7, HEIGHT / 2 + 5);
+        let right = pixel(&image, 3 * WIDTH / 4 + 7, HEIGHT / 2 + 5);
+        assert!(left[0] > 200 && left[1] < 100, "{:?}", left);
+        assert!(right[1] > 150 && right[0] < 100, "{:?}", right);
+        assert_eq!(scene.app.get_frame_stats().culled_commands, 0);
+    }
+
+    #[test]
+    fn higher_order_cameras_should_be_composited_on_top() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        let minimap = {
+            let mut world = scene.world();
+            let main = main_camera(&world);
+            world.get_mut::<Camera>(main).unwrap().background_color = Option::from(GRAY);
+
+            // Top right corner, viewports start from the bottom left of the screen
+            let minimap = Camera {
+                viewport: (0.75f32, 0.75f32, 0.25f32, 0.25f32),
+                order: 1,
+                background_color: Option::from(BLUE),
+                ..Camera::default()
+            };
+            spawn_camera(&mut world, minimap, 0f32)
+        };
+
+        let image = scene.render_frames(2);
+        assert_eq!(pixel(&image, WIDTH - 10, 10), rgba(BLUE));
+        assert_eq!(pixel(&image, 10, HEIGHT - 10), rgba(GRAY));
+
+        scene.world().get_mut::<Camera>(minimap).unwrap().order = -1;
+        let image = scene.render_frames(1);
+        assert_eq!(pixel(&image, WIDTH - 10, 10), rgba(GRAY));
+    }
+
+    #[test]
+    fn removed_cameras_should_stop_rendering() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        let (left, right) = {
+            let mut world = scene.world();
+            let main = main_camera(&world);
+            world.get_mut::<Camera>(main).unwrap().background_color = Option::from(GRAY);
+
+            let corner = |x: f32| Camera {
+                viewport: (x, 0.75f32, 0.25f32, 0.25f32),
+                order: 1,
+                background_color: Option::from(BLUE),
+                ..Camera::default()
+            };
+            (
+                spawn_camera(&mut world, corner(0f32), 0f32),
+                spawn_camera(&mut world, corner(0.75f32), 0f32),
+            )
+        };
+        let image = scene.render_frames(2);
+        assert_eq!(pixel(&image, 10, 10), rgba(BLUE));
+        assert_eq!(pixel(&image, WIDTH - 10, 10), rgba(BLUE));
+
+        // Despawning the entity or removing its component both release the camera
+        scene.world().despawn(left);
+        scene.world().entity_mut(right).remove::<Camera>();
+        let image = scene.render_frames(1);
+        assert_eq!(pixel(&image, 10, 10), rgba(GRAY));
+        assert_eq!(pixel(&image, WIDTH - 10, 10), rgba(GRAY));
+
+        let world = scene.world();
+        let main = main_camera(&world);
+        assert_eq!(world.resource::<CameraBinding>().cameras.len(), 1);
+        let frustums = &world.resource::<CameraCullingState>().camera_frustums;
+        assert_eq!(frustums.len(), 1);
+        assert_eq!(frustums[0].0, main);
+    }
+
+    #[test]
+    fn cameras_with_an_output_target_should_not_be_composited() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            let offscreen = Camera {
+                order: 1,
+                output_target: Option::from(RenderTarget {
+                    name: String::from("offscreen"),
+                    resolution: None,
+                }),
+                background_color: Option::from(BLUE),
+                ..Camera::default()
+            };
+            spawn_camera(&mut world, offscreen, 0f32);
+        }
+
+        let image = scene.render_frames(2);
+        assert_eq!(pixel(&image, 10, 10), rgba(ARGB8Color::black()));
+    }
+
+    #[test]
+    fn each_camera_should_own_a_framebuffer_and_a_frame_block_update() {
+        let (recording, mut renderer) = recording_renderer("Multi Camera");
+
+        let minimap = renderer.add_camera(RenderingCamera {
+            viewport: Vector4::new(0.75f32, 0.75f32, 0.25f32, 0.25f32),
+            order: 1,
+            ..RenderingCamera::default()
+        });
+        assert_eq!(renderer.get_cameras_count(), 2);
+
+        let count = |calls: &Vec<GfxCall>, predicate: fn(&GfxCall) -> bool| {
+            calls.iter().filter(|call| predicate(call)).count()
+        };
+
+        recording.take_calls();
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        assert_eq!(
+            count(&calls, |call| matches!(
+                call,
+                GfxCall::AllocFramebuffer { .. }
+            )),
+            2
+        );
+        assert_eq!(
+            count(&calls, |call| matches!(
+                call,
+                GfxCall::UpdateUniformBuffer { .. }
+            )),
+            2
+        );
+        assert_eq!(
+            count(&calls, |call| matches!(
+                call,
+                GfxCall::BlitMainFramebuffer { .. }
+            )),
+            2
+        );
+
+        // Framebuffers are kept while the camera size doesn't change
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        assert_eq!(
+            count(&calls, |call| matches!(
+                call,
+                GfxCall::AllocFramebuffer { .. }
+            )),
+            0
+        );
+
+        // Resizing the viewport replaces the framebuffer, removing the camera releases it
+        let mut camera = renderer.get_camera(minimap).unwrap().clone();
+        camera.viewport = Vector4::new(0.5f32, 0.5f32, 0.5f32, 0.5f32);
+        renderer.update_camera_settings(minimap, camera);
+        renderer.render(1f32 / 60f32);
+        renderer.remove_camera(minimap);
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        assert_eq!(
+            count(&calls, |call| matches!(
+                call,
+                GfxCall::AllocFramebuffer { .. }
+            )),
+            1
+        );
+        assert_eq!(
+            count(&calls, |call| matches!(
+                call,
+                GfxCall::ReleaseFramebuffer { .. }
+            )),
+            2
+        );
+        assert_eq!(renderer.get_cameras_count(), 1);
+        assert!(renderer.get_camera(MAIN_CAMERA).is_some());
+        assert!(recording.log().violations().is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, Projection, SpriteRenderer2D, Transform};
+    use crate::engine::ecs::resources::CameraCullingState;
+    use crate::engine::rendering::components::RenderingCamera;
+    use crate::engine::rendering::gfx_recording::GfxCall;
+    use crate::engine::rendering::renderer::MAIN_CAMERA;
+    use crate::engine::utils::maths::{compute_projection, Frustum, Rect};
+    use crate::tests::fixtures::{recording_renderer, spawn_sprite};
+    use crate::tests::golden::GoldenScene;
+    use glm::{Vector3, Vector4};
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const WINDOW: Rect<u32> = Rect {
+        x: 0,
+        y: 0,
+        width: WIDTH,
+        height: HEIGHT,
+    };
+
+    fn perspective_camera() -> RenderingCamera {
+        RenderingCamera {
+            projection: Projection::Perspective,
+            fov: 90f32,
+            near: 0.5f32,
+            far: 10f32,
+            ..RenderingCamera::default()
+        }
+    }
+
+    // Main camera at z = -1, 90° of vertical fov: 120px per world unit on the z = 0 plane
+    fn perspective_scene() -> GoldenScene {
+        let scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            let camera = world
+                .resource::<CameraCullingState>()
+                .camera_entity
+                .unwrap();
+            let mut camera = world.get_mut::<Camera>(camera).unwrap();
+            camera.mode = Projection::Perspective;
+            camera.fov = 90f32;
+        }
+        scene
+    }
+
+    fn project(camera: &RenderingCamera, point: Vector4<f32>) -> Vector3<f32> {
+        let clip = compute_projection(camera, &WINDOW).mul_v(&point);
+        Vector3::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w)
+    }
+
+    fn is_red(pixel: [u8; 4]) -> bool {
+        pixel[0] > 200 && pixel[1] < 100
+    }
+
+    fn is_green(pixel: [u8; 4]) -> bool {
+        pixel[1] > 150 && pixel[0] < 100
+    }
+
+    #[test]
+    fn perspective_projection_should_map_the_frustum_to_clip_space() {
+        let camera = perspective_camera();
+        let aspect = WIDTH as f32 / HEIGHT as f32;
+
+        let near = project(&camera, Vector4::new(0f32, 0.5f32, 0.5f32, 1f32));
+        let far = project(&camera, Vector4::new(10f32 * aspect, 0f32, 10f32, 1f32));
+        assert!((near.z + 1f32).abs() < 1e-4, "{:?}", near);
+        assert!((near.y - 1f32).abs() < 1e-4, "{:?}", near);
+        assert!((far.z - 1f32).abs() < 1e-4, "{:?}", far);
+        assert!((far.x - 1f32).abs() < 1e-4, "{:?}", far);
+
+        // Twice the distance, half the size
+        let close = project(&camera, Vector4::new(0.5f32, 0f32, 1f32, 1f32));
+        let distant = project(&camera, Vector4::new(0.5f32, 0f32, 2f32, 1f32));
+        assert!((close.x - 2f32 * distant.x).abs() < 1e-4);
+    }
+
+    #[test]
+    fn perspective_frustums_should_widen_with_the_distance() {
+        let mut camera = perspective_camera();
+        camera.transform.position.z = -1f32;
+        let frustum = Frustum::from_camera(&camera, &WINDOW);
+        let sprite = |x: f32| Rect {
+            x,
+            y: 0f32,
+            width: 0.5f32,
+            height: 0.5f32,
+        };
+
+        // Half width of 4/3 at one unit, 4 at three units
+        assert!(!frustum.intersects(sprite(2.5f32), 0f32));
+        assert!(frustum.intersects(sprite(2.5f32), 2f32));
+        // Behind the near plane or past the far one
+        assert!(!frustum.intersects(sprite(0f32), -1.2f32));
+        assert!(!frustum.intersects(sprite(0f32), 10f32));
+
+        let orthographic = Frustum::from_camera(&RenderingCamera::default(), &WINDOW);
+        let area = orthographic.get_rect_at(25f32).unwrap();
+        assert_eq!((area.width, area.height), (3.2f32, 2.4f32));
+    }
+
+    #[test]
+    fn distant_sprites_should_look_smaller() {
+        let mut scene = perspective_scene();
+        {
+            let mut world = scene.world();
+            spawn_sprite(
+                &mut world,
+                "Red/texture_01.png",
+                (-0.6f32, 0f32, 0f32),
+                0.5f32,
+            );
+            spawn_sprite(
+                &mut world,
+                "Green/texture_01.png",
+                (1.2f32, 0f32, 1f32),
+                0.5f32,
+            );
+        }
+
+        // 60px wide red sprite centered at x = 88, 30px wide green one centered at x = 232
+        let image = scene.render_frames(2);
+        let pixel = |x: u32| image.get_pixel(x, HEIGHT / 2 + 5).0;
+        assert!(is_red(pixel(88 + 25)));
+        assert!(is_green(pixel(232 + 10)));
+        assert!(!is_green(pixel(232 + 20)));
+    }
+
+    #[test]
+    fn depth_test_should_hide_sprites_behind_whatever_their_order() {
+        let mut scene = perspective_scene();
+        {
+            let mut world = scene.world();
+            spawn_sprite(&mut world, "Red/texture_01.png", (0f32, 0f32, 0f32), 0.5f32);
+            spawn_sprite(&mut world, "Green/texture_01.png", (0f32, 0f32, 1f32), 2f32);
+            let mut query = world.query::<(&Transform, &mut SpriteRenderer2D)>();
+            for (transform, mut sprite) in query.iter_mut(&mut world) {
+                if transform.position.z > 0f32 {
+                    sprite.order_in_layer = 1;
+                }
+            }
+        }
+
+        // The green sprite is drawn last but it is behind the red one, only its border shows
+        let image = scene.render_frames(2);
+        assert!(is_red(image.get_pixel(WIDTH / 2 + 7, HEIGHT / 2 + 5).0));
+        assert!(is_green(image.get_pixel(WIDTH / 2 + 45, HEIGHT / 2 + 5).0));
+    }
+
+    #[test]
+    fn perspective_cameras_should_cull_against_their_frustum() {
+        let mut scene = perspective_scene();
+        {
+            let mut world = scene.world();
+            spawn_sprite(
+                &mut world,
+                "Red/texture_01.png",
+                (2.5f32, 0f32, 0f32),
+                0.5f32,
+            );
+            spawn_sprite(
+                &mut world,
+                "Red/texture_01.png",
+                (2.5f32, 0f32, 2f32),
+                0.5f32,
+            );
+            spawn_sprite(
+                &mut world,
+                "Red/texture_01.png",
+                (0f32, 0f32, -2f32),
+                0.5f32,
+            );
+        }
+
+        scene.render_frames(2);
+        assert_eq!(scene.app.get_frame_stats().culled_commands, 2);
+    }
+
+    #[test]
+    fn screen_positions_should_be_converted_to_world_positions() {
+        let (recording, mut renderer) = recording_renderer("Perspective");
+
+        // Orthographic main camera, the top right corner is at (1.6, 1.2) on every plane
+        let corner = renderer
+            .screen_to_world(MAIN_CAMERA, (WIDTH as f32, 0f32), 3f32)
+            .unwrap();
+        assert_eq!((corner.x, corner.y, corner.z), (1.6f32, 1.2f32, 3f32));
+
+        let mut camera = perspective_camera();
+        camera.transform.position.z = -1f32;
+        renderer.update_camera_settings(MAIN_CAMERA, camera);
+        let center = renderer
+            .screen_to_world(
+                MAIN_CAMERA,
+                (WIDTH as f32 / 2f32, HEIGHT as f32 / 2f32),
+                0f32,
+            )
+            .unwrap();
+        let corner = renderer
+            .screen_to_world(MAIN_CAMERA, (WIDTH as f32, 0f32), 1f32)
+            .unwrap();
+        assert_eq!((center.x, center.y), (0f32, 0f32));
+        assert!((corner.x - 8f32 / 3f32).abs() < 1e-4 && (corner.y - 2f32).abs() < 1e-4);
+        assert!(renderer
+            .screen_to_world(MAIN_CAMERA, (0f32, 0f32), -1f32)
+            .is_none());
+        assert!(renderer
+            .screen_to_world(MAIN_CAMERA, (-1f32, 0f32), 0f32)
+            .is_none());
+
+        // Only perspective passes test the depth
+        recording.take_calls();
+        renderer.render(1f32 / 60f32);
+        assert!(recording
+            .take_calls()
+            .iter()
+            .any(|call| matches!(call, GfxCall::SetDepthTest { enabled: true })));
+    }
+}
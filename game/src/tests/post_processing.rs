@@ -0,0 +1,322 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, PostProcessStack};
+    use crate::engine::ecs::config::EcsUpdateSchedule;
+    use crate::engine::ecs::resources::CameraCullingState;
+    use crate::engine::rendering::components::{ARGB8Color, PostEffect};
+    use crate::engine::rendering::gfx_recording::{GfxCall, UniformValue};
+    use crate::engine::rendering::renderer::MAIN_CAMERA;
+    use crate::tests::fixtures::{recording_renderer, spawn_sprite};
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::prelude::*;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const NEUTRAL_LUT: &str = "LUT/neutral_16.png";
+    const GRAY: ARGB8Color = ARGB8Color {
+        r: 128,
+        g: 128,
+        b: 128,
+        a: 255,
+    };
+
+    fn main_camera(world: &World) -> Entity {
+        world
+            .resource::<CameraCullingState>()
+            .camera_entity
+            .unwrap()
+    }
+
+    // Red sprite 50px wide centered on the screen, rendered with the main camera effects
+    fn render_with_effects(
+        effects: Vec<PostEffect>,
+        background: Option<ARGB8Color>,
+    ) -> image::RgbaImage {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            spawn_sprite(&mut world, "Red/texture_01.png", (0f32, 0f32, 0f32), 0.5f32);
+            let camera = main_camera(&world);
+            world.get_mut::<Camera>(camera).unwrap().background_color = background;
+            world
+                .entity_mut(camera)
+                .insert(PostProcessStack { effects });
+        }
+        scene.render_frames(2)
+    }
+
+    fn fade_in_vignette(mut query: Query<&mut PostProcessStack>) {
+        for mut stack in query.iter_mut() {
+            if let Some(PostEffect::Vignette { intensity, .. }) = stack.effects.first_mut() {
+                *intensity = (*intensity + 0.2f32).min(1f32);
+            }
+        }
+    }
+
+    #[test]
+    fn vignette_should_darken_the_corners_only() {
+        let vignette = PostEffect::Vignette {
+            intensity: 1f32,
+            smoothness: 0.5f32,
+        };
+        let image = render_with_effects(vec![vignette], Option::from(GRAY));
+        let corner = image.get_pixel(0, 0).0;
+        let border = image.get_pixel(WIDTH / 2 + 40, 5).0;
+        let center = image.get_pixel(WIDTH / 2 + 7, HEIGHT / 2 + 5).0;
+        assert!(corner[0] < 10, "{:?}", corner);
+        assert!(border[0] > 10 && border[0] < 128, "{:?}", border);
+        assert!(center[0] > 200 && center[1] < 100, "{:?}", center);
+    }
+
+    #[test]
+    fn neutral_lut_should_keep_the_colors() {
+        let reference = render_with_effects(vec![], Option::from(GRAY));
+        let graded = render_with_effects(
+            vec![PostEffect::ColorGrading {
+                lut: String::from(NEUTRAL_LUT),
+                intensity: 1f32,
+            }],
+            Option::from(GRAY),
+        );
+
+        for (x, y) in [
+            (10, 10),
+            (WIDTH / 2 + 7, HEIGHT / 2 + 5),
+            (WIDTH / 2 - 20, HEIGHT / 2),
+        ] {
+            let (expected, pixel) = (reference.get_pixel(x, y).0, graded.get_pixel(x, y).0);
+            for channel in 0..3 {
+                assert!(
+                    expected[channel].abs_diff(pixel[channel]) <= 2,
+                    "{:?} {:?}",
+                    expected,
+                    pixel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chromatic_aberration_should_offset_the_red_channel_outward() {
+        // 10% of the distance to the center, the sprite left edge is at x = 135
+        let image = render_with_effects(
+            vec![PostEffect::ChromaticAberration { intensity: 0.1f32 }],
+            None,
+        );
+
+        let outside = image.get_pixel(WIDTH / 2 - 28, HEIGHT / 2 + 5).0;
+        let inside = image.get_pixel(WIDTH / 2 - 22, HEIGHT / 2 + 5).0;
+        assert_eq!(outside[0], 0, "{:?}", outside);
+        assert!(inside[0] > 200, "{:?}", inside);
+
+        let reference = render_with_effects(vec![], None);
+        let inside = reference.get_pixel(WIDTH / 2 - 22, HEIGHT / 2 + 5).0;
+        assert!(inside[0] > 200, "{:?}", inside);
+        let shifted = reference.get_pixel(WIDTH / 2 + 20, HEIGHT / 2 + 5).0;
+        let aberrated = image.get_pixel(WIDTH / 2 + 20, HEIGHT / 2 + 5).0;
+        assert_eq!(shifted[1], aberrated[1]);
+    }
+
+    #[test]
+    fn blur_and_pixelation_should_be_chained_in_order() {
+        // The blur spreads the sprite edge, then every 4px cell shows a single color
+        let image = render_with_effects(
+            vec![
+                PostEffect::Blur { radius: 2f32 },
+                PostEffect::Pixelation { cell_size: 4f32 },
+            ],
+            None,
+        );
+
+        let reference = render_with_effects(vec![], None);
+        assert_eq!(reference.get_pixel(WIDTH / 2 + 26, HEIGHT / 2 + 10).0[0], 0);
+
+        for (cell_x, cell_y) in [(34, 32), (40, 33), (44, 32), (46, 32), (48, 32)] {
+            let first = image.get_pixel(cell_x * 4, cell_y * 4).0;
+            for (x, y) in [(3, 0), (0, 3), (3, 3), (1, 2)] {
+                assert_eq!(image.get_pixel(cell_x * 4 + x, cell_y * 4 + y).0, first);
+            }
+        }
+
+        // The cell 46 covers the sprite right edge at x = 185, blurred with the black background
+        let edge = image.get_pixel(46 * 4, 32 * 4).0;
+        let inside = image.get_pixel(40 * 4, 32 * 4).0;
+        assert!(
+            edge[0] > 0 && edge[0] < inside[0],
+            "{:?} {:?}",
+            edge,
+            inside
+        );
+        assert_eq!(image.get_pixel(48 * 4, 32 * 4).0[0], 0);
+    }
+
+    #[test]
+    fn systems_should_animate_the_effects_parameters() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            let camera = main_camera(&world);
+            world.get_mut::<Camera>(camera).unwrap().background_color = Option::from(GRAY);
+            world.entity_mut(camera).insert(PostProcessStack {
+                effects: vec![PostEffect::Vignette {
+                    intensity: 0f32,
+                    smoothness: 0.5f32,
+                }],
+            });
+            world
+                .resource_mut::<Schedules>()
+                .get_mut(EcsUpdateSchedule)
+                .unwrap()
+                .add_systems(fade_in_vignette);
+        }
+
+        let first = scene.render_frames(1).get_pixel(0, 0).0[0];
+        let second = scene.render_frames(1).get_pixel(0, 0).0[0];
+        let faded = scene.render_frames(5).get_pixel(0, 0).0[0];
+        assert!(
+            first > second && second > faded,
+            "{} {} {}",
+            first,
+            second,
+            faded
+        );
+        assert!(faded < 10);
+
+        // Removing the stack brings the camera back to its plain image
+        {
+            let mut world = scene.world();
+            let camera = main_camera(&world);
+            world.entity_mut(camera).remove::<PostProcessStack>();
+        }
+        assert_eq!(
+            scene.render_frames(1).get_pixel(0, 0).0,
+            [128, 128, 128, 255]
+        );
+    }
+
+    #[test]
+    fn effects_should_ping_pong_before_writing_the_camera_framebuffer() {
+        let (recording, mut renderer) = recording_renderer("Post Processing");
+        renderer.update_camera_post_effects(
+            MAIN_CAMERA,
+            vec![
+                PostEffect::Blur { radius: 1f32 },
+                PostEffect::ColorGrading {
+                    lut: String::from(NEUTRAL_LUT),
+                    intensity: 0.5f32,
+                },
+                PostEffect::Pixelation { cell_size: 4f32 },
+            ],
+        );
+
+        recording.take_calls();
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        let allocated: Vec<u32> = calls
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::AllocFramebuffer { handle, .. } => Some(*handle),
+                _ => None,
+            })
+            .collect();
+        let used: Vec<u32> = calls
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::UseFramebuffer { handle } => *handle,
+                _ => None,
+            })
+            .collect();
+        let passes: Vec<&Vec<u32>> = calls
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::DrawFullscreenPass { textures, .. } => Some(textures),
+                _ => None,
+            })
+            .collect();
+        let effects: Vec<&UniformValue> = calls
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::SetUniform { name, value, .. } if name == "effect" => Some(value),
+                _ => None,
+            })
+            .collect();
+
+        // Camera framebuffer then the two ping-pong buffers, the last effect writes the first one
+        let (framebuffer, first, second) = (allocated[0], allocated[1], allocated[2]);
+        assert_eq!(allocated.len(), 3);
+        assert_eq!(used, vec![first, second, first, framebuffer]);
+        assert_eq!(passes.len(), 3);
+        assert_eq!(passes[1].len(), 2, "the color grading samples its LUT");
+        assert_eq!(
+            effects,
+            vec![
+                &UniformValue::I32(3),
+                &UniformValue::I32(1),
+                &UniformValue::I32(4)
+            ]
+        );
+        assert!(calls.iter().any(|call| matches!(
+            call,
+            GfxCall::BlitMainFramebuffer { framebuffer: blitted, .. } if *blitted == framebuffer
+        )));
+        assert_eq!(renderer.get_frame_stats().post_effects, 3);
+
+        // One effect left, a single ping-pong buffer is kept and the LUT is released
+        renderer.update_camera_post_effects(MAIN_CAMERA, vec![PostEffect::Blur { radius: 1f32 }]);
+        renderer.render(1f32 / 60f32);
+        renderer.update_camera_post_effects(MAIN_CAMERA, vec![]);
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        let count =
+            |predicate: fn(&GfxCall) -> bool| calls.iter().filter(|call| predicate(call)).count();
+        assert_eq!(
+            count(|call| matches!(call, GfxCall::ReleaseFramebuffer { .. })),
+            3
+        );
+        assert_eq!(
+            count(|call| matches!(call, GfxCall::AllocFramebuffer { .. })),
+            1
+        );
+        assert_eq!(
+            count(|call| matches!(call, GfxCall::ReleaseTexture { .. })),
+            1
+        );
+        assert_eq!(
+            count(|call| matches!(call, GfxCall::DrawFullscreenPass { .. })),
+            1
+        );
+        assert!(renderer
+            .get_camera_post_effects(MAIN_CAMERA)
+            .unwrap()
+            .is_empty());
+        assert!(recording.log().violations().is_empty());
+    }
+
+    #[test]
+    fn updating_a_stack_should_keep_its_lut_texture() {
+        let (recording, mut renderer) = recording_renderer("Post Processing");
+        let grading = |intensity: f32| {
+            vec![PostEffect::ColorGrading {
+                lut: String::from(NEUTRAL_LUT),
+                intensity,
+            }]
+        };
+
+        renderer.update_camera_post_effects(MAIN_CAMERA, grading(0.2f32));
+        renderer.render(1f32 / 60f32);
+        renderer.update_camera_post_effects(MAIN_CAMERA, grading(0.4f32));
+        renderer.render(1f32 / 60f32);
+
+        let calls = recording.take_calls();
+        let count =
+            |predicate: fn(&GfxCall) -> bool| calls.iter().filter(|call| predicate(call)).count();
+        assert_eq!(
+            count(|call| matches!(call, GfxCall::AllocTexture { .. })),
+            1
+        );
+        assert_eq!(
+            count(|call| matches!(call, GfxCall::ReleaseTexture { .. })),
+            0
+        );
+    }
+}
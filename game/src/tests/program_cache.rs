@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::Transform;
+    use crate::engine::rendering::components::{
+        MaskInteraction, MeshInfo, RenderRequest, RenderingMode, SortingOrder,
+    };
+    use crate::engine::rendering::gfx_recording::{GfxCall, GfxDeviceRecording, GfxHandleKind};
+    use crate::engine::rendering::renderer::{RenderCmdHd, Renderer};
+    use crate::engine::rendering::renderer_helpers::apply_shader_defines;
+    use crate::engine::rendering::shaders::{Material, MaterialValue};
+    use crate::tests::fixtures;
+
+    // The calls of the warm up are not part of the checks
+    fn recording_renderer() -> (GfxDeviceRecording, Renderer) {
+        let (recording, renderer) = fixtures::recording_renderer("Program Cache");
+        recording.take_calls();
+        (recording, renderer)
+    }
+
+    fn create_sprite(renderer: &mut Renderer, material: Material) -> RenderCmdHd {
+        renderer.create_render_command(RenderRequest {
+            mesh_info: MeshInfo {
+                file_path: None,
+                count: 0,
+                vertices_set: None,
+            },
+            material,
+            transform: Transform::default(),
+            sorting: SortingOrder::default(),
+            mask_interaction: MaskInteraction::None,
+        })
+    }
+
+    fn linked_programs(recording: &GfxDeviceRecording) -> usize {
+        recording
+            .take_calls()
+            .iter()
+            .filter(|call| matches!(call, GfxCall::AllocShaderModule { .. }))
+            .count()
+    }
+
+    fn program_allocations(recording: &GfxDeviceRecording) -> usize {
+        recording
+            .take_calls()
+            .iter()
+            .filter(|call| {
+                matches!(
+                    call,
+                    GfxCall::AllocShader { .. } | GfxCall::AllocShaderModule { .. }
+                )
+            })
+            .count()
+    }
+
+    #[test]
+    fn sprites_sharing_a_material_should_share_one_program() {
+        let (recording, mut renderer) = recording_renderer();
+        let first = create_sprite(&mut renderer, Material::new());
+        assert!(program_allocations(&recording) > 0);
+
+        let handles: Vec<RenderCmdHd> = (0..99)
+            .map(|_| create_sprite(&mut renderer, Material::default(None)))
+            .collect();
+
+        assert_eq!(program_allocations(&recording), 0);
+        let program = renderer.get_command(first).shader_module.self_handle;
+        for handle in handles {
+            assert_eq!(
+                renderer.get_command(handle).shader_module.self_handle,
+                program
+            );
+        }
+    }
+
+    #[test]
+    fn different_defines_should_link_different_programs() {
+        let (recording, mut renderer) = recording_renderer();
+        let plain = create_sprite(&mut renderer, Material::new());
+        program_allocations(&recording);
+
+        let mut material = Material::new();
+        material.shaders.defines.push(String::from("TINTED 1"));
+        let tinted = create_sprite(&mut renderer, material.clone());
+        assert!(program_allocations(&recording) > 0);
+        create_sprite(&mut renderer, material);
+        assert_eq!(program_allocations(&recording), 0);
+
+        assert_ne!(
+            renderer.get_command(plain).shader_module.self_handle,
+            renderer.get_command(tinted).shader_module.self_handle
+        );
+    }
+
+    #[test]
+    fn keywords_should_share_a_program_whatever_their_order() {
+        let (recording, mut renderer) = recording_renderer();
+        let keywords = |keywords: &[&str]| {
+            let mut material = Material::new();
+            material.shaders.keywords = keywords.iter().map(|k| String::from(*k)).collect();
+            material
+        };
+        let first = create_sprite(&mut renderer, keywords(&["FOG", "SOFT"]));
+        assert!(program_allocations(&recording) > 0);
+
+        let reordered = create_sprite(&mut renderer, keywords(&["SOFT", "FOG"]));
+        assert_eq!(program_allocations(&recording), 0);
+        assert_eq!(
+            renderer.get_command(first).shader_module.self_handle,
+            renderer.get_command(reordered).shader_module.self_handle
+        );
+
+        let other = create_sprite(&mut renderer, keywords(&["FOG"]));
+        assert!(program_allocations(&recording) > 0);
+        assert_ne!(
+            renderer.get_command(first).shader_module.self_handle,
+            renderer.get_command(other).shader_module.self_handle
+        );
+    }
+
+    #[test]
+    fn program_should_be_released_with_its_last_command() {
+        let (recording, mut renderer) = recording_renderer();
+        let mut material = Material::new();
+        material.shaders.defines.push(String::from("RELEASED"));
+        let first = create_sprite(&mut renderer, material.clone());
+        let second = create_sprite(&mut renderer, material);
+        let program = renderer.get_command(first).shader_module.self_handle;
+        let live_programs = recording.log().live_count(GfxHandleKind::Program);
+
+        renderer.remove_render_command(first);
+        assert!(!recording
+            .log()
+            .calls()
+            .contains(&GfxCall::ReleaseShaderModule { handle: program }));
+
+        renderer.remove_render_command(second);
+        let log = recording.log();
+        let releases = log
+            .calls()
+            .iter()
+            .filter(|call| **call == GfxCall::ReleaseShaderModule { handle: program })
+            .count();
+        assert_eq!(releases, 1);
+        assert_eq!(log.live_count(GfxHandleKind::Program), live_programs - 1);
+    }
+
+    #[test]
+    fn batch_programs_should_only_be_built_for_batched_sprites() {
+        let (recording, mut renderer) = recording_renderer();
+        renderer.set_rendering_mode(RenderingMode::Batched);
+
+        // Materials with uniforms are drawn directly, with their own program only
+        let mut material = Material::new();
+        material.shaders.defines.push(String::from("DIRECT 1"));
+        material
+            .uniforms
+            .push((String::from("u_strength"), MaterialValue::Float(0.5f32)));
+        let direct = create_sprite(&mut renderer, material);
+        assert_eq!(linked_programs(&recording), 1);
+        renderer.enqueue_cmd_for_current_frame(direct);
+        renderer.render(1f32 / 60f32);
+        assert_eq!(linked_programs(&recording), 0);
+
+        // The batched & instanced programs are built with the first batch of the material
+        let mut material = Material::new();
+        material.shaders.defines.push(String::from("BATCHED 1"));
+        let batched = create_sprite(&mut renderer, material);
+        assert_eq!(linked_programs(&recording), 1);
+        renderer.enqueue_cmd_for_current_frame(batched);
+        renderer.render(1f32 / 60f32);
+        assert_eq!(linked_programs(&recording), 2);
+        renderer.enqueue_cmd_for_current_frame(batched);
+        renderer.render(1f32 / 60f32);
+        assert_eq!(linked_programs(&recording), 0);
+    }
+
+    #[test]
+    fn defines_should_be_inserted_after_the_version_directive() {
+        let source = String::from("#version 450 core\nvoid main() {}\n");
+        let defines = [String::from("TINTED"), String::from("SCALE 2.0")];
+
+        assert_eq!(
+            apply_shader_defines(source.clone(), &defines),
+            "#version 450 core\n#define TINTED\n#define SCALE 2.0\nvoid main() {}\n"
+        );
+        assert_eq!(apply_shader_defines(source.clone(), &[]), source);
+    }
+}
@@ -0,0 +1,197 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{SortingLayer, SpriteRenderer2D};
+    use crate::engine::rendering::components::{DepthSortMode, RenderingMode};
+    use crate::tests::fixtures::spawn_sprite_renderer;
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::entity::Entity;
+    use bevy_ecs::world::World;
+    use image::{Rgba, RgbaImage};
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const RED: &str = "Red/texture_08.png";
+    const GREEN: &str = "Green/texture_02.png";
+
+    fn spawn_sprite(
+        world: &mut World,
+        texture: &str,
+        position: (f32, f32, f32),
+        layer: SortingLayer,
+        order_in_layer: i16,
+    ) -> Entity {
+        let mut sprite = SpriteRenderer2D::from(String::from(texture), false);
+        sprite.sorting_layer = layer;
+        sprite.order_in_layer = order_in_layer;
+
+        spawn_sprite_renderer(world, sprite, position, 0.5f32)
+    }
+
+    // Slightly off the center, the debug grid axes cross at the origin
+    fn center_pixel(image: &RgbaImage) -> Rgba<u8> {
+        *image.get_pixel(WIDTH / 2 + 7, HEIGHT / 2 + 5)
+    }
+
+    fn is_green(pixel: Rgba<u8>) -> bool {
+        pixel.0[1] > pixel.0[0]
+    }
+
+    #[test]
+    fn higher_order_in_layer_should_be_drawn_on_top_whatever_the_spawn_order() {
+        for green_first in [true, false] {
+            let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+            {
+                let mut world = scene.world();
+                let mut sprites = vec![(GREEN, 1i16), (RED, 0i16)];
+                if !green_first {
+                    sprites.reverse();
+                }
+                for (texture, order) in sprites {
+                    spawn_sprite(
+                        &mut world,
+                        texture,
+                        (0f32, 0f32, 0f32),
+                        SortingLayer::Default,
+                        order,
+                    );
+                }
+            }
+
+            let image = scene.render_frames(2);
+            assert!(
+                is_green(center_pixel(&image)),
+                "green spawned first: {}",
+                green_first
+            );
+        }
+    }
+
+    #[test]
+    fn sorting_layer_should_prevail_over_order_in_layer_and_depth() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            spawn_sprite(
+                &mut world,
+                GREEN,
+                (0f32, 0f32, 0.5f32),
+                SortingLayer::Foreground,
+                -10,
+            );
+            spawn_sprite(
+                &mut world,
+                RED,
+                (0f32, 0f32, 0f32),
+                SortingLayer::Default,
+                10,
+            );
+        }
+
+        assert!(is_green(center_pixel(&scene.render_frames(2))));
+    }
+
+    #[test]
+    fn nearest_sprite_should_be_drawn_last_in_z_mode() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            spawn_sprite(
+                &mut world,
+                GREEN,
+                (0f32, 0f32, 0f32),
+                SortingLayer::Default,
+                0,
+            );
+            spawn_sprite(
+                &mut world,
+                RED,
+                (0f32, 0f32, 0.5f32),
+                SortingLayer::Default,
+                0,
+            );
+        }
+
+        assert!(is_green(center_pixel(&scene.render_frames(2))));
+    }
+
+    #[test]
+    fn lowest_sprite_should_be_drawn_last_in_y_mode() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        scene.app.set_depth_sort_mode(DepthSortMode::Y);
+        {
+            let mut world = scene.world();
+            spawn_sprite(
+                &mut world,
+                GREEN,
+                (0f32, -0.05f32, 0f32),
+                SortingLayer::Default,
+                0,
+            );
+            spawn_sprite(
+                &mut world,
+                RED,
+                (0f32, 0.05f32, 0f32),
+                SortingLayer::Default,
+                0,
+            );
+        }
+
+        assert!(is_green(center_pixel(&scene.render_frames(2))));
+    }
+
+    #[test]
+    fn changing_the_order_in_layer_should_resort_the_sprite() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        let green = {
+            let mut world = scene.world();
+            let green = spawn_sprite(
+                &mut world,
+                GREEN,
+                (0f32, 0f32, 0f32),
+                SortingLayer::Default,
+                0,
+            );
+            spawn_sprite(
+                &mut world,
+                RED,
+                (0f32, 0f32, 0f32),
+                SortingLayer::Default,
+                1,
+            );
+            green
+        };
+        assert!(!is_green(center_pixel(&scene.render_frames(2))));
+
+        scene
+            .world()
+            .get_mut::<SpriteRenderer2D>(green)
+            .unwrap()
+            .order_in_layer = 2;
+        assert!(is_green(center_pixel(&scene.render_frames(2))));
+    }
+
+    #[test]
+    fn sprites_with_equal_keys_should_be_grouped_by_texture() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            for i in 0..8 {
+                let texture = if i % 2 == 0 { RED } else { GREEN };
+                let x = i as f32 * 0.3f32 - 1f32;
+                spawn_sprite(
+                    &mut world,
+                    texture,
+                    (x, 0f32, 0f32),
+                    SortingLayer::Default,
+                    0,
+                );
+            }
+        }
+
+        scene.app.set_rendering_mode(RenderingMode::Batched);
+        scene.render_frames(1);
+        let stats = scene.app.get_frame_stats();
+        assert_eq!(stats.batched_commands, 8);
+        assert_eq!(stats.draw_calls, 2);
+    }
+}
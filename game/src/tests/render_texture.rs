@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, Transform};
+    use crate::engine::rendering::components::{
+        ARGB8Color, MaskInteraction, MeshInfo, RenderRequest, RenderTarget, RenderingCamera,
+        SortingOrder,
+    };
+    use crate::engine::rendering::gfx_recording::GfxCall;
+    use crate::engine::rendering::renderer::{CameraHd, RenderCmdHd, Renderer};
+    use crate::engine::rendering::shaders::Material;
+    use crate::tests::fixtures::{recording_renderer, spawn_sprite};
+    use crate::tests::golden::GoldenScene;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const MONITOR: &str = "monitor";
+
+    fn monitor_target(resolution: (u32, u32)) -> Option<RenderTarget> {
+        Option::from(RenderTarget {
+            name: String::from(MONITOR),
+            resolution: Option::from(resolution),
+        })
+    }
+
+    fn add_monitor_camera(renderer: &mut Renderer, resolution: (u32, u32)) -> CameraHd {
+        renderer.add_camera(RenderingCamera {
+            output_target: monitor_target(resolution),
+            ..RenderingCamera::default()
+        })
+    }
+
+    fn monitor_sprite(renderer: &mut Renderer) -> RenderCmdHd {
+        let handle = renderer.create_render_command(RenderRequest {
+            mesh_info: MeshInfo {
+                file_path: None,
+                count: 0,
+                vertices_set: None,
+            },
+            material: Material {
+                main_texture: Option::from(String::from(MONITOR)),
+                ..Material::new()
+            },
+            transform: Transform::default(),
+            sorting: SortingOrder::default(),
+            mask_interaction: MaskInteraction::None,
+        });
+        renderer.enqueue_cmd_for_current_frame(handle);
+        handle
+    }
+
+    fn sampled_texture(renderer: &Renderer, handle: RenderCmdHd) -> Option<u32> {
+        renderer
+            .get_command(handle)
+            .shader_module
+            .texture_handles
+            .first()
+            .copied()
+    }
+
+    #[test]
+    fn sprites_should_show_the_camera_render_texture_upright() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            // 64px at 100 ppu, the offscreen camera sees 0.64 world units far from the main one
+            let mut transform = Camera::default_transform();
+            transform.position.x = 10f32;
+            world.spawn((
+                Camera {
+                    output_target: monitor_target((64, 64)),
+                    background_color: Option::from(ARGB8Color {
+                        r: 0,
+                        g: 0,
+                        b: 255,
+                        a: 255,
+                    }),
+                    ..Camera::default()
+                },
+                transform,
+            ));
+            spawn_sprite(
+                &mut world,
+                "Red/texture_01.png",
+                (10f32, 0.2f32, 0f32),
+                0.2f32,
+            );
+            spawn_sprite(
+                &mut world,
+                "Green/texture_01.png",
+                (10f32, -0.2f32, 0f32),
+                0.2f32,
+            );
+            spawn_sprite(&mut world, MONITOR, (0f32, 0f32, 0f32), 1f32);
+        }
+
+        // The red sprite is at the top of the monitor, 0.2 / 0.64 of its 100px height
+        let image = scene.render_frames(2);
+        let top = image.get_pixel(WIDTH / 2 + 7, HEIGHT / 2 - 31).0;
+        let bottom = image.get_pixel(WIDTH / 2 + 7, HEIGHT / 2 + 31).0;
+        let border = image.get_pixel(WIDTH / 2 + 40, HEIGHT / 2 + 5).0;
+        assert!(top[0] > 200 && top[1] < 100, "{:?}", top);
+        assert!(bottom[1] > 150 && bottom[0] < 100, "{:?}", bottom);
+        assert_eq!(border, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn render_textures_should_be_sampled_without_reference_counting() {
+        let (recording, mut renderer) = recording_renderer("Render Texture");
+        let camera = add_monitor_camera(&mut renderer, (64, 32));
+        let texture = renderer.get_render_texture(MONITOR);
+        assert!(texture.is_some());
+
+        let sprite = monitor_sprite(&mut renderer);
+        assert_eq!(sampled_texture(&renderer, sprite), texture);
+
+        // The offscreen camera skips the sprite sampling its own texture
+        recording.take_calls();
+        renderer.render(1f32 / 60f32);
+        renderer.enqueue_cmd_for_current_frame(sprite);
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        assert_eq!(renderer.get_frame_stats().draw_calls, 1);
+        assert!(!calls.iter().any(|call| matches!(
+            call,
+            GfxCall::ReleaseTexture { handle } if Some(*handle) == texture
+        )));
+
+        // A new resolution reallocates the texture, the sprite follows it
+        let mut settings = renderer.get_camera(camera).unwrap().clone();
+        settings.output_target = monitor_target((128, 64));
+        renderer.update_camera_settings(camera, settings);
+        let resized = renderer.get_render_texture(MONITOR);
+        assert_ne!(resized, texture);
+        assert_eq!(sampled_texture(&renderer, sprite), resized);
+
+        renderer.remove_camera(camera);
+        assert_eq!(renderer.get_render_texture(MONITOR), None);
+        assert_eq!(sampled_texture(&renderer, sprite), None);
+        assert!(recording.log().violations().is_empty());
+    }
+
+    #[test]
+    fn offscreen_cameras_should_be_rendered_before_the_screen_ones() {
+        let (recording, mut renderer) = recording_renderer("Render Texture");
+        renderer.add_camera(RenderingCamera {
+            order: 5,
+            output_target: monitor_target((64, 64)),
+            ..RenderingCamera::default()
+        });
+
+        recording.take_calls();
+        renderer.render(1f32 / 60f32);
+        let calls = recording.take_calls();
+        let used: Vec<u32> = calls
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::UseFramebuffer { handle } => *handle,
+                _ => None,
+            })
+            .collect();
+        let blitted: Vec<u32> = calls
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::BlitMainFramebuffer { framebuffer, .. } => Some(*framebuffer),
+                _ => None,
+            })
+            .collect();
+
+        // Whatever its order, the offscreen camera is drawn first and never composited
+        assert_eq!(used.len(), 2);
+        assert_eq!(blitted, vec![used[1]]);
+        // Its framebuffer was allocated with the camera, only the screen one is allocated now
+        assert_eq!(
+            calls
+                .iter()
+                .filter(|call| matches!(call, GfxCall::AllocFramebuffer { .. }))
+                .count(),
+            1
+        );
+    }
+}
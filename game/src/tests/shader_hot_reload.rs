@@ -0,0 +1,253 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{SpriteRenderer2D, Transform};
+    use crate::engine::lib::runtime::App;
+    use crate::engine::rendering::components::{
+        MaskInteraction, MeshInfo, RenderRequest, RenderingMode, SortingOrder,
+    };
+    use crate::engine::rendering::gfx_recording::{GfxCall, GfxDeviceRecording, GfxHandleKind};
+    use crate::engine::rendering::renderer::{RenderCmdHd, Renderer};
+    use crate::engine::rendering::shader_watcher::ShaderWatcher;
+    use crate::engine::rendering::shaders::{Material, ShaderInfo, ShaderType};
+    use crate::engine::utils::clock::FixedClock;
+    use crate::engine::utils::file_system::{FileSystem, FileType};
+    use crate::tests::fixtures::{recording_renderer, unwarmed_recording_renderer};
+    use std::fs;
+
+    // Shader written in assets/shaders for one test, removed once dropped
+    struct TempShader {
+        name: &'static str,
+    }
+
+    impl TempShader {
+        fn write(name: &'static str, content: &str) -> Self {
+            FileSystem::write_file(name, content, FileType::Shader);
+            Self { name }
+        }
+    }
+
+    impl Drop for TempShader {
+        fn drop(&mut self) {
+            FileSystem::remove_file(self.name, FileType::Shader);
+        }
+    }
+
+    fn fragment_source() -> String {
+        FileSystem::load_file("fragment.shader", FileType::Shader).unwrap()
+    }
+
+    fn broken_source() -> String {
+        fragment_source().replacen("\n", "\n#error broken on purpose\n", 1)
+    }
+
+    fn create_sprite(renderer: &mut Renderer, fragment: Option<&str>) -> RenderCmdHd {
+        let mut material = Material::default(Option::from(String::from("Red/texture_01.png")));
+        material.shaders.fragment =
+            fragment.map(|name| ShaderInfo::with_name(String::from(name), ShaderType::Fragment));
+
+        renderer.create_render_command(RenderRequest {
+            mesh_info: MeshInfo {
+                file_path: None,
+                count: 0,
+                vertices_set: None,
+            },
+            material,
+            transform: Transform::default(),
+            sorting: SortingOrder::default(),
+            mask_interaction: MaskInteraction::None,
+        })
+    }
+
+    fn program_of(renderer: &Renderer, handle: RenderCmdHd) -> u32 {
+        renderer.get_command(handle).shader_module.self_handle
+    }
+
+    fn released_programs(recording: &GfxDeviceRecording) -> Vec<u32> {
+        recording
+            .take_calls()
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::ReleaseShaderModule { handle } => Some(*handle),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn render_frame(renderer: &mut Renderer, handles: &[RenderCmdHd]) {
+        handles
+            .iter()
+            .for_each(|handle| renderer.enqueue_cmd_for_current_frame(*handle));
+        renderer.render(1f32 / 60f32);
+    }
+
+    #[test]
+    fn watcher_should_report_created_and_modified_files() {
+        let directory = std::env::temp_dir().join("afraid_of_dark_shader_watcher");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("lib")).unwrap();
+        fs::write(directory.join("sprite.shader"), "void main() {}").unwrap();
+
+        let mut watcher = ShaderWatcher::new(directory.clone(), 1f32);
+        assert!(watcher.scan().is_empty());
+
+        // The size changes too, coarse modification times can't hide the edit
+        fs::write(directory.join("sprite.shader"), "void main() { }").unwrap();
+        fs::write(directory.join("lib/common.glsl"), "float common;").unwrap();
+        assert_eq!(
+            watcher.scan(),
+            vec![
+                String::from("lib/common.glsl"),
+                String::from("sprite.shader")
+            ]
+        );
+        assert!(watcher.scan().is_empty());
+
+        // Polling only scans once the interval elapsed
+        fs::write(directory.join("lib/common.glsl"), "float common_value;").unwrap();
+        assert!(watcher.poll(0.6f32).is_empty());
+        assert_eq!(watcher.poll(0.6f32), vec![String::from("lib/common.glsl")]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn reloading_should_swap_the_program_of_every_user() {
+        let shader = TempShader::write("hot_reload_swap.shader", &fragment_source());
+        let (recording, mut renderer) = recording_renderer("Hot Reload");
+        let first = create_sprite(&mut renderer, Option::from(shader.name));
+        let second = create_sprite(&mut renderer, Option::from(shader.name));
+        let other = create_sprite(&mut renderer, None);
+        // Batch programs are built with the first batch
+        render_frame(&mut renderer, &[first, second, other]);
+        let program = program_of(&renderer, first);
+        let other_program = program_of(&renderer, other);
+        let live_programs = recording.log().live_count(GfxHandleKind::Program);
+        recording.take_calls();
+
+        renderer.reload_shaders(&[String::from(shader.name)]);
+        let reloaded = program_of(&renderer, first);
+        assert_ne!(reloaded, program);
+        assert_eq!(program_of(&renderer, second), reloaded);
+        assert_eq!(program_of(&renderer, other), other_program);
+        // The direct, batched & instanced programs of the shader are replaced, nothing else
+        assert_eq!(released_programs(&recording).len(), 3);
+        assert!(!recording.log().is_alive(GfxHandleKind::Program, program));
+        assert_eq!(
+            recording.log().live_count(GfxHandleKind::Program),
+            live_programs
+        );
+
+        // Both paths draw with the new programs
+        for mode in [RenderingMode::Direct, RenderingMode::Batched] {
+            renderer.set_rendering_mode(mode);
+            render_frame(&mut renderer, &[first, second, other]);
+        }
+        assert!(recording.log().violations().is_empty());
+
+        // Later sprites share the reloaded program
+        let third = create_sprite(&mut renderer, Option::from(shader.name));
+        assert_eq!(program_of(&renderer, third), reloaded);
+    }
+
+    #[test]
+    fn failing_reload_should_keep_the_previous_program() {
+        let shader = TempShader::write("hot_reload_failure.shader", &fragment_source());
+        let (recording, mut renderer) = recording_renderer("Hot Reload");
+        let sprite = create_sprite(&mut renderer, Option::from(shader.name));
+        let program = program_of(&renderer, sprite);
+        recording.take_calls();
+
+        FileSystem::write_file(shader.name, &broken_source(), FileType::Shader);
+        renderer.reload_shaders(&[String::from(shader.name)]);
+        assert_eq!(program_of(&renderer, sprite), program);
+        assert!(released_programs(&recording).is_empty());
+        // The vertex shader compiled before the failure is not leaked
+        assert_eq!(recording.log().live_count(GfxHandleKind::Shader), 0);
+        render_frame(&mut renderer, &[sprite]);
+        assert!(recording.log().violations().is_empty());
+
+        // Fixing the shader reloads it
+        FileSystem::write_file(shader.name, &fragment_source(), FileType::Shader);
+        renderer.reload_shaders(&[String::from(shader.name)]);
+        assert_ne!(program_of(&renderer, sprite), program);
+        assert!(released_programs(&recording).contains(&program));
+    }
+
+    #[test]
+    fn broken_shaders_should_fall_back_to_the_default_program() {
+        let shader = TempShader::write("hot_reload_broken.shader", &broken_source());
+        let (recording, mut renderer) = recording_renderer("Hot Reload");
+        let default_sprite = create_sprite(&mut renderer, None);
+
+        // Edited before the sprite spawns, it is drawn with the default shaders instead
+        let sprite = create_sprite(&mut renderer, Option::from(shader.name));
+        assert_eq!(
+            program_of(&renderer, sprite),
+            program_of(&renderer, default_sprite)
+        );
+        render_frame(&mut renderer, &[sprite, default_sprite]);
+        assert!(recording.log().violations().is_empty());
+    }
+
+    #[test]
+    fn includes_should_reload_the_programs_using_them() {
+        let (recording, mut renderer) = recording_renderer("Hot Reload");
+        let sprite = create_sprite(&mut renderer, None);
+        let program = program_of(&renderer, sprite);
+        render_frame(&mut renderer, &[sprite]);
+        let live_programs = recording.log().live_count(GfxHandleKind::Program);
+        recording.take_calls();
+
+        // Sprites, batches & the grid lines include it
+        renderer.reload_shaders(&[String::from("common.glsl")]);
+        assert_ne!(program_of(&renderer, sprite), program);
+        let released = released_programs(&recording).len();
+        assert!(released > 3, "{}", released);
+        assert_eq!(
+            recording.log().live_count(GfxHandleKind::Program),
+            live_programs
+        );
+
+        // The screen blit & post processing programs share this one
+        renderer.reload_shaders(&[String::from("framebuffer_vertex.shader")]);
+        assert_eq!(released_programs(&recording).len(), 2);
+        render_frame(&mut renderer, &[sprite]);
+        assert!(recording.log().violations().is_empty());
+
+        // Unrelated files rebuild nothing
+        renderer.reload_shaders(&[String::from("unknown.shader")]);
+        assert!(released_programs(&recording).is_empty());
+    }
+
+    #[test]
+    fn app_frames_should_poll_the_watcher_with_their_delta() {
+        let shader = TempShader::write("hot_reload_app.shader", &fragment_source());
+        let (recording, renderer) = unwarmed_recording_renderer("Hot Reload");
+        let mut app = App::new("Hot Reload");
+        // Frames as long as the fixed step leave no remainder to the fixed updates
+        app.set_clock(Box::new(FixedClock::new(0.02f32)));
+        app.warm_with(renderer).set_shader_hot_reload(true);
+
+        let mut material = Material::default(None);
+        material.shaders.fragment = Option::from(ShaderInfo::with_name(
+            String::from(shader.name),
+            ShaderType::Fragment,
+        ));
+        let mut sprite = SpriteRenderer2D::from(String::from("Red/texture_01.png"), false);
+        sprite.material = Option::from(material);
+        app.world
+            .as_ref()
+            .unwrap()
+            .borrow_mut()
+            .spawn((Transform::default(), sprite));
+        app.run_frames(1).unwrap();
+        recording.take_calls();
+
+        // 30 frames of 0.02s go past the poll interval
+        let edited = fragment_source() + "\n// edited\n";
+        FileSystem::write_file(shader.name, &edited, FileType::Shader);
+        app.run_frames(30).unwrap();
+        assert!(!released_programs(&recording).is_empty());
+        assert!(recording.log().violations().is_empty());
+    }
+}
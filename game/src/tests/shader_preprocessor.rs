@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::rendering::renderer_storage::RendererStorage;
+    use crate::engine::rendering::shader_preprocessor::{
+        get_error_directive_log, map_compile_log, preprocess_shader,
+    };
+    use crate::engine::rendering::shaders::{ShaderInfo, ShaderType};
+    use std::collections::HashMap;
+
+    fn files(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(name, content)| (String::from(*name), String::from(*content)))
+            .collect()
+    }
+
+    fn preprocess(
+        shaders: &HashMap<String, String>,
+        defines: &[&str],
+        keywords: &[&str],
+    ) -> Result<String, String> {
+        let defines: Vec<String> = defines.iter().map(|define| String::from(*define)).collect();
+        let keywords: Vec<String> = keywords
+            .iter()
+            .map(|keyword| String::from(*keyword))
+            .collect();
+        preprocess_shader("main.shader", &defines, &keywords, |path| {
+            shaders
+                .get(path)
+                .cloned()
+                .ok_or_else(|| format!("Could not open file {}", path))
+        })
+        .map(|shader| shader.source)
+    }
+
+    #[test]
+    fn includes_should_be_expanded_once_with_their_original_lines() {
+        let shaders = files(&[
+            (
+                "main.shader",
+                "#version 430 core\n#include \"common.glsl\"\n\
+                 #include \"light.glsl\"\nvoid main() {}\n",
+            ),
+            ("common.glsl", "float common;\n"),
+            ("light.glsl", "#include \"common.glsl\"\nfloat light;\n"),
+        ]);
+
+        assert_eq!(
+            preprocess(&shaders, &[], &[]).unwrap(),
+            "#version 430 core\n\
+             // Sources: 0 main.shader, 1 common.glsl, 2 light.glsl\n\
+             #line 1 0\n\
+             \n\
+             #line 1 1\n\
+             float common;\n\
+             #line 3 0\n\
+             #line 1 2\n\
+             \n\
+             float light;\n\
+             #line 4 0\n\
+             void main() {}\n"
+        );
+    }
+
+    #[test]
+    fn defines_and_enabled_variants_should_follow_the_version() {
+        let shaders = files(&[
+            (
+                "main.shader",
+                "#version 430 core\n#pragma variants SOFT HARD\n#include \"fog.glsl\"\n",
+            ),
+            ("fog.glsl", "#pragma variants FOG\n"),
+        ]);
+
+        // Declaration order, undeclared keywords are ignored
+        let source = preprocess(&shaders, &["SCALE 2.0"], &["FOG", "UNKNOWN", "SOFT"]).unwrap();
+        assert!(
+            source.starts_with("#version 430 core\n#define SCALE 2.0\n#define SOFT\n#define FOG\n"),
+            "{}",
+            source
+        );
+        assert!(!source.contains("UNKNOWN") && !source.contains("HARD"));
+        assert!(!source.contains("#pragma"));
+
+        let reordered = preprocess(&shaders, &["SCALE 2.0"], &["SOFT", "FOG"]).unwrap();
+        assert_eq!(reordered, source);
+    }
+
+    #[test]
+    fn errors_should_report_the_file_and_line_of_the_directive() {
+        let shaders = files(&[
+            (
+                "main.shader",
+                "#version 430 core\n\n#include \"light.glsl\"\n",
+            ),
+            ("light.glsl", "float light;\n#include \"missing.glsl\"\n"),
+        ]);
+        let error = preprocess(&shaders, &[], &[]).unwrap_err();
+        assert!(
+            error.starts_with("[Shaders] light.glsl:2: Could not open file missing.glsl"),
+            "{}",
+            error
+        );
+
+        let shaders = files(&[
+            ("main.shader", "#include common.glsl\n"),
+            ("common.glsl", "#version 430 core\n"),
+        ]);
+        let error = preprocess(&shaders, &[], &[]).unwrap_err();
+        assert_eq!(error, "[Shaders] main.shader:1: expected #include \"file\"");
+
+        let shaders = files(&[
+            ("main.shader", "#include \"common.glsl\"\n"),
+            ("common.glsl", "#version 430 core\n"),
+        ]);
+        let error = preprocess(&shaders, &[], &[]).unwrap_err();
+        assert_eq!(
+            error,
+            "[Shaders] common.glsl:1: #version is only allowed in the main shader"
+        );
+    }
+
+    #[test]
+    fn compile_logs_should_name_the_original_files() {
+        let source = "#version 430 core\n// Sources: 0 main.shader, 1 common.glsl\n";
+        let log = "1(12) : error C1008: undefined variable \"PROJ\"\n\
+                   0:7(3): error: syntax error\n\
+                   4(2) : unknown source";
+
+        assert_eq!(
+            map_compile_log(source, log),
+            "common.glsl:12 : error C1008: undefined variable \"PROJ\"\n\
+             main.shader:7(3): error: syntax error\n\
+             4(2) : unknown source"
+        );
+    }
+
+    #[test]
+    fn error_directives_should_fail_at_their_original_line() {
+        let shaders = files(&[
+            (
+                "main.shader",
+                "#version 430 core\n#include \"common.glsl\"\nvoid main() {}\n",
+            ),
+            ("common.glsl", "float common;\n#error not ready\n"),
+        ]);
+        let source = preprocess(&shaders, &[], &[]).unwrap();
+
+        assert_eq!(
+            get_error_directive_log(&source),
+            Some(String::from("common.glsl:2 : error: not ready"))
+        );
+        assert_eq!(get_error_directive_log("#version 430 core\n"), None);
+    }
+
+    #[test]
+    fn vertex_shaders_should_share_the_common_header() {
+        let store = RendererStorage::new();
+        for file_name in [
+            "vertex.shader",
+            "batch_vertex.shader",
+            "instanced_vertex.shader",
+            "line_vertex.shader",
+        ] {
+            let info = ShaderInfo::with_name(String::from(file_name), ShaderType::Vertex);
+            let source = store.load_shader_content(&info, &[], &[]).unwrap();
+            assert!(source.starts_with("#version 430 core\n"), "{}", source);
+            assert_eq!(source.matches("uniform FrameData").count(), 1, "{}", source);
+            assert!(!source.contains("#include"), "{}", source);
+        }
+    }
+}
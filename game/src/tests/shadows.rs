@@ -0,0 +1,170 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, Light2D, ShadowCaster2D};
+    use crate::engine::ecs::resources::CameraCullingState;
+    use crate::engine::rendering::components::{
+        ARGB8Color, AmbientLight, LightKind, RenderingLight, RenderingShadowCaster,
+    };
+    use crate::engine::rendering::lighting::{
+        get_light_block_data, LIGHT_FLOATS, LIGHT_HEADER_FLOATS, MAX_LIGHTS, MAX_SHADOW_SEGMENTS,
+    };
+    use crate::engine::utils::maths::identity_mat4;
+    use crate::tests::fixtures::sprite_transform;
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::entity::Entity;
+    use glm::Vector2;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const GRAY: ARGB8Color = ARGB8Color {
+        r: 128,
+        g: 128,
+        b: 128,
+        a: 255,
+    };
+
+    // World (0.5, -0.05), right behind the caster seen from the light
+    const BEHIND: (u32, u32) = (210, 125);
+    // World (0.6, 0.15), in the hard shadow close to its edge
+    const PENUMBRA: (u32, u32) = (220, 105);
+
+    // Dark gray background lit by a light on the left, a 0.2 unit wide box at the origin
+    fn shadowed_scene(light: Light2D) -> (GoldenScene, Entity, Entity) {
+        let scene = GoldenScene::new(WIDTH, HEIGHT);
+        let (light, caster) = {
+            let mut world = scene.world();
+            let camera = world
+                .resource::<CameraCullingState>()
+                .camera_entity
+                .unwrap();
+            let mut camera = world.get_mut::<Camera>(camera).unwrap();
+            camera.background_color = Option::from(GRAY);
+            camera.ambient_light = Option::from(AmbientLight {
+                color: GRAY,
+                intensity: 0f32,
+            });
+
+            let light = world
+                .spawn((sprite_transform((-0.8f32, 0f32, 0f32), 1f32), light))
+                .id();
+            let caster = world
+                .spawn((
+                    sprite_transform((0f32, 0f32, 0f32), 0.2f32),
+                    ShadowCaster2D::SpriteRect,
+                ))
+                .id();
+            (light, caster)
+        };
+        (scene, light, caster)
+    }
+
+    fn red_at(image: &image::RgbaImage, pixel: (u32, u32)) -> u8 {
+        image.get_pixel(pixel.0, pixel.1).0[0]
+    }
+
+    #[test]
+    fn casters_should_block_the_light_behind_them() {
+        let (mut scene, _, caster) = shadowed_scene(Light2D::point(2f32, 1f32));
+        let image = scene.render_frames(2);
+        assert_eq!(red_at(&image, BEHIND), 0);
+        assert_eq!(red_at(&image, PENUMBRA), 0);
+        // In front of the caster, and inside it
+        assert!(red_at(&image, (130, 125)) > 50);
+        assert_eq!(red_at(&image, (165, 115)), 0);
+
+        scene.world().entity_mut(caster).remove::<ShadowCaster2D>();
+        let image = scene.render_frames(1);
+        assert!(red_at(&image, BEHIND) > 40, "{}", red_at(&image, BEHIND));
+    }
+
+    #[test]
+    fn soft_shadows_should_fade_across_the_penumbra() {
+        let (mut scene, _, caster) = shadowed_scene(Light2D {
+            shadow_softness: 0.3f32,
+            ..Light2D::point(2f32, 1f32)
+        });
+        let soft = red_at(&scene.render_frames(2), PENUMBRA);
+
+        scene.world().despawn(caster);
+        let lit = red_at(&scene.render_frames(1), PENUMBRA);
+        assert!(soft > 0 && soft < lit, "{} {}", soft, lit);
+    }
+
+    #[test]
+    fn lights_without_shadows_should_ignore_the_casters() {
+        let (mut scene, light, caster) = shadowed_scene(Light2D {
+            cast_shadows: false,
+            ..Light2D::point(2f32, 1f32)
+        });
+        assert!(red_at(&scene.render_frames(2), BEHIND) > 40);
+
+        // Polygon outlines follow the caster transform, a vertical wall 0.2 unit high once scaled
+        scene
+            .world()
+            .get_mut::<Light2D>(light)
+            .unwrap()
+            .cast_shadows = true;
+        scene
+            .world()
+            .entity_mut(caster)
+            .insert(ShadowCaster2D::Polygon(vec![
+                (0f32, -0.5f32),
+                (0f32, 0.5f32),
+            ]));
+        let image = scene.render_frames(1);
+        assert_eq!(red_at(&image, BEHIND), 0);
+        assert!(red_at(&image, (210, 60)) > 30);
+    }
+
+    #[test]
+    fn light_block_should_pack_the_casters_edges() {
+        let light = RenderingLight {
+            kind: LightKind::Point,
+            color: GRAY,
+            intensity: 1f32,
+            radius: 1f32,
+            falloff: 1f32,
+            position: Vector2::new(0f32, 0f32),
+            rotation: 0f32,
+            cast_shadows: true,
+            shadow_softness: 0.5f32,
+        };
+        let caster = |points: &[(f32, f32)]| RenderingShadowCaster {
+            points: points.iter().map(|(x, y)| Vector2::new(*x, *y)).collect(),
+        };
+        let casters = vec![
+            caster(&[(0f32, 0f32), (1f32, 0f32), (0f32, 1f32)]),
+            caster(&[(2f32, 2f32), (3f32, 3f32)]),
+            caster(&[(5f32, 5f32)]),
+        ];
+        let ambient = AmbientLight {
+            color: GRAY,
+            intensity: 0f32,
+        };
+        let data = get_light_block_data(
+            &ambient,
+            &[light],
+            &casters,
+            &identity_mat4(),
+            &identity_mat4(),
+        );
+
+        // Triangles are closed, single segments are kept & lone points dropped
+        assert_eq!(data[20], 4f32);
+        let shadow = LIGHT_HEADER_FLOATS + 12;
+        assert_eq!(&data[shadow..shadow + 2], &[0.5f32, 1f32]);
+        let segments = LIGHT_HEADER_FLOATS + MAX_LIGHTS * LIGHT_FLOATS;
+        assert_eq!(
+            &data[segments..segments + 16],
+            &[
+                0f32, 0f32, 1f32, 0f32, 1f32, 0f32, 0f32, 1f32, 0f32, 1f32, 0f32, 0f32, 2f32, 2f32,
+                3f32, 3f32
+            ]
+        );
+
+        // Extra segments are dropped
+        let many = vec![casters[0].clone(); MAX_SHADOW_SEGMENTS];
+        let data = get_light_block_data(&ambient, &[], &many, &identity_mat4(), &identity_mat4());
+        assert_eq!(data[20], MAX_SHADOW_SEGMENTS as f32);
+    }
+}
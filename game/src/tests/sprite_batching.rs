@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{SpriteRenderer2D, Transform};
+    use crate::engine::lib::runtime::App;
+    use crate::engine::rendering::components::{FrameStats, RenderingMode};
+    use crate::engine::rendering::gfx_device::INSTANCE_FLOATS;
+    use crate::engine::rendering::gfx_recording::GfxCall;
+    use crate::tests::fixtures::{spawn_sprite, unwarmed_recording_renderer};
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::world::World;
+    use glm::vec4;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+
+    // Rotated so the batches transform the quad vertices, not only translate them
+    fn spawn_tilted_sprite(world: &mut World, texture: &str, position: (f32, f32, f32)) {
+        let sprite = spawn_sprite(world, texture, position, 0.5f32);
+        world.get_mut::<Transform>(sprite).unwrap().rotation.z = 15f32;
+    }
+
+    fn render_stats(scene: &mut GoldenScene, mode: RenderingMode) -> FrameStats {
+        scene.app.set_rendering_mode(mode);
+        scene.render_frames(1);
+        scene.app.get_frame_stats()
+    }
+
+    #[test]
+    fn sprites_sharing_texture_and_shader_should_be_drawn_in_one_call() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        for i in 0..100 {
+            let x = (i % 10) as f32 * 0.3f32 - 1.4f32;
+            let y = (i / 10) as f32 * 0.2f32 - 1f32;
+            spawn_tilted_sprite(&mut scene.world(), "Red/texture_08.png", (x, y, 0f32));
+        }
+
+        let batched = render_stats(&mut scene, RenderingMode::Batched);
+        assert_eq!(batched.draw_calls, 1);
+        assert_eq!(batched.batches, 1);
+        assert_eq!(batched.batched_commands, 100);
+
+        let direct = render_stats(&mut scene, RenderingMode::Direct);
+        assert_eq!(direct.draw_calls, 100);
+        assert_eq!(direct.batches, 0);
+    }
+
+    #[test]
+    fn texture_changes_should_break_batches_without_reordering() {
+        // Distinct depths pin the draw order, the sort cannot group the red sprites
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            spawn_tilted_sprite(&mut world, "Red/texture_08.png", (-1f32, 0f32, 0.3f32));
+            spawn_tilted_sprite(&mut world, "Red/texture_08.png", (-0.5f32, 0f32, 0.2f32));
+            spawn_tilted_sprite(&mut world, "Green/texture_02.png", (0f32, 0f32, 0.1f32));
+            spawn_tilted_sprite(&mut world, "Red/texture_08.png", (0.5f32, 0f32, 0f32));
+        }
+
+        let stats = render_stats(&mut scene, RenderingMode::Batched);
+        assert_eq!(stats.draw_calls, 3);
+        assert_eq!(stats.batched_commands, 4);
+    }
+
+    #[test]
+    fn culled_sprites_should_not_be_batched() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        {
+            let mut world = scene.world();
+            spawn_tilted_sprite(&mut world, "Red/texture_08.png", (0f32, 0f32, 0f32));
+            spawn_tilted_sprite(&mut world, "Red/texture_08.png", (20f32, 0f32, 0f32));
+        }
+
+        scene.render_frames(1);
+        let stats = render_stats(&mut scene, RenderingMode::Batched);
+        assert_eq!(stats.culled_commands, 1);
+        assert_eq!(stats.batched_commands, 1);
+        assert_eq!(stats.draw_calls, 1);
+    }
+
+    #[test]
+    fn instanced_tiles_should_be_a_single_instanced_draw() {
+        let (recording, renderer) = unwarmed_recording_renderer("Instancing");
+        let mut app = App::new("Instancing");
+        app.warm_with(renderer);
+        app.set_rendering_mode(RenderingMode::Instanced);
+
+        for i in 0..1000 {
+            let x = (i % 40) as f32 * 0.08f32 - 1.5f32;
+            let y = (i / 40) as f32 * 0.08f32 - 1f32;
+            let mut world = app.world.as_ref().unwrap().borrow_mut();
+            spawn_tilted_sprite(&mut world, "Green/texture_02.png", (x, y, 0f32));
+        }
+
+        app.run_frames(1).unwrap();
+        recording.take_calls();
+        app.run_frames(1).unwrap();
+
+        let log = recording.log();
+        let instanced_draws: Vec<(u32, i32)> = log
+            .draw_calls()
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::DrawCommandInstanced { vao, instances, .. } => Some((*vao, *instances)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(instanced_draws.len(), 1);
+        assert_eq!(instanced_draws[0].1, 1000);
+        assert!(log.calls().contains(&GfxCall::UpdateInstanceBuffer {
+            vao: instanced_draws[0].0,
+            floats_count: 1000 * INSTANCE_FLOATS,
+        }));
+
+        // Static sprites cost no program switch nor uniform upload per sprite (the debug grid
+        // drawn after the sprites pass is not part of this check)
+        let sprites_pass: Vec<&GfxCall> = log
+            .calls()
+            .iter()
+            .take_while(|call| !matches!(call, GfxCall::DrawCommandInstanced { .. }))
+            .filter(|call| {
+                matches!(
+                    call,
+                    GfxCall::UseShaderModule { .. } | GfxCall::SetUniform { .. }
+                )
+            })
+            .collect();
+        assert_eq!(sprites_pass.len(), 1, "{:?}", sprites_pass);
+        assert!(log.violations().is_empty(), "{:?}", log.violations());
+        assert_eq!(app.get_frame_stats().draw_calls, 1);
+    }
+
+    #[test]
+    fn all_rendering_modes_should_produce_the_same_image() {
+        let textures = ["Red/texture_08.png", "Orange/texture_05.png"];
+        let mut images = vec![];
+        let modes = [
+            RenderingMode::Direct,
+            RenderingMode::Batched,
+            RenderingMode::Instanced,
+        ];
+
+        for mode in modes {
+            let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+            for i in 0..6 {
+                let mut world = scene.world();
+                spawn_tilted_sprite(
+                    &mut world,
+                    textures[i / 3],
+                    (i as f32 * 0.4f32 - 1f32, 0f32, 0f32),
+                );
+            }
+            // Tinted sprites move the surface color into the batch vertices
+            let mut world = scene.world();
+            let mut query = world.query::<&mut SpriteRenderer2D>();
+            for mut sprite in query.iter_mut(&mut world) {
+                sprite.material.as_mut().unwrap().color = vec4(0.5f32, 1f32, 0.25f32, 0.75f32);
+            }
+            drop(world);
+
+            scene.app.set_rendering_mode(mode);
+            images.push(scene.render_frames(2));
+        }
+
+        for (mode, image) in modes.iter().zip(images.iter()).skip(1) {
+            let max_difference = images[0]
+                .pixels()
+                .zip(image.pixels())
+                .flat_map(|(a, b)| a.0.iter().zip(b.0.iter()).map(|(x, y)| x.abs_diff(*y)))
+                .max()
+                .unwrap();
+            assert!(
+                max_difference <= 1,
+                "{:?}: max channel difference {}",
+                mode,
+                max_difference
+            );
+        }
+    }
+}
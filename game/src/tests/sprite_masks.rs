@@ -0,0 +1,219 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::{Camera, SpriteMask, SpriteRenderer2D, Transform};
+    use crate::engine::ecs::resources::CameraCullingState;
+    use crate::engine::rendering::components::{
+        ARGB8Color, MaskInteraction, MeshInfo, RenderRequest, RenderingSpriteMask, SortingOrder,
+        StencilMode,
+    };
+    use crate::engine::rendering::gfx_recording::GfxCall;
+    use crate::engine::rendering::renderer::RenderCmdHd;
+    use crate::engine::rendering::shaders::Material;
+    use crate::tests::fixtures::{recording_renderer, spawn_sprite_renderer, sprite_transform};
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::entity::Entity;
+    use glm::Vector2;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const BLUE: ARGB8Color = ARGB8Color {
+        r: 0,
+        g: 0,
+        b: 200,
+        a: 255,
+    };
+
+    // Inside the mask at the origin, then outside of it but still on the sprite
+    const INSIDE: (u32, u32) = (167, 125);
+    const OUTSIDE: (u32, u32) = (230, 160);
+
+    // Blue background, a red sprite 2 units wide at the origin & a 0.5 unit wide mask
+    fn masked_scene(interaction: MaskInteraction) -> (GoldenScene, Entity) {
+        let scene = GoldenScene::new(WIDTH, HEIGHT);
+        let mask = {
+            let mut world = scene.world();
+            let camera = world
+                .resource::<CameraCullingState>()
+                .camera_entity
+                .unwrap();
+            world.get_mut::<Camera>(camera).unwrap().background_color = Option::from(BLUE);
+
+            let mut sprite = SpriteRenderer2D::from(String::from("Red/texture_01.png"), false);
+            sprite.mask_interaction = interaction;
+            spawn_sprite_renderer(&mut world, sprite, (0f32, 0f32, 0f32), 2f32);
+            world
+                .spawn((
+                    sprite_transform((0f32, 0f32, 0f32), 0.5f32),
+                    SpriteMask::Rect,
+                ))
+                .id()
+        };
+        (scene, mask)
+    }
+
+    fn is_sprite(image: &image::RgbaImage, pixel: (u32, u32)) -> bool {
+        let color = image.get_pixel(pixel.0, pixel.1).0;
+        color[2] != BLUE.b || color[0] != 0
+    }
+
+    #[test]
+    fn sprites_visible_inside_masks_should_be_clipped_outside() {
+        let (mut scene, mask) = masked_scene(MaskInteraction::VisibleInsideMask);
+        let image = scene.render_frames(2);
+        assert!(is_sprite(&image, INSIDE));
+        assert!(!is_sprite(&image, OUTSIDE));
+
+        // Masks follow their entity
+        *scene.world().get_mut::<Transform>(mask).unwrap() =
+            sprite_transform((0.7f32, -0.4f32, 0f32), 0.5f32);
+        let image = scene.render_frames(1);
+        assert!(!is_sprite(&image, INSIDE));
+        assert!(is_sprite(&image, OUTSIDE));
+
+        // Without any mask nothing is visible inside
+        scene.world().despawn(mask);
+        let image = scene.render_frames(1);
+        assert!(!is_sprite(&image, INSIDE));
+        assert!(!is_sprite(&image, OUTSIDE));
+    }
+
+    #[test]
+    fn sprites_visible_outside_masks_should_have_a_hole() {
+        let (mut scene, _) = masked_scene(MaskInteraction::VisibleOutsideMask);
+        let image = scene.render_frames(2);
+        assert!(!is_sprite(&image, INSIDE));
+        assert!(is_sprite(&image, OUTSIDE));
+    }
+
+    #[test]
+    fn masks_should_not_be_drawn_nor_clip_other_sprites() {
+        let (mut scene, mask) = masked_scene(MaskInteraction::None);
+        let image = scene.render_frames(2);
+        assert!(is_sprite(&image, INSIDE));
+        assert!(is_sprite(&image, OUTSIDE));
+
+        // Nothing but the background where the mask lies without a sprite
+        scene
+            .world()
+            .entity_mut(mask)
+            .insert(sprite_transform((-1.3f32, 0.8f32, 0f32), 0.5f32));
+        let image = scene.render_frames(1);
+        assert!(!is_sprite(&image, (27, 35)));
+    }
+
+    #[test]
+    fn polygon_masks_should_clip_along_their_outline() {
+        let (mut scene, mask) = masked_scene(MaskInteraction::VisibleInsideMask);
+        // Cone opening toward +x from the origin, 1 unit long once scaled
+        scene
+            .world()
+            .entity_mut(mask)
+            .insert(SpriteMask::Polygon(vec![
+                (0f32, 0f32),
+                (2f32, -1f32),
+                (2f32, 1f32),
+            ]));
+
+        let image = scene.render_frames(2);
+        assert!(is_sprite(&image, (230, 125)));
+        assert!(!is_sprite(&image, (230, 160)));
+        assert!(!is_sprite(&image, (130, 125)));
+    }
+
+    #[test]
+    fn concave_polygon_masks_should_leave_their_notches_out() {
+        let (mut scene, mask) = masked_scene(MaskInteraction::VisibleInsideMask);
+        // Arrow head pointing toward -x, the notch at +x doesn't see the first point
+        scene
+            .world()
+            .entity_mut(mask)
+            .insert(SpriteMask::Polygon(vec![
+                (2f32, -1f32),
+                (1f32, 0f32),
+                (2f32, 1f32),
+                (0f32, 0f32),
+            ]));
+
+        let image = scene.render_frames(2);
+        assert!(is_sprite(&image, (240, 85)));
+        assert!(is_sprite(&image, (240, 155)));
+        assert!(!is_sprite(&image, (240, 115)));
+    }
+
+    #[test]
+    fn stencil_mode_should_only_change_with_the_sprites_interaction() {
+        let (recording, mut renderer) = recording_renderer("Masks");
+
+        let interactions = [
+            MaskInteraction::None,
+            MaskInteraction::VisibleInsideMask,
+            MaskInteraction::VisibleInsideMask,
+            MaskInteraction::VisibleOutsideMask,
+        ];
+        let handles: Vec<RenderCmdHd> = interactions
+            .iter()
+            .enumerate()
+            .map(|(i, interaction)| {
+                renderer.create_render_command(RenderRequest {
+                    mesh_info: MeshInfo {
+                        file_path: None,
+                        count: 0,
+                        vertices_set: None,
+                    },
+                    material: Material::default(Option::from(String::from("Red/texture_01.png"))),
+                    transform: sprite_transform((0f32, 0f32, 0f32), 1f32),
+                    sorting: SortingOrder {
+                        order_in_layer: i as i16,
+                        ..SortingOrder::default()
+                    },
+                    mask_interaction: *interaction,
+                })
+            })
+            .collect();
+        renderer.update_sprite_masks(vec![RenderingSpriteMask {
+            points: vec![
+                Vector2::new(0f32, 0f32),
+                Vector2::new(1f32, 0f32),
+                Vector2::new(1f32, 1f32),
+                Vector2::new(0f32, 1f32),
+            ],
+        }]);
+
+        recording.take_calls();
+        handles
+            .iter()
+            .for_each(|handle| renderer.enqueue_cmd_for_current_frame(*handle));
+        renderer.render(1f32 / 60f32);
+        let modes: Vec<StencilMode> = recording
+            .take_calls()
+            .iter()
+            .filter_map(|call| match call {
+                GfxCall::SetStencilMode { mode } => Some(*mode),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            modes,
+            vec![
+                StencilMode::WriteMask,
+                StencilMode::Disabled,
+                StencilMode::VisibleInside,
+                StencilMode::VisibleOutside,
+                StencilMode::Disabled,
+            ]
+        );
+        // The mask, then one batch per stencil mode
+        let stats = renderer.get_frame_stats();
+        assert_eq!((stats.draw_calls, stats.batches), (4, 3));
+        assert!(recording.log().violations().is_empty());
+
+        // Without masks the stencil is never used
+        renderer.update_sprite_masks(vec![]);
+        renderer.enqueue_cmd_for_current_frame(handles[0]);
+        renderer.render(1f32 / 60f32);
+        assert!(!recording
+            .take_calls()
+            .iter()
+            .any(|call| matches!(call, GfxCall::SetStencilMode { .. })));
+    }
+}
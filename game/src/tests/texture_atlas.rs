@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod tests {
+    use crate::engine::ecs::components::SpriteRenderer2D;
+    use crate::engine::rendering::atlas::{AtlasPacker, TextureAtlas};
+    use crate::engine::rendering::components::RenderingMode;
+    use crate::engine::rendering::renderer_storage::RendererStorage;
+    use crate::engine::rendering::shaders::Texture;
+    use crate::engine::utils::maths::Rect;
+    use crate::tests::fixtures::spawn_sprite_renderer;
+    use crate::tests::golden::GoldenScene;
+    use bevy_ecs::world::World;
+    use image::RgbaImage;
+    use std::rc::Rc;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+    const ATLAS: &str = "solid.atlas";
+
+    fn solid_texture(width: u32, height: u32, texel: [u8; 4]) -> Rc<Texture> {
+        Rc::new(Texture {
+            data: texel.repeat((width * height) as usize),
+            width,
+            height,
+            channels: 4,
+        })
+    }
+
+    fn texel_at(texture: &Texture, x: u32, y: u32) -> [u8; 4] {
+        let start = ((y * texture.width + x) * 4) as usize;
+        texture.data[start..start + 4].try_into().unwrap()
+    }
+
+    fn overlaps(a: &Rect<u32>, b: &Rect<u32>) -> bool {
+        a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+    }
+
+    fn solid_atlas() -> TextureAtlas {
+        let mut packer = AtlasPacker::new();
+        packer
+            .add("red", solid_texture(8, 8, [255, 0, 0, 255]))
+            .add("green", solid_texture(8, 8, [0, 255, 0, 255]));
+        packer.pack().unwrap()
+    }
+
+    fn spawn_region(world: &mut World, region: &str, x: f32) {
+        let sprite = SpriteRenderer2D::from_atlas(String::from(ATLAS), String::from(region), false);
+        spawn_sprite_renderer(world, sprite, (x, 0f32, 0f32), 0.4f32);
+    }
+
+    // Pixels at the center of the left and right sprites
+    fn sprite_pixels(image: &RgbaImage) -> ([u8; 4], [u8; 4]) {
+        let y = HEIGHT / 2 + 5;
+        (
+            image.get_pixel(WIDTH / 4, y).0,
+            image.get_pixel(WIDTH * 3 / 4, y).0,
+        )
+    }
+
+    #[test]
+    fn packed_regions_should_not_overlap_and_keep_their_texels() {
+        let mut packer = AtlasPacker::new();
+        let sizes = [(30, 10), (12, 40), (25, 25), (7, 3), (64, 8)];
+        for (i, (width, height)) in sizes.iter().enumerate() {
+            packer.add(
+                &format!("region_{}", i),
+                solid_texture(*width, *height, [i as u8 * 40, 0, 0, 255]),
+            );
+        }
+        let atlas = packer.pack().unwrap();
+
+        assert_eq!(atlas.get_regions_count(), sizes.len());
+        assert!(atlas.texture.width.is_power_of_two());
+        assert!(atlas.texture.height.is_power_of_two());
+
+        let regions: Vec<Rect<u32>> = (0..sizes.len())
+            .map(|i| *atlas.get_region(&format!("region_{}", i)).unwrap())
+            .collect();
+        for (i, region) in regions.iter().enumerate() {
+            assert_eq!((region.width, region.height), sizes[i]);
+            assert!(region.x + region.width <= atlas.texture.width);
+            assert!(region.y + region.height <= atlas.texture.height);
+            for other in regions.iter().skip(i + 1) {
+                assert!(!overlaps(region, other), "{:?} {:?}", region, other);
+            }
+
+            let corner = (region.x + region.width - 1, region.y + region.height - 1);
+            assert_eq!(
+                texel_at(&atlas.texture, corner.0, corner.1),
+                [i as u8 * 40, 0, 0, 255]
+            );
+        }
+    }
+
+    #[test]
+    fn padding_should_extrude_the_border_texels() {
+        let mut packer = AtlasPacker::new().with_padding(2);
+        packer.add("blue", solid_texture(4, 4, [0, 0, 255, 255]));
+        let atlas = packer.pack().unwrap();
+        let region = *atlas.get_region("blue").unwrap();
+
+        assert_eq!((region.x, region.y), (2, 2));
+        assert_eq!(texel_at(&atlas.texture, 0, 0), [0, 0, 255, 255]);
+        assert_eq!(
+            texel_at(&atlas.texture, region.x + region.width + 1, region.y),
+            [0, 0, 255, 255]
+        );
+    }
+
+    #[test]
+    fn sheet_regions_should_be_given_from_the_top_left_corner() {
+        let description = "# two frames on a 64x32 sheet\n\
+                           sheet sheet.png\n\
+                           region idle 0 0 16 16\n\
+                           region walk 16 16 16 16\n";
+        let atlas =
+            TextureAtlas::from_description(description, |_| Ok(solid_texture(64, 32, [0; 4])))
+                .unwrap();
+
+        // Texture rows are stored bottom to top
+        assert_eq!(atlas.get_region("idle").unwrap().y, 16);
+        assert_eq!(atlas.get_region("walk").unwrap().y, 0);
+        assert_eq!(
+            atlas.get_uv_rect("walk").unwrap().as_array(),
+            &[0.25f32, 0f32, 0.25f32, 0.5f32]
+        );
+    }
+
+    #[test]
+    fn invalid_descriptions_should_be_reported() {
+        let load = |_: &str| Ok(solid_texture(4, 4, [0; 4]));
+
+        assert!(TextureAtlas::from_description("region idle 0 0 4 4", load).is_err());
+        assert!(TextureAtlas::from_description("sheet a.png\nregion idle 0 0 8 8", load).is_err());
+        assert!(TextureAtlas::from_description("sheet a.png\ntexture b.png", load).is_err());
+        assert!(TextureAtlas::from_description("pack b.png", load).is_err());
+    }
+
+    #[test]
+    fn atlas_files_should_be_loaded_once_from_the_assets() {
+        let storage = RendererStorage::new();
+        let atlas = storage.load_atlas("prototype.atlas").unwrap();
+
+        assert_eq!(atlas.get_regions_count(), 3);
+        assert!(Rc::ptr_eq(
+            &atlas,
+            &storage.load_atlas("prototype.atlas").unwrap()
+        ));
+        assert!(Rc::ptr_eq(
+            &atlas.texture,
+            &storage.load_texture("prototype.atlas").unwrap()
+        ));
+    }
+
+    #[test]
+    fn sprites_of_one_atlas_should_sample_their_region_in_a_single_batch() {
+        let modes = [
+            RenderingMode::Direct,
+            RenderingMode::Batched,
+            RenderingMode::Instanced,
+        ];
+
+        for mode in modes {
+            let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+            scene.app.register_atlas(ATLAS, solid_atlas());
+            scene.app.set_rendering_mode(mode);
+            {
+                let mut world = scene.world();
+                spawn_region(&mut world, "red", -0.8f32);
+                spawn_region(&mut world, "green", 0.8f32);
+            }
+
+            let (left, right) = sprite_pixels(&scene.render_frames(2));
+            assert_eq!(left, [255, 0, 0, 255], "{:?}", mode);
+            assert_eq!(right, [0, 255, 0, 255], "{:?}", mode);
+
+            let expected_draws = if mode == RenderingMode::Direct { 2 } else { 1 };
+            assert_eq!(scene.app.get_frame_stats().draw_calls, expected_draws);
+        }
+    }
+
+    #[test]
+    fn changing_the_region_should_update_the_sampled_area() {
+        let mut scene = GoldenScene::new(WIDTH, HEIGHT);
+        scene.app.register_atlas(ATLAS, solid_atlas());
+        {
+            let mut world = scene.world();
+            spawn_region(&mut world, "red", -0.8f32);
+            spawn_region(&mut world, "green", 0.8f32);
+        }
+        scene.render_frames(1);
+
+        {
+            let mut world = scene.world();
+            let mut query = world.query::<&mut SpriteRenderer2D>();
+            for mut sprite in query.iter_mut(&mut world) {
+                let atlas = sprite.atlas.as_mut().unwrap();
+                atlas.region = String::from(if atlas.region == "red" {
+                    "green"
+                } else {
+                    "red"
+                });
+            }
+        }
+
+        let (left, right) = sprite_pixels(&scene.render_frames(1));
+        assert_eq!(left, [0, 255, 0, 255]);
+        assert_eq!(right, [255, 0, 0, 255]);
+    }
+}